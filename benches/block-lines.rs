@@ -0,0 +1,58 @@
+// std imports
+use std::{io::Cursor, path::PathBuf, sync::Arc};
+
+// third-party imports
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+// local imports
+use hl::{
+    index::Indexer,
+    input::{Block, BufPool, IndexedInput, InputReference},
+    Settings,
+};
+
+// ---
+
+fn benchmark(c: &mut Criterion) {
+    let mut c = c.benchmark_group("block-lines");
+    let settings = Settings::default();
+    let indexer =
+        Indexer::new(1, 256 * 1024, 1024 * 1024, PathBuf::new(), &settings.fields.predefined).with_cache(false);
+    let data = (0..100000)
+        .map(|i| {
+            format!(
+                "{{\"ts\":\"2020-01-01T00:{:02}:{:02}.{:03}Z\",\"msg\":\"message #{}\"}}\n",
+                i / 60000 % 60,
+                i / 1000 % 60,
+                i % 1000,
+                i
+            )
+        })
+        .collect::<String>();
+    let input =
+        Arc::new(IndexedInput::open_sequential(InputReference::Stdin, Box::new(Cursor::new(data)), &indexer).unwrap());
+    let blocks = input.index.source().blocks.len();
+
+    let run = |pool: Option<&Arc<BufPool>>| {
+        for i in 0..blocks {
+            let block = Block::new(input.clone(), i);
+            let block = match pool {
+                Some(pool) => block.with_buf_pool(pool.clone()),
+                None => block,
+            };
+            for line in block.into_lines().unwrap() {
+                black_box(line.bytes());
+            }
+        }
+    };
+
+    c.bench_function("unpooled", |b| b.iter(|| run(None)));
+
+    let pool = Arc::new(BufPool::new());
+    c.bench_function("pooled", |b| b.iter(|| run(Some(&pool))));
+}
+
+// ---
+
+criterion_group!(benches, benchmark);
+criterion_main!(benches);
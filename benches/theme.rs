@@ -98,6 +98,7 @@ fn benchmark(c: &mut Criterion) {
         .into(),
         levels: HashMap::new(),
         indicators: themecfg::IndicatorPack::default(),
+        icons: themecfg::IconPack::default(),
     });
     let fields = vec![
         (b"key1", b"value1"),
@@ -0,0 +1,11 @@
+#![no_main]
+
+use hl::settings::InputFormat;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = hl::logfmt::parse(data);
+    let mut buf = Vec::new();
+    let _ = hl::docker::unwrap(data, &mut buf);
+    let _ = hl::logfmt::normalize(InputFormat::Auto, data, &mut buf);
+});
@@ -0,0 +1,20 @@
+#![no_main]
+
+use std::iter::empty;
+
+use hl::{Parser, ParserSettings, Settings};
+use libfuzzer_sys::fuzz_target;
+use once_cell::sync::Lazy;
+
+static PARSER: Lazy<Parser> = Lazy::new(|| {
+    let settings = Settings::default();
+    Parser::new(ParserSettings::new(&settings.fields.predefined, empty(), true))
+});
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(record) = PARSER.parse_slice(data) {
+        if let Some(ts) = record.ts {
+            let _ = ts.unix_utc();
+        }
+    }
+});
@@ -0,0 +1,14 @@
+#![no_main]
+
+use hl::timestamp::{self, rfc3339};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = timestamp::parse(data);
+    if let Ok(s) = std::str::from_utf8(data) {
+        if let Some(ts) = rfc3339::Timestamp::parse(s) {
+            let tz = ts.timezone();
+            let _ = (ts.date(), ts.time(), ts.fraction(), tz.sign(), tz.hour(), tz.minute());
+        }
+    }
+});
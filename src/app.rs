@@ -1,21 +1,22 @@
 // std imports
-use std::cmp::{Reverse, max};
-use std::collections::BTreeMap;
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::cmp::{Reverse, max, min};
+use std::collections::{BTreeMap, HashSet, VecDeque};
 use std::convert::{TryFrom, TryInto};
 use std::fs;
-use std::io::{BufWriter, Write};
+use std::io::{BufWriter, Read, Write};
 use std::iter::repeat;
 use std::ops::Range;
 use std::path::PathBuf;
 use std::rc::Rc;
-use std::sync::Arc;
-use std::time::{Duration,Instant};
-
-// unix-only std imports
-#[cfg(unix)]
-use std::os::unix::fs::MetadataExt;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
 
 // third-party imports
+use chrono::{DateTime, FixedOffset, Utc};
+use clap::ValueEnum;
 use closure::closure;
 use crossbeam_channel::{self as channel, Receiver, RecvError, Sender,RecvTimeoutError};
 use crossbeam_utils::thread;
@@ -26,30 +27,72 @@ use sha2::{Digest, Sha256};
 use std::num::{NonZeroU32, NonZeroUsize};
 
 // local imports
+use crate::binary;
+use crate::bugreport;
+use crate::cache::IndexCache;
+use crate::cancellation::{self, Cancellation};
+use crate::checkpoint::Checkpoint;
+use crate::clipboard::{Clipboard, CopyFormat, MAX_COPY_SIZE};
+use crate::columns;
+use crate::context::ContextSelector;
+use crate::compression::{self, Decompression};
 use crate::datefmt::{DateTimeFormat, DateTimeFormatter};
+use crate::dedup::{Dedup, Deduplicator};
+use crate::diagnostics;
+use crate::diff::Differ;
 use crate::error::*;
+use crate::export::RecordExporter;
 use crate::fmtx::aligned_left;
+use crate::follow::{FollowedFile, Rotation};
 use crate::fsmon::{self, EventKind};
-use crate::formatting::RecordFormatter;
+use crate::highlight::Highlighter;
+use crate::formatting::{Lanes, RecordFormatter};
 use crate::index::{Indexer, Timestamp};
-use crate::input::{BlockLine, InputHolder, InputReference, Input};
-use crate::model::{Filter, Parser, ParserSettings, RawRecord, Record};
+use crate::input::{self, Block, BlockLine, Blocks, BufPool, IndexedInput, InputHolder, InputReference, Input, MergedBlocks};
+use crate::iox::CancellableReader;
+use crate::limit::RecordLimiter;
+use crate::locale::Locale;
+use crate::logfmt;
+use crate::model::{Filter, Level, Parser, ParserSettings, RawRecord, Record};
+use crate::palette::ColorBy;
+use crate::pattern::FilePattern;
+use crate::prefilter::Prefilter;
+use crate::progress::Progress;
 use crate::scanning::{BufFactory, Scanner, Segment, SegmentBufFactory};
-use crate::settings::{Fields, Formatting};
+use crate::schema::SchemaValidator;
+use crate::settings::{DuplicateKeys, Fields, Formatting, InputFormat, Multiline, OutputFormat, TimeGaps, Transform};
+use crate::snapshot::{Snapshot, SnapshotOptions};
+use crate::stats::{Stats, StatsOptions};
+use crate::summary::Summary;
+use crate::syslog;
+use crate::terminal::Capabilities;
+use crate::text::TextFormat;
 use crate::theme::{Element, StylingPush, Theme};
+use crate::timestamp::TimeFormat;
 use crate::timezone::Tz;
-use crate::IncludeExcludeKeyFilter;
+use crate::tracking::{Series, Tracking};
+use crate::transform::Transformer;
+use crate::ui::{Document, Viewer};
+use crate::{FieldSelection, IncludeExcludeKeyFilter};
 
 // TODO: merge Options to Settings and replace Options with Settings.
 
 // ---
 
+const SNAPSHOT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// Minimum interval between saves of the checkpoint file in follow mode, see --checkpoint option.
+const CHECKPOINT_SAVE_INTERVAL: Duration = Duration::from_secs(1);
+const TEXT_DETECTION_SAMPLE_SIZE: u64 = 64 * 1024;
+
+// ---
+
 pub struct Options {
     pub theme: Arc<Theme>,
     pub time_format: DateTimeFormat,
     pub raw_fields: bool,
     pub buffer_size: NonZeroUsize,
     pub max_message_size: NonZeroUsize,
+    pub max_stream_buffer_size: NonZeroUsize,
     pub concurrency: usize,
     pub filter: Filter,
     pub fields: FieldOptions,
@@ -59,14 +102,64 @@ pub struct Options {
     pub sort: bool,
     pub follow: bool,
     pub sync_interval: Duration,
+    pub snapshot: Option<SnapshotOptions>,
+    pub split_screen: Option<usize>,
+    pub replay: Option<f64>,
+    pub window: Option<TimeWindow>,
+    pub transforms: Vec<Transform>,
+    pub explode: Option<String>,
+    pub max_runtime: Option<Duration>,
+    pub index_cache: bool,
+    pub index_cache_max_size: Option<u64>,
+    pub index_concurrency: usize,
+    pub progress: bool,
+    pub sort_fields: bool,
+    pub summary: bool,
+    pub count_records: bool,
+    pub tracking: Option<Tracking>,
+    pub skip_binary: bool,
+    pub skip_errors: bool,
+    pub schema: Option<Arc<SchemaValidator>>,
+    pub output_format: OutputFormat,
+    pub columns: Vec<String>,
     pub input_info: Option<InputInfo>,
+    pub show_source: bool,
     pub dump_index: bool,
     pub app_dirs: Option<AppDirs>,
+    pub icons: bool,
+    pub time_gaps: Option<TimeGaps>,
+    pub lanes: Option<Lanes>,
+    pub color_by: Option<ColorBy>,
+    pub watch: Vec<Watch>,
+    pub copy: Option<CopyFormat>,
+    pub time_formats: Vec<TimeFormat>,
+    pub locale: Option<&'static Locale>,
+    pub input_format: InputFormat,
+    pub text_format: TextFormat,
+    pub multiline: Option<Multiline>,
+    pub terminal: Capabilities,
+    pub context: Option<Context>,
+    pub limit: Option<Limit>,
+    pub interactive: bool,
+    pub heartbeat: Option<Duration>,
+    pub stats: Option<StatsOptions>,
+    pub dedup: Option<Dedup>,
+    pub checkpoint: Option<Arc<Mutex<Checkpoint>>>,
 }
 
 pub struct FieldOptions {
     pub filter: Arc<IncludeExcludeKeyFilter>,
+    pub selection: Arc<FieldSelection>,
     pub settings: Fields,
+    /// Prefixes removed from keys of top-level fields when they are shown, see --strip-prefix option.
+    pub strip_prefixes: Vec<String>,
+}
+
+/// Pattern of files to start following as soon as they appear, see --follow option.
+#[derive(Clone, Debug)]
+pub struct Watch {
+    pub pattern: FilePattern,
+    pub tags: Vec<(String, String)>,
 }
 
 #[derive(Eq, PartialEq, Copy, Clone)]
@@ -77,34 +170,323 @@ pub enum InputInfo {
     Minimal,
 }
 
+/// Number of records shown before and after each matching record, see --before, --after and --context options.
+#[derive(Clone, Copy, Debug)]
+pub struct Context {
+    pub before: usize,
+    pub after: usize,
+}
+
+/// Number of first or last matching records to show, see --head and --tail options.
+#[derive(Clone, Copy, Debug)]
+pub enum Limit {
+    Head(usize),
+    Tail(usize),
+}
+
+#[derive(Clone, Copy)]
+pub struct TimeWindow {
+    pub size: Duration,
+    pub page: usize,
+}
+
 pub struct App {
     options: Options,
+    started: Instant,
+    truncated: AtomicBool,
+    cancellation: Cancellation,
+    summary: Mutex<Summary>,
+    clipboard: Mutex<Option<Clipboard>>,
+    exporter: Option<RecordExporter>,
+    prefilter: Option<Prefilter>,
 }
 
 pub type Output = dyn Write + Send + Sync;
 
 impl App {
     pub fn new(options: Options) -> Self {
-        Self { options }
+        Self {
+            clipboard: Mutex::new(options.copy.map(|format| Clipboard::new(format).with_limit(MAX_COPY_SIZE))),
+            exporter: RecordExporter::new(options.output_format, options.columns.clone()),
+            prefilter: Prefilter::new(&options.filter).filter(|_| options.context.is_none()),
+            options,
+            started: Instant::now(),
+            truncated: AtomicBool::new(false),
+            cancellation: Cancellation::new(),
+            summary: Mutex::new(Summary::default()),
+        }
+    }
+
+    /// Returns the handle that stops processing when cancelled, making [`App::run`] return [`Error::Cancelled`]
+    /// after the output produced so far is written.
+    pub fn cancellation(&self) -> &Cancellation {
+        &self.cancellation
     }
 
     pub fn run(&self, inputs: Vec<InputHolder>, output: &mut Output) -> Result<()> {
-        if self.options.follow {
-            self.follow(inputs.into_iter().map(|x|x.reference).collect(), output)
+        let transformers = inputs.iter().map(|x| self.transformer(&x.tags)).collect_vec();
+        self.summary.lock().unwrap().inputs = inputs.len();
+        if let (Some(exporter), None, None) = (&self.exporter, self.options.split_screen, &self.options.stats) {
+            let mut buf = Vec::new();
+            exporter.header(&mut buf);
+            output.write_all(&buf)?;
+        }
+        let result = if let Some(width) = self.options.split_screen {
+            self.split(inputs, &transformers, width, output)
+        } else if let Some(stats) = &self.options.stats {
+            self.stats(inputs, &transformers, stats, output)
+        } else if self.options.interactive {
+            self.interactive(inputs, output)
+        } else if self.options.follow {
+            self.follow(inputs.into_iter().map(|x|x.reference).collect(), &transformers, output)
+        } else if self.options.sort {
+            self.sort(inputs, &transformers, output)
+        } else {
+            self.cat(inputs, &transformers, output)
+        };
+        if self.options.summary {
+            output.flush()?;
+            self.summary.lock().unwrap().print(&mut std::io::stderr(), self.started.elapsed())?;
+        } else if let Some(series) = &self.summary.lock().unwrap().series {
+            output.flush()?;
+            series.print(&mut std::io::stderr())?;
+        }
+        match result {
+            // reported below as exceeding of the time budget, which cancels processing
+            Err(Error::Cancelled) if self.truncated.load(Ordering::Relaxed) => {}
+            result => result?,
+        }
+        self.check_type_mismatch()?;
+        if let Some(clipboard) = &*self.clipboard.lock().unwrap() {
+            output.flush()?;
+            if self.options.terminal.clipboard {
+                let size = clipboard.copy(&self.options.terminal)?;
+                if size < clipboard.data().len() {
+                    diagnostics::warning(format_args!("copied data is truncated to {} bytes", size));
+                }
+                if clipboard.dropped() != 0 {
+                    diagnostics::warning(format_args!(
+                        "{} earliest messages are not copied because of the clipboard size limit",
+                        clipboard.dropped()
+                    ));
+                }
+                diagnostics::info(format_args!("copied {} messages to clipboard", clipboard.records()));
+            } else {
+                diagnostics::warning(format_args!(
+                    "terminal does not support setting clipboard contents, set terminal.clipboard to true in the configuration file to copy anyway"
+                ));
+            }
+        }
+        match self.options.max_runtime {
+            Some(budget) if self.truncated.load(Ordering::Relaxed) => Err(Error::TimeBudgetExceeded { budget }),
+            _ if self.cancellation.is_cancelled() => Err(Error::Cancelled),
+            _ => self.check_failed_inputs(),
+        }
+    }
+
+    /// Reports the error of the input and records it as failed if --skip-errors option is enabled,
+    /// otherwise returns the error. Further errors of an input already recorded as failed are ignored.
+    pub fn skip_failed_input(&self, description: String, err: Error) -> Result<()> {
+        if !self.options.skip_errors || matches!(err, Error::Cancelled | Error::TimeBudgetExceeded { .. }) {
+            return Err(err);
+        }
+        let mut summary = self.summary.lock().unwrap();
+        if !summary.failed.contains(&description) {
+            diagnostics::warning(format_args!("skipping {}: {}", description, err));
+            summary.failed.push(description);
+        }
+        Ok(())
+    }
+
+    /// Makes reading of a stream input like stdin, a socket or a command output stop as soon as processing is cancelled,
+    /// files are read without blocking for long, so they are kept as is.
+    fn cancellable(&self, input: Input) -> Input {
+        match input.reference {
+            InputReference::File(_) => input,
+            reference => {
+                let stream = CancellableReader::new(input.stream, self.cancellation.clone());
+                Input::new(reference, Box::new(stream))
+            }
+        }
+    }
+
+    fn check_failed_inputs(&self) -> Result<()> {
+        match self.summary.lock().unwrap().failed.len() {
+            0 => Ok(()),
+            n => Err(Error::InputsFailed(n)),
+        }
+    }
+
+    /// Returns the numbers of parsed and matching records processed so far, if records are counted.
+    pub fn record_counts(&self) -> Option<(u64, u64)> {
+        self.summary_enabled().then(|| {
+            let summary = self.summary.lock().unwrap();
+            (summary.parsed, summary.matched)
+        })
+    }
+
+    /// Returns processing statistics collected so far, which are partial while running or after cancellation.
+    ///
+    /// Only the number of inputs is collected unless --summary option or record counting is enabled.
+    pub fn summary(&self) -> Summary {
+        self.summary.lock().unwrap().clone()
+    }
+
+    pub fn plan(&self, inputs: &[InputHolder], sink: &str, output: &mut Output) -> Result<()> {
+        let mode = if self.options.split_screen.is_some() {
+            "split"
+        } else if self.options.stats.is_some() {
+            "stats"
+        } else if self.options.interactive {
+            "interactive"
+        } else if self.options.follow {
+            "follow"
         } else if self.options.sort {
-            self.sort(inputs, output)
+            "sort"
         } else {
-            self.cat(inputs, output)
+            "concatenate"
+        };
+        writeln!(output, "mode: {}", mode)?;
+        writeln!(output, "inputs:")?;
+        let formats = self.input_formats(inputs);
+        for (i, input) in inputs.iter().enumerate() {
+            let (compression, seekable) = match &input.reference {
+                // streams cannot be read ahead without consuming their data, so compression is detected once they are read
+                InputReference::Stdin | InputReference::Listen(_) | InputReference::Command(_) => (
+                    match input.decompression {
+                        Decompression::Auto => Some("auto"),
+                        decompression => decompression.resolve(&[]).map(|x| x.name()),
+                    },
+                    false,
+                ),
+                InputReference::File(path) => {
+                    // detected by the first bytes of the file the same way as when it is read
+                    let head = fs::File::open(path).and_then(|mut file| compression::read_head(&mut file));
+                    (
+                        input.decompression.resolve(&head.unwrap_or_default()).map(|x| x.name()),
+                        fs::metadata(path).map(|meta| meta.is_file()).unwrap_or(false),
+                    )
+                }
+            };
+            let format = formats[i].to_possible_value();
+            let scan = match (mode, seekable, compression) {
+                ("sort" | "stats", true, None) => "indexed",
+                ("sort" | "stats", true, Some(_)) if self.options.index_cache => "unpacked",
+                _ => "sequential",
+            };
+            write!(
+                output,
+                "  #{} {}: format={} compression={} scan={}",
+                i,
+                input.reference.description(),
+                format.as_ref().map_or("auto", |x| x.get_name()),
+                compression.unwrap_or("none"),
+                scan
+            )?;
+            for (key, value) in &input.tags {
+                write!(output, " tag:{}={}", key, value)?;
+            }
+            writeln!(output)?;
+        }
+        writeln!(output, "transforms: {:#?}", self.options.transforms)?;
+        writeln!(output, "filter: {:#?}", self.options.filter)?;
+        writeln!(output, "output: {}", sink)?;
+        Ok(())
+    }
+
+    /// Builds indexes of the inputs unless cached ones are up to date, prunes the index cache if requested
+    /// and shows its usage, see `hl index` command.
+    pub fn index(&self, inputs: Vec<InputHolder>, rebuild: bool, prune: bool, output: &mut Output) -> Result<()> {
+        let descriptions = inputs.iter().map(|x| x.reference.description()).collect_vec();
+        let formats = self.input_formats(&inputs);
+        let inputs = self.index_inputs(inputs, &formats, rebuild)?;
+        for (input, description) in inputs.iter().zip(descriptions) {
+            let source = input.index.source();
+            let mut stats = Stats::default();
+            stats.add_indexed(&source.stat);
+            writeln!(
+                output,
+                "indexed {}: {} lines in {} blocks, {} records ({})",
+                description,
+                source.stat.lines_valid + source.stat.lines_invalid,
+                source.blocks.len(),
+                source.stat.records,
+                stats.format_levels()
+            )?;
+        }
+        let cache = self.index_cache();
+        if prune {
+            let removed = cache.prune()?;
+            writeln!(output, "removed {} files of {} bytes", removed.files, removed.bytes)?;
+        } else {
+            self.evict_cached();
+        }
+        let entries = cache.entries()?;
+        writeln!(output, "cache directory: {}", cache.dir().display())?;
+        writeln!(output, "cached files: {}", entries.len())?;
+        write!(output, "cache size: {} bytes", entries.iter().map(|entry| entry.size).sum::<u64>())?;
+        match cache.max_size() {
+            Some(max_size) => writeln!(output, " of {} bytes allowed", max_size)?,
+            None => writeln!(output)?,
+        }
+        self.check_failed_inputs()
+    }
+
+    pub fn diff(&self, left: InputHolder, right: InputHolder, key: Option<&str>, output: &mut Output) -> Result<()> {
+        let read = |input: InputHolder| -> Result<Vec<u8>> {
+            let mut input = input.open()?;
+            let mut buf = Vec::new();
+            input.stream.read_to_end(&mut buf).map_err(|e| {
+                std::io::Error::new(
+                    e.kind(),
+                    format!("failed to read {}: {}", input.reference.description(), e),
+                )
+            })?;
+            Ok(buf)
+        };
+        let transformers = (self.transformer(&left.tags), self.transformer(&right.tags));
+        let (mut left, mut right) = (read(left)?, read(right)?);
+        if self.options.input_format != InputFormat::Json {
+            left = logfmt::normalize_all(self.detect_format(&left), &self.options.text_format, &left);
+            right = logfmt::normalize_all(self.detect_format(&right), &self.options.text_format, &right);
+        }
+        if !transformers.0.is_empty() {
+            left = transformers.0.apply_all(&left);
+        }
+        if !transformers.1.is_empty() {
+            right = transformers.1.apply_all(&right);
         }
+
+        let parser = self.parser();
+        let mut formatter = self.formatter();
+        Differ::new(&parser, &self.options.filter).with_key(key).run(
+            &left,
+            &right,
+            &mut formatter,
+            &self.options.theme,
+            output,
+        )?;
+        self.check_type_mismatch()
     }
 
-    fn cat(&self, inputs: Vec<InputHolder>, output: &mut Output) -> Result<()> {
+    fn cat(&self, inputs: Vec<InputHolder>, transformers: &[Transformer], output: &mut Output) -> Result<()> {
         let input_badges = self.input_badges(inputs.iter().map(|x| &x.reference));
+        let source_labels = self.source_labels(inputs.iter().map(|x| &x.reference));
+        let formats = self.input_formats(&inputs);
+        let descriptions = inputs.iter().map(|x| x.reference.description()).collect_vec();
+        let now = syslog::reference_time();
 
+        // inputs failed to open are kept as None to keep indices of the rest of them
         let inputs = inputs
             .into_iter()
-            .map(|x| x.open())
-            .collect::<std::io::Result<Vec<_>>>()?;
+            .map(|x| {
+                let description = x.reference.description();
+                match x.open() {
+                    Ok(input) => Ok(Some(self.cancellable(input))),
+                    Err(err) => self.skip_failed_input(description, err.into()).map(|_| None),
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
 
         let n = self.options.concurrency;
         let sfi = Arc::new(SegmentBufFactory::new(self.options.buffer_size.try_into()?));
@@ -114,15 +496,36 @@ impl App {
             // prepare receive/transmit channels for input data
             let (txi, rxi): (Vec<_>, Vec<_>) = (0..n).map(|_| channel::bounded(1)).unzip();
             // prepare receive/transmit channels for output data
-            let (txo, rxo): (Vec<_>, Vec<_>) = (0..n).into_iter().map(|_| channel::bounded::<(usize, Vec<u8>)>(1)).unzip();
+            let (txo, rxo): (Vec<_>, Vec<_>) = (0..n).into_iter().map(|_| channel::bounded::<(usize, Vec<u8>, Option<Clipboard>, Option<Spans>)>(1)).unzip();
             // spawn reader thread
             let reader = scope.spawn(closure!(clone sfi, |_| -> Result<()> {
                 let mut tx = StripedSender::new(txi);
-                let scanner = Scanner::new(sfi, "\n".to_string());
-                for (i, mut input) in inputs.into_iter().enumerate() {
+                let scanner = Scanner::new(sfi, "\n".to_string()).with_continuations(self.options.multiline.is_some());
+                for (i, input) in inputs.into_iter().enumerate() {
+                    let mut input = match input {
+                        Some(input) => input,
+                        None => continue,
+                    };
+                    // offset of the segment in the input
+                    let mut offset = 0;
                     for item in scanner.items(&mut input.stream).with_max_segment_size(self.options.max_message_size.into()) {
-                        if tx.send((i, item?)).is_none() {
-                            break;
+                        if self.stopped() {
+                            return Ok(());
+                        }
+                        let item = match item {
+                            Ok(item) => item,
+                            Err(err) => {
+                                self.skip_failed_input(input.reference.description(), err.into())?;
+                                break;
+                            }
+                        };
+                        let first = offset;
+                        let data = match &item {
+                            Segment::Complete(segment) | Segment::Incomplete(segment, _) => segment.data(),
+                        };
+                        offset += data.len() as u64;
+                        if tx.send((i, first, item)).is_none() {
+                            return Ok(());
                         }
                     }
                 }
@@ -130,22 +533,44 @@ impl App {
             }));
             // spawn processing threads
             for (rxi, txo) in izip!(rxi, txo) {
-                scope.spawn(closure!(ref bfo, ref parser, ref sfi, ref input_badges, |_| {
+                scope.spawn(closure!(ref bfo, ref parser, ref sfi, ref input_badges, ref source_labels, ref formats, ref now, ref descriptions, |_| {
                     let mut formatter = self.formatter();
-                    let mut processor = SegmentProcessor::new(&parser, &mut formatter, &self.options.filter);
-                    for (i, segment) in rxi.iter() {
+                    for (i, offset, segment) in rxi.iter() {
                         let prefix = input_badges.as_ref().map(|b|b[i].as_str()).unwrap_or("");
                         match segment {
                             Segment::Complete(segment) => {
                                 let mut buf = bfo.new_buf();
-                                processor.run(segment.data(), &mut buf, prefix, &mut RecordIgnorer{});
+                                let mut processor = SegmentProcessor::new(&parser, &mut formatter, &self.options.filter)
+                                    .with_input_format(formats[i])
+                                    .with_text_format(&self.options.text_format)
+                                    .with_reference_time(*now)
+                                    .with_multiline(self.max_continuation_lines())
+                                    .with_prefilter(self.prefilter.as_ref())
+                                    .with_transformer(&transformers[i])
+                                    .with_summary(self.summary_enabled())
+                                    .with_tracking(self.options.tracking.as_ref())
+                                    .with_skip_binary(self.options.skip_binary)
+                                    .with_validator(self.options.schema.as_deref())
+                                    .with_exporter(self.exporter.as_ref())
+                                    .with_context(self.context().is_some())
+                                    .with_source(source_labels.as_ref().map(|labels| (labels[i].as_str(), offset)));
+                                let mut collector = SourceCollector::new(self.options.copy.is_some());
+                                let mut spans = SpanCollector::new(&mut collector).with_dedup(self.dedup());
+                                let _origin = bugreport::origin(descriptions[i].clone(), offset);
+                                processor.run(segment.data(), &mut buf, prefix, &mut spans);
+                                let spans = (self.context().is_some() || self.options.limit.is_some() || self.dedup().is_some())
+                                    .then(|| spans.spans);
+                                if let Some(summary) = processor.summary() {
+                                    self.summary.lock().unwrap().merge(summary);
+                                }
                                 sfi.recycle(segment);
-                                if let Err(_) = txo.send((i, buf)) {
+                                let copied = self.options.copy.map(|format| collector.collect(format, &buf));
+                                if let Err(_) = txo.send((i, buf, copied, spans)) {
                                     break;
                                 };
                             }
                             Segment::Incomplete(segment, _) => {
-                                if let Err(_) = txo.send((i, segment.to_vec())) {
+                                if let Err(_) = txo.send((i, segment.to_vec(), None, None)) {
                                     break;
                                 }
                             }
@@ -155,10 +580,59 @@ impl App {
             }
             // spawn writer thread
             let writer = scope.spawn(closure!(ref bfo, |_| -> Result<()> {
-                for (_, buf) in StripedReceiver::new(rxo) {
-                    output.write_all(&buf[..])?;
+                let mut clipboard = self.clipboard.lock().unwrap();
+                let mut selector = self.context_selector();
+                let mut limiter = self.options.limit.map(RecordLimiter::new);
+                let mut dedup = self.dedup().map(|_| Deduplicator::new());
+                let mut last = None;
+                for (i, buf, copied, spans) in StripedReceiver::new(rxo) {
+                    match (&mut selector, &mut limiter, spans) {
+                        (Some(selector), _, spans) => {
+                            // context does not cross boundaries of inputs
+                            if last.replace(i).map_or(false, |last| last != i) {
+                                selector.reset();
+                            }
+                            match spans {
+                                Some(spans) => {
+                                    for (span, matched, _) in spans {
+                                        selector.push(&mut *output, &buf[span], matched)?;
+                                    }
+                                }
+                                // a part of a line exceeding the maximum message size is not a record,
+                                // so it is shown only around matching records
+                                None => selector.push(&mut *output, &buf, false)?,
+                            }
+                        }
+                        (None, limiter, spans) if dedup.is_some() || limiter.is_some() => {
+                            // lines that are not records come between spans, like raw lines and parts of messages exceeding the maximum size,
+                            // each of them is never collapsed and is counted as a record
+                            let spans = spans.unwrap_or_default();
+                            let mut offset = 0;
+                            for (span, _, key) in spans {
+                                for line in buf[offset..span.start].split_inclusive(|c| *c == b'\n') {
+                                    if !emit(&mut *output, &mut dedup, limiter, None, line)? {
+                                        return Ok(());
+                                    }
+                                }
+                                offset = span.end;
+                                if !emit(&mut *output, &mut dedup, limiter, key.map(|key| (i, key)), &buf[span])? {
+                                    return Ok(());
+                                }
+                            }
+                            for line in buf[offset..].split_inclusive(|c| *c == b'\n') {
+                                if !emit(&mut *output, &mut dedup, limiter, None, line)? {
+                                    return Ok(());
+                                }
+                            }
+                        }
+                        _ => output.write_all(&buf[..])?,
+                    }
                     bfo.recycle(buf);
+                    if let (Some(clipboard), Some(copied)) = (clipboard.as_mut(), copied) {
+                        clipboard.merge(&copied);
+                    }
                 }
+                finish(&mut *output, &mut dedup, &mut limiter)?;
                 Ok(())
             }));
             // collect errors from reader and writer threads
@@ -171,31 +645,178 @@ impl App {
         Ok(())
     }
 
-    fn sort(&self, inputs: Vec<InputHolder>, output: &mut Output) -> Result<()> {
-        let mut output = BufWriter::new(output);
-        let param_hash = hex::encode(self.parameters_hash()?);
-        let cache_dir = self
+    fn interactive(&self, inputs: Vec<InputHolder>, output: &mut Output) -> Result<()> {
+        let (files, threads) = self.index_concurrency(inputs.len());
+        let indexer = self.indexer()?.with_concurrency(threads);
+        let references = inputs
+            .iter()
+            .map(|x| (x.reference.clone(), x.decompression, x.mmap))
+            .collect_vec();
+        let open = |inputs: Vec<InputHolder>| -> Result<Document> {
+            let inputs = input::index_all(inputs, files, |_, x| x.index(&indexer))?;
+            Ok(Document::new(inputs.into_iter().map(Arc::new).collect()))
+        };
+        let document = open(inputs)?;
+        let viewer = Viewer::new(
+            document,
+            self.parser(),
+            self.formatter(),
+            &self.options.filter,
+            self.options.input_format,
+            self.options.text_format.clone(),
+        );
+        viewer.run(output, |document| {
+            // files that have only been appended to are indexed from where they were indexed up to,
+            // others are indexed again in full
+            let mut extended = Vec::with_capacity(references.len());
+            let mut reopened = Vec::new();
+            for ((reference, decompression, mmap), input) in references.iter().zip(document.inputs()) {
+                match input.extend(&indexer)? {
+                    Some(input) => extended.push(Some(Arc::new(input))),
+                    None => {
+                        extended.push(None);
+                        reopened.push(reference.hold()?.with_decompression(*decompression).with_mmap(*mmap));
+                    }
+                }
+            }
+            let mut reopened = input::index_all(reopened, files, |_, x| x.index(&indexer))?
+                .into_iter()
+                .map(Arc::new);
+            let inputs = extended.into_iter().flat_map(|input| input.or_else(|| reopened.next()));
+            Ok(Document::new(inputs.collect()))
+        })
+    }
+
+    /// Returns the index cache with its size limit.
+    pub(crate) fn index_cache(&self) -> IndexCache {
+        let dir = self
             .options
             .app_dirs
             .as_ref()
             .map(|dirs| dirs.cache_dir.clone())
-            .unwrap_or_else(|| PathBuf::from(".cache"))
-            .join(param_hash);
-        fs::create_dir_all(&cache_dir)?;
-        let indexer = Indexer::new(
+            .unwrap_or_else(|| PathBuf::from(".cache"));
+        IndexCache::new(dir).with_max_size(self.options.index_cache_max_size)
+    }
+
+    /// Removes the least recently used cached files exceeding the size limit of the index cache,
+    /// files used by this run are kept.
+    fn evict_cached(&self) {
+        if !self.options.index_cache {
+            return;
+        }
+        let since = SystemTime::now() - self.started.elapsed();
+        if let Err(err) = self.index_cache().evict(since) {
+            diagnostics::warning(format_args!("failed to clean up index cache: {}", err));
+        }
+    }
+
+    /// Indexes the inputs in the given formats, see [Self::input_formats], up to --index-concurrency of them at a time,
+    /// reporting the combined progress if --progress option is enabled.
+    fn index_inputs(&self, inputs: Vec<InputHolder>, formats: &[InputFormat], rebuild: bool) -> Result<Vec<IndexedInput>> {
+        let progress = self.options.progress.then(|| {
+            Arc::new(Progress::new(inputs.iter().filter_map(|x| x.size).sum()).with_files(inputs.len()))
+        });
+        let _reporter = progress.as_ref().map(|progress| progress.report());
+        let (files, threads) = self.index_concurrency(inputs.len());
+        let cache = self.index_cache_dir()?;
+        input::index_all(inputs, files, |i, x| {
+            let indexer = self
+                .indexer_in(cache.clone())?
+                .with_input_format(formats[i])
+                .with_rebuild(rebuild)
+                .with_concurrency(threads);
+            // each file is counted by a progress of its own, so that it is counted in full once it is done,
+            // even if it has not been read because of an up to date cached index
+            let file = progress
+                .as_ref()
+                .map(|progress| Arc::new(Progress::new(x.size.unwrap_or(0)).with_parent(progress.clone())));
+            let (x, indexer) = match &file {
+                Some(file) => (x.with_progress(file.clone()), indexer.with_progress(file.clone())),
+                None => (x, indexer),
+            };
+            let reference = x.reference.clone();
+            let result = x.index(&indexer).or_else(|err| {
+                // failed inputs are replaced with empty ones to keep indices of the rest of them
+                self.skip_failed_input(reference.description(), err)?;
+                IndexedInput::open_sequential(reference, Box::new(std::io::empty()), &indexer)
+            });
+            if let (Some(progress), Some(file)) = (&progress, &file) {
+                file.finish();
+                progress.file_done();
+            }
+            result
+        })
+    }
+
+    /// Returns the number of inputs indexed at the same time, see --index-concurrency option, and the number of threads
+    /// each of them is indexed with, so that together they use no more than the processing threads.
+    fn index_concurrency(&self, inputs: usize) -> (usize, usize) {
+        let files = self.options.index_concurrency.clamp(1, inputs.max(1));
+        (files, (self.options.concurrency / files).max(1))
+    }
+
+    /// Adds counts of records per level of the indexed inputs to the summary shown by --summary option,
+    /// along with their sizes, as they are read in full for indexing even if only some of their blocks are processed.
+    fn summarize_indexed<'a, I: IntoIterator<Item = &'a IndexedInput>>(&self, inputs: I) {
+        if self.options.summary {
+            let mut summary = self.summary.lock().unwrap();
+            for input in inputs {
+                summary.bytes += input.index.source().size;
+                summary.add_indexed(&input.index.source().stat);
+            }
+        }
+    }
+
+    fn indexer(&self) -> Result<Indexer> {
+        self.indexer_in(self.index_cache_dir()?)
+    }
+
+    /// Returns the directory of cached indexes for the current parameters and whether indexes are cached,
+    /// which they are not if the cache is disabled or the directory cannot be created.
+    fn index_cache_dir(&self) -> Result<(PathBuf, bool)> {
+        let param_hash = hex::encode(self.parameters_hash()?);
+        let cache_dir = self.index_cache().dir().join(param_hash);
+        let mut cache = self.options.index_cache;
+        if cache {
+            if let Err(err) = fs::create_dir_all(&cache_dir) {
+                diagnostics::warning(format_args!(
+                    "failed to create cache directory '{}': {}, falling back to in-memory indexing",
+                    cache_dir.display(),
+                    err
+                ));
+                cache = false;
+            }
+        }
+        Ok((cache_dir, cache))
+    }
+
+    fn indexer_in(&self, (cache_dir, cache): (PathBuf, bool)) -> Result<Indexer> {
+        Ok(Indexer::new(
             self.options.concurrency,
             NonZeroU32::try_from(self.options.buffer_size)?.try_into()?,
             NonZeroU32::try_from(self.options.max_message_size)?.try_into()?,
             cache_dir,
             &self.options.fields.settings.predefined,
-        );
+        )
+        .with_cache(cache)
+        .with_time_formats(self.options.time_formats.clone())
+        .with_input_format(self.options.input_format)
+        .with_text_format(self.options.text_format.clone())
+        .with_multiline(self.max_continuation_lines())
+        .with_max_stream_buffer_size(self.options.max_stream_buffer_size.get())
+        .with_cancellation(self.cancellation.clone())
+        .with_max_runtime(self.started, self.options.max_runtime))
+    }
 
+    fn sort(&self, inputs: Vec<InputHolder>, transformers: &[Transformer], output: &mut Output) -> Result<()> {
+        let mut output = BufWriter::new(output);
         let input_badges = self.input_badges(inputs.iter().map(|x| &x.reference));
+        let source_labels = self.source_labels(inputs.iter().map(|x| &x.reference));
+        let formats = self.input_formats(&inputs);
 
-        let inputs = inputs
-            .into_iter()
-            .map(|x| x.index(&indexer))
-            .collect::<Result<Vec<_>>>()?;
+        let inputs = self.index_inputs(inputs, &formats, false)?;
+        self.evict_cached();
+        self.summarize_indexed(&inputs);
 
         if self.options.dump_index {
             for input in inputs {
@@ -217,9 +838,80 @@ impl App {
             return Ok(());
         }
 
+        let inputs = inputs.into_iter().map(Arc::new).collect_vec();
+        let skip_before = self.tail_threshold(&inputs, transformers);
+        let prefixes = (&input_badges, &source_labels);
+        if !self.sort_blocks(&inputs, &formats, transformers, prefixes, skip_before, &mut output)? {
+            // some of the skipped blocks might have had the last records, so all blocks are processed again
+            self.sort_blocks(&inputs, &formats, transformers, prefixes, None, &mut output)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes matching records of blocks of the inputs in chronological order,
+    /// skipping blocks with all records older than the given timestamp if any.
+    ///
+    /// Returns false if the last records to show with --tail option might have been in the skipped blocks.
+    fn sort_blocks(
+        &self,
+        inputs: &[Arc<IndexedInput>],
+        formats: &[InputFormat],
+        transformers: &[Transformer],
+        (input_badges, source_labels): (&Option<Vec<String>>, &Option<Vec<String>>),
+        skip_before: Option<Timestamp>,
+        output: &mut BufWriter<&mut Output>,
+    ) -> Result<bool> {
+        // blocks without matching records are not skipped if records around matching ones are shown
+        let context = self.context();
+        let now = syslog::reference_time();
+        let sorted = inputs
+            .iter()
+            .map(|input| Blocks::new(input.clone(), 0..input.index.source().blocks.len()).sorted())
+            .collect();
+        let mut blocks = MergedBlocks::new(sorted)
+            .filter_map(|(block, i)| {
+                let src = block.source_block();
+                if src.stat.lines_valid == 0 {
+                    return None;
+                }
+                if let Some(level) = self.options.filter.level_bound().filter(|_| context.is_none()) {
+                    if !src.match_level(level) {
+                        return None;
+                    }
+                }
+                if let Some(query) = self.options.filter.query.as_ref().filter(|_| context.is_none()) {
+                    if !query.may_match_levels(|level| src.has_level(level)) {
+                        return None;
+                    }
+                }
+                let offset = block.offset();
+                src.stat
+                    .ts_min_max
+                    .map(|(ts_min, ts_max)| (block, ts_min, ts_max, i, offset))
+            })
+            .peekable();
+
+        // limit blocks and messages to the selected time window if any, blocks come ordered by their minimum timestamp
+        let window = self.options.window.and_then(|window| {
+            let origin = blocks.peek()?.1;
+            let start = origin.add(window.size * u32::try_from(window.page - 1).unwrap_or(u32::MAX));
+            Some((start, start.add(window.size)))
+        });
+        // skip blocks entirely outside of the time range filter if any
+        let since = self.options.filter.since.filter(|_| context.is_none()).map(Timestamp::from);
+        let until = self.options.filter.until.filter(|_| context.is_none()).map(Timestamp::from);
+        let blocks = blocks
+            .take_while(|block| window.map(|(_, end)| block.1 < end).unwrap_or(true))
+            .take_while(|block| until.map(|until| block.1 <= until).unwrap_or(true))
+            .filter(|block| window.map(|(start, _)| block.2 >= start).unwrap_or(true))
+            .filter(|block| since.map(|since| block.2 >= since).unwrap_or(true))
+            .filter(|block| skip_before.map(|ts| block.2 >= ts).unwrap_or(true));
+
         let n = self.options.concurrency;
         let parser = self.parser();
-        thread::scope(|scope| -> Result<()> {
+        let buf_pool = Arc::new(BufPool::new());
+        thread::scope(|scope| -> Result<bool> {
             // prepare transmit/receive channels for data produced by pusher thread
             let (txp, rxp): (Vec<_>, Vec<_>) = (0..n).map(|_| channel::bounded(1)).unzip();
             // prepare transmit/receive channels for data produced by worker threads
@@ -228,32 +920,11 @@ impl App {
                 .unzip();
             // spawn pusher thread
             let pusher = scope.spawn(closure!(|_| -> Result<()> {
-                let mut blocks: Vec<_> = inputs
-                    .into_iter()
-                    .enumerate()
-                    .map(|(i, input)| input.into_blocks().map(move |block| (block, i)))
-                    .flatten()
-                    .filter_map(|(block, i)| {
-                        let src = block.source_block();
-                        if src.stat.lines_valid == 0 {
-                            return None;
-                        }
-                        if let Some(level) = self.options.filter.level {
-                            if !src.match_level(level) {
-                                return None;
-                            }
-                        }
-                        let offset = block.offset();
-                        src.stat
-                            .ts_min_max
-                            .map(|(ts_min, ts_max)| (block, ts_min, ts_max, i, offset))
-                    })
-                    .collect();
-
-                blocks.sort_by(|a, b| (a.1, a.2, a.3, a.4).partial_cmp(&(b.1, b.2, b.3, b.4)).unwrap());
-
                 let mut output = StripedSender::new(txp);
                 for (j, (block, ts_min, _, i, _)) in blocks.into_iter().enumerate() {
+                    if self.stopped() {
+                        break;
+                    }
                     if output.send((block, ts_min, i, j)).is_none() {
                         break;
                     }
@@ -263,35 +934,137 @@ impl App {
             // spawn worker threads
             let mut workers = Vec::with_capacity(n);
             for (rxp, txw) in izip!(rxp, txw) {
-                workers.push(scope.spawn(closure!(ref parser, |_| -> Result<()> {
+                workers.push(scope.spawn(closure!(ref parser, ref buf_pool, |_| -> Result<()> {
                     let mut formatter = self.formatter();
                     for (block, ts_min, i, j) in rxp.iter() {
+                        let mut summary = Summary {
+                            series: self.options.tracking.as_ref().map(Series::new),
+                            ..Default::default()
+                        };
                         let mut buf = Vec::with_capacity(2 * usize::try_from(block.size())?);
                         let mut items = Vec::with_capacity(2 * usize::try_from(block.lines_valid())?);
-                        for line in block.into_lines()? {
+                        let mut copied = self.options.copy.map(Clipboard::new);
+                        let mut normalized = Vec::new();
+                        let mut transformed = Vec::new();
+                        // level, rendered item and number of attached lines of the last record
+                        let mut parent: Option<(Option<Level>, Option<usize>, usize)> = None;
+                        let max_continuation_lines = self.max_continuation_lines();
+                        let block_offset = block.offset();
+                        let origin = bugreport::origin(inputs[i].reference.description(), block_offset);
+                        // an empty block is still sent if reading fails, so that the merger gets all blocks in order
+                        let lines = match block.with_buf_pool(buf_pool.clone()).into_lines() {
+                            Ok(lines) => Some(lines),
+                            Err(err) => {
+                                self.skip_failed_input(inputs[i].reference.description(), err.into())?;
+                                None
+                            }
+                        };
+                        for line in lines.into_iter().flatten() {
                             if line.len() == 0 {
                                 continue;
                             }
-                            if let Ok(record) = json::from_slice(line.bytes()) {
-                                let record = parser.parse(record);
-                                if record.matches(&self.options.filter) {
-                                    let offset = buf.len();
-                                    formatter.format_record(&mut buf, &record);
-                                    if let Some(ts) = record.ts {
-                                        if let Some(unix_ts) = ts.unix_utc() {
-                                            items.push((unix_ts.into(), offset..buf.len()));
+                            origin.set_offset(block_offset + line.offset() as u64);
+                            let _watch = bugreport::watch(line.bytes());
+                            let prefilter = self.prefilter.as_ref().filter(|_| transformers[i].is_empty());
+                            if prefiltered(prefilter, line.bytes(), max_continuation_lines.is_some()) {
+                                parent = Some((None, None, 0));
+                                summary.skipped += 1;
+                                continue;
+                            }
+                            let data = logfmt::normalize_with(formats[i], &self.options.text_format, now, line.bytes(), &mut normalized);
+                            let data = if !transformers[i].is_empty() && transformers[i].apply(data, &mut transformed) {
+                                &transformed[..]
+                            } else {
+                                data
+                            };
+                            // exploded records come separated by newlines
+                            for data in rtrim(data, b'\n').split(|c| *c == b'\n') {
+                                if let Ok(record) = json::from_slice(data) {
+                                    summary.parsed += 1;
+                                    let record = parser.parse(record);
+                                    parent = Some((record.level, None, 0));
+                                    let matched = record.matches(&self.options.filter);
+                                    if matched || context.is_some() {
+                                        if matched {
+                                            summary.matched += 1;
+                                        }
+                                        let offset = buf.len();
+                                        match &self.exporter {
+                                            None => {
+                                                if let Some(labels) = source_labels {
+                                                    // the same location as in the unsorted output, line numbers are not kept in the index
+                                                    let location = block_offset + line.offset() as u64;
+                                                    write!(&mut buf, "{}@{} ", labels[i], location)?;
+                                                }
+                                                formatter.format_record(&mut buf, &record);
+                                                if let Some(validator) = self.options.schema.as_ref().filter(|_| matched) {
+                                                    summary.add_violations(validator.validate(data, &mut buf));
+                                                }
+                                            }
+                                            Some(exporter) => exporter.export(&mut buf, &record, trim(data)),
+                                        }
+                                        if let Some(ts) = record.ts {
+                                            if let Some(unix_ts) = ts.unix_utc() {
+                                                let ts: Timestamp = unix_ts.into();
+                                                if window.map(|(start, end)| ts >= start && ts < end).unwrap_or(true) {
+                                                    let copy_offset = copied.as_ref().map_or(0, |c| c.data().len());
+                                                    if let Some(copied) = copied.as_mut().filter(|_| matched) {
+                                                        copied.push(&buf[offset..], trim(data));
+                                                    }
+                                                    let copy_end = copied.as_ref().map_or(0, |c| c.data().len());
+                                                    let key = self.dedup().map(|dedup| dedup.key(&record));
+                                                    items.push((ts, offset..buf.len(), copy_offset..copy_end, matched, key));
+                                                    parent = Some((record.level, Some(items.len() - 1), 0));
+                                                    if matched {
+                                                        summary.rendered += 1;
+                                                        summary.add_ts(ts);
+                                                        if let Some(series) = &mut summary.series {
+                                                            if let Some(value) = record.number(series.key()) {
+                                                                series.add(ts, value);
+                                                            }
+                                                        }
+                                                    }
+                                                } else {
+                                                    buf.truncate(offset);
+                                                }
+                                            } else {
+                                                buf.truncate(offset);
+                                                if matched {
+                                                    diagnostics::warning(format_args!("skipped message because timestamp cannot be parsed: {:#?}", ts))
+                                                }
+                                            }
                                         } else {
-                                            eprintln!("skipped message because timestamp cannot be parsed: {:#?}", ts)
+                                            buf.truncate(offset);
+                                            if matched {
+                                                diagnostics::warning(format_args!("skipped message with missing timestamp"))
+                                            }
+                                        }
+                                    }
+                                } else if let Some((level, item, count)) = parent.as_mut().filter(|(_, _, count)| {
+                                    max_continuation_lines.map_or(false, |max| *count < max)
+                                        && self.exporter.is_none()
+                                        && !binary::is_binary(data)
+                                }) {
+                                    *count += 1;
+                                    if let Some(item) = item.and_then(|item| items.get_mut(item)) {
+                                        if item.1.end == buf.len() {
+                                            formatter.format_continuation(&mut buf, level, data);
+                                            item.1.end = buf.len();
                                         }
-                                    } else {
-                                        eprintln!("skipped message with missing timestamp")
                                     }
+                                } else {
+                                    parent = None;
+                                    summary.invalid += 1;
                                 }
                             }
                         }
+                        if self.summary_enabled() || self.options.tracking.is_some() {
+                            self.summary.lock().unwrap().merge(&summary);
+                        }
 
                         let buf = Arc::new(buf);
-                        if txw.send((OutputBlock { ts_min, buf, items }, i, j)).is_err() {
+                        let copied = Arc::new(copied.map(Clipboard::into_data).unwrap_or_default());
+                        if txw.send((OutputBlock { ts_min, buf, items, copied }, i, j)).is_err() {
                             break;
                         }
                     }
@@ -299,11 +1072,19 @@ impl App {
                 })));
             }
             // spawn merger thread
-            let merger = scope.spawn(|_| -> Result<()> {
+            let merger = scope.spawn(|_| -> Result<bool> {
                 let mut input = StripedReceiver::new(rxw);
                 let (mut tsi, mut tso) = (None, None);
+                let mut prev_ts: Option<Timestamp> = None;
                 let mut workspace = Vec::new();
                 let mut done = false;
+                let mut clipboard = self.clipboard.lock().unwrap();
+                let mut selector = self.context_selector();
+                let mut limiter = self.options.limit.map(RecordLimiter::new);
+                let mut dedup = self.dedup().map(|_| Deduplicator::new());
+                // number of records not older than the oldest records of the blocks that are not skipped
+                let mut recent = 0;
+                let mut record = Vec::new();
 
                 // Workspace rules
                 // 1. Can process messages up to max `ts_min` of the blocks in workspace
@@ -338,36 +1119,322 @@ impl App {
                     if tso >= tsi && !done {
                         continue;
                     }
-                    if let Some(badges) = &input_badges {
-                        output.write_all(&badges[item.2].as_bytes())?;
+                    if let Some(speed) = self.options.replay {
+                        if let Some(prev) = prev_ts.filter(|prev| ts > *prev) {
+                            output.flush()?;
+                            std::thread::sleep((ts - prev).div_f64(speed));
+                        }
+                        prev_ts = Some(ts);
+                    }
+                    match (&mut selector, &mut limiter) {
+                        (None, None) if dedup.is_none() => {
+                            if let Some(badges) = input_badges {
+                                output.write_all(&badges[item.2].as_bytes())?;
+                            }
+                            output.write_all((item.0).1.bytes())?;
+                        }
+                        (Some(selector), _) => {
+                            record.clear();
+                            if let Some(badges) = input_badges {
+                                record.extend_from_slice(&badges[item.2].as_bytes());
+                            }
+                            record.extend_from_slice((item.0).1.bytes());
+                            selector.push(&mut *output, &record, (item.0).3)?;
+                        }
+                        (None, limiter) => {
+                            record.clear();
+                            if let Some(badges) = input_badges {
+                                record.extend_from_slice(&badges[item.2].as_bytes());
+                            }
+                            record.extend_from_slice((item.0).1.bytes());
+                            if skip_before.map(|skip_before| ts >= skip_before).unwrap_or(false) {
+                                recent += 1;
+                            }
+                            if !emit(&mut *output, &mut dedup, limiter, (item.0).4.map(|key| (item.2, key)), &record)? {
+                                return Ok(true);
+                            }
+                        }
+                    }
+                    if let Some(clipboard) = clipboard.as_mut().filter(|_| (item.0).3) {
+                        clipboard.push_rendered((item.0).2.bytes());
                     }
-                    output.write_all((item.0).1.bytes())?;
                     match item.1.next() {
                         Some(head) => item.0 = head,
                         None => drop(workspace.swap_remove(k)),
                     }
                 }
 
-                Ok(())
+                if let (Some(_), Some(Limit::Tail(n))) = (skip_before, self.options.limit) {
+                    if recent < n {
+                        return Ok(false);
+                    }
+                }
+                finish(&mut *output, &mut dedup, &mut limiter)?;
+                Ok(true)
             });
 
             pusher.join().unwrap()?;
             for worker in workers {
                 worker.join().unwrap()?;
             }
-            merger.join().unwrap()?;
+            merger.join().unwrap()
+        })
+        .unwrap()
+    }
 
-            Ok(())
+    /// Returns the timestamp all records that may be shown with --tail option are not older than,
+    /// estimated by numbers of lines in blocks of the index, if each of the lines is going to be shown as a record.
+    fn tail_threshold(&self, inputs: &[Arc<IndexedInput>], transformers: &[Transformer]) -> Option<Timestamp> {
+        let n = match self.options.limit {
+            Some(Limit::Tail(n)) => u64::try_from(n).ok()?,
+            _ => return None,
+        };
+        if !self.options.filter.is_empty()
+            || self.options.window.is_some()
+            || self.summary_enabled()
+            || self.options.tracking.is_some()
+            || self.dedup().is_some()
+            || transformers.iter().any(|transformer| !transformer.is_empty())
+        {
+            return None;
+        }
+        let mut blocks = inputs
+            .iter()
+            .flat_map(|input| input.index.source().blocks.iter())
+            .filter_map(|block| block.stat.ts_min_max.map(|(ts_min, _)| (ts_min, block.stat.lines_valid)))
+            .collect_vec();
+        blocks.sort_by_key(|&(ts_min, _)| Reverse(ts_min));
+        let mut count = 0;
+        for (ts_min, lines) in blocks {
+            count += lines;
+            if count >= n {
+                return Some(ts_min);
+            }
+        }
+        None
+    }
+
+    /// Writes statistics of matching records instead of the records, computed in parallel over blocks of the inputs.
+    fn stats(
+        &self,
+        inputs: Vec<InputHolder>,
+        transformers: &[Transformer],
+        options: &StatsOptions,
+        output: &mut Output,
+    ) -> Result<()> {
+        let formats = self.input_formats(&inputs);
+        let now = syslog::reference_time();
+        let inputs = self.index_inputs(inputs, &formats, false)?.into_iter().map(Arc::new).collect_vec();
+        self.evict_cached();
+        self.summarize_indexed(inputs.iter().map(|x| x.as_ref()));
+
+        // without filtering, records are counted by the index and the inputs are not read again
+        if options.by.is_none() && self.options.filter.is_empty() && transformers.iter().all(|x| x.is_empty()) {
+            let mut stats = Stats::default();
+            let mut summary = Summary::default();
+            for input in &inputs {
+                let source = input.index.source();
+                for block in &source.blocks {
+                    stats.add_indexed(&block.stat);
+                }
+                summary.parsed += source.stat.records;
+                summary.invalid += source.stat.lines_invalid;
+                summary.matched += source.stat.records;
+                if let Some((min, max)) = source.stat.ts_min_max {
+                    summary.add_ts(min);
+                    summary.add_ts(max);
+                }
+            }
+            if self.summary_enabled() {
+                self.summary.lock().unwrap().merge(&summary);
+            }
+            let mut output = BufWriter::new(output);
+            stats.print(&mut output, options)?;
+            output.flush()?;
+            return Ok(());
+        }
+
+        // skip blocks that cannot have matching records according to the index
+        let level = self.options.filter.level_bound();
+        let since = self.options.filter.since.map(Timestamp::from);
+        let until = self.options.filter.until.map(Timestamp::from);
+        let (tx, rx) = channel::unbounded();
+        for (i, input) in inputs.iter().enumerate() {
+            for (j, src) in input.index.source().blocks.iter().enumerate() {
+                if src.stat.lines_valid == 0 || level.map_or(false, |level| !src.match_level(level)) {
+                    continue;
+                }
+                if let Some(query) = &self.options.filter.query {
+                    if !query.may_match_levels(|level| src.has_level(level)) {
+                        continue;
+                    }
+                }
+                if let Some((ts_min, ts_max)) = src.stat.ts_min_max {
+                    if since.map_or(false, |since| ts_max < since) || until.map_or(false, |until| ts_min > until) {
+                        continue;
+                    }
+                }
+                tx.send((i, j)).ok();
+            }
+        }
+        drop(tx);
+
+        let parser = self.parser();
+        let buf_pool = Arc::new(BufPool::new());
+        let max_continuation_lines = self.max_continuation_lines();
+        let stats = thread::scope(|scope| -> Result<Stats> {
+            let workers = (0..self.options.concurrency)
+                .map(|_| {
+                    scope.spawn(closure!(ref parser, ref buf_pool, ref inputs, ref formats, ref rx, |_| -> Result<Stats> {
+                        let mut stats = Stats::default();
+                        let mut summary = Summary::default();
+                        let mut normalized = Vec::new();
+                        let mut transformed = Vec::new();
+                        for (i, j) in rx.iter() {
+                            if self.stopped() {
+                                break;
+                            }
+                            let block = Block::new(inputs[i].clone(), j).with_buf_pool(buf_pool.clone());
+                            for line in block.into_lines()? {
+                                if line.len() == 0 {
+                                    continue;
+                                }
+                                let prefilter = self.prefilter.as_ref().filter(|_| transformers[i].is_empty());
+                                if prefiltered(prefilter, line.bytes(), max_continuation_lines.is_some()) {
+                                    summary.skipped += 1;
+                                    continue;
+                                }
+                                let data = logfmt::normalize_with(formats[i], &self.options.text_format, now, line.bytes(), &mut normalized);
+                                let data = if !transformers[i].is_empty() && transformers[i].apply(data, &mut transformed) {
+                                    &transformed[..]
+                                } else {
+                                    data
+                                };
+                                for data in rtrim(data, b'\n').split(|c| *c == b'\n') {
+                                    let record = match json::from_slice(data) {
+                                        Ok(record) => parser.parse(record),
+                                        Err(_) => {
+                                            summary.invalid += 1;
+                                            continue;
+                                        }
+                                    };
+                                    summary.parsed += 1;
+                                    if !record.matches(&self.options.filter) {
+                                        continue;
+                                    }
+                                    summary.matched += 1;
+                                    let ts = record.ts.as_ref().and_then(|ts| ts.unix_utc()).map(Timestamp::from);
+                                    if let Some(ts) = ts {
+                                        summary.add_ts(ts);
+                                    }
+                                    let value = options.by.as_deref().and_then(|key| record.value(key)).map(|value| {
+                                        let s = value.get();
+                                        if s.starts_with('"') {
+                                            json::from_str(s).unwrap_or(Cow::Borrowed(s))
+                                        } else {
+                                            Cow::Borrowed(s)
+                                        }
+                                    });
+                                    stats.add(record.level, ts, value.as_deref());
+                                }
+                            }
+                        }
+                        if self.summary_enabled() {
+                            self.summary.lock().unwrap().merge(&summary);
+                        }
+                        Ok(stats)
+                    }))
+                })
+                .collect_vec();
+            let mut result = Stats::default();
+            for worker in workers {
+                result.merge(worker.join().unwrap()?);
+            }
+            Ok(result)
         })
         .unwrap()?;
 
+        let mut output = BufWriter::new(output);
+        stats.print(&mut output, options)?;
+        output.flush()?;
         Ok(())
     }
 
-    fn follow(&self, inputs: Vec<InputReference>, output: &mut Output) -> Result<()> {
-        let input_badges = self.input_badges(inputs.iter());
+    fn split(&self, inputs: Vec<InputHolder>, transformers: &[Transformer], width: usize, output: &mut Output) -> Result<()> {
+        let parser = self.parser();
+        let mut formatter = self.formatter();
+        let formats = self.input_formats(&inputs);
+        let sfi = Arc::new(SegmentBufFactory::new(self.options.buffer_size.try_into()?));
+        let scanner = Scanner::new(sfi, "\n".to_string()).with_continuations(self.options.multiline.is_some());
+        let mut streams = inputs
+            .into_iter()
+            .map(|input| Ok(input.open()?.stream))
+            .collect::<Result<Vec<_>>>()?;
+        let mut segments = streams
+            .iter_mut()
+            .map(|stream| scanner.items(stream).with_max_segment_size(self.options.max_message_size.into()))
+            .collect_vec();
+
+        let separator = " \u{2502} ";
+        let column = width.saturating_sub(separator.chars().count()) / 2;
+        let blank = " ".repeat(column);
+
+        // inputs are read one segment at a time and merged by timestamps of their lines, so that they are not kept
+        // in memory, lines of a segment are ordered by their timestamps but inputs are expected to be chronological
+        let mut pending = segments.iter().map(|_| VecDeque::new()).collect_vec();
+        loop {
+            for (i, items) in segments.iter_mut().enumerate() {
+                while pending[i].is_empty() {
+                    let segment = match items.next() {
+                        Some(segment) => segment?,
+                        None => break,
+                    };
+                    let data = match &segment {
+                        Segment::Complete(segment) | Segment::Incomplete(segment, _) => segment.data(),
+                    };
+                    let mut buf = Vec::new();
+                    let mut index_builder = TimestampIndexBuilder::new(i, false);
+                    SegmentProcessor::new(&parser, &mut formatter, &self.options.filter)
+                        .with_input_format(formats[i])
+                        .with_text_format(&self.options.text_format)
+                        .with_multiline(self.max_continuation_lines())
+                        .with_prefilter(self.prefilter.as_ref())
+                        .with_transformer(&transformers[i])
+                        .with_skip_binary(self.options.skip_binary)
+                        .run(data, &mut buf, "", &mut index_builder);
+                    let buf = Rc::new(buf);
+                    let mut lines = index_builder.result.lines;
+                    lines.sort_by_key(|line| line.ts);
+                    pending[i].extend(lines.into_iter().map(|line| (line.ts, buf.clone(), line.location)));
+                }
+            }
+            // the left input goes first if timestamps are equal
+            let next = (0..pending.len())
+                .filter(|&i| !pending[i].is_empty())
+                .min_by_key(|&i| pending[i][0].0);
+            let (i, (_, buf, location)) = match next.and_then(|i| Some((i, pending[i].pop_front()?))) {
+                Some(line) => line,
+                None => break,
+            };
+            let text = String::from_utf8_lossy(&buf[location]);
+            for line in text.trim_end_matches('\n').split('\n') {
+                for row in columns::wrap(line, column) {
+                    let (left, right) = if i == 0 { (&row, &blank) } else { (&blank, &row) };
+                    writeln!(output, "{}{}{}", left, separator, right.trim_end())?;
+                }
+            }
+        }
+        Ok(())
+    }
 
+    fn follow(&self, inputs: Vec<InputReference>, transformers: &[Transformer], output: &mut Output) -> Result<()> {
         let m = inputs.len();
+        let watch = &self.options.watch;
+        let watch_transformers = watch.iter().map(|w| self.transformer(&w.tags)).collect_vec();
+        let transformers = transformers.iter().chain(watch_transformers.iter()).collect_vec();
+        let patterns = watch.iter().map(|w| InputReference::File(w.pattern.path())).collect_vec();
+        let input_badges = self.input_badges(inputs.iter().chain(patterns.iter()));
+
         let n = self.options.concurrency;
         let parser = self.parser();
         let sfi = Arc::new(SegmentBufFactory::new(self.options.buffer_size.try_into()?));
@@ -377,111 +1444,134 @@ impl App {
             let (txi, rxi) = channel::bounded(1);
             // prepare receive/transmit channels for output data
             let (txo, rxo) = channel::bounded(1);
+            // prepare channel for offsets of followed files to record in the checkpoint
+            let (txc, rxc) = channel::unbounded();
+            let feed = Feed {
+                sfi: sfi.clone(),
+                segments: txi,
+                sequence: Arc::new(AtomicUsize::new(0)),
+                commits: txc,
+            };
             // spawn reader threads
-            let mut readers = Vec::with_capacity(m);
+            let mut readers = Vec::with_capacity(m + 1);
+            let mut known = HashSet::new();
             for (i, input_ref) in inputs.into_iter().enumerate() {
-                let reader = scope.spawn(closure!(clone sfi, clone txi, |_| -> Result<()> {
-                    let scanner = Scanner::new(sfi.clone(), "\n".to_string());
-                    let mut meta = None;
-                    if let InputReference::File(filename) = &input_ref { 
-                        meta = Some(fs::metadata(filename)?);
-                    }
-                    let mut input = Some(input_ref.open()?);
-                    let is_file = |meta: &Option<fs::Metadata>| meta.as_ref().map(|m|m.is_file()).unwrap_or(false);
-                    let process = |input: &mut Option<Input>, is_file: bool| {
-                        if let Some(input) = input {
-                            for (j, item) in scanner.items(&mut input.stream).with_max_segment_size(self.options.max_message_size.into()).enumerate() {
-                                if txi.send((i, j, item?)).is_err() {
-                                    break;
+                if let InputReference::File(path) = &input_ref {
+                    known.insert(path.clone());
+                }
+                let reader = scope.spawn(closure!(clone feed, |_| {
+                    let description = input_ref.description();
+                    self.follow_input(i, i, input_ref, &feed).or_else(|err| self.skip_failed_input(description, err))
+                }));
+                readers.push(reader);
+            }
+            // spawn discovery thread watching for new files
+            if !watch.is_empty() {
+                let discovery = scope.spawn(closure!(clone feed, |scope| -> Result<()> {
+                    let mut next = m;
+                    let mut discover = || -> Result<()> {
+                        for (k, w) in watch.iter().enumerate() {
+                            for path in w.pattern.scan()? {
+                                if !known.insert(path.clone()) {
+                                    continue;
                                 }
+                                diagnostics::notice(format_args!("following new file {}", path.display()));
+                                let i = next;
+                                next += 1;
+                                let input_ref = InputReference::File(path);
+                                scope.spawn(closure!(clone feed, |_| {
+                                    if let Err(err) = self.follow_input(i, m + k, input_ref, &feed) {
+                                        diagnostics::warning(format_args!("stopped following new file: {}", err));
+                                    }
+                                }));
                             }
-                            Ok(!is_file)
-                        } else {
-                            Ok(false)
                         }
+                        Ok(())
                     };
-                    if let InputReference::File(filename) = &input_ref {
-                        if process(&mut input, is_file(&meta))? {
-                            return Ok(())
-                        }
-                        fsmon::run(vec![filename.clone()], |event| {
-                            match event.kind {
-                                EventKind::Modify(_) | EventKind::Create(_) | EventKind::Any | EventKind::Other => {
-                                    if let (Some(old_meta), Ok(new_meta)) = (&meta, fs::metadata(&filename)) {
-                                        if old_meta.len() > new_meta.len() {
-                                            input = None;
-                                        }
-                                        #[cfg(unix)]
-                                        if old_meta.ino() != new_meta.ino() || old_meta.dev() != new_meta.dev() {
-                                            input = None;
-                                        }
-                                        meta = Some(new_meta);
-                                    }
-                                    if input.is_none() {
-                                        input = input_ref.open().ok();
-                                    }
-                                    if process(&mut input, is_file(&meta))? {
-                                        return Ok(())
-                                    }
-                                    Ok(())
-                                }
-                                EventKind::Remove(_) => {
-                                    input = None;
-                                    Ok(())
-                                },
-                                EventKind::Access(_) => Ok(()),
-                            }
-                        })
-                    } else {
-                        process(&mut input, is_file(&meta)).map(|_|())
-                    }
+                    discover()?;
+                    let dirs = watch.iter().map(|w| w.pattern.dir().to_owned()).collect();
+                    fsmon::watch_dirs(dirs, || self.stopped(), |event| match event.kind {
+                        EventKind::Access(_) => Ok(()),
+                        _ => discover(),
+                    })
                 }));
-                readers.push(reader);
+                readers.push(discovery);
             }
-            drop(txi);
-
+            drop(feed);
 
             // spawn processing threads
             let mut workers = Vec::with_capacity(n);
             for _ in 0..n {
                 let worker = scope.spawn(closure!(ref bfo, ref parser, ref sfi, ref input_badges, clone rxi, clone txo, |_| {
                     let mut formatter = self.formatter();
-                    let mut processor = SegmentProcessor::new(&parser, &mut formatter, &self.options.filter);
-                    for (i, j, segment) in rxi.iter() {
-                        let prefix = input_badges.as_ref().map(|b|b[i].as_str()).unwrap_or("");
+                    for (i, s, j, seq, (description, offset), segment) in rxi.iter() {
+                        let prefix = input_badges.as_ref().map(|b|b[s].as_str()).unwrap_or("");
                         match segment {
                             Segment::Complete(segment) => {
                                 let mut buf = bfo.new_buf();
-                                let mut index_builder = TimestampIndexBuilder{result: TimestampIndex::new(j)};
+                                let mut index_builder = TimestampIndexBuilder::new(j, self.options.snapshot.is_some() || self.options.copy.is_some());
+                                let mut processor = SegmentProcessor::new(&parser, &mut formatter, &self.options.filter)
+                                    .with_input_format(self.options.input_format)
+                                    .with_text_format(&self.options.text_format)
+                                    .with_multiline(self.max_continuation_lines())
+                                    .with_prefilter(self.prefilter.as_ref())
+                                    .with_transformer(transformers[s])
+                                    .with_summary(self.summary_enabled())
+                                    .with_tracking(self.options.tracking.as_ref())
+                                    .with_skip_binary(self.options.skip_binary)
+                                    .with_validator(self.options.schema.as_deref())
+                                    .with_exporter(self.exporter.as_ref());
+                                let _origin = bugreport::origin(description, offset);
                                 processor.run(segment.data(), &mut buf, prefix, &mut index_builder);
+                                if let Some(summary) = processor.summary() {
+                                    self.summary.lock().unwrap().merge(summary);
+                                }
                                 sfi.recycle(segment);
-                                if txo.send((i, buf, index_builder.result)).is_err() {
+                                if txo.send((i, buf, index_builder.result, seq)).is_err() {
+                                    return;
+                                };
+                            }
+                            Segment::Incomplete(_, _) => {
+                                // nothing is shown, but the merger has to know the segment is done with
+                                if txo.send((i, bfo.new_buf(), TimestampIndex::new(j), seq)).is_err() {
                                     return;
                                 };
                             }
-                            Segment::Incomplete(_, _) => {}
                         }
                     }
                 }));
                 workers.push(worker);
             }
+            drop(rxi);
             drop(txo);
 
             // spawn merger thread
             let merger = scope.spawn(move |_| -> Result<()> {
-                type Key = (Timestamp, usize, usize, usize); // (ts, input, block, offset)
-                type Line = (Rc<Vec<u8>>, Range<usize>, Instant); // (buf, location, instant)
-               
+                type Key = (Timestamp, usize, usize, usize); // (ts, input, segment, offset)
+                type Line = (Rc<Vec<u8>>, Range<usize>, Instant, Vec<u8>); // (buf, location, instant, source)
+
+                let mut snapshot = self.options.snapshot.clone().map(Snapshot::new).transpose()?;
+                let mut clipboard = self.clipboard.lock().unwrap();
                 let mut window = BTreeMap::<Key,Line>::new();
                 let mut last_ts: Option<Timestamp> = None;
                 let mut prev_ts: Option<Timestamp> = None;
                 let mut mem_usage = 0;
+                let mut reported = None;
                 let mem_limit = n * usize::from(self.options.buffer_size);
+                let mut next_beat = self.options.heartbeat.map(|interval| Instant::now() + interval);
+                let mut beat_due = false;
+                // number of lines in the window by sequence number of the segment they come from
+                let mut segments = BTreeMap::<usize, usize>::new();
+                // number of leading segments shown completely
+                let mut shown = 0;
+                let mut commits = Commits::new(self.options.checkpoint.as_deref(), rxc);
 
                 loop {
+                    // messages received so far are shown without waiting for synchronization when stopping
+                    let stopping = self.stopped();
                     let deadline = Instant::now().checked_sub(self.options.sync_interval);
                     while let Some(first) = window.first_key_value() {
-                        if deadline.map(|deadline| first.1.2 > deadline).unwrap_or(true) && mem_usage < mem_limit {
+                        if !stopping && deadline.map(|deadline| first.1.2 > deadline).unwrap_or(true) && mem_usage < mem_limit {
                             break;
                         }
                         if let Some(entry) = window.pop_first() {
@@ -494,6 +1584,53 @@ impl App {
                             mem_usage -= entry.1.1.end - entry.1.1.start;
                             output.write_all(sync_indicator.value.as_bytes())?;
                             output.write_all(&entry.1.0[entry.1.1.clone()])?;
+                            beat_due = true;
+                            if let Some(snapshot) = &mut snapshot {
+                                snapshot.push(&entry.1.0[entry.1.1.clone()], &entry.1.3);
+                            }
+                            if let Some(clipboard) = clipboard.as_mut() {
+                                clipboard.push(&entry.1.0[entry.1.1.clone()], &entry.1.3);
+                            }
+                            if let Some(lines) = segments.get_mut(&entry.0.2) {
+                                *lines -= 1;
+                            }
+                        }
+                    }
+                    while segments.first_key_value() == Some((&shown, &0)) {
+                        segments.pop_first();
+                        shown += 1;
+                    }
+                    commits.apply(shown, stopping, output)?;
+
+                    if stopping {
+                        break;
+                    }
+
+                    if let Some(beat) = next_beat.filter(|beat| Instant::now() >= *beat) {
+                        // markers are not repeated while nothing is shown
+                        if beat_due {
+                            self.heartbeat(output)?;
+                            beat_due = false;
+                        }
+                        next_beat = self.options.heartbeat.map(|interval| max(beat + interval, Instant::now()));
+                    }
+
+                    if self.options.tracking.is_some() {
+                        let status = self.summary.lock().unwrap().series.as_ref().and_then(|series| {
+                            let bucket = series.completed().filter(|bucket| Some(*bucket) > reported)?;
+                            reported = Some(bucket);
+                            Some(series.status(bucket))
+                        });
+                        if let Some(status) = status {
+                            output.flush()?;
+                            diagnostics::progress(format_args!("{}", status));
+                        }
+                    }
+
+                    if let Some(snapshot) = snapshot.as_ref().filter(|snapshot| snapshot.requested()) {
+                        match snapshot.save() {
+                            Ok(path) => diagnostics::info(format_args!("saved snapshot to {}", path.display())),
+                            Err(err) => diagnostics::warning(format_args!("failed to save snapshot: {}", err)),
                         }
                     }
 
@@ -503,14 +1640,25 @@ impl App {
                     } else {
                         None
                     };
-                    match rxo.recv_timeout(timeout.unwrap_or(std::time::Duration::MAX)) {
-                        Ok((i, buf, index)) => {
+                    let wait = match (timeout, &snapshot) {
+                        (Some(timeout), _) => timeout,
+                        (None, Some(_)) => SNAPSHOT_POLL_INTERVAL,
+                        (None, None) => Duration::MAX,
+                    };
+                    let wait = match next_beat {
+                        Some(beat) => min(wait, beat.saturating_duration_since(Instant::now())),
+                        None => wait,
+                    };
+                    let wait = min(wait, cancellation::POLL_INTERVAL);
+                    match rxo.recv_timeout(wait) {
+                        Ok((i, buf, index, seq)) => {
                             let buf = Rc::new(buf);
+                            segments.insert(seq, index.lines.len());
                             for line in index.lines {
                                 last_ts = Some(last_ts.map(|last_ts| std::cmp::max(last_ts, line.ts)).unwrap_or(line.ts));
                                 mem_usage += line.location.end - line.location.start;
-                                let key = (line.ts, i, index.block, line.location.start);
-                                let value = (buf.clone(), line.location, Instant::now());
+                                let key = (line.ts, i, seq, line.location.start);
+                                let value = (buf.clone(), line.location, Instant::now(), line.source);
                                 window.insert(key, value);
                             }
                         }
@@ -522,25 +1670,260 @@ impl App {
                         }
                     }
                 }
+                commits.apply(shown, true, output)
+            });
+
+            for reader in readers {
+                reader.join().unwrap()?;
+            }
+
+            for worker in workers {
+                worker.join().unwrap();
+            }
+
+            merger.join().unwrap()?;
+
+            Ok(())
+        })
+        .unwrap()?;
+
+        Ok(())
+    }
+
+    /// Writes a line with the current time, see --heartbeat option.
+    fn heartbeat(&self, output: &mut Output) -> Result<()> {
+        let formatter = DateTimeFormatter::new(self.options.time_format.clone(), self.options.time_zone)
+            .with_locale(self.options.locale);
+        let now: DateTime<FixedOffset> = Utc::now().into();
+        let mut buf = Vec::new();
+        self.options.theme.apply(&mut buf, &None, |s| {
+            s.element(Element::Time, |s| {
+                s.batch(|buf| {
+                    buf.extend_from_slice(b"--- ");
+                    formatter.format(buf, now);
+                    buf.extend_from_slice(b" ---");
+                })
+            })
+        });
+        buf.push(b'\n');
+        output.write_all(&buf)?;
+        Ok(())
+    }
+
+    fn follow_input(
+        &self,
+        i: usize,
+        s: usize,
+        input_ref: InputReference,
+        feed: &Feed,
+    ) -> Result<()> {
+        let scanner =
+            Scanner::new(feed.sfi.clone(), "\n".to_string()).with_continuations(self.options.multiline.is_some());
+        // number of bytes read after the last newline, which are read again by the next run
+        let tail = Cell::new(0);
+        // offset of the next segment in the input
+        let read = Cell::new(0);
+        let description = input_ref.description();
+        let process = |input: &mut Input| -> Result<bool> {
+            for (j, item) in scanner.items(&mut input.stream).with_max_segment_size(self.options.max_message_size.into()).enumerate() {
+                if self.stopped() {
+                    return Ok(true);
+                }
+                let item = item?;
+                let data = match &item {
+                    Segment::Complete(segment) | Segment::Incomplete(segment, _) => segment.data(),
+                };
+                tail.set(match data.iter().rposition(|c| *c == b'\n') {
+                    Some(k) => (data.len() - k - 1) as u64,
+                    None => tail.get() + data.len() as u64,
+                });
+                let offset = read.replace(read.get() + data.len() as u64);
+                let seq = feed.sequence.fetch_add(1, Ordering::Relaxed);
+                if feed.segments.send((i, s, j, seq, (description.clone(), offset), item)).is_err() {
+                    break;
+                }
+            }
+            Ok(false)
+        };
+
+        let filename = match &input_ref {
+            InputReference::File(filename) => filename,
+            _ => return process(&mut self.cancellable(input_ref.open()?)).map(|_| ()),
+        };
+
+        // continue from the offset saved by the previous run and record offsets of complete lines read,
+        // which are saved by the merger once everything read before them is shown, see --checkpoint option
+        let checkpoint = self.options.checkpoint.as_ref();
+        let commit = |file: &FollowedFile| {
+            if checkpoint.is_some() && !file.is_compressed() {
+                feed.commits
+                    .send(Commit {
+                        end: feed.sequence.load(Ordering::Relaxed),
+                        path: filename.clone(),
+                        meta: file.metadata().clone(),
+                        offset: file.position().saturating_sub(tail.get()),
+                    })
+                    .ok();
+            }
+        };
+        let offset = match (checkpoint, fs::metadata(filename)) {
+            (Some(checkpoint), Ok(meta)) if meta.is_file() => checkpoint.lock().unwrap().offset(filename, &meta)?,
+            _ => 0,
+        };
+
+        let mut file = FollowedFile::open_at(filename, offset)?;
+        if file.is_compressed() && checkpoint.is_some() {
+            diagnostics::warning(format_args!(
+                "{} is compressed, so it is processed in full each time",
+                input_ref.description()
+            ));
+            if offset != 0 {
+                file = FollowedFile::open(filename)?;
+            }
+        }
+        read.set(if file.is_compressed() { 0 } else { offset });
+        let mut file = Some(file);
+        if let Some(file) = &mut file {
+            if process(&mut file.input)? || !file.is_file() {
+                return Ok(());
+            }
+            commit(file);
+        }
+        fsmon::run(vec![filename.clone()], || self.stopped(), |event| {
+            match event.kind {
+                EventKind::Modify(_) | EventKind::Create(_) | EventKind::Any | EventKind::Other => {
+                    match file.as_ref().and_then(|file| file.check(filename)) {
+                        Some(Rotation::Truncated) => {
+                            diagnostics::notice(format_args!("{} has been truncated", input_ref.description()));
+                            file = None;
+                        }
+                        Some(Rotation::Replaced) => {
+                            if let Some(mut old) = file.take() {
+                                process(&mut old.input)?;
+                            }
+                            diagnostics::notice(format_args!("{} has been rotated", input_ref.description()));
+                        }
+                        None => {}
+                    }
+                    if file.is_none() {
+                        file = FollowedFile::open(filename).ok();
+                        tail.set(0);
+                        read.set(0);
+                        if file.is_some() {
+                            diagnostics::notice(format_args!("reopened {}", input_ref.description()));
+                        }
+                    }
+                    if let Some(file) = &mut file {
+                        if !process(&mut file.input)? {
+                            commit(file);
+                        }
+                    }
+                    Ok(())
+                }
+                EventKind::Remove(_) => {
+                    if let Some(mut old) = file.take() {
+                        process(&mut old.input)?;
+                    }
+                    Ok(())
+                }
+                EventKind::Access(_) => Ok(()),
+            }
+        })
+    }
+
+    fn check_type_mismatch(&self) -> Result<()> {
+        match self.type_mismatch() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns an error describing the first value met by a filter with strict typing which kind differs from the filter value.
+    fn type_mismatch(&self) -> Option<Error> {
+        let query = self.options.filter.query.as_ref();
+        self.options.filter.fields.type_mismatch().or_else(|| query.and_then(|q| q.type_mismatch()))
+    }
+
+    /// Returns the number of records to show around matching records, unless all records match anyway
+    /// or the number of records to show is limited.
+    fn context(&self) -> Option<Context> {
+        self.options
+            .context
+            .filter(|_| !self.options.filter.is_empty() && self.options.limit.is_none())
+    }
+
+    fn context_selector(&self) -> Option<ContextSelector> {
+        self.context()
+            .map(|context| ContextSelector::new(context.before, context.after).with_separators(self.exporter.is_none()))
+    }
+
+    /// Returns formats of the inputs, with files having no records at the beginning treated as plain text in auto format.
+    ///
+    /// The beginning of a file is read by opening it once more, so only regular files are checked,
+    /// and data of other inputs like named pipes is not consumed before it is processed.
+    fn input_formats(&self, inputs: &[InputHolder]) -> Vec<InputFormat> {
+        inputs
+            .iter()
+            .map(|input| match (&input.reference, self.options.input_format) {
+                (InputReference::File(path), InputFormat::Auto)
+                    if fs::metadata(path).map_or(false, |meta| meta.is_file()) =>
+                {
+                    let mut sample = Vec::new();
+                    let sampled = input
+                        .reference
+                        .hold()
+                        .and_then(|holder| {
+                            holder
+                                .with_decompression(input.decompression)
+                                .with_encoding(input.encoding)
+                                .open()
+                        })
+                        .and_then(|opened| opened.stream.take(TEXT_DETECTION_SAMPLE_SIZE).read_to_end(&mut sample));
+                    match sampled {
+                        Ok(_) => self.detect_format(&sample),
+                        Err(_) => InputFormat::Auto,
+                    }
+                }
+                (_, format) => format,
+            })
+            .collect()
+    }
 
-                Ok(())
-            });
-
-            for reader in readers {
-                reader.join().unwrap()?;
-            }
+    /// Returns the input format, or text format if it is auto and the sample from the beginning of an input has no records.
+    fn detect_format(&self, sample: &[u8]) -> InputFormat {
+        match self.options.input_format {
+            InputFormat::Auto if logfmt::is_unstructured(sample) => InputFormat::Text,
+            format => format,
+        }
+    }
 
-            for worker in workers {
-                worker.join().unwrap();
-            }
+    /// Returns settings of collapsing of duplicate records, which is not done for records exported in a machine-readable format.
+    fn dedup(&self) -> Option<&Dedup> {
+        self.options.dedup.as_ref().filter(|_| self.exporter.is_none())
+    }
 
-            merger.join().unwrap()?;
+    /// Returns true if the summary is collected, either to be shown with --summary option or to count records.
+    fn summary_enabled(&self) -> bool {
+        self.options.summary || self.options.count_records
+    }
 
-            Ok(())
-        })
-        .unwrap()?;
+    /// Returns true if processing should stop because it is cancelled, out of time
+    /// or a filter with strict typing met a value of another type, which is reported as an error.
+    fn stopped(&self) -> bool {
+        self.cancellation.is_cancelled() || self.out_of_time() || self.type_mismatch().is_some()
+    }
 
-        Ok(())
+    /// Returns true once the time budget is exceeded, see --max-runtime option, and cancels processing
+    /// to stop reading of streams waiting for data.
+    fn out_of_time(&self) -> bool {
+        match self.options.max_runtime {
+            Some(budget) if self.started.elapsed() >= budget => {
+                self.truncated.store(true, Ordering::Relaxed);
+                self.cancellation.cancel();
+                true
+            }
+            _ => false,
+        }
     }
 
     fn parameters_hash(&self) -> Result<[u8; 32]> {
@@ -551,6 +1934,9 @@ impl App {
                 &self.options.buffer_size,
                 &self.options.max_message_size,
                 &self.options.fields.settings.predefined,
+                self.options.time_formats.iter().map(|format| format.as_str()).collect_vec(),
+                &self.options.input_format,
+                self.max_continuation_lines(),
             ),
         )?;
         Ok(hasher.finalize().into())
@@ -561,29 +1947,77 @@ impl App {
             &self.options.fields.settings.predefined,
             &self.options.fields.settings.ignore,
             self.options.filter.since.is_some() || self.options.filter.until.is_some() || self.options.follow,
-        ))
+        )
+        .with_duplicate_keys(self.options.fields.settings.duplicate_keys)
+        .with_time_formats(self.options.time_formats.clone()))
+    }
+
+    fn transformer(&self, tags: &[(String, String)]) -> Transformer {
+        let tags = tags
+            .iter()
+            .map(|(key, value)| Transform::Tag {
+                key: key.clone(),
+                value: value.clone(),
+            })
+            .collect_vec();
+        Transformer::new(self.options.transforms.iter().chain(tags.iter()))
+            .with_duplicate_suffixes(self.options.fields.settings.duplicate_keys == DuplicateKeys::KeepBothWithSuffix)
+            .with_explode(self.options.explode.clone())
     }
 
     fn formatter(&self) -> RecordFormatter {
         RecordFormatter::new(
             self.options.theme.clone(),
-            DateTimeFormatter::new(self.options.time_format.clone(), self.options.time_zone),
+            DateTimeFormatter::new(self.options.time_format.clone(), self.options.time_zone)
+                .with_locale(self.options.locale),
             self.options.hide_empty_fields,
             self.options.fields.filter.clone(),
             self.options.formatting.clone(),
         )
         .with_field_unescaping(!self.options.raw_fields)
+        .with_field_selection(self.options.fields.selection.clone())
+        .with_icons(self.options.icons)
+        .with_sorted_fields(self.options.sort_fields)
+        .with_time_gaps(self.options.time_gaps)
+        .with_lanes(self.options.lanes.clone())
+        .with_color_by(self.options.color_by.clone())
+        .with_locale(self.options.locale)
+        .with_continuation_indent(self.options.multiline.map_or(0, |multiline| multiline.indent))
+        .with_highlighter(Highlighter::new(&self.options.filter))
+        .with_stripped_prefixes(self.options.fields.strip_prefixes.clone())
     }
 
-    fn input_badges<'a, I: IntoIterator<Item = &'a InputReference>>(&self, inputs: I) -> Option<Vec<String>> {
-        let name = |input: &InputReference| match input {
-            InputReference::Stdin => "<stdin>".to_owned(),
-            InputReference::File(path) => path.to_string_lossy().to_string(),
-        };
+    fn max_continuation_lines(&self) -> Option<usize> {
+        self.options.multiline.map(|multiline| multiline.max_lines)
+    }
+
+    /// Returns labels of the inputs shown with the location of each message by --show-source option,
+    /// each starting with a lane marker colored differently for each input.
+    fn source_labels<'a, I: IntoIterator<Item = &'a InputReference>>(&self, inputs: I) -> Option<Vec<String>> {
+        if !self.options.show_source {
+            return None;
+        }
+        let theme = &self.options.theme;
+        let labels = inputs.into_iter().enumerate().map(|(i, input)| {
+            let mut buf = Vec::new();
+            buf.extend_from_slice(theme.lanes.marker(i).as_bytes());
+            buf.push(b' ');
+            theme.apply(&mut buf, &None, |s| {
+                s.element(Element::InputName, |s| {
+                    s.element(Element::InputNameInner, |s| s.batch(|buf| buf.extend_from_slice(input_name(input).as_bytes())))
+                });
+            });
+            String::from_utf8(buf).unwrap()
+        });
+        Some(labels.collect())
+    }
 
-        let mut badges = inputs.into_iter().map(|x| name(x).chars().collect_vec()).collect_vec();
+    fn input_badges<'a, I: IntoIterator<Item = &'a InputReference>>(&self, inputs: I) -> Option<Vec<String>> {
+        let mut badges = inputs.into_iter().map(|x| input_name(x).chars().collect_vec()).collect_vec();
 
         match &self.options.input_info {
+            // source labels already show the inputs
+            _ if self.options.show_source => return None,
             None => return None,
             Some(InputInfo::Auto) => {
                 if badges.len() < 2 {
@@ -680,6 +2114,18 @@ pub struct SegmentProcessor<'a> {
     parser: &'a Parser,
     formatter: &'a mut RecordFormatter,
     filter: &'a Filter,
+    transformer: Option<&'a Transformer>,
+    input_format: InputFormat,
+    text_format: &'a TextFormat,
+    now: Option<DateTime<FixedOffset>>,
+    multiline: Option<usize>,
+    summary: Option<Summary>,
+    skip_binary: bool,
+    validator: Option<&'a SchemaValidator>,
+    exporter: Option<&'a RecordExporter>,
+    prefilter: Option<&'a Prefilter>,
+    context: bool,
+    source: Option<(&'a str, u64)>,
 }
 
 impl<'a> SegmentProcessor<'a> {
@@ -688,35 +2134,251 @@ impl<'a> SegmentProcessor<'a> {
             parser,
             formatter,
             filter,
+            transformer: None,
+            input_format: InputFormat::default(),
+            text_format: TextFormat::builtin(),
+            now: None,
+            multiline: None,
+            summary: None,
+            skip_binary: false,
+            validator: None,
+            exporter: None,
+            prefilter: None,
+            context: false,
+            source: None,
+        }
+    }
+
+    /// Sets the exporter of matched records in a machine-readable format, records are formatted as text if not set.
+    pub(crate) fn with_exporter(mut self, exporter: Option<&'a RecordExporter>) -> Self {
+        self.exporter = exporter;
+        self
+    }
+
+    pub(crate) fn with_validator(mut self, validator: Option<&'a SchemaValidator>) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    pub(crate) fn with_skip_binary(mut self, skip: bool) -> Self {
+        self.skip_binary = skip;
+        self
+    }
+
+    pub(crate) fn with_summary(mut self, enabled: bool) -> Self {
+        self.summary = if enabled { Some(Summary::default()) } else { None };
+        self
+    }
+
+    /// Enables tracking of a numeric field, which also enables collection of the summary.
+    pub(crate) fn with_tracking(mut self, tracking: Option<&Tracking>) -> Self {
+        if let Some(tracking) = tracking {
+            self.summary.get_or_insert_with(Summary::default).series = Some(Series::new(tracking));
         }
+        self
+    }
+
+    pub(crate) fn summary(&self) -> Option<&Summary> {
+        self.summary.as_ref()
+    }
+
+    /// Sets the format of input lines, which are converted to JSON records if needed before transformation.
+    pub(crate) fn with_input_format(mut self, format: InputFormat) -> Self {
+        self.input_format = format;
+        self
+    }
+
+    /// Sets the conversion of plain text lines used in text input format.
+    pub(crate) fn with_text_format(mut self, format: &'a TextFormat) -> Self {
+        self.text_format = format;
+        self
+    }
+
+    /// Sets the reference time of syslog timestamps without a year, which is the time each segment is processed at if not set.
+    pub(crate) fn with_reference_time(mut self, now: DateTime<FixedOffset>) -> Self {
+        self.now = Some(now);
+        self
+    }
+
+    /// Enables attaching of up to the given number of lines that are not records to the preceding record.
+    pub(crate) fn with_multiline(mut self, max_lines: Option<usize>) -> Self {
+        self.multiline = max_lines;
+        self
+    }
+
+    /// Enables skipping of lines that cannot match the filter without parsing them, unless a transformer is set.
+    pub(crate) fn with_prefilter(mut self, prefilter: Option<&'a Prefilter>) -> Self {
+        self.prefilter = prefilter;
+        self
+    }
+
+    /// Enables formatting of records not matching the filter, which are observed as possible context of matching records.
+    pub(crate) fn with_context(mut self, enabled: bool) -> Self {
+        self.context = enabled;
+        self
+    }
+
+    /// Enables showing of the input label and the byte offset in the input before each record and each line that is not a record,
+    /// given the offset of the processed data in the input.
+    pub(crate) fn with_source(mut self, source: Option<(&'a str, u64)>) -> Self {
+        self.source = source;
+        self
+    }
+
+    pub(crate) fn with_transformer(mut self, transformer: &'a Transformer) -> Self {
+        self.transformer = Some(transformer).filter(|transformer| !transformer.is_empty());
+        self
     }
 
     pub fn run<O>(&mut self, data: &[u8], buf: &mut Vec<u8>, prefix: &str, observer: &mut O)
     where
         O: RecordObserver,
     {
-        for data in rtrim(data, b'\n').split(|c| *c == b'\n') {
-            if data.len() == 0 {
+        if let Some(summary) = &mut self.summary {
+            summary.bytes += data.len() as u64;
+        }
+        let mut normalized = Vec::new();
+        let mut transformed = Vec::new();
+        let now = self.now.unwrap_or_else(syslog::reference_time);
+        // level, whether it matched and number of attached lines of the last record
+        let mut parent: Option<(Option<Level>, bool, usize)> = None;
+        let watch = bugreport::watch(data);
+        let mut line_offset = 0;
+        for line in rtrim(data, b'\n').split(|c| *c == b'\n') {
+            let start = line_offset;
+            watch.set_offset(line_offset);
+            line_offset += line.len() + 1;
+            if line.len() == 0 {
+                continue;
+            }
+            let prefilter = self.prefilter.filter(|_| self.transformer.is_none());
+            if prefiltered(prefilter, line, self.multiline.is_some()) {
+                parent = Some((None, false, 0));
+                if let Some(summary) = &mut self.summary {
+                    summary.skipped += 1;
+                }
                 continue;
             }
+            let data = logfmt::normalize_with(self.input_format, self.text_format, now, line, &mut normalized);
+            let data = match self.transformer {
+                Some(transformer) if transformer.apply(data, &mut transformed) => &transformed[..],
+                _ => data,
+            };
             let mut stream = json::Deserializer::from_slice(data).into_iter::<RawRecord>();
             let mut some = false;
+            let mut offset = 0;
             while let Some(Ok(record)) = stream.next() {
                 some = true;
+                let source = &data[offset..stream.byte_offset()];
+                offset = stream.byte_offset();
                 let record = self.parser.parse(record);
-                if record.matches(self.filter) {
+                let matched = record.matches(self.filter);
+                parent = Some((record.level, matched, 0));
+                if let Some(summary) = &mut self.summary {
+                    summary.parsed += 1;
+                    if matched {
+                        summary.matched += 1;
+                        summary.rendered += 1;
+                        if let Some(ts) = record.ts.as_ref().and_then(|ts| ts.unix_utc()) {
+                            summary.add_ts(ts.into());
+                            if let Some(series) = &mut summary.series {
+                                if let Some(value) = record.number(series.key()) {
+                                    series.add(ts.into(), value);
+                                }
+                            }
+                        }
+                    }
+                }
+                if matched {
                     let begin = buf.len();
-                    buf.extend(prefix.as_bytes());
-                    self.formatter.format_record(buf, &record);
+                    match self.exporter {
+                        None => {
+                            self.push_prefix(buf, prefix, start);
+                            self.formatter.format_record(buf, &record);
+                            if let Some(validator) = self.validator {
+                                let violations = validator.validate(source, buf);
+                                if let Some(summary) = &mut self.summary {
+                                    summary.add_violations(violations);
+                                }
+                            }
+                        }
+                        Some(exporter) => exporter.export(buf, &record, trim(source)),
+                    }
                     let end = buf.len();
+                    observer.observe_source(source);
                     observer.observe_record(&record, begin..end);
+                } else if self.context {
+                    let begin = buf.len();
+                    match self.exporter {
+                        None => {
+                            self.push_prefix(buf, prefix, start);
+                            self.formatter.format_record(buf, &record);
+                        }
+                        Some(exporter) => exporter.export(buf, &record, trim(source)),
+                    }
+                    observer.observe_context(begin..buf.len());
+                }
+            }
+            if !some {
+                if self.attach(line, &mut parent) {
+                    if let Some((level, matched, _)) = parent.filter(|(_, matched, _)| *matched || self.context) {
+                        let begin = buf.len();
+                        self.push_prefix(buf, prefix, start);
+                        self.formatter.format_continuation(buf, &level, line);
+                        if matched {
+                            observer.observe_continuation(begin..buf.len());
+                        } else {
+                            observer.observe_context_continuation(begin..buf.len());
+                        }
+                    }
+                    continue;
                 }
+                parent = None;
             }
             let remainder = if some { &data[stream.byte_offset()..] } else { data };
+            if remainder.len() != 0 {
+                if let Some(summary) = &mut self.summary {
+                    summary.invalid += 1;
+                }
+            }
             if remainder.len() != 0 && self.filter.is_empty() {
-                buf.extend_from_slice(remainder);
-                buf.push(b'\n');
+                if !binary::is_binary(remainder) {
+                    self.push_source(buf, start);
+                    buf.extend_from_slice(remainder);
+                    buf.push(b'\n');
+                } else if !self.skip_binary {
+                    self.push_source(buf, start);
+                    binary::preview(remainder, buf);
+                    buf.push(b'\n');
+                }
+            }
+        }
+    }
+
+    /// Writes the prefix and the source location of the line at the given offset in the processed data if enabled.
+    fn push_prefix(&self, buf: &mut Vec<u8>, prefix: &str, offset: usize) {
+        buf.extend(prefix.as_bytes());
+        self.push_source(buf, offset);
+    }
+
+    /// Writes the input label and the offset in the input, given the offset in the processed data, if enabled.
+    fn push_source(&self, buf: &mut Vec<u8>, offset: usize) {
+        if let Some((label, start)) = self.source {
+            buf.extend(label.as_bytes());
+            buf.extend(format!("@{} ", start + offset as u64).as_bytes());
+        }
+    }
+
+    /// Checks whether the line that is not a record is to be attached to the preceding record, counting it if so.
+    fn attach(&self, line: &[u8], parent: &mut Option<(Option<Level>, bool, usize)>) -> bool {
+        match (self.multiline, parent) {
+            (Some(max), Some((_, _, count)))
+                if *count < max && self.exporter.is_none() && !binary::is_binary(line) =>
+            {
+                *count += 1;
+                true
             }
+            _ => false,
         }
     }
 }
@@ -725,6 +2387,18 @@ impl<'a> SegmentProcessor<'a> {
 
 pub trait RecordObserver {
     fn observe_record<'a>(&mut self, record: &'a Record<'a>, location: Range<usize>);
+
+    /// Observes a line attached to the previously observed record, ignored by default.
+    fn observe_continuation(&mut self, _location: Range<usize>) {}
+
+    /// Observes a record not matching the filter formatted as possible context of matching records, ignored by default.
+    fn observe_context(&mut self, _location: Range<usize>) {}
+
+    /// Observes a line attached to the previously observed context record, ignored by default.
+    fn observe_context_continuation(&mut self, _location: Range<usize>) {}
+
+    /// Observes source of the record that is going to be observed next, ignored by default.
+    fn observe_source(&mut self, _source: &[u8]) {}
 }
 
 // ---
@@ -737,14 +2411,214 @@ impl RecordObserver for RecordIgnorer {
 
 // ---
 
+struct SourceCollector {
+    enabled: bool,
+    records: Vec<(Range<usize>, Vec<u8>)>,
+    source: Vec<u8>,
+}
+
+impl SourceCollector {
+    fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            records: Vec::new(),
+            source: Vec::new(),
+        }
+    }
+
+    fn collect(self, format: CopyFormat, buf: &[u8]) -> Clipboard {
+        let mut clipboard = Clipboard::new(format);
+        for (location, source) in self.records {
+            clipboard.push(&buf[location], &source);
+        }
+        clipboard
+    }
+}
+
+impl RecordObserver for SourceCollector {
+    fn observe_record<'a>(&mut self, _: &'a Record<'a>, location: Range<usize>) {
+        if self.enabled {
+            self.records.push((location, std::mem::take(&mut self.source)));
+        }
+    }
+
+    fn observe_continuation(&mut self, location: Range<usize>) {
+        if let Some((last, _)) = self.records.last_mut().filter(|(last, _)| last.end == location.start) {
+            last.end = location.end;
+        }
+    }
+
+    fn observe_source(&mut self, source: &[u8]) {
+        if self.enabled {
+            self.source = trim(source).to_vec();
+        }
+    }
+}
+
+// ---
+
+/// Locations of formatted records in a buffer, whether they match the filter and their keys for collapsing of duplicates.
+type Spans = Vec<(Range<usize>, bool, Option<u64>)>;
+
+/// Collects locations of matching records and records formatted as their possible context.
+struct SpanCollector<'a, O> {
+    inner: &'a mut O,
+    spans: Spans,
+    dedup: Option<&'a Dedup>,
+}
+
+impl<'a, O> SpanCollector<'a, O> {
+    fn new(inner: &'a mut O) -> Self {
+        Self {
+            inner,
+            spans: Vec::new(),
+            dedup: None,
+        }
+    }
+
+    fn with_dedup(mut self, dedup: Option<&'a Dedup>) -> Self {
+        self.dedup = dedup;
+        self
+    }
+
+    fn extend(&mut self, location: Range<usize>, matched: bool) {
+        if let Some((last, _, _)) = self
+            .spans
+            .last_mut()
+            .filter(|(last, m, _)| *m == matched && last.end == location.start)
+        {
+            last.end = location.end;
+        }
+    }
+}
+
+impl<'a, O: RecordObserver> RecordObserver for SpanCollector<'a, O> {
+    fn observe_record<'b>(&mut self, record: &'b Record<'b>, location: Range<usize>) {
+        self.spans.push((location.clone(), true, self.dedup.map(|dedup| dedup.key(record))));
+        self.inner.observe_record(record, location);
+    }
+
+    fn observe_continuation(&mut self, location: Range<usize>) {
+        self.extend(location.clone(), true);
+        self.inner.observe_continuation(location);
+    }
+
+    fn observe_context(&mut self, location: Range<usize>) {
+        self.spans.push((location, false, None));
+    }
+
+    fn observe_context_continuation(&mut self, location: Range<usize>) {
+        self.extend(location, false);
+    }
+
+    fn observe_source(&mut self, source: &[u8]) {
+        self.inner.observe_source(source);
+    }
+}
+
+// ---
+
+/// Channels and counters shared by threads reading followed files.
+#[derive(Clone)]
+struct Feed {
+    sfi: Arc<SegmentBufFactory>,
+    /// Segments with indices of their input and its source, their number within a read, their sequence number,
+    /// and the description of their input with their offset in it.
+    segments: Sender<(usize, usize, usize, usize, (String, u64), Segment)>,
+    /// Number of segments read from all followed files so far.
+    sequence: Arc<AtomicUsize>,
+    commits: Sender<Commit>,
+}
+
+/// Offset of a followed file reached after reading segments with sequence numbers below `end`.
+struct Commit {
+    end: usize,
+    path: PathBuf,
+    meta: fs::Metadata,
+    offset: u64,
+}
+
+/// Offsets of followed files waiting to be recorded in the checkpoint until all data read before them is shown.
+struct Commits<'a> {
+    checkpoint: Option<&'a Mutex<Checkpoint>>,
+    rx: Receiver<Commit>,
+    pending: Vec<Commit>,
+    dirty: bool,
+    saved: Instant,
+}
+
+impl<'a> Commits<'a> {
+    fn new(checkpoint: Option<&'a Mutex<Checkpoint>>, rx: Receiver<Commit>) -> Self {
+        Self {
+            checkpoint,
+            rx,
+            pending: Vec::new(),
+            dirty: false,
+            saved: Instant::now(),
+        }
+    }
+
+    /// Records the offsets reached within the given number of leading segments, which are shown completely,
+    /// and saves the checkpoint if it has not been saved for [CHECKPOINT_SAVE_INTERVAL] or if forced.
+    fn apply(&mut self, shown: usize, force: bool, output: &mut Output) -> Result<()> {
+        let checkpoint = match self.checkpoint {
+            Some(checkpoint) => checkpoint,
+            None => return Ok(()),
+        };
+        self.pending.extend(self.rx.try_iter());
+        let (ready, pending): (Vec<_>, Vec<_>) =
+            std::mem::take(&mut self.pending).into_iter().partition(|commit| commit.end <= shown);
+        self.pending = pending;
+        let mut checkpoint = checkpoint.lock().unwrap();
+        for commit in ready {
+            checkpoint.update(&commit.path, &commit.meta, commit.offset)?;
+            self.dirty = true;
+        }
+        if self.dirty && (force || self.saved.elapsed() >= CHECKPOINT_SAVE_INTERVAL) {
+            output.flush()?;
+            checkpoint.save()?;
+            self.dirty = false;
+            self.saved = Instant::now();
+        }
+        Ok(())
+    }
+}
+
+// ---
+
 struct TimestampIndexBuilder {
     result: TimestampIndex,
+    keep_source: bool,
+    source: Vec<u8>,
+}
+
+impl TimestampIndexBuilder {
+    fn new(block: usize, keep_source: bool) -> Self {
+        Self {
+            result: TimestampIndex::new(block),
+            keep_source,
+            source: Vec::new(),
+        }
+    }
 }
 
 impl RecordObserver for TimestampIndexBuilder {
     fn observe_record<'a>(&mut self, record: &'a Record<'a>, location: Range<usize>) {
+        let source = std::mem::take(&mut self.source);
         if let Some(ts) = record.ts.as_ref().and_then(|ts| ts.unix_utc()).map(|ts| ts.into()) {
-            self.result.lines.push(TimestampIndexLine { location, ts });
+            self.result.lines.push(TimestampIndexLine { location, ts, source });
+        }
+    }
+
+    fn observe_continuation(&mut self, location: Range<usize>) {
+        if let Some(last) = self.result.lines.last_mut().filter(|last| last.location.end == location.start) {
+            last.location.end = location.end;
+        }
+    }
+
+    fn observe_source(&mut self, source: &[u8]) {
+        if self.keep_source {
+            self.source = trim(source).to_vec();
         }
     }
 }
@@ -770,6 +2644,7 @@ impl TimestampIndex {
 struct TimestampIndexLine {
     location: Range<usize>,
     ts: Timestamp,
+    source: Vec<u8>,
 }
 
 // ---
@@ -777,15 +2652,22 @@ struct TimestampIndexLine {
 struct OutputBlock {
     ts_min: crate::index::Timestamp,
     buf: Arc<Vec<u8>>,
-    items: Vec<(Timestamp, Range<usize>)>,
+    items: Vec<(Timestamp, Range<usize>, Range<usize>, bool, Option<u64>)>, // (ts, formatted, copied, matched, dedup key)
+    copied: Arc<Vec<u8>>,
 }
 
 impl OutputBlock {
-    pub fn into_lines(self) -> impl Iterator<Item = (Timestamp, BlockLine)> {
-        let buf = self.buf;
-        self.items
-            .into_iter()
-            .map(move |(ts, range)| (ts, BlockLine::new(buf.clone(), range.clone())))
+    pub fn into_lines(self) -> impl Iterator<Item = (Timestamp, BlockLine, BlockLine, bool, Option<u64>)> {
+        let (buf, copied) = (self.buf, self.copied);
+        self.items.into_iter().map(move |(ts, range, copy_range, matched, key)| {
+            (
+                ts,
+                BlockLine::new(buf.clone(), range.clone()),
+                BlockLine::new(copied.clone(), copy_range),
+                matched,
+                key,
+            )
+        })
     }
 }
 
@@ -836,6 +2718,64 @@ impl<T> StripedSender<T> {
 
 // ---
 
+fn trim(s: &[u8]) -> &[u8] {
+    let begin = s.iter().position(|c| !c.is_ascii_whitespace()).unwrap_or(s.len());
+    let end = s.iter().rposition(|c| !c.is_ascii_whitespace()).map(|i| i + 1).unwrap_or(begin);
+    &s[begin..end]
+}
+
+/// Checks whether the line is to be skipped without parsing because it cannot match the filter.
+/// With attaching of continuation lines, only lines starting like a JSON object are checked, others may be attached to a matching record.
+fn prefiltered(prefilter: Option<&Prefilter>, line: &[u8], multiline: bool) -> bool {
+    match prefilter {
+        Some(prefilter) => (!multiline || trim(line).starts_with(b"{")) && !prefilter.matches(line),
+        None => false,
+    }
+}
+
+/// Writes the formatted record, collapsing it with the preceding duplicates and limiting the number of records if enabled.
+///
+/// Returns false if no more records are needed.
+fn emit<W: Write + ?Sized>(
+    output: &mut W,
+    dedup: &mut Option<Deduplicator>,
+    limiter: &mut Option<RecordLimiter>,
+    key: Option<(usize, u64)>,
+    record: &[u8],
+) -> std::io::Result<bool> {
+    let record = match dedup {
+        Some(dedup) => match dedup.push(key, record) {
+            Some(record) => record,
+            None => return Ok(true),
+        },
+        None => record,
+    };
+    match limiter {
+        Some(limiter) => limiter.push(output, record),
+        None => output.write_all(record).map(|_| true),
+    }
+}
+
+/// Writes the records kept to collapse duplicates or to show the last ones.
+fn finish<W: Write + ?Sized>(
+    output: &mut W,
+    dedup: &mut Option<Deduplicator>,
+    limiter: &mut Option<RecordLimiter>,
+) -> std::io::Result<()> {
+    if let Some(record) = dedup.as_mut().and_then(|dedup| dedup.finish()) {
+        match limiter {
+            Some(limiter) => {
+                limiter.push(&mut *output, record)?;
+            }
+            None => output.write_all(record)?,
+        }
+    }
+    if let Some(limiter) = limiter {
+        limiter.finish(output)?;
+    }
+    Ok(())
+}
+
 fn rtrim<'a>(s: &'a [u8], c: u8) -> &'a [u8] {
     if s.len() > 0 && s[s.len() - 1] == c {
         &s[..s.len() - 1]
@@ -844,6 +2784,15 @@ fn rtrim<'a>(s: &'a [u8], c: u8) -> &'a [u8] {
     }
 }
 
+fn input_name(input: &InputReference) -> String {
+    match input {
+        InputReference::Stdin => "<stdin>".to_owned(),
+        InputReference::Listen(address) => address.to_string(),
+        InputReference::Command(command) => command.join(" "),
+        InputReference::File(path) => path.to_string_lossy().to_string(),
+    }
+}
+
 fn common_prefix_len<'a, V, I>(items: &'a Vec<I>) -> usize
 where
     V: 'a + Eq + PartialEq + Copy,
@@ -868,3 +2817,340 @@ where
         i += 1;
     }
 }
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datefmt::LinuxDateFormat;
+    use crate::model::FieldFilterSet;
+    use crate::settings::{Punctuation, Settings};
+    use crate::terminal::ColorDepth;
+    use chrono::Offset;
+
+    /// Returns options processing inputs with default settings, without colors and without the index cache.
+    fn options() -> Options {
+        let settings = Settings::default();
+        Options {
+            theme: Arc::new(Theme::none()),
+            time_format: LinuxDateFormat::new("%y-%m-%d %T.%3N").compile(),
+            raw_fields: false,
+            buffer_size: NonZeroUsize::new(4096).unwrap(),
+            max_message_size: NonZeroUsize::new(64 * 1024).unwrap(),
+            max_stream_buffer_size: NonZeroUsize::new(1024 * 1024).unwrap(),
+            concurrency: 2,
+            filter: Filter::default(),
+            fields: FieldOptions {
+                filter: Arc::new(IncludeExcludeKeyFilter::default()),
+                selection: Arc::new(FieldSelection::default()),
+                settings: settings.fields,
+                strip_prefixes: Vec::new(),
+            },
+            formatting: Formatting {
+                punctuation: Punctuation::test_default(),
+                ..Default::default()
+            },
+            time_zone: Tz::FixedOffset(Utc.fix()),
+            hide_empty_fields: false,
+            sort: false,
+            follow: false,
+            sync_interval: Duration::from_millis(100),
+            snapshot: None,
+            split_screen: None,
+            replay: None,
+            window: None,
+            transforms: Vec::new(),
+            explode: None,
+            max_runtime: None,
+            index_cache: false,
+            index_cache_max_size: None,
+            index_concurrency: 1,
+            progress: false,
+            sort_fields: false,
+            summary: false,
+            count_records: false,
+            tracking: None,
+            skip_binary: false,
+            skip_errors: false,
+            schema: None,
+            output_format: OutputFormat::Text,
+            columns: Vec::new(),
+            input_info: None,
+            show_source: false,
+            dump_index: false,
+            app_dirs: None,
+            icons: false,
+            time_gaps: None,
+            lanes: None,
+            color_by: None,
+            watch: Vec::new(),
+            copy: None,
+            time_formats: Vec::new(),
+            locale: None,
+            input_format: InputFormat::Auto,
+            text_format: TextFormat::new(&settings.text_input, &settings.level_aliases).unwrap(),
+            multiline: None,
+            terminal: Capabilities {
+                color_depth: ColorDepth::None,
+                clipboard: false,
+                multiplexer: None,
+            },
+            context: None,
+            limit: None,
+            interactive: false,
+            heartbeat: None,
+            stats: None,
+            dedup: None,
+            checkpoint: None,
+        }
+    }
+
+    /// Writes the files to a temporary directory named after the test and runs the app on them,
+    /// returning the result and the output with the directory path removed.
+    fn run(name: &str, options: Options, files: &[(&str, &[u8])]) -> (Result<()>, String) {
+        run_app(name, &App::new(options), files)
+    }
+
+    fn run_app(name: &str, app: &App, files: &[(&str, &[u8])]) -> (Result<()>, String) {
+        let dir = std::env::temp_dir().join(format!("hl-app-test-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let inputs = files
+            .iter()
+            .map(|(name, data)| {
+                let path = dir.join(name);
+                fs::write(&path, data).unwrap();
+                InputReference::File(path).hold().unwrap()
+            })
+            .collect();
+        let mut output = Vec::new();
+        let result = app.run(inputs, &mut output);
+        fs::remove_dir_all(&dir).ok();
+        let prefix = format!("{}{}", dir.display(), std::path::MAIN_SEPARATOR);
+        (result, String::from_utf8(output).unwrap().replace(&prefix, ""))
+    }
+
+    fn process(data: &str, filter: &Filter) -> String {
+        let settings = Settings::default();
+        let parser = Parser::new(ParserSettings::new(&settings.fields.predefined, std::iter::empty(), false));
+        let mut formatter = RecordFormatter::new(
+            Arc::new(Theme::none()),
+            DateTimeFormatter::new(
+                LinuxDateFormat::new("%y-%m-%d %T.%3N").compile(),
+                Tz::FixedOffset(Utc.fix()),
+            ),
+            false,
+            Arc::new(IncludeExcludeKeyFilter::default()),
+            Formatting {
+                punctuation: Punctuation::test_default(),
+                ..Default::default()
+            },
+        )
+        .with_continuation_indent(2);
+        let mut buf = Vec::new();
+        SegmentProcessor::new(&parser, &mut formatter, filter)
+            .with_multiline(Some(2))
+            .run(data.as_bytes(), &mut buf, "", &mut RecordIgnorer {});
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn test_continuation_lines() {
+        let all = Filter::default();
+        let other = Filter {
+            fields: FieldFilterSet::new(["msg=other"]).unwrap(),
+            ..Default::default()
+        };
+        let data = "{\"msg\":\"boom\"}\n\tat Main.run\n\tat Main.main\n\tat Thread.run\n";
+
+        let result = process(data, &all);
+        assert!(result.contains(" boom\n  \tat Main.run\n  \tat Main.main\n\tat Thread.run\n"), "{:?}", result);
+        assert_eq!(process(data, &other), "");
+
+        // parent record state is not carried over from the previous segment,
+        // so continuation lines starting a segment are shown as is or hidden by a filter
+        assert_eq!(process("\tat Main.run\n", &all), "\tat Main.run\n");
+        assert_eq!(process("\tat Main.run\n", &other), "");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_cancel_run() {
+        // the command produces no output for a while, so the reader is blocked when processing is cancelled
+        let app = App::new(options());
+        let input = InputReference::Command(vec!["sleep".into(), "3".into()]).hold().unwrap();
+        let started = Instant::now();
+        let result = std::thread::scope(|scope| {
+            scope.spawn(|| {
+                std::thread::sleep(Duration::from_millis(100));
+                app.cancellation().cancel();
+            });
+            app.run(vec![input], &mut Vec::new())
+        });
+        assert!(matches!(result, Err(Error::Cancelled)), "{:?}", result);
+        assert!(started.elapsed() < Duration::from_secs(2), "{:?}", started.elapsed());
+    }
+
+    #[test]
+    fn test_split_screen() {
+        let record = |ts: &str, msg: &str| format!("{{\"ts\":\"2024-01-01T00:00:0{}Z\",\"msg\":\"{}\"}}\n", ts, msg);
+        let left = record("0", "first") + &record("2", "third");
+        let right = record("1", "second");
+        let options = Options {
+            split_screen: Some(200),
+            ..options()
+        };
+        let files: &[(&str, &[u8])] = &[("left.log", left.as_bytes()), ("right.log", right.as_bytes())];
+        let (result, output) = run("split", options, files);
+        result.unwrap();
+
+        // messages of both inputs are merged by timestamp, each one shown in the column of its input
+        let columns = output.lines().filter_map(|line| line.split_once('\u{2502}')).collect_vec();
+        let expected = [("first", ""), ("", "second"), ("third", "")];
+        let shown = |text: &str, msg: &str| if msg.is_empty() { text.trim().is_empty() } else { text.contains(msg) };
+        assert_eq!(columns.len(), expected.len(), "{}", output);
+        for ((left, right), (l, r)) in columns.into_iter().zip(expected) {
+            assert!(shown(left, l) && shown(right, r), "{}", output);
+        }
+    }
+
+    #[test]
+    fn test_summary() {
+        let record =
+            |ts: &str, level: &str| format!("{{\"ts\":\"2024-01-01T00:00:0{}Z\",\"level\":\"{}\"}}\n", ts, level);
+        let data = record("0", "info") + &record("1", "error") + &record("2", "info");
+        let options = || Options {
+            // each record gets a block of its own, so that blocks without errors are skipped when sorting
+            buffer_size: NonZeroUsize::new(64).unwrap(),
+            summary: true,
+            filter: Filter {
+                level: Some(Level::Error),
+                ..Default::default()
+            },
+            ..options()
+        };
+
+        // all data is read either way, even if some blocks are skipped according to the index
+        for (name, sort) in [("summary-cat", false), ("summary-sort", true)] {
+            let app = App::new(Options { sort, ..options() });
+            let (result, _) = run_app(name, &app, &[("app.log", data.as_bytes())]);
+            result.unwrap();
+            let summary = app.summary.lock().unwrap();
+            assert_eq!(summary.bytes, data.len() as u64, "{}", name);
+            assert_eq!(summary.matched, 1, "{}", name);
+            assert_eq!(summary.rendered, 1, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_plan() {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(b"{\"msg\":\"a\"}\n").unwrap();
+        let packed = encoder.finish().unwrap();
+        let files: &[(&str, &[u8])] = &[
+            ("packed.log", &packed),
+            ("plain.gz", b"2024-01-02 03:04:05 INFO started\nfailed\n"),
+        ];
+
+        // names of the files do not tell their compression, so it is detected by their contents
+        let dir = std::env::temp_dir().join(format!("hl-app-test-plan-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let inputs = files
+            .iter()
+            .map(|(name, data)| {
+                let path = dir.join(name);
+                fs::write(&path, data).unwrap();
+                InputReference::File(path).hold().unwrap()
+            })
+            .collect_vec();
+        let mut output = Vec::new();
+        let result = App::new(options()).plan(&inputs, "stdout", &mut output);
+        fs::remove_dir_all(&dir).ok();
+        result.unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        for expected in [
+            "packed.log: format=auto compression=gzip scan=sequential",
+            "plain.gz: format=text compression=none scan=sequential",
+        ] {
+            assert!(output.contains(expected), "{}", output);
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_max_runtime() {
+        let data = b"{\"ts\":\"2024-01-01T00:00:00Z\",\"msg\":\"a\"}\n";
+        let budgeted = |budget| Options {
+            max_runtime: Some(budget),
+            ..options()
+        };
+
+        // the budget is checked while indexing, before anything is shown
+        let options = Options {
+            sort: true,
+            ..budgeted(Duration::ZERO)
+        };
+        let (result, output) = run("budget-sort", options, &[("app.log", data)]);
+        assert!(matches!(result, Err(Error::TimeBudgetExceeded { .. })), "{:?}", result);
+        assert_eq!(output, "");
+
+        // following an input that produces no output stops once the budget is exceeded
+        let app = App::new(Options {
+            follow: true,
+            ..budgeted(Duration::from_millis(100))
+        });
+        let input = InputReference::Command(vec!["sleep".into(), "3".into()]).hold().unwrap();
+        let started = Instant::now();
+        let result = app.run(vec![input], &mut Vec::new());
+        assert!(matches!(result, Err(Error::TimeBudgetExceeded { .. })), "{:?}", result);
+        assert!(started.elapsed() < Duration::from_secs(2), "{:?}", started.elapsed());
+    }
+
+    #[test]
+    fn test_skip_errors() {
+        let good = b"{\"ts\":\"2024-01-01T00:00:00Z\",\"msg\":\"good\"}\n";
+        // gzip header followed by data that is not deflate stream, so reading fails after opening
+        let bad = b"\x1f\x8b\x08\x00\x00\x00\x00\x00\x00\xff\xff\xff\xff\xff\xff\xff\xff\xff";
+        let files: &[(&str, &[u8])] = &[("bad.log.gz", bad), ("good.log", good)];
+        let skipping = || Options {
+            skip_errors: true,
+            ..options()
+        };
+
+        // cat reads inputs directly, sort reads them while indexing
+        for (name, sort) in [("skip-cat", false), ("skip-sort", true)] {
+            let (result, output) = run(name, Options { sort, ..skipping() }, files);
+            assert!(matches!(result, Err(Error::InputsFailed(1))), "{}: {:?}", name, result);
+            assert!(output.contains(" good\n"), "{}: {:?}", name, output);
+
+            let (result, _) = run(name, Options { sort, ..options() }, files);
+            assert!(result.is_err() && !matches!(result, Err(Error::InputsFailed(_))), "{}: {:?}", name, result);
+        }
+    }
+
+    #[test]
+    fn test_show_source() {
+        let data = "{\"ts\":\"2024-01-01T00:00:00Z\",\"msg\":\"a\"}\nraw\n{\"ts\":\"2024-01-01T00:00:01Z\",\"msg\":\"b\"}\n";
+        let options = || Options {
+            show_source: true,
+            ..options()
+        };
+
+        // both modes show the byte offset of the line in the input, lines that are not records are labeled too
+        let (result, output) = run("source-cat", options(), &[("app.log", data.as_bytes())]);
+        result.unwrap();
+        let lines = output.lines().collect_vec();
+        assert_eq!(lines.len(), 3, "{:?}", output);
+        assert!(lines[0].contains(" app.log@0 ") && lines[0].ends_with(" a"), "{:?}", output);
+        assert!(lines[1].ends_with(" app.log@40 raw"), "{:?}", output);
+        assert!(lines[2].contains(" app.log@44 ") && lines[2].ends_with(" b"), "{:?}", output);
+
+        let (result, output) = run("source-sort", Options { sort: true, ..options() }, &[("app.log", data.as_bytes())]);
+        result.unwrap();
+        let lines = output.lines().collect_vec();
+        assert_eq!(lines.len(), 2, "{:?}", output);
+        assert!(lines[0].contains(" app.log@0 ") && lines[0].ends_with(" a"), "{:?}", output);
+        assert!(lines[1].contains(" app.log@44 ") && lines[1].ends_with(" b"), "{:?}", output);
+    }
+}
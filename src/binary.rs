@@ -0,0 +1,78 @@
+// std imports
+use std::str;
+
+// ---
+
+const PREVIEW_SIZE: usize = 16;
+const MAX_NON_TEXT_RATIO: f64 = 0.3;
+
+// ---
+
+/// Detects whether the data looks like binary content rather than text.
+///
+/// Data containing zero bytes or a significant share of control characters and invalid UTF-8 sequences is considered binary.
+pub fn is_binary(data: &[u8]) -> bool {
+    let mut non_text = 0;
+    let mut rest = data;
+    while !rest.is_empty() {
+        let (valid, skip) = match str::from_utf8(rest) {
+            Ok(valid) => (valid, rest.len()),
+            Err(err) => {
+                let valid = str::from_utf8(&rest[..err.valid_up_to()]).unwrap_or_default();
+                let invalid = err.error_len().unwrap_or(rest.len() - err.valid_up_to());
+                non_text += invalid;
+                (valid, err.valid_up_to() + invalid)
+            }
+        };
+        for c in valid.chars() {
+            match c {
+                '\0' => return true,
+                '\t' | '\r' | '\x1b' => {}
+                c if c.is_control() => non_text += 1,
+                _ => {}
+            }
+        }
+        rest = &rest[skip..];
+    }
+    non_text as f64 > data.len() as f64 * MAX_NON_TEXT_RATIO
+}
+
+/// Writes a short hex and ASCII preview of the binary data along with its length.
+pub fn preview(data: &[u8], buf: &mut Vec<u8>) {
+    let head = &data[..data.len().min(PREVIEW_SIZE)];
+    buf.extend_from_slice(format!("[binary data, {} bytes]", data.len()).as_bytes());
+    for byte in head {
+        buf.extend_from_slice(format!(" {:02x}", byte).as_bytes());
+    }
+    buf.extend_from_slice(b"  |");
+    buf.extend(head.iter().map(|&byte| if byte.is_ascii_graphic() || byte == b' ' { byte } else { b'.' }));
+    buf.push(b'|');
+    if data.len() > head.len() {
+        buf.extend_from_slice(b"...");
+    }
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_binary() {
+        assert!(!is_binary(b"plain text with a\ttab"));
+        assert!(!is_binary("unicode text ✓".as_bytes()));
+        assert!(is_binary(b"text\0with zero byte"));
+        assert!(is_binary(b"\x01\x02\x03\xff\xfe abc"));
+    }
+
+    #[test]
+    fn test_preview() {
+        let mut buf = Vec::new();
+        preview(b"AB\x00\xffcdefghijklmnopqrstuvwxyz", &mut buf);
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "[binary data, 28 bytes] 41 42 00 ff 63 64 65 66 67 68 69 6a 6b 6c 6d 6e  |AB..cdefghijklmn|..."
+        );
+    }
+}
@@ -0,0 +1,213 @@
+// std imports
+use std::backtrace::Backtrace;
+use std::cell::{Cell, RefCell};
+use std::fmt::Display;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::marker::PhantomData;
+use std::panic;
+use std::path::PathBuf;
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// ---
+
+/// Number of bytes around the offending input offset included in a bug report.
+const CONTEXT_SIZE: usize = 32;
+
+thread_local! {
+    static INPUT: Cell<Option<(*const u8, usize, usize)>> = Cell::new(None);
+    static ORIGIN: RefCell<Option<String>> = RefCell::new(None);
+    static ORIGIN_OFFSET: Cell<u64> = Cell::new(0);
+}
+
+// ---
+
+/// Installs a panic hook that writes a bug report to a temporary file and prints its path after the panic message.
+///
+/// The report contains the version, the platform, the invocation arguments with values redacted,
+/// the name of the input being processed by the panicking thread and the offset within it with a few sanitized bytes
+/// around it, and a backtrace.
+pub fn install() {
+    let args = redact(std::env::args().skip(1));
+    let default = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default(info);
+        let report = report(info, &args, input());
+        match save(&report) {
+            Ok(path) => eprintln!(
+                "hl crashed unexpectedly, please attach bug report '{}' when reporting the issue",
+                path.display()
+            ),
+            Err(err) => eprintln!("hl crashed unexpectedly, failed to save bug report: {}", err),
+        }
+    }));
+}
+
+/// Marks the data as the input being processed by the current thread until the returned guard is dropped,
+/// so that the position within it can be included in a bug report.
+pub fn watch(data: &[u8]) -> Watch<'_> {
+    let prev = INPUT.with(|input| input.replace(Some((data.as_ptr(), data.len(), 0))));
+    Watch {
+        prev,
+        _data: PhantomData,
+    }
+}
+
+/// Guard of the watched input data, see [watch].
+pub struct Watch<'a> {
+    prev: Option<(*const u8, usize, usize)>,
+    _data: PhantomData<&'a [u8]>,
+}
+
+impl<'a> Watch<'a> {
+    /// Sets the offset within the watched data being processed now.
+    #[inline]
+    pub fn set_offset(&self, offset: usize) {
+        INPUT.with(|input| {
+            if let Some((ptr, len, _)) = input.get() {
+                input.set(Some((ptr, len, offset)));
+            }
+        });
+    }
+}
+
+impl<'a> Drop for Watch<'a> {
+    fn drop(&mut self) {
+        INPUT.with(|input| input.set(self.prev));
+    }
+}
+
+/// Marks the named input as the one being processed by the current thread until the returned guard is dropped,
+/// with data watched by [watch] starting at the given offset within it, so that a bug report names the input
+/// and gives the offset within the whole input instead of the offset within the watched data.
+pub fn origin(name: String, offset: u64) -> Origin {
+    ORIGIN.with(|origin| origin.replace(Some(name)));
+    ORIGIN_OFFSET.with(|value| value.set(offset));
+    Origin {}
+}
+
+/// Guard of the input marked by [origin].
+pub struct Origin {}
+
+impl Origin {
+    /// Sets the offset of data watched next within the input.
+    #[inline]
+    pub fn set_offset(&self, offset: u64) {
+        ORIGIN_OFFSET.with(|value| value.set(offset));
+    }
+}
+
+impl Drop for Origin {
+    fn drop(&mut self) {
+        ORIGIN.with(|origin| origin.replace(None));
+        ORIGIN_OFFSET.with(|value| value.set(0));
+    }
+}
+
+// ---
+
+/// Returns the name of the input being processed by the current thread if known, the offset within it
+/// or within the watched data otherwise, and the sanitized data around the offset.
+fn input() -> Option<(Option<String>, u64, Vec<u8>)> {
+    let (ptr, len, offset) = INPUT.with(|input| input.get())?;
+    // SAFETY: the data is borrowed by a live Watch guard of this thread, panic hook runs before unwinding drops it
+    let data = unsafe { std::slice::from_raw_parts(ptr, len) };
+    let offset = offset.min(len);
+    let context = &data[offset.saturating_sub(CONTEXT_SIZE)..(offset + CONTEXT_SIZE).min(len)];
+    let name = ORIGIN.with(|origin| origin.borrow().clone());
+    let base = if name.is_some() { ORIGIN_OFFSET.with(|value| value.get()) } else { 0 };
+    Some((name, base + offset as u64, sanitize(context)))
+}
+
+fn report(info: &dyn Display, args: &[String], input: Option<(Option<String>, u64, Vec<u8>)>) -> String {
+    let mut report = String::new();
+    report.push_str(&format!("version: {}\n", env!("CARGO_PKG_VERSION")));
+    report.push_str(&format!("os: {} {}\n", std::env::consts::OS, std::env::consts::ARCH));
+    report.push_str(&format!("args: {}\n", args.join(" ")));
+    report.push_str(&format!("panic: {}\n", info));
+    if let Some((name, offset, context)) = input {
+        match name {
+            Some(name) => {
+                report.push_str(&format!("input: {}\n", name));
+                report.push_str(&format!("input offset: {}\n", offset));
+            }
+            None => report.push_str(&format!("input offset within the block being processed: {}\n", offset)),
+        }
+        report.push_str(&format!("input context: {}\n", String::from_utf8_lossy(&context).escape_debug()));
+    }
+    report.push_str(&format!("backtrace:\n{}\n", Backtrace::force_capture()));
+    report
+}
+
+fn save(report: &str) -> io::Result<PathBuf> {
+    let ts = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let path = std::env::temp_dir().join(format!("hl-bug-report-{}-{}.txt", ts, process::id()));
+    let mut file = OpenOptions::new().write(true).create_new(true).open(&path)?;
+    file.write_all(report.as_bytes())?;
+    Ok(path)
+}
+
+/// Replaces values of the arguments with a placeholder, keeping only option names.
+///
+/// A short option followed by more characters in the same argument, like `-fpassword=secret`, keeps only its name
+/// because the rest may be an attached value.
+pub fn redact<I: IntoIterator<Item = String>>(args: I) -> Vec<String> {
+    args.into_iter()
+        .map(|arg| match arg.split_once('=') {
+            Some((name, _)) if name.starts_with("--") => format!("{}=<redacted>", name),
+            _ if arg.starts_with("--") => arg,
+            _ if arg.starts_with('-') && arg.len() > 2 => match arg.char_indices().nth(2) {
+                Some((i, _)) => format!("{}<redacted>", &arg[..i]),
+                None => arg,
+            },
+            _ if arg.starts_with('-') && arg.len() > 1 => arg,
+            _ => "<redacted>".into(),
+        })
+        .collect()
+}
+
+/// Masks letters and digits to keep only the structure of the data, like quotes, braces and separators.
+fn sanitize(data: &[u8]) -> Vec<u8> {
+    data.iter()
+        .map(|&b| match b {
+            b'a'..=b'z' => b'a',
+            b'A'..=b'Z' => b'A',
+            b'0'..=b'9' => b'0',
+            b' '..=b'~' | b'\t' | b'\n' | b'\r' => b,
+            _ => b'?',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        let args = ["-s", "--level=e", "--query", "user=bob", "app.log", "-", "-5"].map(String::from);
+        assert_eq!(
+            redact(args),
+            vec!["-s", "--level=<redacted>", "--query", "<redacted>", "<redacted>", "<redacted>", "-5"]
+        );
+        let args = ["-fpassword=secret", "-qé", "--", "-lw"].map(String::from);
+        assert_eq!(redact(args), vec!["-f<redacted>", "-q<redacted>", "--", "-l<redacted>"]);
+    }
+
+    #[test]
+    fn test_input() {
+        assert_eq!(input(), None);
+        let data = br#"{"msg":"Secret 42"}"#;
+        {
+            let watch = watch(data);
+            watch.set_offset(8);
+            assert_eq!(input(), Some((None, 8, br#"{"aaa":"Aaaaaa 00"}"#.to_vec())));
+            let origin = origin("app.log".into(), 1000);
+            assert_eq!(input(), Some((Some("app.log".into()), 1008, br#"{"aaa":"Aaaaaa 00"}"#.to_vec())));
+            origin.set_offset(2000);
+            assert_eq!(input().map(|(_, offset, _)| offset), Some(2008));
+        }
+        assert_eq!(input(), None);
+    }
+}
@@ -0,0 +1,235 @@
+// std imports
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+// local imports
+use crate::error::Result;
+use crate::index::Index;
+
+// ---
+
+/// Age of a temporary file after which it is considered a leftover of an interrupted write rather than a write in progress.
+const TMP_FILE_EXPIRATION: Duration = Duration::from_secs(3600);
+
+/// Time after which an unused decompressed copy of a file is removed even if the size of the cache is not limited,
+/// as the copies are as large as the decompressed files.
+const UNPACKED_FILE_EXPIRATION: Duration = Duration::from_secs(7 * 24 * 3600);
+
+// ---
+
+/// Directory with cached indexes and decompressed copies of files, one subdirectory per set of indexing parameters,
+/// see `hl index` command.
+pub struct IndexCache {
+    dir: PathBuf,
+    max_size: Option<u64>,
+}
+
+impl IndexCache {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir, max_size: None }
+    }
+
+    /// Sets the limit of the total size of cached files, the least recently used ones are evicted above it.
+    pub fn with_max_size(mut self, size: Option<u64>) -> Self {
+        self.max_size = size;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn max_size(&self) -> Option<u64> {
+        self.max_size
+    }
+
+    /// Returns cached files ordered from the least recently used one.
+    pub fn entries(&self) -> Result<Vec<CacheEntry>> {
+        let mut entries = Vec::new();
+        let dirs = match fs::read_dir(&self.dir) {
+            Ok(dirs) => dirs,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(entries),
+            Err(err) => return Err(err.into()),
+        };
+        for dir in dirs.flatten() {
+            if !is_hash(&dir.file_name().to_string_lossy()) || !dir.path().is_dir() {
+                continue;
+            }
+            for file in fs::read_dir(dir.path())?.flatten() {
+                match file.metadata() {
+                    Ok(meta) if meta.is_file() => entries.push(CacheEntry {
+                        path: file.path(),
+                        size: meta.len(),
+                        used: meta.modified()?,
+                    }),
+                    _ => {}
+                }
+            }
+        }
+        entries.sort_by_key(|entry| entry.used);
+        Ok(entries)
+    }
+
+    /// Removes decompressed copies of files that have not been used for a while, then the least recently used files
+    /// until their total size fits the limit, files used since the given time are kept anyway.
+    pub fn evict(&self, since: SystemTime) -> Result<Removed> {
+        let mut removed = Removed::default();
+        let mut entries = Vec::new();
+        for entry in self.entries()? {
+            if entry.used < since && is_expired_copy(&entry) && fs::remove_file(&entry.path).is_ok() {
+                removed.add(&entry);
+            } else {
+                entries.push(entry);
+            }
+        }
+        let max_size = match self.max_size {
+            Some(max_size) => max_size,
+            None => return Ok(removed),
+        };
+        let mut total = entries.iter().map(|entry| entry.size).sum::<u64>();
+        for entry in entries {
+            if total <= max_size || entry.used >= since {
+                break;
+            }
+            if fs::remove_file(&entry.path).is_ok() {
+                total -= entry.size;
+                removed.add(&entry);
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Removes indexes of files that no longer exist or have changed and leftovers of interrupted writes,
+    /// then evicts the least recently used files exceeding the size limit.
+    pub fn prune(&self) -> Result<Removed> {
+        let mut removed = Removed::default();
+        for entry in self.entries()? {
+            if is_stale(&entry) && fs::remove_file(&entry.path).is_ok() {
+                removed.add(&entry);
+            }
+        }
+        if let Ok(dirs) = fs::read_dir(&self.dir) {
+            for dir in dirs.flatten() {
+                if is_hash(&dir.file_name().to_string_lossy()) {
+                    // fails unless the directory is empty
+                    fs::remove_dir(dir.path()).ok();
+                }
+            }
+        }
+        let evicted = self.evict(SystemTime::now())?;
+        removed.files += evicted.files;
+        removed.bytes += evicted.bytes;
+        Ok(removed)
+    }
+}
+
+// ---
+
+/// File in the index cache.
+#[derive(Clone, Debug)]
+pub struct CacheEntry {
+    pub path: PathBuf,
+    pub size: u64,
+    /// Time the file was last written or used.
+    pub used: SystemTime,
+}
+
+/// Numbers of files and bytes removed from the index cache.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Removed {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+impl Removed {
+    fn add(&mut self, entry: &CacheEntry) {
+        self.files += 1;
+        self.bytes += entry.size;
+    }
+}
+
+// ---
+
+/// Marks the cached file as recently used.
+pub(crate) fn touch(path: &Path) {
+    if let Ok(file) = File::options().write(true).open(path) {
+        file.set_modified(SystemTime::now()).ok();
+    }
+}
+
+fn is_stale(entry: &CacheEntry) -> bool {
+    let name = entry.path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    if name.ends_with(".tmp") {
+        return entry.used.elapsed().map_or(false, |age| age > TMP_FILE_EXPIRATION);
+    }
+    if name.ends_with(".unpacked") {
+        // names of decompressed copies do not keep the source path, they only expire when not used
+        return is_expired_copy(entry);
+    }
+    match File::open(&entry.path).ok().and_then(|mut file| Index::load(&mut file).ok()) {
+        Some(index) => !index.is_current(name.split_once('-').map(|(_, head)| head)),
+        None => true,
+    }
+}
+
+fn is_expired_copy(entry: &CacheEntry) -> bool {
+    entry.path.extension().map_or(false, |ext| ext == "unpacked")
+        && entry.used.elapsed().map_or(false, |age| age > UNPACKED_FILE_EXPIRATION)
+}
+
+fn is_hash(name: &str) -> bool {
+    name.len() == 64 && name.bytes().all(|c| c.is_ascii_hexdigit())
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evict() {
+        let root = std::env::temp_dir().join(format!("hl-cache-test-{}", std::process::id()));
+        let dir = root.join("0".repeat(64));
+        fs::create_dir_all(&dir).unwrap();
+        let now = SystemTime::now();
+        for (name, size, age) in [("a", 10, 30), ("b", 20, 20), ("c", 30, 10)] {
+            let path = dir.join(name);
+            fs::write(&path, vec![0; size]).unwrap();
+            let used = now - Duration::from_secs(age);
+            File::options().write(true).open(&path).unwrap().set_modified(used).unwrap();
+        }
+        touch(&dir.join("a"));
+
+        let cache = IndexCache::new(root.clone()).with_max_size(Some(45));
+        let removed = cache.evict(SystemTime::now() - Duration::from_secs(1)).unwrap();
+        let left = cache.entries().unwrap().into_iter().map(|entry| entry.path).collect::<Vec<_>>();
+        fs::remove_dir_all(&root).ok();
+        assert_eq!(removed, Removed { files: 1, bytes: 20 });
+        assert_eq!(left, vec![dir.join("c"), dir.join("a")]);
+    }
+
+    #[test]
+    fn test_evict_unpacked() {
+        let root = std::env::temp_dir().join(format!("hl-cache-test-unpacked-{}", std::process::id()));
+        let dir = root.join("0".repeat(64));
+        fs::create_dir_all(&dir).unwrap();
+        let now = SystemTime::now();
+        let day = Duration::from_secs(24 * 3600);
+        for (name, age) in [("old.unpacked", 8 * day), ("new.unpacked", day), ("old-index", 8 * day)] {
+            let path = dir.join(name);
+            fs::write(&path, [0; 10]).unwrap();
+            File::options().write(true).open(&path).unwrap().set_modified(now - age).unwrap();
+        }
+
+        // decompressed copies not used for long are removed even if the size is not limited
+        let cache = IndexCache::new(root.clone());
+        let removed = cache.evict(now).unwrap();
+        let left = cache.entries().unwrap().into_iter().map(|entry| entry.path).collect::<Vec<_>>();
+        fs::remove_dir_all(&root).ok();
+        assert_eq!(removed, Removed { files: 1, bytes: 10 });
+        assert_eq!(left, vec![dir.join("old-index"), dir.join("new.unpacked")]);
+    }
+}
@@ -0,0 +1,50 @@
+// std imports
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+// ---
+
+/// Maximum time spent waiting for data or events before checking whether processing is cancelled.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+// ---
+
+/// Shared handle used to stop processing from another thread.
+///
+/// Clones refer to the same state, so a clone kept by the caller can be used to stop an [`App`](crate::app::App)
+/// or an [`Indexer`](crate::index::Indexer) that is running in a different thread.
+#[derive(Clone, Debug, Default)]
+pub struct Cancellation(Arc<AtomicBool>);
+
+impl Cancellation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests all holders of the handle to stop as soon as possible.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancellation() {
+        let cancellation = Cancellation::new();
+        let clone = cancellation.clone();
+        assert!(!clone.is_cancelled());
+        cancellation.cancel();
+        assert!(clone.is_cancelled());
+        assert!(!Cancellation::new().is_cancelled());
+    }
+}
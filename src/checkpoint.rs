@@ -0,0 +1,298 @@
+// std imports
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+// unix-only std imports
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+
+// third-party imports
+use serde::{Deserialize, Serialize};
+use serde_json as json;
+
+// local imports
+use crate::compression;
+use crate::diagnostics;
+use crate::error::Result;
+use crate::input::{InputHolder, InputReference};
+
+// ---
+
+/// Number of bytes at the beginning of a file hashed to tell it from another file with the same identity,
+/// e.g. after it was truncated in place and has grown again.
+const HEAD_LEN: u64 = 256;
+
+/// Size of chunks a file is read backwards by to find the end of its last complete line.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+// ---
+
+/// Offsets input files have been processed up to, persisted between runs, see --checkpoint option.
+pub struct Checkpoint {
+    path: PathBuf,
+    state: State,
+}
+
+impl Checkpoint {
+    /// Loads the checkpoint file, the checkpoint is empty if the file does not exist yet.
+    pub fn load(path: PathBuf) -> Result<Self> {
+        let state = match fs::read(&path) {
+            Ok(data) => json::from_slice(&data)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => State::default(),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(Self { path, state })
+    }
+
+    /// Saves the checkpoint file, replacing it at once so that an interrupted write does not corrupt it.
+    pub fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(format!(".{}.tmp", std::process::id()));
+        fs::write(&tmp, json::to_vec_pretty(&self.state)?)?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+
+    /// Returns the offset processing of the file continues from.
+    ///
+    /// Files rotated or truncated since the offset was recorded are processed from the beginning.
+    pub fn offset(&self, path: &Path, meta: &fs::Metadata) -> Result<u64> {
+        let state = match self.state.files.get(&key(path)) {
+            Some(state) => *state,
+            None => return Ok(0),
+        };
+        let description = || InputReference::File(path.into()).description();
+        if (state.dev, state.ino) != file_id(meta) || head(path, state.offset)? != state.head {
+            diagnostics::notice(format_args!("{} has been rotated since the last run", description()));
+            Ok(0)
+        } else if meta.len() < state.offset {
+            diagnostics::notice(format_args!("{} has been truncated since the last run", description()));
+            Ok(0)
+        } else {
+            Ok(state.offset)
+        }
+    }
+
+    /// Records the offset the file has been processed up to, which is persisted by [Checkpoint::save].
+    pub fn update(&mut self, path: &Path, meta: &fs::Metadata, offset: u64) -> Result<()> {
+        let (dev, ino) = file_id(meta);
+        let state = FileState {
+            dev,
+            ino,
+            head: head(path, offset)?,
+            offset,
+        };
+        self.state.files.insert(key(path), state);
+        Ok(())
+    }
+
+    /// Limits the input file to the complete lines appended since the recorded offset and records the offset it ends at.
+    ///
+    /// Compressed files and inputs other than regular files are kept as is.
+    pub fn resume(&mut self, mut input: InputHolder) -> Result<InputHolder> {
+        let path = match &input.reference {
+            InputReference::File(path) => path.clone(),
+            _ => return Ok(input),
+        };
+        let mut stream = match input.stream.take() {
+            Some(stream) => stream,
+            None => return Ok(input),
+        };
+        let meta = fs::metadata(&path)?;
+        let compressed = input
+            .decompression
+            .resolve(&compression::read_head(&mut stream)?)
+            .is_some();
+        if compressed {
+            diagnostics::warning(format_args!(
+                "{} is compressed, so it is processed in full each time",
+                input.reference.description()
+            ));
+        }
+        if !meta.is_file() || compressed {
+            stream.seek(SeekFrom::Start(0))?;
+            input.stream = Some(stream);
+            return Ok(input);
+        }
+        let start = self.offset(&path, &meta)?;
+        let end = start + complete_len(&mut stream, start, meta.len())?;
+        self.update(&path, &meta, end)?;
+        input.stream = Some(Box::new(Window::new(stream, start, end)?));
+        input.size = Some(end - start);
+        Ok(input)
+    }
+}
+
+// ---
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct State {
+    files: BTreeMap<PathBuf, FileState>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+struct FileState {
+    dev: u64,
+    ino: u64,
+    /// Hash of up to [HEAD_LEN] first bytes of the processed part of the file.
+    head: u64,
+    offset: u64,
+}
+
+// ---
+
+/// Part of the inner stream between the offsets, seen as a whole stream.
+struct Window<R> {
+    inner: R,
+    start: u64,
+    end: u64,
+    pos: u64,
+}
+
+impl<R: Seek> Window<R> {
+    fn new(mut inner: R, start: u64, end: u64) -> io::Result<Self> {
+        inner.seek(SeekFrom::Start(start))?;
+        Ok(Self {
+            inner,
+            start,
+            end,
+            pos: start,
+        })
+    }
+}
+
+impl<R: Read> Read for Window<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.end.saturating_sub(self.pos).min(buf.len() as u64) as usize;
+        if n == 0 {
+            return Ok(0);
+        }
+        let n = self.inner.read(&mut buf[..n])?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R: Seek> Seek for Window<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(offset) => self.start.checked_add(offset),
+            SeekFrom::End(offset) => self.end.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+        };
+        match pos.filter(|pos| *pos >= self.start) {
+            Some(pos) => {
+                self.pos = self.inner.seek(SeekFrom::Start(pos))?;
+                Ok(self.pos - self.start)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative position",
+            )),
+        }
+    }
+}
+
+// ---
+
+/// Returns the length of the complete lines of the stream between the offsets, which is up to the last newline.
+fn complete_len<R: Read + Seek>(stream: &mut R, start: u64, end: u64) -> io::Result<u64> {
+    let mut buf = vec![0; CHUNK_SIZE];
+    let mut pos = end;
+    while pos > start {
+        let n = (pos - start).min(CHUNK_SIZE as u64) as usize;
+        pos -= n as u64;
+        stream.seek(SeekFrom::Start(pos))?;
+        stream.read_exact(&mut buf[..n])?;
+        if let Some(i) = buf[..n].iter().rposition(|c| *c == b'\n') {
+            return Ok(pos + i as u64 + 1 - start);
+        }
+    }
+    Ok(0)
+}
+
+/// Returns the hash of up to [HEAD_LEN] first bytes of the file, but not more than the given length.
+fn head(path: &Path, len: u64) -> io::Result<u64> {
+    let mut data = Vec::new();
+    File::open(path)?.take(len.min(HEAD_LEN)).read_to_end(&mut data)?;
+    Ok(data.iter().fold(0xcbf29ce484222325u64, |h, b| {
+        (h ^ *b as u64).wrapping_mul(0x100000001b3)
+    }))
+}
+
+/// Returns the path the offsets of the file are recorded by, which is the same for any way the file is referred to.
+fn key(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.into())
+}
+
+#[cfg(unix)]
+fn file_id(meta: &fs::Metadata) -> (u64, u64) {
+    (meta.dev(), meta.ino())
+}
+
+#[cfg(not(unix))]
+fn file_id(meta: &fs::Metadata) -> (u64, u64) {
+    let created = meta
+        .created()
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok());
+    (0, created.map_or(0, |created| created.as_nanos() as u64))
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::OpenOptions;
+    use std::io::Write;
+
+    fn run(checkpoint: &Path, path: &Path) -> String {
+        let mut checkpoint = Checkpoint::load(checkpoint.into()).unwrap();
+        let input = InputReference::File(path.into()).hold().unwrap();
+        let mut data = String::new();
+        let mut input = checkpoint.resume(input).unwrap().open().unwrap();
+        input.stream.read_to_string(&mut data).unwrap();
+        checkpoint.save().unwrap();
+        data
+    }
+
+    fn append(path: &Path, data: &str) {
+        OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(path)
+            .unwrap()
+            .write_all(data.as_bytes())
+            .unwrap();
+    }
+
+    #[test]
+    fn test_resume() {
+        let dir = std::env::temp_dir().join(format!("hl-checkpoint-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let checkpoint = dir.join("checkpoint.json");
+        let path = dir.join("app.log");
+
+        append(&path, "a\nb\nc");
+        assert_eq!(run(&checkpoint, &path), "a\nb\n");
+        assert_eq!(run(&checkpoint, &path), "");
+        append(&path, "c\nd\n");
+        assert_eq!(run(&checkpoint, &path), "cc\nd\n");
+
+        // truncated in place and grown again with other data
+        fs::write(&path, "x\ny\nz\nw\n").unwrap();
+        assert_eq!(run(&checkpoint, &path), "x\ny\nz\nw\n");
+        // replaced with a shorter file
+        fs::remove_file(&path).unwrap();
+        append(&path, "e\n");
+        assert_eq!(run(&checkpoint, &path), "e\n");
+
+        fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,240 @@
+// std imports
+use std::collections::VecDeque;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+
+// local imports
+use crate::terminal::Capabilities;
+
+// ---
+
+/// Maximum size of copied data because terminals tend to ignore huge OSC 52 sequences,
+/// see [Clipboard::with_limit], a single larger record is truncated at a line boundary.
+pub const MAX_COPY_SIZE: usize = 1 << 20;
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// ---
+
+/// Format of records copied to the clipboard.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CopyFormat {
+    /// Original JSON records, one per line.
+    Json,
+    /// Formatted messages without styling.
+    Text,
+}
+
+// ---
+
+/// Records collected to be copied to the system clipboard, see --copy-matches option.
+#[derive(Clone, Debug)]
+pub struct Clipboard {
+    format: CopyFormat,
+    data: Vec<u8>,
+    /// Offset of the first kept record in `data`, the ones before it are dropped.
+    start: usize,
+    /// Sizes of the kept records.
+    sizes: VecDeque<usize>,
+    limit: Option<usize>,
+    dropped: usize,
+}
+
+impl Clipboard {
+    pub fn new(format: CopyFormat) -> Self {
+        Self {
+            format,
+            data: Vec::new(),
+            start: 0,
+            sizes: VecDeque::new(),
+            limit: None,
+            dropped: 0,
+        }
+    }
+
+    /// Limits the size of the collected data, the earliest records are dropped to keep the data within the limit,
+    /// but the last record is always kept.
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Appends a record given as its formatted representation and its source trimmed of surrounding whitespace.
+    pub fn push(&mut self, formatted: &[u8], source: &[u8]) {
+        let end = self.data.len();
+        match self.format {
+            CopyFormat::Json => {
+                self.data.extend_from_slice(source);
+                self.data.push(b'\n');
+            }
+            CopyFormat::Text => strip_styles(formatted, &mut self.data),
+        }
+        self.added(end);
+    }
+
+    /// Appends a record previously rendered by [Clipboard::push] of another clipboard with the same format.
+    pub fn push_rendered(&mut self, record: &[u8]) {
+        let end = self.data.len();
+        self.data.extend_from_slice(record);
+        self.added(end);
+    }
+
+    /// Appends all records of another clipboard with the same format.
+    pub fn merge(&mut self, other: &Clipboard) {
+        self.dropped += other.dropped;
+        let mut offset = other.start;
+        for size in &other.sizes {
+            self.push_rendered(&other.data[offset..offset + size]);
+            offset += size;
+        }
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data[self.start..]
+    }
+
+    pub fn into_data(mut self) -> Vec<u8> {
+        self.data.drain(..self.start);
+        self.data
+    }
+
+    /// Returns the number of kept records.
+    pub fn records(&self) -> usize {
+        self.sizes.len()
+    }
+
+    /// Returns the number of records dropped to keep the data within the limit.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// Sends the collected records to the system clipboard of the terminal and returns the number of copied bytes.
+    pub fn copy(&self, terminal: &Capabilities) -> io::Result<usize> {
+        let mut data = &self.data[..];
+        if data.len() > MAX_COPY_SIZE {
+            let end = data[..MAX_COPY_SIZE].iter().rposition(|&c| c == b'\n').map_or(0, |i| i + 1);
+            data = &data[..end];
+        }
+        let sequence = terminal.passthrough(osc52(data));
+        match OpenOptions::new().write(true).open(TTY) {
+            Ok(mut tty) => tty.write_all(&sequence)?,
+            Err(_) => io::stderr().write_all(&sequence)?,
+        }
+        Ok(data.len())
+    }
+
+    /// Accounts the record appended at the given offset, dropping the earliest records if the limit is exceeded.
+    fn added(&mut self, offset: usize) {
+        self.sizes.push_back(self.data.len() - offset);
+        let limit = match self.limit {
+            Some(limit) => limit,
+            None => return,
+        };
+        while self.data.len() - self.start > limit && self.sizes.len() > 1 {
+            self.start += self.sizes.pop_front().unwrap_or_default();
+            self.dropped += 1;
+        }
+        // dropped records are removed at once when they take most of the buffer, which keeps appending amortized O(1)
+        if self.start > self.data.len() / 2 {
+            self.data.drain(..self.start);
+            self.start = 0;
+        }
+    }
+}
+
+// ---
+
+#[cfg(unix)]
+const TTY: &str = "/dev/tty";
+#[cfg(not(unix))]
+const TTY: &str = "CONOUT$";
+
+/// Builds OSC 52 sequence setting clipboard contents.
+fn osc52(data: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(data.len() * 4 / 3 + 16);
+    result.extend(b"\x1b]52;c;");
+    base64(data, &mut result);
+    result.push(b'\x07');
+    result
+}
+
+fn base64(data: &[u8], result: &mut Vec<u8>) {
+    for chunk in data.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &c)| n | (c as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                result.push(BASE64[(n >> (18 - 6 * i) & 0x3f) as usize]);
+            } else {
+                result.push(b'=');
+            }
+        }
+    }
+}
+
+fn strip_styles(data: &[u8], result: &mut Vec<u8>) {
+    let mut i = 0;
+    while i < data.len() {
+        if data[i] == b'\x1b' && data.get(i + 1) == Some(&b'[') {
+            i += 2;
+            while i < data.len() && !(0x40..=0x7e).contains(&data[i]) {
+                i += 1;
+            }
+        } else {
+            result.push(data[i]);
+        }
+        i += 1;
+    }
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terminal::Multiplexer;
+
+    #[test]
+    fn test_base64() {
+        let encode = |data: &[u8]| {
+            let mut result = Vec::new();
+            base64(data, &mut result);
+            String::from_utf8(result).unwrap()
+        };
+        assert_eq!(encode(b""), "");
+        assert_eq!(encode(b"f"), "Zg==");
+        assert_eq!(encode(b"fo"), "Zm8=");
+        assert_eq!(encode(b"foo"), "Zm9v");
+        assert_eq!(encode(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn test_push() {
+        let mut json = Clipboard::new(CopyFormat::Json);
+        json.push(b"\x1b[0;2mformatted\x1b[0m\n", b"{\"msg\":\"a\"}");
+        assert_eq!(json.data(), b"{\"msg\":\"a\"}\n");
+
+        let mut text = Clipboard::new(CopyFormat::Text);
+        text.push(b"\x1b[0;2mformatted\x1b[0m\n", b"{}");
+        text.merge(&json);
+        assert_eq!(text.data(), b"formatted\n{\"msg\":\"a\"}\n");
+        assert_eq!(text.records(), 2);
+
+        let mut limited = Clipboard::new(CopyFormat::Json).with_limit(8);
+        limited.push(b"", b"1");
+        limited.push(b"", b"22");
+        limited.merge(&text);
+        assert_eq!(limited.data(), b"{\"msg\":\"a\"}\n");
+        assert_eq!((limited.records(), limited.dropped()), (1, 3));
+        limited.push(b"", b"3");
+        limited.push(b"", b"4");
+        assert_eq!(limited.data(), b"3\n4\n");
+        assert_eq!((limited.records(), limited.dropped()), (2, 4));
+        assert_eq!(limited.into_data(), b"3\n4\n");
+
+        assert_eq!(osc52(b"fo"), b"\x1b]52;c;Zm8=\x07");
+        assert_eq!(
+            Multiplexer::Tmux.wrap(&osc52(b"fo")),
+            b"\x1bPtmux;\x1b\x1b]52;c;Zm8=\x07\x1b\\"
+        );
+    }
+}
@@ -0,0 +1,76 @@
+// local imports
+use crate::width::char_width;
+
+// ---
+
+const RESET: &str = "\x1b[0m";
+
+// ---
+
+/// Wraps a line containing ANSI escape sequences into rows of exactly the given visible width in terminal columns.
+///
+/// Each row ends with a style reset if needed and the active style is restored at the beginning of the next row.
+/// A wide character not fitting into the rest of a row is moved to the next one, which is padded instead.
+pub fn wrap(line: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut rows = Vec::new();
+    let mut row = String::new();
+    let mut style = String::new();
+    let mut n = 0;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            let mut seq = String::from(c);
+            if chars.peek() == Some(&'[') {
+                seq.extend(chars.next());
+                while let Some(c) = chars.next() {
+                    seq.push(c);
+                    if ('\x40'..='\x7e').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            style = if seq == RESET || seq == "\x1b[m" { String::new() } else { seq.clone() };
+            row.push_str(&seq);
+            continue;
+        }
+        let w = char_width(c);
+        if n + w > width && n != 0 {
+            rows.push(finish(row, n, width, !style.is_empty()));
+            row = style.clone();
+            n = 0;
+        }
+        row.push(c);
+        n += w;
+    }
+    rows.push(finish(row, n, width, !style.is_empty()));
+    rows
+}
+
+fn finish(mut row: String, n: usize, width: usize, styled: bool) -> String {
+    if styled {
+        row.push_str(RESET);
+    }
+    row.extend(std::iter::repeat(' ').take(width.saturating_sub(n)));
+    row
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(wrap("abcde", 3), vec!["abc", "de "]);
+        assert_eq!(wrap("", 2), vec!["  "]);
+        assert_eq!(
+            wrap("\x1b[0;31mabcd\x1b[0m", 3),
+            vec!["\x1b[0;31mabc\x1b[0m", "\x1b[0;31md\x1b[0m  "]
+        );
+        assert_eq!(wrap("\u{65e5}\u{672c}\u{8a9e}", 5), vec!["\u{65e5}\u{672c} ", "\u{8a9e}   "]);
+        assert_eq!(wrap("cafe\u{301}!", 4), vec!["cafe\u{301}", "!   "]);
+        assert_eq!(wrap("a\u{1f600}", 2), vec!["a ", "\u{1f600}"]);
+    }
+}
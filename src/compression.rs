@@ -0,0 +1,681 @@
+// std imports
+use std::collections::VecDeque;
+use std::io::{self, BufReader, Cursor, Read, Write};
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+
+// third-party imports
+use crossbeam_channel::{self as channel, Receiver, Sender};
+use flate2::bufread::{GzDecoder, MultiGzDecoder};
+
+// local imports
+use crate::diagnostics;
+use crate::error::{Error, Result};
+use crate::input::InputStream;
+
+// ---
+
+const MAGIC_LEN: usize = 6;
+
+/// Number of gzip members decoded ahead of the one being read, per thread.
+const MEMBERS_AHEAD_PER_THREAD: usize = 2;
+
+/// Size of compressed gzip data read ahead to look for members decoded concurrently.
+const GZIP_WINDOW_SIZE: usize = 64 * 1024 * 1024;
+
+/// Maximum size of decoded contents of a gzip member decoded concurrently, larger members are decoded sequentially.
+const MAX_MEMBER_SIZE: u64 = 64 * 1024 * 1024;
+
+// ---
+
+/// Compression formats of inputs that are decompressed transparently and of output files compressed on the fly.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Bzip2,
+    Xz,
+}
+
+impl Compression {
+    /// All supported compression formats, used to look up a format by file extension.
+    pub const ALL: [Self; 4] = [Self::Gzip, Self::Zstd, Self::Bzip2, Self::Xz];
+
+    /// Detects compression format by file extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?;
+        Self::ALL
+            .into_iter()
+            .find(|compression| compression.extensions().contains(&extension))
+    }
+
+    /// Detects compression format by the magic bytes at the beginning of the data.
+    pub fn from_magic(head: &[u8]) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|compression| head.starts_with(compression.magic()))
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+            Self::Bzip2 => "bzip2",
+            Self::Xz => "xz",
+        }
+    }
+
+    pub fn extensions(self) -> &'static [&'static str] {
+        match self {
+            Self::Gzip => &["gz"],
+            Self::Zstd => &["zst", "zstd"],
+            Self::Bzip2 => &["bz2"],
+            Self::Xz => &["xz"],
+        }
+    }
+
+    pub fn magic(self) -> &'static [u8] {
+        match self {
+            Self::Gzip => b"\x1f\x8b",
+            Self::Zstd => b"\x28\xb5\x2f\xfd",
+            Self::Bzip2 => b"BZh",
+            Self::Xz => b"\xfd7zXZ\x00",
+        }
+    }
+
+    /// Supported compression levels, from the fastest to the strongest.
+    pub fn levels(self) -> RangeInclusive<u32> {
+        match self {
+            Self::Gzip | Self::Xz => 0..=9,
+            Self::Zstd => 1..=22,
+            Self::Bzip2 => 1..=9,
+        }
+    }
+
+    /// Compression level used unless another one is specified.
+    pub fn default_level(self) -> u32 {
+        match self {
+            Self::Zstd => 3,
+            _ => 6,
+        }
+    }
+
+    /// Wraps the stream with an encoder compressing data written to it at the given or the default level.
+    pub fn encoder<W: Write>(self, stream: W, level: Option<u32>) -> Result<Encoder<W>> {
+        let level = level.unwrap_or(self.default_level());
+        if !self.levels().contains(&level) {
+            return Err(Error::InvalidCompressionLevel {
+                compression: self.name(),
+                level,
+                levels: self.levels(),
+            });
+        }
+        let inner = match self {
+            Self::Gzip => EncoderKind::Gzip(flate2::write::GzEncoder::new(stream, flate2::Compression::new(level))),
+            Self::Zstd => EncoderKind::Zstd(zstd::stream::write::Encoder::new(stream, level as i32)?),
+            Self::Bzip2 => EncoderKind::Bzip2(bzip2::write::BzEncoder::new(stream, bzip2::Compression::new(level))),
+            Self::Xz => EncoderKind::Xz(xz2::write::XzEncoder::new(stream, level)),
+        };
+        Ok(Encoder { inner, finished: false })
+    }
+
+    /// Wraps the stream with a decoder producing decompressed data.
+    pub fn decoder<R: Read + Send + Sync + 'static>(self, stream: R) -> io::Result<InputStream> {
+        let stream = BufReader::new(stream);
+        Ok(match self {
+            Self::Gzip => Box::new(MultiGzDecoder::new(stream)),
+            Self::Zstd => Box::new(zstd::stream::read::Decoder::with_buffer(stream)?),
+            Self::Bzip2 => Box::new(bzip2::bufread::MultiBzDecoder::new(stream)),
+            Self::Xz => Box::new(xz2::bufread::XzDecoder::new_multi_decoder(stream)),
+        })
+    }
+
+    /// Wraps the stream with a decoder producing decompressed data using up to the given number of threads.
+    ///
+    /// Gzip data consisting of multiple members, like concatenated rotated files or output of `pigz --independent`,
+    /// has its members decoded concurrently within a window of [GZIP_WINDOW_SIZE] bytes read ahead.
+    /// Other data is decoded by [Self::decoder].
+    pub fn parallel_decoder<R: Read + Send + Sync + 'static>(
+        self,
+        stream: R,
+        threads: usize,
+    ) -> io::Result<InputStream> {
+        if self != Self::Gzip || threads < 2 {
+            return self.decoder(stream);
+        }
+        Ok(Box::new(ParallelGzDecoder::new(
+            Box::new(stream),
+            threads,
+            GZIP_WINDOW_SIZE,
+        )?))
+    }
+}
+
+// ---
+
+/// Selection of decompression applied to inputs.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Decompression {
+    /// Compression format is detected by the magic bytes at the beginning of the input.
+    #[default]
+    Auto,
+    /// Inputs are never decompressed.
+    Disabled,
+    /// Inputs are always decompressed using the specified format.
+    Forced(Compression),
+}
+
+impl Decompression {
+    /// Resolves compression format of an input given its first bytes.
+    pub fn resolve(self, head: &[u8]) -> Option<Compression> {
+        match self {
+            Self::Auto => Compression::from_magic(head),
+            Self::Disabled => None,
+            Self::Forced(compression) => Some(compression),
+        }
+    }
+
+    /// Reads the first bytes of the stream and wraps it with a decoder if it turns out to be compressed.
+    pub fn apply<R: Read + Send + Sync + 'static>(self, stream: R) -> io::Result<InputStream> {
+        self.apply_parallel(stream, 1)
+    }
+
+    /// Same as [Self::apply], but decodes the stream using up to the given number of threads if possible.
+    pub fn apply_parallel<R: Read + Send + Sync + 'static>(
+        self,
+        mut stream: R,
+        threads: usize,
+    ) -> io::Result<InputStream> {
+        let head = read_head(&mut stream)?;
+        let compression = self.resolve(&head);
+        let stream = Cursor::new(head).chain(stream);
+        match compression {
+            Some(compression) => compression.parallel_decoder(stream, threads),
+            None => Ok(Box::new(stream)),
+        }
+    }
+}
+
+/// Writer compressing data to the inner writer, see [Compression::encoder].
+///
+/// The compressed data is completed by [Encoder::finish] or when the encoder is dropped.
+pub struct Encoder<W: Write> {
+    inner: EncoderKind<W>,
+    finished: bool,
+}
+
+enum EncoderKind<W: Write> {
+    Gzip(flate2::write::GzEncoder<W>),
+    Zstd(zstd::stream::write::Encoder<'static, W>),
+    Bzip2(bzip2::write::BzEncoder<W>),
+    Xz(xz2::write::XzEncoder<W>),
+}
+
+impl<W: Write> Encoder<W> {
+    /// Writes the rest of the compressed data, nothing can be written after it.
+    pub fn finish(&mut self) -> io::Result<()> {
+        if self.finished {
+            return Ok(());
+        }
+        match &mut self.inner {
+            EncoderKind::Gzip(encoder) => encoder.try_finish(),
+            EncoderKind::Zstd(encoder) => encoder.do_finish(),
+            EncoderKind::Bzip2(encoder) => encoder.try_finish(),
+            EncoderKind::Xz(encoder) => encoder.try_finish(),
+        }?;
+        self.finished = true;
+        Ok(())
+    }
+
+    /// Returns the writer receiving the compressed data.
+    pub fn get_mut(&mut self) -> &mut W {
+        match &mut self.inner {
+            EncoderKind::Gzip(encoder) => encoder.get_mut(),
+            EncoderKind::Zstd(encoder) => encoder.get_mut(),
+            EncoderKind::Bzip2(encoder) => encoder.get_mut(),
+            EncoderKind::Xz(encoder) => encoder.get_mut(),
+        }
+    }
+
+    fn inner(&mut self) -> &mut dyn Write {
+        match &mut self.inner {
+            EncoderKind::Gzip(encoder) => encoder,
+            EncoderKind::Zstd(encoder) => encoder,
+            EncoderKind::Bzip2(encoder) => encoder,
+            EncoderKind::Xz(encoder) => encoder,
+        }
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner().flush()
+    }
+}
+
+impl<W: Write> Drop for Encoder<W> {
+    fn drop(&mut self) {
+        if let Err(err) = self.finish() {
+            diagnostics::warning(format_args!("failed to complete compressed output: {}", err));
+        }
+    }
+}
+
+// ---
+
+/// Reads up to the length of the longest magic byte sequence from the beginning of the stream.
+pub fn read_head<R: Read>(stream: &mut R) -> io::Result<Vec<u8>> {
+    let mut head = Vec::with_capacity(MAGIC_LEN);
+    stream.take(MAGIC_LEN as u64).read_to_end(&mut head)?;
+    Ok(head)
+}
+
+// ---
+
+/// Decodes members of gzip data concurrently and reads their contents in order.
+///
+/// Member boundaries cannot be known without decoding, so decoding is started at each offset looking like a member header
+/// within a window of compressed data read ahead. Results for offsets inside of the actual members are discarded,
+/// and the data is rejected if an actual member fails to decode. Once a member does not fit into the window or its
+/// contents exceed [MAX_MEMBER_SIZE], the rest of the data is decoded sequentially by [MultiGzDecoder].
+/// Workers stop when the decoder is dropped and the members queued for them are done.
+struct ParallelGzDecoder {
+    stream: InputStream,
+    /// Compressed data read ahead, starting at offset `base` of the stream.
+    data: Arc<Vec<u8>>,
+    base: u64,
+    eof: bool,
+    window: usize,
+    /// Offsets looking like member headers, not dispatched yet.
+    candidates: VecDeque<u64>,
+    /// Offset up to which the data is scanned for candidates.
+    scanned: u64,
+    /// Offsets being decoded with the end of the data available to the decoding.
+    pending: VecDeque<(u64, u64, Receiver<io::Result<Option<Member>>>)>,
+    jobs: Sender<Job>,
+    ahead: usize,
+    /// Offset of the next member.
+    pos: u64,
+    chunk: Cursor<Vec<u8>>,
+    fallback: Option<MultiGzDecoder<BufReader<io::Chain<Cursor<Vec<u8>>, InputStream>>>>,
+}
+
+/// Decoded contents of a gzip member and the size of the member.
+type Member = (Vec<u8>, usize);
+
+/// Offset of a member to decode, data it is decoded from with its offset and whether the data reaches the end of the stream.
+type Job = (u64, Arc<Vec<u8>>, u64, bool, Sender<io::Result<Option<Member>>>);
+
+impl ParallelGzDecoder {
+    fn new(stream: InputStream, threads: usize, window: usize) -> io::Result<Self> {
+        let (jobs, rx) = channel::unbounded::<Job>();
+        for _ in 0..threads {
+            let rx = rx.clone();
+            thread::spawn(move || {
+                for (offset, data, base, complete, result) in rx {
+                    result
+                        .send(decode_gzip_member(&data[(offset - base) as usize..], complete))
+                        .ok();
+                }
+            });
+        }
+        let mut decoder = Self {
+            stream,
+            data: Arc::new(Vec::new()),
+            base: 0,
+            eof: false,
+            window,
+            candidates: VecDeque::new(),
+            scanned: 0,
+            pending: VecDeque::new(),
+            jobs,
+            ahead: threads * MEMBERS_AHEAD_PER_THREAD,
+            pos: 0,
+            chunk: Cursor::new(Vec::new()),
+            fallback: None,
+        };
+        decoder.fill(true)?;
+        // nothing to decode concurrently in a file with a single member
+        if decoder.candidates.len() < 2 {
+            decoder.fall_back();
+        }
+        Ok(decoder)
+    }
+
+    /// Reads more data into the window if less than half of it is left, or anyway if forced.
+    fn fill(&mut self, force: bool) -> io::Result<()> {
+        let consumed = (self.pos - self.base) as usize;
+        if self.eof || (!force && self.data.len() - consumed >= self.window / 2) {
+            return Ok(());
+        }
+        let mut data = Vec::with_capacity(self.window.max(self.data.len() - consumed));
+        data.extend_from_slice(&self.data[consumed..]);
+        let limit = self.window.saturating_sub(data.len()) as u64;
+        self.stream.by_ref().take(limit).read_to_end(&mut data)?;
+        self.eof = data.len() < self.window;
+        self.base = self.pos;
+        let end = self.base + data.len() as u64;
+        for offset in self.scanned.max(self.base)..end.saturating_sub(3) {
+            let i = (offset - self.base) as usize;
+            if is_gzip_member_header(&data[i..i + 4]) {
+                self.candidates.push_back(offset);
+            }
+        }
+        self.scanned = end.saturating_sub(3).max(self.scanned);
+        self.data = Arc::new(data);
+        Ok(())
+    }
+
+    /// Starts decoding at the next candidate offsets until enough of them are in progress.
+    fn dispatch(&mut self) -> io::Result<()> {
+        while self.pending.len() < self.ahead {
+            let offset = match self.candidates.pop_front() {
+                Some(offset) if offset < self.pos => continue,
+                Some(offset) => offset,
+                None => break,
+            };
+            self.pending.push_back(self.start(offset)?);
+        }
+        Ok(())
+    }
+
+    fn start(&self, offset: u64) -> io::Result<(u64, u64, Receiver<io::Result<Option<Member>>>)> {
+        let (tx, rx) = channel::bounded(1);
+        let job = (offset, self.data.clone(), self.base, self.eof, tx);
+        self.jobs.send(job).map_err(|_| stopped())?;
+        Ok((offset, self.base + self.data.len() as u64, rx))
+    }
+
+    /// Switches to sequential decoding of the data starting at the current position.
+    fn fall_back(&mut self) {
+        let rest = self.data[(self.pos - self.base) as usize..].to_vec();
+        let stream = std::mem::replace(&mut self.stream, Box::new(io::empty()));
+        self.fallback = Some(MultiGzDecoder::new(BufReader::new(Cursor::new(rest).chain(stream))));
+        self.pending.clear();
+        self.candidates.clear();
+        self.data = Arc::new(Vec::new());
+    }
+
+    /// Returns decoded contents of the member starting at the current position, or None at the end of the data.
+    fn next_member(&mut self) -> io::Result<Option<Vec<u8>>> {
+        loop {
+            self.fill(false)?;
+            if self.eof && self.pos == self.base + self.data.len() as u64 {
+                return Ok(None);
+            }
+            self.dispatch()?;
+            let (end, rx) = match self.pending.pop_front() {
+                Some((offset, _, _)) if offset < self.pos => continue,
+                Some((offset, end, rx)) if offset == self.pos => (end, rx),
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("invalid gzip member header at offset {}", self.pos),
+                    ))
+                }
+            };
+            match rx.recv().map_err(|_| stopped())?? {
+                Some((contents, size)) => {
+                    self.pos += size as u64;
+                    return Ok(Some(contents));
+                }
+                None if end < self.base + self.data.len() as u64 => {
+                    // more data has been read since the decoding was started
+                    let job = self.start(self.pos)?;
+                    self.pending.push_front(job);
+                }
+                None if !self.eof && self.pos != self.base => {
+                    self.fill(true)?;
+                    let job = self.start(self.pos)?;
+                    self.pending.push_front(job);
+                }
+                None => {
+                    self.fall_back();
+                    return Ok(Some(Vec::new()));
+                }
+            }
+        }
+    }
+}
+
+impl Read for ParallelGzDecoder {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if let Some(fallback) = &mut self.fallback {
+                return fallback.read(buf);
+            }
+            let n = self.chunk.read(buf)?;
+            if n != 0 || buf.is_empty() {
+                return Ok(n);
+            }
+            match self.next_member()? {
+                Some(contents) => self.chunk = Cursor::new(contents),
+                None => return Ok(0),
+            }
+        }
+    }
+}
+
+/// Returns true if the bytes look like a beginning of a gzip member, i.e. have magic bytes, deflate method and valid flags.
+fn is_gzip_member_header(head: &[u8]) -> bool {
+    head[0] == 0x1f && head[1] == 0x8b && head[2] == 8 && head[3] & 0xe0 == 0
+}
+
+/// Decodes the member at the beginning of the data, returns None if the member does not end within the data
+/// while it is not the complete rest of the stream, or if its contents exceed [MAX_MEMBER_SIZE].
+fn decode_gzip_member(data: &[u8], complete: bool) -> io::Result<Option<Member>> {
+    let mut decoder = GzDecoder::new(data);
+    let mut contents = Vec::new();
+    let result = decoder.by_ref().take(MAX_MEMBER_SIZE + 1).read_to_end(&mut contents);
+    match result {
+        Ok(_) if contents.len() as u64 > MAX_MEMBER_SIZE => Ok(None),
+        Ok(_) => Ok(Some((contents, data.len() - decoder.into_inner().len()))),
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof && !complete => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+fn stopped() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "gzip decoding thread stopped unexpectedly")
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(Compression::from_path(Path::new("app.log.gz")), Some(Compression::Gzip));
+        assert_eq!(
+            Compression::from_path(Path::new("app.log.zst")),
+            Some(Compression::Zstd)
+        );
+        assert_eq!(
+            Compression::from_path(Path::new("app.log.bz2")),
+            Some(Compression::Bzip2)
+        );
+        assert_eq!(Compression::from_path(Path::new("app.log.xz")), Some(Compression::Xz));
+        assert_eq!(Compression::from_path(Path::new("app.log")), None);
+    }
+
+    #[test]
+    fn test_from_magic() {
+        assert_eq!(Compression::from_magic(b"\x1f\x8b\x08\x00"), Some(Compression::Gzip));
+        assert_eq!(
+            Compression::from_magic(b"\x28\xb5\x2f\xfd\x00"),
+            Some(Compression::Zstd)
+        );
+        assert_eq!(Compression::from_magic(b"BZh91AY"), Some(Compression::Bzip2));
+        assert_eq!(Compression::from_magic(b"\xfd7zXZ\x00\x00"), Some(Compression::Xz));
+        assert_eq!(Compression::from_magic(b"{\"msg\""), None);
+        assert_eq!(Compression::from_magic(b""), None);
+    }
+
+    #[test]
+    fn test_gzip_decoder() {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut encoder, b"{\"msg\":\"test\"}\n").unwrap();
+        let data = encoder.finish().unwrap();
+
+        let mut result = String::new();
+        Compression::Gzip
+            .decoder(Cursor::new(data.clone()))
+            .unwrap()
+            .read_to_string(&mut result)
+            .unwrap();
+        assert_eq!(result, "{\"msg\":\"test\"}\n");
+
+        let mut result = String::new();
+        Decompression::Auto
+            .apply(Cursor::new(data))
+            .unwrap()
+            .read_to_string(&mut result)
+            .unwrap();
+        assert_eq!(result, "{\"msg\":\"test\"}\n");
+
+        let mut result = String::new();
+        Decompression::Auto
+            .apply(Cursor::new(b"plain".to_vec()))
+            .unwrap()
+            .read_to_string(&mut result)
+            .unwrap();
+        assert_eq!(result, "plain");
+    }
+
+    #[test]
+    fn test_decoders() {
+        let data = b"{\"msg\":\"test\"}\n".repeat(100);
+        let compressed = [
+            (Compression::Zstd, zstd::stream::encode_all(data.as_slice(), 3).unwrap()),
+            (Compression::Bzip2, {
+                let mut encoder = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+                encoder.write_all(&data).unwrap();
+                encoder.finish().unwrap()
+            }),
+            (Compression::Xz, {
+                let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 6);
+                encoder.write_all(&data).unwrap();
+                encoder.finish().unwrap()
+            }),
+        ];
+        for (compression, compressed) in compressed {
+            // detected by the magic bytes or forced, and not decoded if disabled
+            for decompression in [Decompression::Auto, Decompression::Forced(compression)] {
+                let mut result = Vec::new();
+                decompression
+                    .apply(Cursor::new(compressed.clone()))
+                    .unwrap()
+                    .read_to_end(&mut result)
+                    .unwrap();
+                assert_eq!(result, data, "{}", compression.name());
+            }
+            let mut result = Vec::new();
+            Decompression::Disabled
+                .apply(Cursor::new(compressed.clone()))
+                .unwrap()
+                .read_to_end(&mut result)
+                .unwrap();
+            assert_eq!(result, compressed, "{}", compression.name());
+        }
+    }
+
+    #[test]
+    fn test_encoder() {
+        let data = "{\"msg\":\"test\"}\n".repeat(100);
+        for compression in Compression::ALL {
+            let mut encoder = compression.encoder(Vec::new(), None).unwrap();
+            encoder.write_all(data.as_bytes()).unwrap();
+            encoder.finish().unwrap();
+            let compressed = std::mem::take(encoder.get_mut());
+            assert!(compressed.len() < data.len());
+
+            let mut result = String::new();
+            Decompression::Auto
+                .apply(Cursor::new(compressed))
+                .unwrap()
+                .read_to_string(&mut result)
+                .unwrap();
+            assert_eq!(result, data, "{}", compression.name());
+        }
+        assert!(Compression::Gzip.encoder(Vec::new(), Some(10)).is_err());
+        assert!(Compression::Zstd.encoder(Vec::new(), Some(0)).is_err());
+    }
+
+    fn gzip(data: &[u8], level: flate2::Compression) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), level);
+        std::io::Write::write_all(&mut encoder, data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_gzip_members() {
+        let mut data = Vec::new();
+        let mut expected = Vec::new();
+        for i in 0..20 {
+            let line = format!("{{\"msg\":\"member {}\"}}\n", i).repeat(i * 100);
+            data.extend(gzip(line.as_bytes(), flate2::Compression::default()));
+            expected.extend(line.into_bytes());
+        }
+        // stored contents looking like a member header must not confuse the decoder
+        let fake = b"\x1f\x8b\x08\x00garbage";
+        data.extend(gzip(fake, flate2::Compression::none()));
+        expected.extend(fake);
+
+        for threads in [1, 4] {
+            let mut result = Vec::new();
+            Compression::Gzip
+                .parallel_decoder(Cursor::new(data.clone()), threads)
+                .unwrap()
+                .read_to_end(&mut result)
+                .unwrap();
+            assert_eq!(result, expected);
+        }
+
+        // windows smaller than some of the members make the decoder read more data or fall back to sequential decoding
+        for window in [256, 4096, 16384] {
+            let mut result = Vec::new();
+            ParallelGzDecoder::new(Box::new(Cursor::new(data.clone())), 4, window)
+                .unwrap()
+                .read_to_end(&mut result)
+                .unwrap();
+            assert_eq!(result, expected);
+        }
+
+        // single member is decoded sequentially
+        let line = "{\"msg\":\"single\"}\n".repeat(1000);
+        let mut result = Vec::new();
+        ParallelGzDecoder::new(
+            Box::new(Cursor::new(gzip(line.as_bytes(), flate2::Compression::default()))),
+            4,
+            256,
+        )
+        .unwrap()
+        .read_to_end(&mut result)
+        .unwrap();
+        assert_eq!(result, line.as_bytes());
+
+        let mut result = Vec::new();
+        Decompression::Auto
+            .apply_parallel(Cursor::new(data.clone()), 4)
+            .unwrap()
+            .read_to_end(&mut result)
+            .unwrap();
+        assert_eq!(result, expected);
+
+        data.extend(b"\x1f\x8b\x08\x00trailing garbage");
+        let mut result = Vec::new();
+        assert!(Compression::Gzip
+            .parallel_decoder(Cursor::new(data), 4)
+            .unwrap()
+            .read_to_end(&mut result)
+            .is_err());
+    }
+}
@@ -0,0 +1,163 @@
+// std imports
+use std::collections::VecDeque;
+use std::io::{self, Write};
+
+// ---
+
+/// Line written between groups of records that are not adjacent.
+const SEPARATOR: &[u8] = b"--\n";
+
+const CSI: &[u8] = b"\x1b[";
+const FAINT: &[u8] = b"\x1b[2m";
+const RESET: &[u8] = b"\x1b[m";
+
+// ---
+
+/// Selects records to show among matching records and records around them, given in output order,
+/// see --before, --after and --context options.
+///
+/// Records shown for context are made faint if they are styled.
+pub struct ContextSelector {
+    before: usize,
+    after: usize,
+    separators: bool,
+    ring: VecDeque<Vec<u8>>,
+    remaining: usize,
+    skipped: bool,
+    shown: bool,
+}
+
+impl ContextSelector {
+    pub fn new(before: usize, after: usize) -> Self {
+        Self {
+            before,
+            after,
+            separators: true,
+            ring: VecDeque::with_capacity(before),
+            remaining: 0,
+            skipped: false,
+            shown: false,
+        }
+    }
+
+    /// Enables or disables separator lines between groups of records that are not adjacent.
+    pub fn with_separators(mut self, enabled: bool) -> Self {
+        self.separators = enabled;
+        self
+    }
+
+    /// Writes the record if it matches or is close enough to a matching one, otherwise keeps it for a while.
+    pub fn push<W: Write + ?Sized>(&mut self, output: &mut W, record: &[u8], matched: bool) -> io::Result<()> {
+        if matched {
+            if self.skipped && self.shown && self.separators {
+                output.write_all(SEPARATOR)?;
+            }
+            for record in self.ring.drain(..) {
+                write_faint(output, &record)?;
+            }
+            output.write_all(record)?;
+            self.remaining = self.after;
+            self.skipped = false;
+            self.shown = true;
+        } else if self.remaining != 0 {
+            write_faint(output, record)?;
+            self.remaining -= 1;
+        } else if self.before != 0 {
+            if self.ring.len() == self.before {
+                self.ring.pop_front();
+                self.skipped = true;
+            }
+            self.ring.push_back(record.to_vec());
+        } else {
+            self.skipped = true;
+        }
+        Ok(())
+    }
+
+    /// Forgets records kept for context, so that records of another input are not shown around the next matching record.
+    pub fn reset(&mut self) {
+        self.skipped |= !self.ring.is_empty() || self.remaining != 0;
+        self.ring.clear();
+        self.remaining = 0;
+    }
+}
+
+/// Writes the record making it faint, also after each style sequence in it, if it has any.
+fn write_faint<W: Write + ?Sized>(output: &mut W, record: &[u8]) -> io::Result<()> {
+    if find(record, CSI).is_none() {
+        return output.write_all(record);
+    }
+    let end = record.iter().rposition(|&b| b != b'\n').map_or(0, |i| i + 1);
+    let (mut text, newlines) = record.split_at(end);
+    output.write_all(FAINT)?;
+    while let Some(i) = find(text, CSI) {
+        let n = text[i..].iter().position(|&b| b == b'm').map_or(text.len(), |n| i + n + 1);
+        output.write_all(&text[..n])?;
+        output.write_all(FAINT)?;
+        text = &text[n..];
+    }
+    output.write_all(text)?;
+    output.write_all(RESET)?;
+    output.write_all(newlines)
+}
+
+fn find(text: &[u8], pattern: &[u8]) -> Option<usize> {
+    text.windows(pattern.len()).position(|w| w == pattern)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn select(before: usize, after: usize, records: &[(&str, bool)]) -> String {
+        let mut selector = ContextSelector::new(before, after);
+        let mut output = Vec::new();
+        for (record, matched) in records {
+            selector.push(&mut output, format!("{}\n", record).as_bytes(), *matched).unwrap();
+        }
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_context() {
+        let records = [
+            ("a", false),
+            ("b", false),
+            ("c", true),
+            ("d", false),
+            ("e", false),
+            ("f", false),
+            ("g", false),
+            ("h", true),
+            ("i", true),
+            ("j", false),
+        ];
+        assert_eq!(select(1, 1, &records), "b\nc\nd\n--\ng\nh\ni\nj\n");
+        assert_eq!(select(0, 0, &records), "c\n--\nh\ni\n");
+        assert_eq!(select(2, 0, &records), "a\nb\nc\n--\nf\ng\nh\ni\n");
+        assert_eq!(select(0, 4, &records), "c\nd\ne\nf\ng\nh\ni\nj\n");
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut selector = ContextSelector::new(1, 1).with_separators(false);
+        let mut output = Vec::new();
+        selector.push(&mut output, b"a\n", true).unwrap();
+        selector.reset();
+        selector.push(&mut output, b"b\n", false).unwrap();
+        selector.reset();
+        selector.push(&mut output, b"c\n", true).unwrap();
+        assert_eq!(output, b"a\nc\n");
+    }
+
+    #[test]
+    fn test_faint() {
+        let mut output = Vec::new();
+        write_faint(&mut output, b"\x1b[0;32mok\x1b[m x\n").unwrap();
+        assert_eq!(output, b"\x1b[2m\x1b[0;32m\x1b[2mok\x1b[m\x1b[2m x\x1b[m\n");
+
+        let mut output = Vec::new();
+        write_faint(&mut output, b"plain\n").unwrap();
+        assert_eq!(output, b"plain\n");
+    }
+}
@@ -1,30 +1,43 @@
 use std::cmp::{max, min, PartialOrd};
 
 use bitmask::bitmask;
-use chrono::{DateTime, Datelike, FixedOffset, NaiveDateTime, Offset, TimeZone, Timelike};
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, NaiveDateTime, Offset, TimeZone, Timelike};
 use chrono_tz::OffsetName;
 
-use crate::fmtx::{aligned_left, Alignment, Counter, Push};
+use crate::fmtx::{aligned_left, Alignment, Push};
+use crate::locale::Locale;
 use crate::timestamp::rfc3339;
 use crate::timezone::Tz;
+use crate::width::char_width;
 
 // ---
 
 pub struct DateTimeFormatter {
     format: Vec<Item>,
     tz: Tz,
+    locale: Option<&'static Locale>,
 }
 
 impl DateTimeFormatter {
     pub fn new(format: Vec<Item>, tz: Tz) -> Self {
-        Self { format, tz }
+        Self {
+            format,
+            tz,
+            locale: None,
+        }
+    }
+
+    /// Enables locale month and weekday names instead of English ones.
+    pub fn with_locale(mut self, locale: Option<&'static Locale>) -> Self {
+        self.locale = locale;
+        self
     }
 
     pub fn format<B>(&self, buf: &mut B, dt: DateTime<FixedOffset>)
     where
         B: Push<u8>,
     {
-        format_date(buf, dt.with_timezone(&self.tz), &self.format)
+        format_date(buf, dt.with_timezone(&self.tz), &self.format, self.locale)
     }
 
     pub fn reformat_rfc3339<'a, B>(&self, buf: &mut B, ts: rfc3339::Timestamp<'a>) -> Option<()>
@@ -32,20 +45,31 @@ impl DateTimeFormatter {
         B: Push<u8>,
     {
         if ts.timezone().is_utc() && self.tz.is_utc() {
-            reformat_rfc3339(buf, ts, &self.format);
+            reformat_rfc3339(buf, ts, &self.format, self.locale);
             Some(())
         } else {
             None
         }
     }
 
+    /// Returns the maximum width of formatted timestamps in terminal columns,
+    /// trying days of all months and weekdays because their names may vary in width.
     pub fn max_length(&self) -> usize {
-        let mut counter = Counter::new();
-        let ts = NaiveDateTime::from_timestamp_opt(1654041600, 999_999_999).unwrap();
-        let offset = self.tz.offset_from_utc_datetime(&ts);
-        let tts = DateTime::<Tz>::from_naive_utc_and_offset(ts, offset);
-        self.format(&mut counter, tts.with_timezone(&offset.fix()));
-        counter.result()
+        let mut buf = Vec::new();
+        let mut result = 0;
+        for month in 1..=12 {
+            for day in 1..=7 {
+                let ts = NaiveDate::from_ymd_opt(2022, month, day)
+                    .and_then(|date| date.and_hms_nano_opt(0, 0, 0, 999_999_999))
+                    .unwrap();
+                let offset = self.tz.offset_from_utc_datetime(&ts);
+                let tts = DateTime::<Tz>::from_naive_utc_and_offset(ts, offset);
+                buf.clear();
+                self.format(&mut buf, tts.with_timezone(&offset.fix()));
+                result = max(result, String::from_utf8_lossy(&buf).chars().map(char_width).sum());
+            }
+        }
+        result
     }
 }
 
@@ -362,14 +386,14 @@ impl<'a> From<LinuxDateFormat<'a>> for Vec<Item> {
 
 // ---
 
-pub fn format_date<T, B, F>(buf: &mut B, dto: DateTime<Tz>, format: F)
+pub fn format_date<T, B, F>(buf: &mut B, dto: DateTime<Tz>, format: F, locale: Option<&'static Locale>)
 where
     B: Push<u8>,
     T: AsRef<Item>,
     F: IntoIterator<Item = T>,
 {
     let dt = dto.naive_local();
-    let mut f = Formatter::new(buf);
+    let mut f = Formatter::new(buf).with_locale(locale);
     for item in format {
         match *item.as_ref() {
             Item::Char(b) => {
@@ -496,8 +520,12 @@ where
 
 // ---
 
-pub fn reformat_rfc3339<'a, T, B, F>(buf: &mut B, sts: rfc3339::Timestamp<'a>, format: F)
-where
+pub fn reformat_rfc3339<'a, T, B, F>(
+    buf: &mut B,
+    sts: rfc3339::Timestamp<'a>,
+    format: F,
+    locale: Option<&'static Locale>,
+) where
     T: AsRef<Item>,
     B: Push<u8>,
     F: IntoIterator<Item = T>,
@@ -541,7 +569,7 @@ where
         }
     };
 
-    let mut f = Formatter::new(buf);
+    let mut f = Formatter::new(buf).with_locale(locale);
 
     for item in format {
         match *item.as_ref() {
@@ -751,12 +779,19 @@ where
 
 struct Formatter<'a, B: Push<u8>> {
     buf: &'a mut B,
+    locale: Option<&'static Locale>,
 }
 
 impl<'a, B: Push<u8>> Formatter<'a, B> {
     #[inline]
     fn new(buf: &'a mut B) -> Self {
-        Self { buf }
+        Self { buf, locale: None }
+    }
+
+    #[inline]
+    fn with_locale(mut self, locale: Option<&'static Locale>) -> Self {
+        self.locale = locale;
+        self
     }
 
     #[inline]
@@ -783,12 +818,18 @@ impl<'a, B: Push<u8>> Formatter<'a, B> {
 
     #[inline]
     fn month_short(&mut self, index: usize, flags: Flags) {
+        if let Some(locale) = self.locale {
+            return self.name(locale.months_short[index], flags, max_chars(&locale.months_short));
+        }
         let text = &MONTHS_SHORT[case_index(flags)][index].as_bytes();
         self.buf.extend_from_slice(text);
     }
 
     #[inline]
     fn month_long(&mut self, index: usize, flags: Flags) {
+        if let Some(locale) = self.locale {
+            return self.name(locale.months_long[index], flags, max_chars(&locale.months_long));
+        }
         let text = &MONTHS_LONG[case_index(flags)][index].as_bytes();
         if flags.contains(NoPadding) {
             self.buf.extend_from_slice(text)
@@ -799,12 +840,18 @@ impl<'a, B: Push<u8>> Formatter<'a, B> {
 
     #[inline]
     fn weekday_short(&mut self, index: usize, flags: Flags) {
+        if let Some(locale) = self.locale {
+            return self.name(locale.weekdays_short[index], flags, max_chars(&locale.weekdays_short));
+        }
         let text = &WEEKDAYS_SHORT[case_index(flags)][index].as_bytes();
         self.buf.extend_from_slice(text);
     }
 
     #[inline]
     fn weekday_long(&mut self, index: usize, flags: Flags) {
+        if let Some(locale) = self.locale {
+            return self.name(locale.weekdays_long[index], flags, max_chars(&locale.weekdays_long));
+        }
         let text = &WEEKDAYS_LONG[case_index(flags)][index].as_bytes();
         if flags.contains(NoPadding) {
             self.buf.extend_from_slice(text)
@@ -813,6 +860,27 @@ impl<'a, B: Push<u8>> Formatter<'a, B> {
         }
     }
 
+    /// Writes a locale name converting its case according to the flags and right-aligning it to the width in characters.
+    fn name(&mut self, text: &str, flags: Flags, width: usize) {
+        if !flags.contains(NoPadding) {
+            for _ in text.chars().count()..width {
+                self.buf.push(b' ');
+            }
+        }
+        let mut tmp = [0; 4];
+        for c in text.chars() {
+            if flags.contains(UpperCase) {
+                c.to_uppercase()
+                    .for_each(|c| self.text(c.encode_utf8(&mut tmp).as_bytes()));
+            } else if flags.contains(LowerCase) {
+                c.to_lowercase()
+                    .for_each(|c| self.text(c.encode_utf8(&mut tmp).as_bytes()));
+            } else {
+                self.text(c.encode_utf8(&mut tmp).as_bytes());
+            }
+        }
+    }
+
     #[inline]
     fn weekday_numeric(&mut self, dt: &NaiveDateTime, flags: Flags) {
         let value = if flags.contains(FromSunday) {
@@ -941,6 +1009,13 @@ fn reformat_numeric<B: Push<u8>>(buf: &mut B, flags: Flags, b: &[u8]) {
 
 // ---
 
+#[inline]
+fn max_chars(names: &[&str]) -> usize {
+    names.iter().map(|name| name.chars().count()).max().unwrap_or_default()
+}
+
+// ---
+
 #[inline]
 fn case_index(flags: Flags) -> usize {
     if flags.contains(UpperCase) {
@@ -1065,4 +1140,33 @@ mod tests {
     fn test_compile_offset() {
         assert_eq!(format("%:z"), vec![Item::TimeZoneOffset((Flags::none(), 2))]);
     }
+
+    #[test]
+    fn test_locale() {
+        let tz = Tz::FixedOffset(FixedOffset::east_opt(0).unwrap());
+        let ts = DateTime::parse_from_rfc3339("2023-03-06T10:00:00Z").unwrap();
+        let formatter = DateTimeFormatter::new(format("%a %d %b %^B %Y"), tz).with_locale(Locale::find("de-DE"));
+
+        let mut buf = Vec::new();
+        formatter.format(&mut buf, ts);
+        assert_eq!(String::from_utf8(buf).unwrap(), "Mo 06 Mär      MÄRZ 2023");
+
+        let mut buf = Vec::new();
+        let ts = rfc3339::Timestamp::parse("2023-03-06T10:00:00Z").unwrap();
+        formatter.reformat_rfc3339(&mut buf, ts).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), "Mo 06 Mär      MÄRZ 2023");
+
+        let formatter = DateTimeFormatter::new(format("%b %a"), tz).with_locale(Locale::find("fr-FR"));
+        let mut buf = Vec::new();
+        formatter.format(&mut buf, DateTime::parse_from_rfc3339("2023-05-06T10:00:00Z").unwrap());
+        assert_eq!(String::from_utf8(buf).unwrap(), "  mai sam.");
+        assert_eq!(formatter.max_length(), 10);
+    }
+
+    #[test]
+    fn test_max_length() {
+        let tz = Tz::FixedOffset(FixedOffset::east_opt(0).unwrap());
+        assert_eq!(DateTimeFormatter::new(format("%-B"), tz).max_length(), 9);
+        assert_eq!(DateTimeFormatter::new(format("%y-%m-%d %T.%3N"), tz).max_length(), 21);
+    }
 }
@@ -0,0 +1,186 @@
+// std imports
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+// local imports
+use crate::model::Record;
+
+// ---
+
+/// Settings of collapsing of consecutive duplicate records, see --dedup option.
+#[derive(Clone, Debug, Default)]
+pub struct Dedup {
+    /// Keys of fields compared along with the level and the message, all fields are compared if empty.
+    pub fields: Vec<String>,
+}
+
+impl Dedup {
+    /// Returns a hash of the parts of the record that are compared to detect duplicates, the timestamp is never compared.
+    ///
+    /// Continuation lines attached to the record are not part of it, they are compared by [Deduplicator] as formatted.
+    pub(crate) fn key(&self, record: &Record) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        record.level.hash(&mut hasher);
+        record.message.map(|message| message.get()).hash(&mut hasher);
+        if self.fields.is_empty() {
+            record.logger.hash(&mut hasher);
+            record.caller.map(|caller| (caller.name, caller.line)).hash(&mut hasher);
+            for (key, value) in record.fields() {
+                (key, value.get()).hash(&mut hasher);
+            }
+        } else {
+            for key in &self.fields {
+                record.value(key).map(|value| value.get()).hash(&mut hasher);
+            }
+        }
+        hasher.finish()
+    }
+}
+
+// ---
+
+/// Collapses consecutive formatted records with equal keys and equal continuation lines into the first one of them,
+/// given in output order, with a suffix showing the number of collapsed records.
+///
+/// Keys are paired with the index of the input, so records of different inputs are never collapsed.
+#[derive(Default)]
+pub struct Deduplicator {
+    pending: Vec<u8>,
+    key: Option<(usize, u64)>,
+    count: usize,
+    ready: Vec<u8>,
+}
+
+impl Deduplicator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keeps the record unless it duplicates the kept one, and returns the previously kept one if it is complete.
+    ///
+    /// Records without a key are never collapsed.
+    pub fn push(&mut self, key: Option<(usize, u64)>, record: &[u8]) -> Option<&[u8]> {
+        if self.count != 0 && key.is_some() && key == self.key && continuation(record) == continuation(&self.pending) {
+            self.count += 1;
+            return None;
+        }
+        let complete = self.complete();
+        std::mem::swap(&mut self.pending, &mut self.ready);
+        self.pending.clear();
+        self.pending.extend_from_slice(record);
+        self.key = key;
+        self.count = 1;
+        if complete {
+            Some(&self.ready)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the last kept record if any.
+    pub fn finish(&mut self) -> Option<&[u8]> {
+        let complete = self.complete();
+        std::mem::swap(&mut self.pending, &mut self.ready);
+        self.count = 0;
+        if complete {
+            Some(&self.ready)
+        } else {
+            None
+        }
+    }
+
+    /// Appends the number of collapsed records to the first line of the kept record, returns false if nothing is kept.
+    fn complete(&mut self) -> bool {
+        if self.count > 1 {
+            let suffix = format!(" (x{})", self.count);
+            let end = self.pending.iter().position(|&c| c == b'\n').unwrap_or(self.pending.len());
+            self.pending.splice(end..end, suffix.bytes());
+        }
+        self.count != 0
+    }
+}
+
+/// Returns the lines of the formatted record following the first one.
+fn continuation(record: &[u8]) -> &[u8] {
+    match record.iter().position(|&c| c == b'\n') {
+        Some(end) => &record[end + 1..],
+        None => &[],
+    }
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Parser, ParserSettings};
+    use crate::settings::Settings;
+    use std::iter::empty;
+
+    fn dedup(records: &[(Option<(usize, u64)>, &str)]) -> String {
+        let mut dedup = Deduplicator::new();
+        let mut output = Vec::new();
+        for (key, record) in records {
+            if let Some(record) = dedup.push(*key, record.as_bytes()) {
+                output.extend_from_slice(record);
+            }
+        }
+        if let Some(record) = dedup.finish() {
+            output.extend_from_slice(record);
+        }
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_deduplicator() {
+        assert_eq!(
+            dedup(&[
+                (Some((0, 1)), "a\n"),
+                (Some((0, 1)), "a\n"),
+                (Some((0, 2)), "b\n  at x\n"),
+                (Some((0, 2)), "b\n  at x\n"),
+                (Some((0, 2)), "b\n  at x\n"),
+                (None, "raw\n"),
+                (None, "raw\n"),
+                (Some((0, 1)), "a\n"),
+            ]),
+            "a (x2)\nb (x3)\n  at x\nraw\nraw\na\n"
+        );
+        assert_eq!(dedup(&[]), "");
+    }
+
+    #[test]
+    fn test_deduplicator_boundaries() {
+        // records with different continuation lines or from different inputs are kept
+        assert_eq!(
+            dedup(&[
+                (Some((0, 2)), "b\n  at x\n"),
+                (Some((0, 2)), "b\n  at y\n"),
+                (Some((0, 2)), "b\n"),
+                (Some((1, 2)), "b\n"),
+                (Some((1, 2)), "b\n"),
+            ]),
+            "b\n  at x\nb\n  at y\nb\nb (x2)\n"
+        );
+    }
+
+    #[test]
+    fn test_key() {
+        let settings = Settings::default();
+        let parser = Parser::new(ParserSettings::new(&settings.fields.predefined, empty(), false));
+        let key = |dedup: &Dedup, data: &str| dedup.key(&parser.parse_slice(data.as_bytes()).unwrap());
+        let all = Dedup::default();
+        let a = r#"{"ts":"2024-01-01T00:00:00Z","level":"error","msg":"m","x":1,"y":2}"#;
+        let b = r#"{"ts":"2024-01-01T00:00:01Z","level":"error","msg":"m","x":1,"y":2}"#;
+        let c = r#"{"ts":"2024-01-01T00:00:01Z","level":"error","msg":"m","x":1,"y":3}"#;
+        let d = r#"{"ts":"2024-01-01T00:00:01Z","level":"info","msg":"m","x":1,"y":2}"#;
+        assert_eq!(key(&all, a), key(&all, b));
+        assert_ne!(key(&all, a), key(&all, c));
+        assert_ne!(key(&all, a), key(&all, d));
+        let x = Dedup {
+            fields: vec!["x".into()],
+        };
+        assert_eq!(key(&x, a), key(&x, c));
+        assert_ne!(key(&x, a), key(&x, d));
+    }
+}
@@ -0,0 +1,77 @@
+// std imports
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+// third-party imports
+use nu_ansi_term::{Color, Style};
+
+// ---
+
+static VERBOSITY: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+
+// ---
+
+/// Verbosity of diagnostic messages of the application itself written to stderr.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[repr(u8)]
+pub enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+    Debug,
+}
+
+impl Verbosity {
+    pub fn new(quiet: bool, verbose: u8) -> Self {
+        match (quiet, verbose) {
+            (true, _) => Self::Quiet,
+            (false, 0) => Self::Normal,
+            (false, 1) => Self::Verbose,
+            (false, _) => Self::Debug,
+        }
+    }
+}
+
+pub fn set_verbosity(verbosity: Verbosity) {
+    VERBOSITY.store(verbosity as u8, Ordering::Relaxed);
+}
+
+pub fn verbosity() -> Verbosity {
+    match VERBOSITY.load(Ordering::Relaxed) {
+        0 => Verbosity::Quiet,
+        1 => Verbosity::Normal,
+        2 => Verbosity::Verbose,
+        _ => Verbosity::Debug,
+    }
+}
+
+/// Writes a warning, which is shown unless quiet mode is enabled.
+pub fn warning(args: fmt::Arguments) {
+    emit(Verbosity::Normal, Color::Yellow.normal(), "warning", args);
+}
+
+/// Writes an informational message, which is shown unless quiet mode is enabled.
+pub fn info(args: fmt::Arguments) {
+    emit(Verbosity::Normal, Color::Green.normal(), "info", args);
+}
+
+/// Writes a progress report, which is shown unless quiet mode is enabled.
+pub fn progress(args: fmt::Arguments) {
+    emit(Verbosity::Normal, Color::Blue.normal(), "progress", args);
+}
+
+/// Writes a notice, which is shown in verbose mode.
+pub fn notice(args: fmt::Arguments) {
+    emit(Verbosity::Verbose, Color::Cyan.normal(), "notice", args);
+}
+
+/// Writes a debug message, which is shown in debug mode.
+pub fn debug(args: fmt::Arguments) {
+    emit(Verbosity::Debug, Style::new().dimmed(), "debug", args);
+}
+
+fn emit(level: Verbosity, style: Style, label: &str, args: fmt::Arguments) {
+    if verbosity() >= level {
+        eprintln!("{}: {}", style.paint(label), args);
+    }
+}
@@ -0,0 +1,270 @@
+// std imports
+use std::cmp::max;
+use std::collections::{HashMap, VecDeque};
+use std::io::Write;
+
+// third-party imports
+use serde_json as json;
+
+// local imports
+use crate::error::*;
+use crate::formatting::RecordFormatter;
+use crate::model::{Filter, Level, Parser, RawRecord, Record};
+use crate::theme::{Indicator, Theme};
+
+// ---
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Alignment {
+    Removed(usize),
+    Added(usize),
+    Paired(usize, usize),
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct FieldChange<'a> {
+    pub key: &'a str,
+    pub old: Option<&'a str>,
+    pub new: Option<&'a str>,
+}
+
+// ---
+
+pub struct Differ<'a> {
+    parser: &'a Parser,
+    filter: &'a Filter,
+    key: Option<&'a str>,
+}
+
+impl<'a> Differ<'a> {
+    pub fn new(parser: &'a Parser, filter: &'a Filter) -> Self {
+        Self {
+            parser,
+            filter,
+            key: None,
+        }
+    }
+
+    pub fn with_key(mut self, key: Option<&'a str>) -> Self {
+        self.key = key;
+        self
+    }
+
+    pub fn run(
+        &self,
+        left: &[u8],
+        right: &[u8],
+        formatter: &mut RecordFormatter,
+        theme: &Theme,
+        output: &mut dyn Write,
+    ) -> Result<()> {
+        let (left, right) = (self.records(left), self.records(right));
+        let indicators = &theme.indicators.diff;
+        let mut buf = Vec::new();
+
+        for alignment in self.align(&left, &right) {
+            buf.clear();
+            match alignment {
+                Alignment::Removed(i) => {
+                    marked(&mut buf, &indicators.removed);
+                    formatter.format_record(&mut buf, &left[i]);
+                }
+                Alignment::Added(j) => {
+                    marked(&mut buf, &indicators.added);
+                    formatter.format_record(&mut buf, &right[j]);
+                }
+                Alignment::Paired(i, j) => {
+                    let changes = changes(&left[i], &right[j]);
+                    if changes.is_empty() {
+                        continue;
+                    }
+                    marked(&mut buf, &indicators.changed);
+                    formatter.format_record(&mut buf, &right[j]);
+                    for change in changes {
+                        if let Some(old) = change.old {
+                            buf.extend_from_slice(b"    ");
+                            marked(&mut buf, &indicators.removed);
+                            buf.extend_from_slice(change.key.as_bytes());
+                            buf.extend_from_slice(b": ");
+                            buf.extend_from_slice(old.as_bytes());
+                            buf.push(b'\n');
+                        }
+                        if let Some(new) = change.new {
+                            buf.extend_from_slice(b"    ");
+                            marked(&mut buf, &indicators.added);
+                            buf.extend_from_slice(change.key.as_bytes());
+                            buf.extend_from_slice(b": ");
+                            buf.extend_from_slice(new.as_bytes());
+                            buf.push(b'\n');
+                        }
+                    }
+                }
+            }
+            output.write_all(&buf)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn records<'b>(&self, data: &'b [u8]) -> Vec<Record<'b>> {
+        data.split(|c| *c == b'\n')
+            .filter_map(|line| json::from_slice::<RawRecord>(line).ok())
+            .map(|record| self.parser.parse(record))
+            .filter(|record| record.matches(self.filter))
+            .collect()
+    }
+
+    pub fn align(&self, left: &[Record], right: &[Record]) -> Vec<Alignment> {
+        let key = match self.key {
+            Some(key) => key,
+            None => {
+                return (0..max(left.len(), right.len()))
+                    .map(|i| match (i < left.len(), i < right.len()) {
+                        (true, true) => Alignment::Paired(i, i),
+                        (true, false) => Alignment::Removed(i),
+                        _ => Alignment::Added(i),
+                    })
+                    .collect();
+            }
+        };
+
+        let mut index = HashMap::<&str, VecDeque<usize>>::new();
+        for (j, record) in right.iter().enumerate() {
+            if let Some(value) = field(record, key) {
+                index.entry(value).or_default().push_back(j);
+            }
+        }
+
+        let mut paired = vec![false; right.len()];
+        let mut result = Vec::with_capacity(max(left.len(), right.len()));
+        for (i, record) in left.iter().enumerate() {
+            match field(record, key).and_then(|value| index.get_mut(value)?.pop_front()) {
+                Some(j) => {
+                    paired[j] = true;
+                    result.push(Alignment::Paired(i, j));
+                }
+                None => result.push(Alignment::Removed(i)),
+            }
+        }
+        result.extend(
+            paired
+                .iter()
+                .enumerate()
+                .filter(|(_, paired)| !**paired)
+                .map(|(j, _)| Alignment::Added(j)),
+        );
+        result
+    }
+}
+
+// ---
+
+pub fn changes<'a>(left: &Record<'a>, right: &Record<'a>) -> Vec<FieldChange<'a>> {
+    let mut result = Vec::new();
+    let mut push = |key: &'a str, old: Option<&'a str>, new: Option<&'a str>| {
+        if old != new {
+            result.push(FieldChange { key, old, new });
+        }
+    };
+
+    push("message", left.message.map(|x| x.get()), right.message.map(|x| x.get()));
+    push("level", left.level.map(level_name), right.level.map(level_name));
+    push("logger", left.logger, right.logger);
+    push("caller", left.caller.map(|x| x.name), right.caller.map(|x| x.name));
+    push("caller-line", left.caller.and_then(|x| x.line), right.caller.and_then(|x| x.line));
+    for (key, value) in left.fields() {
+        push(key, Some(value.get()), extra(right, key));
+    }
+    for (key, value) in right.fields() {
+        if extra(left, key).is_none() {
+            push(key, None, Some(value.get()));
+        }
+    }
+
+    result
+}
+
+fn field<'a>(record: &Record<'a>, key: &str) -> Option<&'a str> {
+    match key {
+        "msg" | "message" => record.message.map(|x| x.get()),
+        "logger" => record.logger,
+        "caller" => record.caller.map(|x| x.name),
+        _ => extra(record, key),
+    }
+}
+
+fn extra<'a>(record: &Record<'a>, key: &str) -> Option<&'a str> {
+    record.fields().find(|(k, _)| *k == key).map(|(_, v)| v.get())
+}
+
+fn level_name(level: Level) -> &'static str {
+    match level {
+        Level::Trace => "trace",
+        Level::Debug => "debug",
+        Level::Info => "info",
+        Level::Warning => "warning",
+        Level::Error => "error",
+        Level::Fatal => "fatal",
+    }
+}
+
+fn marked(buf: &mut Vec<u8>, indicator: &Indicator) {
+    buf.extend_from_slice(indicator.value.as_bytes());
+    buf.push(b' ');
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::ParserSettings;
+    use crate::settings::Settings;
+
+    fn parser() -> Parser {
+        let settings = Settings::default();
+        Parser::new(ParserSettings::new(&settings.fields.predefined, &settings.fields.ignore, false))
+    }
+
+    #[test]
+    fn test_align_by_key() {
+        let parser = parser();
+        let filter = Filter::default();
+        let differ = Differ::new(&parser, &filter).with_key(Some("id"));
+        let left = differ.records(b"{\"msg\":\"a\",\"id\":1}\n{\"msg\":\"b\",\"id\":2}\n");
+        let right = differ.records(b"{\"msg\":\"c\",\"id\":3}\n{\"msg\":\"b\",\"id\":2}\n");
+        assert_eq!(
+            differ.align(&left, &right),
+            vec![Alignment::Removed(0), Alignment::Paired(1, 1), Alignment::Added(0)]
+        );
+    }
+
+    #[test]
+    fn test_changes() {
+        let parser = parser();
+        let filter = Filter::default();
+        let differ = Differ::new(&parser, &filter);
+        let left = differ.records(b"{\"msg\":\"a\",\"x\":1,\"y\":2}\n");
+        let right = differ.records(b"{\"msg\":\"a\",\"x\":3,\"z\":4}\n");
+        assert_eq!(
+            changes(&left[0], &right[0]),
+            vec![
+                FieldChange {
+                    key: "x",
+                    old: Some("1"),
+                    new: Some("3"),
+                },
+                FieldChange {
+                    key: "y",
+                    old: Some("2"),
+                    new: None,
+                },
+                FieldChange {
+                    key: "z",
+                    old: None,
+                    new: Some("4"),
+                },
+            ]
+        );
+    }
+}
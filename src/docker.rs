@@ -0,0 +1,125 @@
+// third-party imports
+use serde::{de::IgnoredAny, Deserialize};
+use serde_json as json;
+
+// local imports
+use crate::logfmt;
+
+// ---
+
+/// Name of the field the container timestamp is added as, used as the timestamp of records having no own timestamp.
+pub const CONTAINER_TIME_FIELD: &str = "_container_time";
+
+/// Name of the field the output stream of the container, `stdout` or `stderr`, is added as.
+pub const CONTAINER_STREAM_FIELD: &str = "_container_stream";
+
+/// Name of the field plain text lines are put to.
+const MESSAGE_FIELD: &str = "msg";
+
+// ---
+
+/// Returns true if the line is a line of Docker json-file log, e.g. `{"log":"...\n","stream":"stdout","time":"..."}`.
+///
+/// The line has to start with `log` field and have exactly `log`, `stream` and `time` fields,
+/// so that application records having a `log` field are not taken for wrapped ones.
+pub fn is_wrapped(line: &[u8]) -> bool {
+    let start = line.iter().position(|b| !b.is_ascii_whitespace()).unwrap_or(line.len());
+    line[start..].starts_with(br#"{"log":"#) && json::from_slice::<Wrapper<IgnoredAny>>(line).is_ok()
+}
+
+/// Unwraps a line of Docker json-file log to the buffer as a JSON record.
+///
+/// The `log` field containing a JSON or logfmt record becomes the record, plain text becomes its message.
+/// The container timestamp and output stream are added as [CONTAINER_TIME_FIELD] and [CONTAINER_STREAM_FIELD] fields.
+/// Returns false if the line is not a valid Docker json-file log line having exactly `log`, `stream` and `time` fields,
+/// leaving the buffer empty.
+pub fn unwrap(line: &[u8], buf: &mut Vec<u8>) -> bool {
+    buf.clear();
+    let wrapper = match json::from_slice::<Wrapper<String>>(line) {
+        Ok(wrapper) => wrapper,
+        Err(_) => return false,
+    };
+    let inner = wrapper.log.trim_end_matches(['\n', '\r']);
+
+    let trimmed = inner.trim();
+    if trimmed.starts_with('{') && trimmed.ends_with('}') && json::from_str::<IgnoredAny>(trimmed).is_ok() {
+        buf.extend_from_slice(trimmed.as_bytes());
+    } else if !logfmt::to_json(inner.as_bytes(), buf) {
+        buf.extend_from_slice(b"{}");
+        push_field(buf, MESSAGE_FIELD, inner);
+    }
+
+    push_field(buf, CONTAINER_TIME_FIELD, &wrapper.time);
+    push_field(buf, CONTAINER_STREAM_FIELD, &wrapper.stream);
+    true
+}
+
+// ---
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Wrapper<L> {
+    log: L,
+    stream: String,
+    time: String,
+}
+
+/// Appends a string field to the JSON object in the buffer.
+fn push_field(buf: &mut Vec<u8>, key: &str, value: &str) {
+    buf.pop();
+    if buf.iter().rev().find(|b| !b.is_ascii_whitespace()) != Some(&b'{') {
+        buf.push(b',');
+    }
+    json::to_writer(&mut *buf, key).ok();
+    buf.push(b':');
+    json::to_writer(&mut *buf, value).ok();
+    buf.push(b'}');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(line: &str) -> Option<String> {
+        let mut buf = Vec::new();
+        if unwrap(line.as_bytes(), &mut buf) {
+            Some(String::from_utf8(buf).unwrap())
+        } else {
+            None
+        }
+    }
+
+    #[test]
+    fn test_unwrap() {
+        assert_eq!(
+            convert(r#"{"log":"{\"ts\":\"2023-12-07T20:07:05Z\",\"msg\":\"hi\"}\n","stream":"stdout","time":"2023-12-07T20:07:05.1Z"}"#)
+                .as_deref(),
+            Some(
+                r#"{"ts":"2023-12-07T20:07:05Z","msg":"hi","_container_time":"2023-12-07T20:07:05.1Z","_container_stream":"stdout"}"#
+            )
+        );
+        assert_eq!(
+            convert(r#"{"log":"level=info msg=hi\n","stream":"stderr","time":"2023-12-07T20:07:05.1Z"}"#).as_deref(),
+            Some(r#"{"level":"info","msg":"hi","_container_time":"2023-12-07T20:07:05.1Z","_container_stream":"stderr"}"#)
+        );
+        assert_eq!(
+            convert(r#"{"log":"started server\r\n","stream":"stdout","time":"2023-12-07T20:07:05.1Z"}"#).as_deref(),
+            Some(r#"{"msg":"started server","_container_time":"2023-12-07T20:07:05.1Z","_container_stream":"stdout"}"#)
+        );
+        assert_eq!(
+            convert(r#"{"log":"{ }\n","stream":"stdout","time":"t"}"#).as_deref(),
+            Some(r#"{ "_container_time":"t","_container_stream":"stdout"}"#)
+        );
+        assert_eq!(convert(r#"{"log":"{}\n"}"#), None);
+        assert_eq!(convert(r#"{"log":"hi\n","stream":"stdout","time":"t","level":"info"}"#), None);
+        assert_eq!(convert(r#"{"msg":"hi"}"#), None);
+    }
+
+    #[test]
+    fn test_is_wrapped() {
+        assert!(is_wrapped(br#"{"log":"hi\n","stream":"stdout","time":"2023-12-07T20:07:05.1Z"}"#));
+        assert!(!is_wrapped(br#"{"msg":"hi","log":"x"}"#));
+        assert!(!is_wrapped(br#"{"log":"hi\n"}"#));
+        assert!(!is_wrapped(br#"{"log":"hi\n","stream":"stdout","time":"t","host":"a"}"#));
+    }
+}
@@ -0,0 +1,255 @@
+// std imports
+use std::io::{self, Read};
+
+// third-party imports
+use clap::ValueEnum;
+
+// ---
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+const UTF16LE_BOM: &[u8] = b"\xFF\xFE";
+const UTF16BE_BOM: &[u8] = b"\xFE\xFF";
+
+/// Number of bytes needed to detect the encoding.
+const HEAD_LEN: usize = 4;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Characters of bytes 0x80 to 0xBF in windows-1251 code page, bytes 0xC0 to 0xFF are letters from U+0410 to U+044F.
+const WINDOWS_1251: [u16; 64] = [
+    0x0402, 0x0403, 0x201A, 0x0453, 0x201E, 0x2026, 0x2020, 0x2021, 0x20AC, 0x2030, 0x0409, 0x2039, 0x040A, 0x040C,
+    0x040B, 0x040F, 0x0452, 0x2018, 0x2019, 0x201C, 0x201D, 0x2022, 0x2013, 0x2014, 0xFFFD, 0x2122, 0x0459, 0x203A,
+    0x045A, 0x045C, 0x045B, 0x045F, 0x00A0, 0x040E, 0x045E, 0x0408, 0x00A4, 0x0490, 0x00A6, 0x00A7, 0x0401, 0x00A9,
+    0x0404, 0x00AB, 0x00AC, 0x00AD, 0x00AE, 0x0407, 0x00B0, 0x00B1, 0x0406, 0x0456, 0x0491, 0x00B5, 0x00B6, 0x00B7,
+    0x0451, 0x2116, 0x0454, 0x00BB, 0x0458, 0x0405, 0x0455, 0x0457,
+];
+
+/// Characters of bytes 0x80 to 0x9F in windows-1252 code page, the rest of the bytes are the same as in latin1.
+const WINDOWS_1252: [u16; 32] = [
+    0x20AC, 0xFFFD, 0x201A, 0x0192, 0x201E, 0x2026, 0x2020, 0x2021, 0x02C6, 0x2030, 0x0160, 0x2039, 0x0152, 0xFFFD,
+    0x017D, 0xFFFD, 0xFFFD, 0x2018, 0x2019, 0x201C, 0x201D, 0x2022, 0x2013, 0x2014, 0x02DC, 0x2122, 0x0161, 0x203A,
+    0x0153, 0xFFFD, 0x017E, 0x0178,
+];
+
+// ---
+
+/// Character encoding of inputs converted to UTF-8 before parsing, see --input-encoding option.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Encoding {
+    /// Detected by a byte order mark or by zero bytes of UTF-16 text, UTF-8 otherwise.
+    #[default]
+    Auto,
+    #[value(name = "utf-8", alias = "utf8")]
+    Utf8,
+    #[value(name = "utf-16le", alias = "utf16le")]
+    Utf16Le,
+    #[value(name = "utf-16be", alias = "utf16be")]
+    Utf16Be,
+    #[value(name = "windows-1251", alias = "cp1251")]
+    Windows1251,
+    #[value(name = "windows-1252", alias = "cp1252", alias = "latin1")]
+    Windows1252,
+}
+
+impl Encoding {
+    /// Returns the encoding of data starting with the given bytes, which are detected in auto mode.
+    pub fn resolve(self, head: &[u8]) -> Self {
+        if self != Self::Auto {
+            return self;
+        }
+        if head.starts_with(UTF16LE_BOM) {
+            Self::Utf16Le
+        } else if head.starts_with(UTF16BE_BOM) {
+            Self::Utf16Be
+        } else if let [a, 0, b, 0, ..] = head {
+            // text starting with 2 ASCII characters
+            if *a != 0 && *b != 0 {
+                Self::Utf16Le
+            } else {
+                Self::Utf8
+            }
+        } else if let [0, a, 0, b, ..] = head {
+            if *a != 0 && *b != 0 {
+                Self::Utf16Be
+            } else {
+                Self::Utf8
+            }
+        } else {
+            Self::Utf8
+        }
+    }
+
+    /// Returns true if data starting with the given bytes needs to be converted, so it cannot be read in place.
+    pub fn transcodes(self, head: &[u8]) -> bool {
+        self.resolve(head) != Self::Utf8 || head.starts_with(UTF8_BOM)
+    }
+
+    /// Returns the reader converting data of the stream to UTF-8 and removing the byte order mark if any.
+    pub fn apply<R: Read>(self, stream: R) -> Transcoder<R> {
+        Transcoder {
+            inner: stream,
+            encoding: self,
+            input: Vec::new(),
+            output: Vec::new(),
+            pos: 0,
+            started: false,
+            eof: false,
+        }
+    }
+}
+
+// ---
+
+/// Reader converting data of the inner reader to UTF-8, invalid sequences are replaced with U+FFFD.
+pub struct Transcoder<R> {
+    inner: R,
+    encoding: Encoding,
+    input: Vec<u8>,
+    output: Vec<u8>,
+    pos: usize,
+    started: bool,
+    eof: bool,
+}
+
+impl<R: Read> Transcoder<R> {
+    /// Reads more data and converts it, returns false at the end of the data.
+    fn fill(&mut self) -> io::Result<bool> {
+        self.output.clear();
+        self.pos = 0;
+        while self.output.is_empty() {
+            if self.eof {
+                return Ok(false);
+            }
+            let len = self.input.len();
+            self.input.resize(len + CHUNK_SIZE, 0);
+            let n = self.inner.read(&mut self.input[len..])?;
+            self.input.truncate(len + n);
+            self.eof = n == 0;
+            if !self.started {
+                if self.input.len() < HEAD_LEN && !self.eof {
+                    continue;
+                }
+                self.start();
+            }
+            self.convert();
+        }
+        Ok(true)
+    }
+
+    /// Detects the encoding and skips the byte order mark.
+    fn start(&mut self) {
+        self.started = true;
+        self.encoding = self.encoding.resolve(&self.input);
+        let bom: &[u8] = match self.encoding {
+            Encoding::Utf8 => UTF8_BOM,
+            Encoding::Utf16Le => UTF16LE_BOM,
+            Encoding::Utf16Be => UTF16BE_BOM,
+            _ => b"",
+        };
+        if self.input.starts_with(bom) {
+            self.input.drain(..bom.len());
+        }
+    }
+
+    fn convert(&mut self) {
+        match self.encoding {
+            Encoding::Auto | Encoding::Utf8 => std::mem::swap(&mut self.input, &mut self.output),
+            Encoding::Utf16Le => self.convert_utf16(u16::from_le_bytes),
+            Encoding::Utf16Be => self.convert_utf16(u16::from_be_bytes),
+            Encoding::Windows1251 => self.convert_single_byte(|c| match c {
+                0x80..=0xBF => WINDOWS_1251[usize::from(c - 0x80)],
+                _ => 0x0410 + u16::from(c - 0xC0),
+            }),
+            Encoding::Windows1252 => self.convert_single_byte(|c| match c {
+                0x80..=0x9F => WINDOWS_1252[usize::from(c - 0x80)],
+                _ => u16::from(c),
+            }),
+        }
+    }
+
+    fn convert_utf16(&mut self, unit: fn([u8; 2]) -> u16) {
+        let mut units = self
+            .input
+            .chunks_exact(2)
+            .map(|pair| unit([pair[0], pair[1]]))
+            .collect::<Vec<_>>();
+        let mut used = units.len() * 2;
+        // a high surrogate at the end of the chunk waits for the low one
+        if !self.eof && matches!(units.last(), Some(0xD800..=0xDBFF)) {
+            units.pop();
+            used -= 2;
+        }
+        let mut buf = [0; 4];
+        for c in char::decode_utf16(units) {
+            let c = c.unwrap_or(char::REPLACEMENT_CHARACTER);
+            self.output.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+        }
+        self.input.drain(..used);
+        if self.eof && !self.input.is_empty() {
+            self.input.clear();
+            self.output
+                .extend_from_slice(char::REPLACEMENT_CHARACTER.encode_utf8(&mut buf).as_bytes());
+        }
+    }
+
+    fn convert_single_byte<F: Fn(u8) -> u16>(&mut self, high: F) {
+        let mut buf = [0; 4];
+        for &c in &self.input {
+            if c < 0x80 {
+                self.output.push(c);
+            } else {
+                let c = char::from_u32(u32::from(high(c))).unwrap_or(char::REPLACEMENT_CHARACTER);
+                self.output.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            }
+        }
+        self.input.clear();
+    }
+}
+
+impl<R: Read> Read for Transcoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.output.len() {
+            // data already in UTF-8 is read directly once the byte order mark is skipped
+            if self.started && self.encoding == Encoding::Utf8 && self.input.is_empty() {
+                return self.inner.read(buf);
+            }
+            if !self.fill()? {
+                return Ok(0);
+            }
+        }
+        let n = buf.len().min(self.output.len() - self.pos);
+        buf[..n].copy_from_slice(&self.output[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(encoding: Encoding, data: &[u8]) -> String {
+        let mut result = String::new();
+        encoding.apply(data).read_to_string(&mut result).unwrap();
+        result
+    }
+
+    #[test]
+    fn test_transcoder() {
+        let utf16le = b"\xFF\xFE{\0}\0\n\0\x3D\xD8\x00\xDE".to_vec();
+        assert_eq!(convert(Encoding::Auto, &utf16le), "{}\n\u{1F600}");
+        assert_eq!(convert(Encoding::Auto, b"\0{\0}\0\n"), "{}\n");
+        assert_eq!(convert(Encoding::Auto, b"\xEF\xBB\xBF{}"), "{}");
+        assert_eq!(convert(Encoding::Auto, b"{}"), "{}");
+        assert_eq!(
+            convert(Encoding::Windows1251, b"\xCF\xF0\xE8\xE2\xE5\xF2 \xB9"),
+            "Привет №"
+        );
+        assert_eq!(convert(Encoding::Windows1252, b"caf\xE9 \x80"), "café €");
+        assert_eq!(convert(Encoding::Utf16Le, b"a\0b"), "a\u{FFFD}");
+        assert!(Encoding::Auto.transcodes(b"\xEF\xBB\xBF{}"));
+        assert!(!Encoding::Auto.transcodes(b"{\"a\":1}"));
+    }
+}
@@ -35,8 +35,34 @@ pub enum Error {
     InvalidLevel(#[from] InvalidLevelError),
     #[error("cannot recognize time {0:?}")]
     UnrecognizedTime(String),
+    #[error("cannot recognize logfmt record {0:?}")]
+    UnrecognizedLogfmt(String),
     #[error("unknown theme {name:?}, use any of {known:?}")]
     UnknownTheme { name: String, known: Vec<String> },
+    #[error("unknown element {element:?} in theme {theme:?}, use any of {known:?}")]
+    UnknownThemeElement {
+        theme: String,
+        element: String,
+        known: Vec<String>,
+    },
+    #[error("unknown profile {name:?}, use any of {known:?}")]
+    UnknownProfile { name: String, known: Vec<String> },
+    #[error("unknown workspace {name:?}, use any of {known:?}")]
+    UnknownWorkspace { name: String, known: Vec<String> },
+    #[error("compression level {level} is not supported by {compression}, use a level in range {levels:?}")]
+    InvalidCompressionLevel {
+        compression: &'static str,
+        level: u32,
+        levels: std::ops::RangeInclusive<u32>,
+    },
+    #[error("unknown locale {name:?}, use any of {known:?}")]
+    UnknownLocale { name: String, known: Vec<String> },
+    #[error("unknown time zone {name:?}, similar known time zones are {known:?}")]
+    UnknownTimeZone { name: String, known: Vec<String> },
+    #[error("history has no invocation #{number}, there are {count} of them")]
+    UnknownHistoryEntry { number: usize, count: usize },
+    #[error("arguments of invocation #{number} are redacted, see history.redact setting")]
+    RedactedHistoryEntry { number: usize },
     #[error("failed to parse utf-8 string: {0}")]
     Utf8Error(#[from] std::str::Utf8Error),
     #[error("failed to construct utf-8 string from bytes: {0}")]
@@ -45,8 +71,21 @@ pub enum Error {
     YamlError(#[from] serde_yaml::Error),
     #[error("wrong field filter format: {0}")]
     WrongFieldFilter(String),
+    #[error("field {key:?} has {actual} value {value:?} while filter with strict typing expects {expected}")]
+    TypeMismatch {
+        key: String,
+        expected: &'static str,
+        actual: &'static str,
+        value: String,
+    },
+    #[error("wrong logger level threshold {0:?}, use form like {:?}", "app.db.*=debug")]
+    WrongLoggerLevel(String),
+    #[error("wrong ip network {0:?}, use forms like {:?}, {:?} or {:?}", "10.0.0.0/8", "fe80::/10", "192.168.1.1")]
+    WrongIpNetwork(String),
     #[error("wrong regular expression: {0}")]
     WrongRegularExpression(#[from] regex::Error),
+    #[error("regular expression {pattern:?} is too complex, its compiled size exceeds the limit of {limit} bytes")]
+    RegexTooComplex { pattern: String, limit: usize },
     #[error("inconsistent index: {details}")]
     InconsistentIndex { details: String },
     #[error("failed to open file '{}' for reading: {source}", HILITE.paint(.path.to_string_lossy()))]
@@ -67,6 +106,31 @@ pub enum Error {
         #[source]
         source: io::Error,
     },
+    #[error("{}:{line}: {source}", HILITE.paint(.path.to_string_lossy()))]
+    InvalidQuery {
+        path: PathBuf,
+        line: usize,
+        #[source]
+        source: Box<Error>,
+    },
+    #[error("invalid json schema in file '{}': {message}", HILITE.paint(.path.to_string_lossy()))]
+    InvalidSchema { path: PathBuf, message: String },
+    #[error("invalid query {query:?} at position {position}: {message}")]
+    InvalidQueryExpression {
+        query: String,
+        position: usize,
+        message: String,
+    },
+    #[error("recursive inclusion of query file '{}'", HILITE.paint(.path.to_string_lossy()))]
+    RecursiveQueryInclude { path: PathBuf },
+    #[error("split screen view requires exactly 2 inputs, got {0}")]
+    WrongSplitScreenInputCount(usize),
+    #[error("time budget of {budget:?} exceeded, output is truncated")]
+    TimeBudgetExceeded { budget: std::time::Duration },
+    #[error("processing is cancelled, output is incomplete")]
+    Cancelled,
+    #[error("{0} of the inputs could not be processed, output is incomplete")]
+    InputsFailed(usize),
     #[error("invalid index header")]
     InvalidIndexHeader,
     #[error("requested sorting of messages in {} file '{}' that is not currently supported", HILITE.paint(.format), HILITE.paint(.path.to_string_lossy()))]
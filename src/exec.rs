@@ -0,0 +1,99 @@
+// std imports
+use std::io::{self, Read};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+
+// third-party imports
+use crossbeam_channel as channel;
+
+// local imports
+use crate::iox::{forward_lines, ChannelReader};
+
+// ---
+
+/// Spawns the command and returns a stream with lines of its standard output and standard error.
+///
+/// The child process is killed when the stream is dropped.
+pub fn spawn(command: &[String]) -> io::Result<ChildStream> {
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty command"))?;
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let (tx, rx) = channel::bounded(64);
+    if let Some(stdout) = child.stdout.take() {
+        let tx = tx.clone();
+        thread::spawn(move || forward_lines(stdout, tx));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        thread::spawn(move || forward_lines(stderr, tx));
+    }
+    Ok(ChildStream {
+        child,
+        reader: ChannelReader::new(rx),
+    })
+}
+
+/// Splits a command line into arguments separated by whitespace, honoring single and double quotes and backslash escapes.
+pub fn split(command: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut arg: Option<String> = None;
+    let mut quote = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match (c, quote) {
+            ('\\', Some('\'')) => arg.get_or_insert_with(String::new).push(c),
+            ('\\', _) => arg.get_or_insert_with(String::new).extend(chars.next()),
+            (c, Some(q)) if c == q => quote = None,
+            ('\'' | '"', None) => {
+                arg.get_or_insert_with(String::new);
+                quote = Some(c);
+            }
+            (c, None) if c.is_whitespace() => args.extend(arg.take()),
+            (c, _) => arg.get_or_insert_with(String::new).push(c),
+        }
+    }
+    args.extend(arg);
+    args
+}
+
+// ---
+
+/// Output of a child process, see [spawn].
+pub struct ChildStream {
+    child: Child,
+    reader: ChannelReader,
+}
+
+impl Read for ChildStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+impl Drop for ChildStream {
+    fn drop(&mut self) {
+        if let Ok(None) = self.child.try_wait() {
+            self.child.kill().ok();
+        }
+        self.child.wait().ok();
+    }
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split() {
+        assert_eq!(split("kubectl logs -f pod/xyz"), vec!["kubectl", "logs", "-f", "pod/xyz"]);
+        assert_eq!(split(r#"sh -c 'echo "a b"'  x\ y """#), vec!["sh", "-c", r#"echo "a b""#, "x y", ""]);
+        assert_eq!(split("  "), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,243 @@
+// std imports
+use std::borrow::Cow;
+
+// third-party imports
+use clap::ValueEnum;
+use serde_json::{self as json, value::RawValue};
+
+// local imports
+use crate::model::Record;
+use crate::settings::OutputFormat;
+
+// ---
+
+/// Name of the column with the timestamp of a record.
+pub const TIME: &str = "time";
+/// Name of the column with the level of a record.
+pub const LEVEL: &str = "level";
+/// Name of the column with the logger of a record.
+pub const LOGGER: &str = "logger";
+/// Name of the column with the message of a record.
+pub const MESSAGE: &str = "message";
+/// Name of the column with the caller of a record.
+pub const CALLER: &str = "caller";
+
+/// Columns exported in CSV format if none are selected.
+const DEFAULT_CSV_COLUMNS: [&str; 4] = [TIME, LEVEL, LOGGER, MESSAGE];
+
+// ---
+
+/// Exports records in a machine-readable format, see --output-format option.
+///
+/// Columns are either keys of fields, where nested keys are separated by dots, or names of the predefined fields.
+/// Without selected columns, JSON records are exported as is and logfmt records contain all fields.
+pub struct RecordExporter {
+    format: Format,
+    columns: Vec<String>,
+}
+
+impl RecordExporter {
+    /// Returns None for text format, which is rendered by the record formatter instead.
+    pub fn new(format: OutputFormat, columns: Vec<String>) -> Option<Self> {
+        let format = match format {
+            OutputFormat::Text => return None,
+            OutputFormat::Json => Format::Json,
+            OutputFormat::Logfmt => Format::Logfmt,
+            OutputFormat::Csv => Format::Csv,
+        };
+        let columns = if columns.is_empty() && format == Format::Csv {
+            DEFAULT_CSV_COLUMNS.iter().map(|&column| column.into()).collect()
+        } else {
+            columns
+        };
+        Some(Self { format, columns })
+    }
+
+    /// Writes the header row preceding exported records, if the format has one.
+    pub fn header(&self, buf: &mut Vec<u8>) {
+        if self.format == Format::Csv {
+            for (i, column) in self.columns.iter().enumerate() {
+                if i != 0 {
+                    buf.push(b',');
+                }
+                push_csv(buf, column);
+            }
+            buf.push(b'\n');
+        }
+    }
+
+    /// Writes the record followed by a newline, the source is the original JSON record.
+    pub fn export(&self, buf: &mut Vec<u8>, record: &Record, source: &[u8]) {
+        match self.format {
+            Format::Json if self.columns.is_empty() => buf.extend_from_slice(source),
+            Format::Json => {
+                buf.push(b'{');
+                let mut first = true;
+                for (key, value) in self.columns.iter().filter_map(|key| Some((key, column(record, key)?))) {
+                    if !first {
+                        buf.push(b',');
+                    }
+                    first = false;
+                    json::to_writer(&mut *buf, key).ok();
+                    buf.push(b':');
+                    match value {
+                        Value::Text(text) => json::to_writer(&mut *buf, &text).ok(),
+                        Value::Raw(raw) => json::to_writer(&mut *buf, raw).ok(),
+                    };
+                }
+                buf.push(b'}');
+            }
+            Format::Logfmt if self.columns.is_empty() => {
+                let predefined = [TIME, LEVEL, LOGGER, MESSAGE, CALLER].into_iter();
+                let predefined = predefined.filter_map(|key| Some((key, column(record, key)?)));
+                let fields = record.fields().map(|&(key, value)| (key, Value::Raw(value)));
+                push_logfmt_fields(buf, predefined.chain(fields));
+            }
+            Format::Logfmt => {
+                let columns = self.columns.iter().filter_map(|key| Some((key.as_str(), column(record, key)?)));
+                push_logfmt_fields(buf, columns);
+            }
+            Format::Csv => {
+                for (i, key) in self.columns.iter().enumerate() {
+                    if i != 0 {
+                        buf.push(b',');
+                    }
+                    match column(record, key) {
+                        Some(Value::Text(text)) => push_csv(buf, &text),
+                        Some(Value::Raw(raw)) => match raw.get().as_bytes()[0] {
+                            b'"' => push_csv(buf, &json::from_str::<Cow<str>>(raw.get()).unwrap_or_default()),
+                            b'n' => {}
+                            _ => push_csv(buf, raw.get()),
+                        },
+                        None => {}
+                    }
+                }
+            }
+        }
+        buf.push(b'\n');
+    }
+}
+
+// ---
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Format {
+    Json,
+    Logfmt,
+    Csv,
+}
+
+enum Value<'a> {
+    Text(Cow<'a, str>),
+    Raw(&'a RawValue),
+}
+
+fn column<'a>(record: &Record<'a>, key: &str) -> Option<Value<'a>> {
+    match key {
+        TIME => record.ts.as_ref().map(|ts| Value::Text(ts.raw().into())),
+        LEVEL => record
+            .level
+            .and_then(|level| level.to_possible_value())
+            .map(|value| Value::Text(value.get_name().to_owned().into())),
+        LOGGER => record.logger.map(|logger| Value::Text(logger.into())),
+        MESSAGE => record.message.map(Value::Raw),
+        CALLER => record.caller.as_ref().map(|caller| Value::Text(caller.text())),
+        _ => record.value(key).map(Value::Raw),
+    }
+}
+
+fn push_logfmt_fields<'a, I>(buf: &mut Vec<u8>, fields: I)
+where
+    I: Iterator<Item = (&'a str, Value<'a>)>,
+{
+    for (i, (key, value)) in fields.enumerate() {
+        if i != 0 {
+            buf.push(b' ');
+        }
+        buf.extend_from_slice(key.as_bytes());
+        buf.push(b'=');
+        match value {
+            Value::Text(text) => push_logfmt(buf, &text),
+            Value::Raw(raw) => match raw.get().as_bytes()[0] {
+                b'"' => push_logfmt(buf, &json::from_str::<Cow<str>>(raw.get()).unwrap_or_default()),
+                b'{' | b'[' => {
+                    json::to_writer(&mut *buf, raw.get()).ok();
+                }
+                _ => buf.extend_from_slice(raw.get().as_bytes()),
+            },
+        }
+    }
+}
+
+/// Writes the value bare if possible, otherwise quoted.
+fn push_logfmt(buf: &mut Vec<u8>, value: &str) {
+    if value.is_empty() || value.chars().any(|c| c == ' ' || c == '=' || c == '"' || c.is_control()) {
+        json::to_writer(&mut *buf, value).ok();
+    } else {
+        buf.extend_from_slice(value.as_bytes());
+    }
+}
+
+/// Writes the value quoted if it contains separators, quotes or line breaks.
+fn push_csv(buf: &mut Vec<u8>, value: &str) {
+    if value.contains([',', '"', '\n', '\r']) {
+        buf.push(b'"');
+        buf.extend_from_slice(value.replace('"', "\"\"").as_bytes());
+        buf.push(b'"');
+    } else {
+        buf.extend_from_slice(value.as_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Parser, ParserSettings};
+    use crate::settings::Settings;
+    use std::iter::empty;
+
+    fn export(format: OutputFormat, columns: &[&str], source: &str) -> String {
+        let exporter = RecordExporter::new(format, columns.iter().map(|&c| c.into()).collect()).unwrap();
+        let settings = Settings::default();
+        let parser = Parser::new(ParserSettings::new(&settings.fields.predefined, empty(), false));
+        let record = parser.parse_slice(source.as_bytes()).unwrap();
+        let mut buf = Vec::new();
+        exporter.header(&mut buf);
+        exporter.export(&mut buf, &record, source.as_bytes());
+        String::from_utf8(buf).unwrap()
+    }
+
+    const SOURCE: &str = r#"{"ts":"2023-12-07T20:07:05Z","level":"info","msg":"a \"b\", c","user":{"id":42},"tags":["x"],"x":null}"#;
+
+    #[test]
+    fn test_json() {
+        assert_eq!(export(OutputFormat::Json, &[], SOURCE), format!("{}\n", SOURCE));
+        assert_eq!(
+            export(OutputFormat::Json, &["level", "user.id", "missing"], SOURCE),
+            "{\"level\":\"info\",\"user.id\":42}\n"
+        );
+    }
+
+    #[test]
+    fn test_logfmt() {
+        assert_eq!(
+            export(OutputFormat::Logfmt, &[], SOURCE),
+            "time=2023-12-07T20:07:05Z level=info message=\"a \\\"b\\\", c\" user=\"{\\\"id\\\":42}\" tags=\"[\\\"x\\\"]\" x=null\n"
+        );
+        assert_eq!(export(OutputFormat::Logfmt, &["user.id", "level"], SOURCE), "user.id=42 level=info\n");
+    }
+
+    #[test]
+    fn test_csv() {
+        assert_eq!(
+            export(OutputFormat::Csv, &[], SOURCE),
+            "time,level,logger,message\n2023-12-07T20:07:05Z,info,,\"a \"\"b\"\", c\"\n"
+        );
+        assert_eq!(export(OutputFormat::Csv, &["x", "user.id", "tags"], SOURCE), "x,user.id,tags\n,42,\"[\"\"x\"\"]\"\n");
+    }
+
+    #[test]
+    fn test_text() {
+        assert!(RecordExporter::new(OutputFormat::Text, Vec::new()).is_none());
+    }
+}
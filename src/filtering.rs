@@ -1,5 +1,7 @@
 use std::{collections::HashMap, hash::Hash};
 
+use wildmatch::WildMatch;
+
 // ---
 
 pub trait KeyNormalize: Clone {
@@ -179,6 +181,66 @@ impl PartialEq<&str> for Key {
 
 // ---
 
+/// Selection of top-level fields by wildcard patterns, see --output-fields and --hide options.
+///
+/// A pattern like `k8s.*` also matches `k8s` key itself, so that it selects both nested and flattened fields.
+#[derive(Default, Clone)]
+pub struct FieldSelection {
+    shown: Vec<KeyPattern>,
+    hidden: Vec<KeyPattern>,
+}
+
+impl FieldSelection {
+    /// Shows only fields matching the patterns, ordered by the first matching pattern, or all fields if empty.
+    pub fn with_shown<I: IntoIterator<Item = S>, S: AsRef<str>>(mut self, patterns: I) -> Self {
+        self.shown = patterns.into_iter().map(|p| KeyPattern::new(p.as_ref())).collect();
+        self
+    }
+
+    /// Hides fields matching the patterns.
+    pub fn with_hidden<I: IntoIterator<Item = S>, S: AsRef<str>>(mut self, patterns: I) -> Self {
+        self.hidden = patterns.into_iter().map(|p| KeyPattern::new(p.as_ref())).collect();
+        self
+    }
+
+    /// Returns true if fields are to be reordered by the patterns of shown fields.
+    pub fn ordered(&self) -> bool {
+        !self.shown.is_empty()
+    }
+
+    /// Returns position of the field with the given key in the output order, or None if it is not selected.
+    pub fn rank(&self, key: &str) -> Option<usize> {
+        if self.hidden.iter().any(|p| p.matches(key)) {
+            return None;
+        }
+        if self.shown.is_empty() {
+            return Some(0);
+        }
+        self.shown.iter().position(|p| p.matches(key))
+    }
+}
+
+#[derive(Clone)]
+struct KeyPattern {
+    key: WildMatch,
+    parent: Option<WildMatch>,
+}
+
+impl KeyPattern {
+    fn new(pattern: &str) -> Self {
+        Self {
+            key: WildMatch::new(pattern),
+            parent: pattern.strip_suffix(".*").map(WildMatch::new),
+        }
+    }
+
+    fn matches(&self, key: &str) -> bool {
+        self.key.matches(key) || self.parent.as_ref().map_or(false, |parent| parent.matches(key))
+    }
+}
+
+// ---
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -198,4 +260,29 @@ mod tests {
         let b = a.get("b").unwrap();
         assert_eq!(b.setting(), IncludeExcludeSetting::Include);
     }
+
+    #[test]
+    fn test_field_selection() {
+        let selection = FieldSelection::default();
+        assert!(!selection.ordered());
+        assert_eq!(selection.rank("a"), Some(0));
+
+        let selection = FieldSelection::default()
+            .with_shown(["trace_id", "http.*", "user*"])
+            .with_hidden(["k8s.*", "user-agent"]);
+        assert!(selection.ordered());
+        assert_eq!(selection.rank("trace_id"), Some(0));
+        assert_eq!(selection.rank("http"), Some(1));
+        assert_eq!(selection.rank("http.status"), Some(1));
+        assert_eq!(selection.rank("httpx"), None);
+        assert_eq!(selection.rank("user.id"), Some(2));
+        assert_eq!(selection.rank("user-agent"), None);
+        assert_eq!(selection.rank("k8s"), None);
+        assert_eq!(selection.rank("span_id"), None);
+
+        let selection = FieldSelection::default().with_hidden(["k8s.*"]);
+        assert_eq!(selection.rank("k8s.pod"), None);
+        assert_eq!(selection.rank("k8s"), None);
+        assert_eq!(selection.rank("k8sx"), Some(0));
+    }
 }
@@ -0,0 +1,225 @@
+// std imports
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+// unix-only std imports
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+
+// local imports
+use crate::compression::{self, Decompression};
+use crate::input::{Input, InputReference};
+
+// ---
+
+/// Change of a followed file detected by [FollowedFile::check].
+#[derive(Debug, Eq, PartialEq)]
+pub enum Rotation {
+    /// The file was truncated in place, e.g. by logrotate with `copytruncate` option.
+    Truncated,
+    /// Another file has been created at the path after the followed one was renamed or removed.
+    Replaced,
+}
+
+// ---
+
+/// File opened for following, keeps track of its identity and the number of bytes consumed from it.
+pub struct FollowedFile {
+    pub input: Input,
+    meta: fs::Metadata,
+    position: Arc<AtomicU64>,
+    compressed: bool,
+}
+
+impl FollowedFile {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Self::open_at(path, 0)
+    }
+
+    /// Opens the file skipping the given number of bytes, e.g. processed by the previous run.
+    pub fn open_at(path: &Path, offset: u64) -> io::Result<Self> {
+        let reference = InputReference::File(path.into());
+        let mut file = File::open(path)
+            .map_err(|e| io::Error::new(e.kind(), format!("failed to open {}: {}", reference.description(), e)))?;
+        let meta = file.metadata()?;
+        let compressed = meta.is_file() && Decompression::Auto.resolve(&compression::read_head(&mut file)?).is_some();
+        if meta.is_file() {
+            file.seek(SeekFrom::Start(offset))?;
+        }
+        let position = Arc::new(AtomicU64::new(offset));
+        let stream = Box::new(PositionTracker::new(file, position.clone()));
+        let input = Input::open_stream(&path.into(), stream, Decompression::Auto)?;
+        Ok(Self {
+            input,
+            meta,
+            position,
+            compressed,
+        })
+    }
+
+    /// Returns true if the followed file is a regular file and can grow, unlike a pipe.
+    pub fn is_file(&self) -> bool {
+        self.meta.is_file()
+    }
+
+    pub fn metadata(&self) -> &fs::Metadata {
+        &self.meta
+    }
+
+    /// Returns true if the file is compressed, so that positions in it do not correspond to positions in decompressed data.
+    pub fn is_compressed(&self) -> bool {
+        self.compressed
+    }
+
+    /// Returns the number of bytes consumed from the file.
+    pub fn position(&self) -> u64 {
+        self.position.load(Ordering::Relaxed)
+    }
+
+    /// Compares the followed file with the one currently found at the path.
+    ///
+    /// Nothing is reported while the path does not exist, the rest of a renamed file can still be read in this case.
+    pub fn check(&self, path: &Path) -> Option<Rotation> {
+        let current = fs::metadata(path).ok()?;
+        if !same_file(&self.meta, &current) {
+            Some(Rotation::Replaced)
+        } else if current.len() < self.position.load(Ordering::Relaxed) {
+            Some(Rotation::Truncated)
+        } else {
+            None
+        }
+    }
+}
+
+// ---
+
+struct PositionTracker<R> {
+    inner: R,
+    position: Arc<AtomicU64>,
+}
+
+impl<R> PositionTracker<R> {
+    fn new(inner: R, position: Arc<AtomicU64>) -> Self {
+        Self { inner, position }
+    }
+}
+
+impl<R: Read> Read for PositionTracker<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.position.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+impl<R: Seek> Seek for PositionTracker<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = self.inner.seek(pos)?;
+        self.position.store(position, Ordering::Relaxed);
+        Ok(position)
+    }
+}
+
+// ---
+
+#[cfg(unix)]
+fn same_file(a: &fs::Metadata, b: &fs::Metadata) -> bool {
+    a.dev() == b.dev() && a.ino() == b.ino()
+}
+
+#[cfg(not(unix))]
+fn same_file(a: &fs::Metadata, b: &fs::Metadata) -> bool {
+    a.created().ok() == b.created().ok()
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::OpenOptions;
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    fn read(file: &mut FollowedFile) -> String {
+        let mut data = String::new();
+        file.input.stream.read_to_string(&mut data).unwrap();
+        data
+    }
+
+    fn append(path: &Path, data: &str) {
+        OpenOptions::new().append(true).create(true).open(path).unwrap().write_all(data.as_bytes()).unwrap();
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("hl-follow-test-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_truncate() {
+        let dir = temp_dir("truncate");
+        let path = dir.join("app.log");
+        append(&path, "a\nb\n");
+        let mut file = FollowedFile::open(&path).unwrap();
+        assert_eq!(read(&mut file), "a\nb\n");
+        assert_eq!(file.check(&path), None);
+        fs::write(&path, "c\n").unwrap();
+        assert_eq!(file.check(&path), Some(Rotation::Truncated));
+        let mut file = FollowedFile::open(&path).unwrap();
+        assert_eq!(read(&mut file), "c\n");
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_open_at() {
+        let dir = temp_dir("open-at");
+        let path = dir.join("app.log");
+        append(&path, "a\nb\n");
+        let mut file = FollowedFile::open_at(&path, 2).unwrap();
+        assert_eq!(file.position(), 2);
+        assert_eq!(read(&mut file), "b\n");
+        assert_eq!(file.position(), 4);
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_rename_and_create() {
+        let dir = temp_dir("rename");
+        let path = dir.join("app.log");
+        let rotated = dir.join("app.log.1");
+        append(&path, "a\n");
+        let mut file = FollowedFile::open(&path).unwrap();
+        assert_eq!(read(&mut file), "a\n");
+        fs::rename(&path, &rotated).unwrap();
+        append(&rotated, "b\n");
+        assert_eq!(file.check(&path), None);
+        append(&path, "c\n");
+        assert_eq!(file.check(&path), Some(Rotation::Replaced));
+        assert_eq!(read(&mut file), "b\n");
+        let mut file = FollowedFile::open(&path).unwrap();
+        assert_eq!(read(&mut file), "c\n");
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_copytruncate() {
+        let dir = temp_dir("copytruncate");
+        let path = dir.join("app.log");
+        append(&path, "a\nb\n");
+        let mut file = FollowedFile::open(&path).unwrap();
+        assert_eq!(read(&mut file), "a\nb\n");
+        fs::copy(&path, dir.join("app.log.1")).unwrap();
+        OpenOptions::new().write(true).open(&path).unwrap().set_len(0).unwrap();
+        assert_eq!(file.check(&path), Some(Rotation::Truncated));
+        append(&path, "c\n");
+        assert_eq!(file.check(&path), Some(Rotation::Truncated));
+        let mut file = FollowedFile::open(&path).unwrap();
+        assert_eq!(read(&mut file), "c\n");
+        fs::remove_dir_all(dir).ok();
+    }
+}
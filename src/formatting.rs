@@ -1,22 +1,31 @@
 // std imports
-use std::sync::Arc;
+use std::collections::HashSet;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
 
 // third-party imports
+use itertools::{Either, Itertools};
 use json::{de::Read, de::StrRead, value::RawValue};
+use once_cell::sync::Lazy;
 use serde_json as json;
 
 // local imports
 use crate::datefmt;
-use crate::filtering::IncludeExcludeSetting;
+use crate::diagnostics;
+use crate::filtering::{FieldSelection, IncludeExcludeSetting};
 use crate::fmtx;
+use crate::highlight::Highlighter;
+use crate::locale::{is_measurement, Locale};
 use crate::model;
-use crate::settings::Formatting;
+use crate::palette::{self, ColorBy, ColorTarget};
+use crate::settings::{CallerPosition, EmptyValueDisplay, Formatting, TimeGaps};
 use crate::theme;
+use crate::themecfg;
 use crate::IncludeExcludeKeyFilter;
 use datefmt::DateTimeFormatter;
 use fmtx::{aligned_left, centered};
 use model::Level;
-use theme::{Element, StylingPush, Theme};
+use theme::{Element, SourceKind, StylingPush, Theme};
 
 // ---
 
@@ -24,6 +33,36 @@ type Buf = Vec<u8>;
 
 // ---
 
+/// Maximum number of distinct keys reported as shown the same after stripping prefixes.
+const MAX_KEY_COLLISION_WARNINGS: usize = 16;
+
+static KEY_COLLISIONS_WARNED: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+// ---
+
+/// Number of distinct lanes, values of the lane field are distributed among them by hash,
+/// the same as the number of colors of the default palette used by --color-by option.
+const LANE_COUNT: usize = themecfg::DEFAULT_PALETTE.len();
+
+/// Settings of lanes marking messages by values of a field, e.g. thread id, see --lanes-by option.
+#[derive(Clone, Debug)]
+pub struct Lanes {
+    /// Key of the field which values define the lanes.
+    pub key: String,
+    /// Number of columns each next lane is shifted by.
+    pub indent: usize,
+}
+
+impl Lanes {
+    /// Returns the lane of the record, which is the same for the same field values in any run.
+    fn lane(&self, rec: &model::Record) -> Option<usize> {
+        let (_, value) = rec.fields().find(|(k, _)| *k == self.key)?;
+        Some(palette::slot(value.get(), LANE_COUNT))
+    }
+}
+
+// ---
+
 pub struct RecordFormatter {
     theme: Arc<Theme>,
     unescape_fields: bool,
@@ -31,7 +70,18 @@ pub struct RecordFormatter {
     ts_width: usize,
     hide_empty_fields: bool,
     fields: Arc<IncludeExcludeKeyFilter>,
+    selection: Arc<FieldSelection>,
     cfg: Formatting,
+    icons: bool,
+    sort_fields: bool,
+    time_gaps: Option<TimeGaps>,
+    prev_ts: Option<(i64, u32)>,
+    lanes: Option<Lanes>,
+    color_by: Option<ColorBy>,
+    locale: Option<&'static Locale>,
+    continuation_indent: usize,
+    highlighter: Highlighter,
+    stripped_prefixes: Vec<String>,
 }
 
 impl RecordFormatter {
@@ -50,7 +100,18 @@ impl RecordFormatter {
             ts_width,
             hide_empty_fields,
             fields,
+            selection: Arc::new(FieldSelection::default()),
             cfg,
+            icons: false,
+            sort_fields: false,
+            time_gaps: None,
+            prev_ts: None,
+            lanes: None,
+            color_by: None,
+            locale: None,
+            continuation_indent: 0,
+            highlighter: Highlighter::default(),
+            stripped_prefixes: Vec::new(),
         }
     }
 
@@ -59,12 +120,88 @@ impl RecordFormatter {
         self
     }
 
+    /// Sets selection of top-level fields to show, in addition to the field filter.
+    pub fn with_field_selection(mut self, value: Arc<FieldSelection>) -> Self {
+        self.selection = value;
+        self
+    }
+
+    pub fn with_icons(mut self, value: bool) -> Self {
+        self.icons = value;
+        self
+    }
+
+    pub fn with_sorted_fields(mut self, value: bool) -> Self {
+        self.sort_fields = value;
+        self
+    }
+
+    /// Enables styling of timestamps depending on the gap from the timestamp of the previously formatted record.
+    pub fn with_time_gaps(mut self, value: Option<TimeGaps>) -> Self {
+        self.time_gaps = value;
+        self
+    }
+
+    /// Enables marking of messages with lanes by values of a field.
+    pub fn with_lanes(mut self, value: Option<Lanes>) -> Self {
+        self.lanes = value;
+        self
+    }
+
+    /// Enables coloring of records by values of a field, either with a gutter block or of the value itself.
+    pub fn with_color_by(mut self, value: Option<ColorBy>) -> Self {
+        self.color_by = value;
+        self
+    }
+
+    /// Enables rendering of numbers with locale decimal and digit group separators.
+    /// Timestamps are localized by the timestamp formatter.
+    pub fn with_locale(mut self, value: Option<&'static Locale>) -> Self {
+        self.locale = value;
+        self
+    }
+
+    /// Sets the number of spaces continuation lines attached to a record are indented by.
+    pub fn with_continuation_indent(mut self, value: usize) -> Self {
+        self.continuation_indent = value;
+        self
+    }
+
+    /// Enables highlighting of parts of messages and field values matching the filters.
+    pub fn with_highlighter(mut self, value: Highlighter) -> Self {
+        self.highlighter = value;
+        self
+    }
+
+    /// Sets prefixes removed from keys of top-level fields when they are shown, the first matching one is removed.
+    pub fn with_stripped_prefixes(mut self, value: Vec<String>) -> Self {
+        self.stripped_prefixes = value;
+        self
+    }
+
+    /// Formats a line that is not a record, like a stack trace line, attached to the previously formatted record.
+    pub fn format_continuation(&self, buf: &mut Buf, level: &Option<Level>, line: &[u8]) {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        buf.resize(buf.len() + self.continuation_indent, b' ');
+        self.theme.apply(buf, level, |s| {
+            s.element(Element::Message, |s| s.batch(|buf| buf.extend_from_slice(line)));
+        });
+        buf.push(b'\n')
+    }
+
     pub fn format_record(&mut self, buf: &mut Buf, rec: &model::Record) {
+        let time = self.time_element(rec);
+        let lane = self.lanes.as_ref().map(|lanes| (lanes.lane(rec), lanes.indent));
+        if let Some(color_by) = self.color_by.as_ref().filter(|c| c.target == ColorTarget::Gutter) {
+            let palette = &self.theme.palette;
+            buf.extend_from_slice(palette.gutter(color_by.slot(rec, palette.colors())).as_bytes());
+            buf.push(b' ');
+        }
         self.theme.apply(buf, &rec.level, |s| {
             //
             // time
             //
-            s.element(Element::Time, |s| {
+            s.element(Element::Time, |s| s.element(time, |s| {
                 s.batch(|buf| {
                     if let Some(ts) = &rec.ts {
                         aligned_left(buf, self.ts_width, b' ', |mut buf| {
@@ -86,28 +223,65 @@ impl RecordFormatter {
                         });
                     }
                 })
-            });
+            }));
             //
             // level
             //
             s.space();
-            s.element(Element::Level, |s| {
-                s.batch(|buf| {
-                    buf.extend_from_slice(self.cfg.punctuation.level_left_separator.as_bytes());
+            if self.icons {
+                s.element(Element::Level, |s| {
+                    s.element(Element::LevelInner, |s| {
+                        s.batch(|buf| buf.extend_from_slice(self.theme.icons.level(rec.level).as_bytes()))
+                    });
                 });
-                s.element(Element::LevelInner, |s| {
+                if let Some(source) = source_kind(rec) {
+                    s.element(Element::Icon, |s| {
+                        s.batch(|buf| buf.extend_from_slice(self.theme.icons.source(source).as_bytes()))
+                    });
+                }
+            } else {
+                s.element(Element::Level, |s| {
                     s.batch(|buf| {
-                        buf.extend_from_slice(match rec.level {
-                            Some(Level::Debug) => b"DBG",
-                            Some(Level::Info) => b"INF",
-                            Some(Level::Warning) => b"WRN",
-                            Some(Level::Error) => b"ERR",
-                            _ => b"(?)",
+                        buf.extend_from_slice(self.cfg.punctuation.level_left_separator.as_bytes());
+                    });
+                    s.element(Element::LevelInner, |s| {
+                        s.batch(|buf| {
+                            buf.extend_from_slice(match rec.level {
+                                Some(Level::Trace) => b"TRC",
+                                Some(Level::Debug) => b"DBG",
+                                Some(Level::Info) => b"INF",
+                                Some(Level::Warning) => b"WRN",
+                                Some(Level::Error) => b"ERR",
+                                Some(Level::Fatal) => b"FTL",
+                                None => b"(?)",
+                            })
                         })
-                    })
+                    });
+                    s.batch(|buf| buf.extend_from_slice(self.cfg.punctuation.level_right_separator.as_bytes()));
                 });
-                s.batch(|buf| buf.extend_from_slice(self.cfg.punctuation.level_right_separator.as_bytes()));
-            });
+            }
+            //
+            // caller in the gutter
+            //
+            if self.cfg.caller_position == CallerPosition::Gutter {
+                if let Some(caller) = &rec.caller {
+                    self.format_caller(s, caller);
+                }
+            }
+            //
+            // lane
+            //
+            if let Some((lane, indent)) = lane {
+                s.space();
+                let lanes = &self.theme.lanes;
+                s.styled(|buf| match lane {
+                    Some(lane) => {
+                        buf.resize(buf.len() + lane * indent, b' ');
+                        buf.extend_from_slice(lanes.marker(lane).as_bytes());
+                    }
+                    None => buf.extend_from_slice(lanes.blank().as_bytes()),
+                });
+            }
             //
             // logger
             //
@@ -123,22 +297,34 @@ impl RecordFormatter {
             //
             // message text
             //
-            if let Some(text) = rec.message {
+            if let Some(text) = rec.message.filter(|text| self.empty_value_display(text) != Some(EmptyValueDisplay::Hidden)) {
                 s.batch(|buf| buf.push(b' '));
                 s.element(Element::Message, |s| self.format_message(s, text));
             }
             //
             // fields
             //
+            if !self.stripped_prefixes.is_empty() {
+                self.check_key_collisions(rec);
+            }
             let mut some_fields_hidden = false;
-            for (k, v) in rec.fields() {
-                if !self.hide_empty_fields
+            let fields = rec.fields().map(|field| (self.selection.rank(field.0), field));
+            let fields = if self.sort_fields || self.selection.ordered() {
+                let sort_fields = self.sort_fields;
+                Either::Left(fields.sorted_by_key(|&(rank, &(k, _))| (rank, if sort_fields { k } else { "" })))
+            } else {
+                Either::Right(fields)
+            };
+            for (rank, &(k, v)) in fields {
+                if rank.is_none() {
+                    some_fields_hidden = true;
+                } else if !self.hide_empty_fields
                     || match v.get() {
                         r#""""# | "null" | "{}" | "[]" => false,
                         _ => true,
                     }
                 {
-                    some_fields_hidden |= !self.format_field(s, k, v, Some(&self.fields));
+                    some_fields_hidden |= !self.format_field(s, k, v, Some(&self.fields), true);
                 }
             }
             if some_fields_hidden {
@@ -149,17 +335,11 @@ impl RecordFormatter {
             //
             // caller
             //
-            if let Some(text) = rec.caller {
-                s.element(Element::Caller, |s| {
-                    s.batch(|buf| {
-                        buf.push(b' ');
-                        buf.extend_from_slice(self.cfg.punctuation.source_location_separator.as_bytes())
-                    });
-                    s.element(Element::CallerInner, |s| {
-                        s.batch(|buf| buf.extend_from_slice(text.as_bytes()))
-                    });
-                });
-            };
+            if self.cfg.caller_position == CallerPosition::End {
+                if let Some(caller) = &rec.caller {
+                    self.format_caller(s, caller);
+                }
+            }
         });
         //
         // eol
@@ -167,33 +347,151 @@ impl RecordFormatter {
         buf.push(b'\n')
     }
 
+    fn time_element(&mut self, rec: &model::Record) -> Element {
+        let (gaps, ts) = match (self.time_gaps, rec.ts.as_ref().and_then(|ts| ts.unix_utc())) {
+            (Some(gaps), Some(ts)) => (gaps, ts),
+            _ => return Element::Time,
+        };
+        let prev = match self.prev_ts.replace(ts) {
+            Some(prev) => prev,
+            None => return Element::Time,
+        };
+        let nanos = |(sec, nsec): (i64, u32)| sec as i128 * 1_000_000_000 + nsec as i128;
+        let gap = (nanos(ts) - nanos(prev)).unsigned_abs();
+        if gap >= gaps.critical.as_nanos() {
+            Element::TimeGapCritical
+        } else if gap >= gaps.warning.as_nanos() {
+            Element::TimeGapWarning
+        } else {
+            Element::Time
+        }
+    }
+
+    fn format_caller<S: StylingPush<Buf>>(&self, s: &mut S, caller: &model::Caller) {
+        s.element(Element::Caller, |s| {
+            s.batch(|buf| {
+                buf.push(b' ');
+                buf.extend_from_slice(self.cfg.punctuation.source_location_separator.as_bytes())
+            });
+            s.element(Element::CallerInner, |s| {
+                s.batch(|buf| {
+                    buf.extend_from_slice(caller.name.as_bytes());
+                    if let Some(line) = caller.line {
+                        buf.push(b':');
+                        buf.extend_from_slice(line.as_bytes());
+                    }
+                })
+            });
+        });
+    }
+
     fn format_field<S: StylingPush<Buf>>(
         &self,
         s: &mut S,
         key: &str,
         value: &RawValue,
         filter: Option<&IncludeExcludeKeyFilter>,
+        top: bool,
     ) -> bool {
-        let mut fv = FieldFormatter::new(self);
+        let mut fv = FieldFormatter::new(self, top);
         fv.format(s, key, value, filter, IncludeExcludeSetting::Unspecified)
     }
 
     fn format_value<S: StylingPush<Buf>>(&self, s: &mut S, value: &RawValue) {
-        let mut fv = FieldFormatter::new(self);
+        let mut fv = FieldFormatter::new(self, false);
         fv.format_value(s, value, None, IncludeExcludeSetting::Unspecified);
     }
 
+    /// Returns the key of a top-level field to show, without the first matching prefix to strip, unless it is the whole key.
+    fn shown_key<'k>(&self, key: &'k str) -> &'k str {
+        self.stripped_prefixes
+            .iter()
+            .find_map(|prefix| key.strip_prefix(prefix.as_str()).filter(|rest| !rest.is_empty()))
+            .unwrap_or(key)
+    }
+
+    /// Returns true if the top-level field is an object which fields are shown as top-level ones,
+    /// because its key followed by a dot is a prefix to strip, e.g. `kubernetes:{pod:…}` for `kubernetes.` prefix.
+    ///
+    /// Only the object of the top-level field is flattened, so `kubernetes.labels.` prefix strips literal
+    /// `kubernetes.labels.app` keys and flattens `kubernetes.labels:{app:…}`, but not `kubernetes:{labels:{app:…}}`.
+    fn flattened(&self, key: &str, value: &RawValue) -> bool {
+        value.get().starts_with('{')
+            && self
+                .stripped_prefixes
+                .iter()
+                .any(|prefix| prefix.len() == key.len() + 1 && prefix.starts_with(key) && prefix.ends_with('.'))
+    }
+
+    /// Warns once per key about top-level fields shown with the same key after stripping prefixes.
+    fn check_key_collisions(&self, rec: &model::Record) {
+        let mut shown = Vec::new();
+        for &(key, value) in rec.fields() {
+            if self.flattened(key, value) {
+                if let Ok(item) = json::from_str::<model::Object>(value.get()) {
+                    shown.extend(item.fields.iter().map(|&(k, _)| k));
+                }
+            } else {
+                shown.push(self.shown_key(key));
+            }
+        }
+        shown.sort_unstable();
+        for key in shown.iter().tuple_windows().filter_map(|(a, b)| (a == b).then_some(*a)) {
+            let mut warned = KEY_COLLISIONS_WARNED.lock().unwrap();
+            if warned.len() < MAX_KEY_COLLISION_WARNINGS && !warned.contains(key) {
+                warned.insert(key.to_owned());
+                diagnostics::warning(format_args!(
+                    "several fields are shown with key {:?} after stripping prefixes, use full keys to tell them apart",
+                    key
+                ));
+            }
+        }
+    }
+
+    /// Returns the configured rendering of the value if it is null or an empty string.
+    fn empty_value_display(&self, value: &RawValue) -> Option<EmptyValueDisplay> {
+        match value.get() {
+            "null" => Some(self.cfg.empty_values.null),
+            r#""""# => Some(self.cfg.empty_values.empty_string),
+            _ => None,
+        }
+    }
+
+    /// Formats the value as the configured symbol if it is null or an empty string configured to be shown so.
+    fn format_empty_symbol<S: StylingPush<Buf>>(&self, s: &mut S, value: &RawValue) -> bool {
+        if self.empty_value_display(value) != Some(EmptyValueDisplay::Symbol) {
+            return false;
+        }
+        s.element(Element::Null, |s| {
+            s.batch(|buf| buf.extend_from_slice(self.cfg.empty_values.symbol.as_bytes()))
+        });
+        true
+    }
+
+    /// Writes the number with locale separators if a locale is set and the number is a measurement, see [is_measurement].
+    fn format_number(&self, buf: &mut Buf, value: &str, measurement: bool) {
+        match self.locale.filter(|_| measurement) {
+            Some(locale) => locale.format_number(buf, value),
+            None => buf.extend_from_slice(value.as_bytes()),
+        }
+    }
+
     fn format_message<S: StylingPush<Buf>>(&self, s: &mut S, value: &RawValue) {
+        if self.format_empty_symbol(s, value) {
+            return;
+        }
         match value.get().as_bytes()[0] {
             b'"' => {
                 s.element(Element::Message, |s| {
-                    s.batch(|buf| format_str_unescaped(buf, value.get()))
+                    if self.highlighter.is_empty() {
+                        s.batch(|buf| format_str_unescaped(buf, value.get()))
+                    } else {
+                        format_str_highlighted(s, value.get(), |text| self.highlighter.message(text))
+                    }
                 });
             }
             b'0'..=b'9' | b'-' | b'+' | b'.' => {
-                s.element(Element::Number, |s| {
-                    s.batch(|buf| buf.extend_from_slice(value.get().as_bytes()))
-                });
+                s.element(Element::Number, |s| s.batch(|buf| self.format_number(buf, value.get(), false)));
             }
             b't' | b'f' => {
                 s.element(Element::Boolean, |s| {
@@ -211,7 +509,7 @@ impl RecordFormatter {
                     s.batch(|buf| buf.push(b'{'));
                     let mut has_some = false;
                     for (k, v) in item.fields.iter() {
-                        has_some |= self.format_field(s, k, v, None)
+                        has_some |= self.format_field(s, k, v, None, false)
                     }
                     s.batch(|buf| {
                         if has_some {
@@ -275,18 +573,57 @@ impl RecordFormatter {
     }
 }
 
+fn source_kind(rec: &model::Record) -> Option<SourceKind> {
+    for (key, _) in rec.fields() {
+        match *key {
+            "kubernetes" | "k8s" => return Some(SourceKind::K8s),
+            "docker" | "container_id" | "container_name" => return Some(SourceKind::Docker),
+            "syslog" | "SYSLOG_IDENTIFIER" | "SYSLOG_FACILITY" => return Some(SourceKind::Syslog),
+            key if key.starts_with("k8s.") || key.starts_with("k8s_") => return Some(SourceKind::K8s),
+            _ => {}
+        }
+    }
+    None
+}
+
 fn format_str_unescaped(buf: &mut Buf, s: &str) {
     let mut reader = StrRead::new(&s[1..]);
     reader.parse_str_raw(buf).unwrap();
 }
 
+/// Formats the string unescaped, styling the parts of it found by the function with `match` element.
+fn format_str_highlighted<S, F>(s: &mut S, value: &str, find: F)
+where
+    S: StylingPush<Buf>,
+    F: FnOnce(&str) -> Vec<Range<usize>>,
+{
+    let mut text = Vec::new();
+    format_str_unescaped(&mut text, value);
+    let ranges = std::str::from_utf8(&text).map(find).unwrap_or_default();
+    let mut pos = 0;
+    for range in ranges {
+        s.batch(|buf| buf.extend_from_slice(&text[pos..range.start]));
+        s.element(Element::Match, |s| s.batch(|buf| buf.extend_from_slice(&text[range.clone()])));
+        pos = range.end;
+    }
+    s.batch(|buf| buf.extend_from_slice(&text[pos..]));
+}
+
 struct FieldFormatter<'a> {
     rf: &'a RecordFormatter,
+    // nesting level of the field being formatted, top-level fields of a record have 0
+    depth: usize,
+    // true if numbers of the field being formatted are measurements, see [is_measurement]
+    measurement: bool,
 }
 
 impl<'a> FieldFormatter<'a> {
-    fn new(rf: &'a RecordFormatter) -> Self {
-        Self { rf }
+    fn new(rf: &'a RecordFormatter, top: bool) -> Self {
+        Self {
+            rf,
+            depth: if top { 0 } else { 1 },
+            measurement: false,
+        }
     }
 
     fn format<S: StylingPush<Buf>>(
@@ -310,9 +647,24 @@ impl<'a> FieldFormatter<'a> {
         if setting == IncludeExcludeSetting::Exclude && leaf {
             return false;
         }
+        if self.rf.empty_value_display(value) == Some(EmptyValueDisplay::Hidden) {
+            return true;
+        }
+        if self.depth == 0 && self.rf.flattened(key, value) {
+            let item = json::from_str::<model::Object>(value.get()).unwrap();
+            let mut all_shown = true;
+            self.depth += 1;
+            for (k, v) in item.fields.iter() {
+                all_shown &= self.format(s, k, v, filter, setting);
+            }
+            self.depth -= 1;
+            return all_shown;
+        }
         s.space();
+        self.measurement = self.rf.locale.is_some() && is_measurement(key);
+        let shown = if self.depth == 0 { self.rf.shown_key(key) } else { key };
         s.element(Element::Key, |s| {
-            for b in key.as_bytes() {
+            for b in shown.as_bytes() {
                 let b = if *b == b'_' { b'-' } else { *b };
                 s.batch(|buf| buf.push(b));
             }
@@ -320,8 +672,29 @@ impl<'a> FieldFormatter<'a> {
         s.element(Element::Field, |s| {
             s.batch(|buf| buf.extend_from_slice(self.rf.cfg.punctuation.field_key_value_separator.as_bytes()));
         });
-        if self.rf.unescape_fields {
-            self.format_value(s, value, filter, setting);
+        if let Some(slot) = self.colored_slot(key, value) {
+            let palette = &self.rf.theme.palette;
+            s.styled(|buf| {
+                palette.paint(slot, buf, |buf| match value.get().as_bytes()[0] {
+                    b'"' if self.rf.unescape_fields => {
+                        buf.extend_from_slice(self.rf.cfg.punctuation.string_opening_quote.as_bytes());
+                        format_str_unescaped(buf, value.get());
+                        buf.extend_from_slice(self.rf.cfg.punctuation.string_closing_quote.as_bytes());
+                    }
+                    _ => buf.extend_from_slice(value.get().as_bytes()),
+                })
+            });
+        } else if self.rf.unescape_fields {
+            let highlighted = self.depth == 0 && self.rf.highlighter.has_field(key);
+            if highlighted && value.get().len() > 2 && value.get().starts_with('"') {
+                s.element(Element::String, |s| {
+                    s.batch(|buf| buf.extend_from_slice(self.rf.cfg.punctuation.string_opening_quote.as_bytes()));
+                    format_str_highlighted(s, value.get(), |text| self.rf.highlighter.field(key, text));
+                    s.batch(|buf| buf.extend_from_slice(self.rf.cfg.punctuation.string_closing_quote.as_bytes()));
+                });
+            } else {
+                self.format_value(s, value, filter, setting);
+            }
         } else {
             s.element(Element::String, |s| {
                 s.batch(|buf| buf.extend_from_slice(value.get().as_bytes()))
@@ -330,6 +703,16 @@ impl<'a> FieldFormatter<'a> {
         true
     }
 
+    /// Returns the palette slot of the value if it is the value of the top-level field chosen to be colored.
+    fn colored_slot(&self, key: &str, value: &RawValue) -> Option<usize> {
+        match &self.rf.color_by {
+            Some(color_by) if self.depth == 0 && color_by.target == ColorTarget::Value && color_by.key == key => {
+                Some(palette::slot(value.get(), self.rf.theme.palette.colors()))
+            }
+            _ => None,
+        }
+    }
+
     fn format_value<S: StylingPush<Buf>>(
         &mut self,
         s: &mut S,
@@ -337,6 +720,9 @@ impl<'a> FieldFormatter<'a> {
         filter: Option<&IncludeExcludeKeyFilter>,
         setting: IncludeExcludeSetting,
     ) {
+        if self.rf.format_empty_symbol(s, value) {
+            return;
+        }
         match value.get().as_bytes()[0] {
             b'"' => {
                 s.element(Element::String, |s| {
@@ -349,7 +735,7 @@ impl<'a> FieldFormatter<'a> {
             }
             b'0'..=b'9' | b'-' | b'+' | b'.' => {
                 s.element(Element::Number, |s| {
-                    s.batch(|buf| buf.extend_from_slice(value.get().as_bytes()))
+                    s.batch(|buf| self.rf.format_number(buf, value.get(), self.measurement))
                 });
             }
             b't' | b'f' => {
@@ -367,9 +753,11 @@ impl<'a> FieldFormatter<'a> {
                 s.element(Element::Object, |s| {
                     s.batch(|buf| buf.push(b'{'));
                     let mut some_fields_hidden = false;
+                    self.depth += 1;
                     for (k, v) in item.fields.iter() {
                         some_fields_hidden |= !self.format(s, k, v, filter, setting);
                     }
+                    self.depth -= 1;
                     if some_fields_hidden {
                         s.element(Element::Ellipsis, |s| s.batch(|buf| buf.extend_from_slice(b" ...")));
                     }
@@ -417,12 +805,15 @@ const HEXDIGIT: [u8; 16] = [
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::model::Record;
+    use crate::model::{Caller, Record};
     use crate::theme::Theme;
     use crate::themecfg::testing;
     use crate::timestamp::Timestamp;
     use crate::timezone::Tz;
-    use crate::{error::Error, settings::Punctuation};
+    use crate::{
+        error::Error,
+        settings::{EmptyValues, Punctuation},
+    };
     use chrono::{Offset, Utc};
     use datefmt::LinuxDateFormat;
     use json::value::RawValue;
@@ -439,6 +830,7 @@ mod tests {
             Arc::new(IncludeExcludeKeyFilter::default()),
             Formatting {
                 punctuation: Punctuation::test_default(),
+                ..Default::default()
             },
         );
         let mut buf = Vec::new();
@@ -446,6 +838,56 @@ mod tests {
         Ok(String::from_utf8(buf)?)
     }
 
+    /// Returns formatting settings with the test punctuation.
+    fn formatting() -> Formatting {
+        Formatting {
+            punctuation: Punctuation::test_default(),
+            ..Default::default()
+        }
+    }
+
+    /// Returns a formatter with the given theme and settings, showing time in UTC in the same format as `format`.
+    fn formatter_with(theme: Theme, formatting: Formatting) -> RecordFormatter {
+        RecordFormatter::new(
+            Arc::new(theme),
+            DateTimeFormatter::new(
+                LinuxDateFormat::new("%y-%m-%d %T.%3N").compile(),
+                Tz::FixedOffset(Utc.fix()),
+            ),
+            false,
+            Arc::new(IncludeExcludeKeyFilter::default()),
+            formatting,
+        )
+    }
+
+    /// Returns a formatter without styles and with the test punctuation.
+    fn formatter() -> RecordFormatter {
+        formatter_with(Theme::none(), formatting())
+    }
+
+    fn render(formatter: &mut RecordFormatter, rec: &Record) -> String {
+        let mut buf = Vec::new();
+        formatter.format_record(&mut buf, rec);
+        String::from_utf8(buf).unwrap()
+    }
+
+    fn raw(value: &'static str) -> &'static RawValue {
+        json::from_str(value).unwrap()
+    }
+
+    /// Returns a record with message `tm` and info level at 2000-01-02T03:04:05.123Z having the given fields.
+    fn record(fields: &[(&'static str, &'static str)]) -> Record<'static> {
+        Record {
+            ts: Some(Timestamp::new("2000-01-02T03:04:05.123Z", None)),
+            message: Some(raw(r#""tm""#)),
+            level: Some(Level::Info),
+            logger: None,
+            caller: None,
+            extra: fields.iter().map(|&(key, value)| (key, raw(value))).collect(),
+            extrax: Vec::default(),
+        }
+    }
+
     #[test]
     fn test_nested_objects() {
         assert_eq!(
@@ -454,7 +896,7 @@ mod tests {
                 message: Some(RawValue::from_string(r#""tm""#.into()).unwrap().as_ref()),
                 level: Some(Level::Debug),
                 logger: Some("tl"),
-                caller: Some("tc"),
+                caller: Some(Caller::new("tc")),
                 extra: heapless::Vec::from_slice(&[
                     ("ka", RawValue::from_string(r#"{"va":{"kb":42}}"#.into()).unwrap().as_ref()),
                 ]).unwrap(),
@@ -464,4 +906,213 @@ mod tests {
             String::from("\u{1b}[0;2;3m00-01-02 03:04:05.123 \u{1b}[0;36m|\u{1b}[0;95mDBG\u{1b}[0;36m|\u{1b}[0;2;3m \u{1b}[0;2;4mtl:\u{1b}[0;2;3m \u{1b}[0;1;39mtm \u{1b}[0;32mka\u{1b}[0;2m:\u{1b}[0;33m{ \u{1b}[0;32mva\u{1b}[0;2m:\u{1b}[0;33m{ \u{1b}[0;32mkb\u{1b}[0;2m:\u{1b}[0;94m42\u{1b}[0;33m } }\u{1b}[0;2;3m @ tc\u{1b}[0m\n"),
         );
     }
+
+    #[test]
+    fn test_icons() {
+        let mut formatter = formatter().with_icons(true);
+        let rec = Record {
+            level: Some(Level::Debug),
+            ..record(&[("container_id", r#""c1""#)])
+        };
+        assert_eq!(
+            render(&mut formatter, &rec),
+            "00-01-02 03:04:05.123 🐛🐳 tm container-id:'c1'\n"
+        );
+    }
+
+    #[test]
+    fn test_time_gaps() {
+        let mut formatter = formatter().with_time_gaps(Some(TimeGaps {
+            warning: std::time::Duration::from_secs(1),
+            critical: std::time::Duration::from_secs(30),
+        }));
+        let at = |ts| Record {
+            ts: Some(Timestamp::new(ts, None)),
+            message: None,
+            level: None,
+            ..record(&[])
+        };
+        assert_eq!(formatter.time_element(&at("2000-01-02T03:04:05.000Z")), Element::Time);
+        assert_eq!(formatter.time_element(&at("2000-01-02T03:04:05.500Z")), Element::Time);
+        assert_eq!(formatter.time_element(&at("2000-01-02T03:04:07.000Z")), Element::TimeGapWarning);
+        assert_eq!(formatter.time_element(&at("2000-01-02T03:05:00.000Z")), Element::TimeGapCritical);
+    }
+
+    #[test]
+    fn test_highlighting() {
+        let filter = crate::model::Filter {
+            fields: crate::model::FieldFilterSet::new(["msg~=fail", "user=admin"]).unwrap(),
+            ..Default::default()
+        };
+        let mut formatter = formatter_with(Theme::from(testing::theme().unwrap()), formatting())
+            .with_highlighter(Highlighter::new(&filter));
+        let rec = Record {
+            ts: None,
+            message: Some(raw(r#""boot failed""#)),
+            level: None,
+            ..record(&[("user", r#""admin""#), ("peer", r#""admin""#)])
+        };
+        let result = render(&mut formatter, &rec);
+        assert!(result.contains("\u{1b}[0;1;39mboot \u{1b}[0;7mfail\u{1b}[0;1;39med"), "{:?}", result);
+        assert!(result.contains("\u{1b}[0;7madmin"), "{:?}", result);
+        assert_eq!(result.matches("\u{1b}[0;7m").count(), 2, "{:?}", result);
+    }
+
+    #[test]
+    fn test_stripped_prefixes() {
+        let mut formatter = formatter().with_stripped_prefixes(vec!["kubernetes.".into(), "labels.".into()]);
+        let rec = record(&[
+            ("kubernetes.pod", r#""p1""#),
+            ("labels.app", r#"{"labels.x":1}"#),
+            ("labels.", "2"),
+            ("kubernetes", r#"{"pod":"p2","ns":"n1"}"#),
+        ]);
+        assert_eq!(
+            render(&mut formatter, &rec),
+            "00-01-02 03:04:05.123 |INF| tm pod:p1 app:{ labels.x:1 } labels.:2 pod:p2 ns:n1\n"
+        );
+        assert!(KEY_COLLISIONS_WARNED.lock().unwrap().contains("pod"));
+    }
+
+    #[test]
+    fn test_sorted_fields() {
+        let mut formatter = formatter().with_sorted_fields(true);
+        let rec = record(&[("kb", "2"), ("ka", "1")]);
+        assert_eq!(
+            render(&mut formatter, &rec),
+            "00-01-02 03:04:05.123 |INF| tm ka:1 kb:2\n"
+        );
+    }
+
+    #[test]
+    fn test_field_selection() {
+        let mut formatter = formatter().with_field_selection(Arc::new(
+            FieldSelection::default().with_shown(["trace-id", "k*"]).with_hidden(["kc"]),
+        ));
+        let rec = record(&[("kb", "2"), ("x", "0"), ("kc", "3"), ("trace-id", "1")]);
+        assert_eq!(
+            render(&mut formatter, &rec),
+            "00-01-02 03:04:05.123 |INF| tm trace-id:1 kb:2 ...\n"
+        );
+    }
+
+    #[test]
+    fn test_caller_position() {
+        let mut formatter = formatter_with(
+            Theme::none(),
+            Formatting {
+                caller_position: CallerPosition::Gutter,
+                ..formatting()
+            },
+        );
+        let rec = Record {
+            logger: Some("tl"),
+            caller: Some(Caller::new("main.go").with_line(Some("42"))),
+            ..record(&[("ka", "1")])
+        };
+        assert_eq!(
+            render(&mut formatter, &rec),
+            "00-01-02 03:04:05.123 |INF| @ main.go:42 tl: tm ka:1\n"
+        );
+    }
+
+    #[test]
+    fn test_lanes() {
+        let mut formatter = formatter().with_lanes(Some(Lanes {
+            key: "thread".into(),
+            indent: 2,
+        }));
+        let mut format = |thread: Option<&'static str>| {
+            let fields = thread.map(|v| ("thread", v));
+            let rec = Record {
+                ts: None,
+                ..record(fields.as_slice())
+            };
+            render(&mut formatter, &rec)
+        };
+        assert!(format(Some("1")).ends_with("|INF|         ┃ tm thread:1\n"));
+        assert!(format(Some("2")).ends_with("|INF|   ┃ tm thread:2\n"));
+        assert!(format(None).ends_with("|INF|   tm\n"));
+    }
+
+    #[test]
+    fn test_color_by() {
+        let formatter = |target| {
+            formatter_with(Theme::from(testing::theme().unwrap()), formatting()).with_color_by(Some(ColorBy {
+                key: "service".into(),
+                target,
+            }))
+        };
+        let format = |formatter: &mut RecordFormatter, service: Option<&'static str>| {
+            let fields = service.map(|v| ("service", v));
+            let rec = Record {
+                ts: None,
+                ..record(fields.as_slice())
+            };
+            render(formatter, &rec)
+        };
+
+        let mut gutter = formatter(ColorTarget::Gutter);
+        let api = format(&mut gutter, Some(r#""api""#));
+        assert!(api.starts_with("\u{1b}[0;38;5;"));
+        assert!(api.contains("▌\u{1b}[0m "));
+        assert_eq!(api, format(&mut gutter, Some(r#""api""#)));
+        assert!(format(&mut gutter, None).starts_with("  "));
+
+        let mut value = formatter(ColorTarget::Value);
+        let api = format(&mut value, Some(r#""api""#));
+        assert!(!api.starts_with("▌"));
+        assert!(api.contains("m'api'\u{1b}[0m"));
+    }
+
+    #[test]
+    fn test_empty_values() {
+        let mut formatter = formatter_with(
+            Theme::none(),
+            Formatting {
+                empty_values: EmptyValues {
+                    null: EmptyValueDisplay::Symbol,
+                    empty_string: EmptyValueDisplay::Hidden,
+                    symbol: "-".into(),
+                },
+                ..formatting()
+            },
+        );
+        let rec = Record {
+            ts: None,
+            message: Some(raw(r#""""#)),
+            ..record(&[
+                ("a", "null"),
+                ("b", r#""""#),
+                ("c", r#"{"d":null,"e":""}"#),
+                ("f", "[null]"),
+            ])
+        };
+        assert!(render(&mut formatter, &rec).ends_with("|INF| a:- c:{ d:- } f:[-]\n"));
+    }
+
+    #[test]
+    fn test_locale_numbers() {
+        let mut formatter = formatter().with_locale(crate::locale::Locale::find("de-DE"));
+        let rec = record(&[
+            ("bytes", "1234567.5"),
+            ("pid", "123456"),
+            ("req", r#"{"user_id":123456,"size":[12345]}"#),
+        ]);
+        assert_eq!(
+            render(&mut formatter, &rec),
+            "00-01-02 03:04:05.123 |INF| tm bytes:1.234.567,5 pid:123456 req:{ user-id:123456 size:[12.345] }\n"
+        );
+    }
+
+    #[test]
+    fn test_continuation() {
+        let formatter = formatter().with_continuation_indent(4);
+        let mut buf = Vec::new();
+        formatter.format_continuation(&mut buf, &Some(Level::Error), b"\tat com.example.Main.run(Main.java:42)\r");
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "    \tat com.example.Main.run(Main.java:42)\n"
+        );
+    }
 }
@@ -2,6 +2,7 @@
 use std::path::PathBuf;
 
 // local imports
+use crate::cancellation::POLL_INTERVAL;
 use crate::error::Result;
 
 // ---
@@ -11,8 +12,10 @@ pub type EventKind = notify::EventKind;
 
 // ---
 
-pub fn run<H>(mut paths: Vec<PathBuf>, mut handle: H) -> Result<()>
+/// Watches the files and calls the handler on any change of them, until `stop` returns true.
+pub fn run<S, H>(mut paths: Vec<PathBuf>, stop: S, mut handle: H) -> Result<()>
 where
+    S: Fn() -> bool,
     H: FnMut(Event) -> Result<()>,
 {
     if paths.is_empty() {
@@ -45,7 +48,7 @@ where
     watch.sort_unstable();
     watch.dedup();
 
-    imp::run(watch, |event| {
+    imp::run(watch, stop, |event| {
         if event.paths.iter().any(|path| paths.binary_search(&path).is_ok()) {
             handle(event)
         } else {
@@ -54,9 +57,25 @@ where
     })
 }
 
+/// Watches the directories and calls the handler on any change of their contents, until `stop` returns true.
+pub fn watch_dirs<S, H>(mut dirs: Vec<PathBuf>, stop: S, handle: H) -> Result<()>
+where
+    S: Fn() -> bool,
+    H: FnMut(Event) -> Result<()>,
+{
+    dirs.retain(|dir| dir.is_dir());
+    dirs.sort_unstable();
+    dirs.dedup();
+    if dirs.is_empty() {
+        return Ok(());
+    }
+
+    imp::run(dirs, stop, handle)
+}
+
 #[cfg(not(target_os = "macos"))]
 mod imp {
-    use std::sync::mpsc::{self};
+    use std::sync::mpsc::{self, RecvTimeoutError};
     use std::time::Duration;
 
     use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
@@ -66,8 +85,9 @@ mod imp {
 
     const FALLBACK_POLLING_INTERVAL: Duration = Duration::from_secs(1);
 
-    pub fn run<H>(paths: Vec<PathBuf>, mut handle: H) -> Result<()>
+    pub fn run<S, H>(paths: Vec<PathBuf>, stop: S, mut handle: H) -> Result<()>
     where
+        S: Fn() -> bool,
         H: FnMut(Event) -> Result<()>,
     {
         let (tx, rx) = mpsc::channel();
@@ -78,11 +98,15 @@ mod imp {
         }
 
         loop {
-            match rx.recv() {
+            match rx.recv_timeout(POLL_INTERVAL) {
                 Ok(Ok(event)) => handle(event)?,
                 Ok(Err(err)) => return Err(err.into()),
-                Err(err) => return Err(Error::RecvTimeoutError { source: err.into() }),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(err) => return Err(Error::RecvTimeoutError { source: err }),
             };
+            if stop() {
+                return Ok(());
+            }
         }
     }
 }
@@ -96,8 +120,9 @@ mod imp {
 
     use super::*;
 
-    pub fn run<H>(paths: Vec<PathBuf>, mut handle: H) -> Result<()>
+    pub fn run<S, H>(paths: Vec<PathBuf>, stop: S, mut handle: H) -> Result<()>
     where
+        S: Fn() -> bool,
         H: FnMut(Event) -> Result<()>,
     {
         let mut watcher = Watcher::new()?;
@@ -123,7 +148,10 @@ mod imp {
             watcher.watch()?;
 
             while synced {
-                let event = if let Some(event) = watcher.poll(None) {
+                if stop() {
+                    return Ok(());
+                }
+                let event = if let Some(event) = watcher.poll(Some(POLL_INTERVAL)) {
                     event
                 } else {
                     continue;
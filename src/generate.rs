@@ -0,0 +1,362 @@
+// std imports
+use std::io::Write;
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// third-party imports
+use chrono::{DateTime, SecondsFormat, Utc};
+use clap::ValueEnum;
+use serde_json as json;
+
+// local imports
+use crate::error::Result;
+use crate::level::Level;
+
+// ---
+
+const SERVICES: [&str; 5] = ["api", "auth", "billing", "search", "worker"];
+const HOSTS: [&str; 3] = ["node-1", "node-2", "node-3"];
+const METHODS: [&str; 4] = ["GET", "GET", "POST", "DELETE"];
+const PATHS: [&str; 5] = ["/api/v1/users", "/api/v1/orders", "/api/v1/search", "/health", "/login"];
+const TABLES: [&str; 3] = ["users", "orders", "payments"];
+const ERRORS: [&str; 4] = [
+    "connection reset by peer",
+    "context deadline exceeded",
+    "duplicate key value violates unique constraint",
+    "too many open files",
+];
+
+/// Maximum gap between timestamps of records generated without a rate, in milliseconds.
+const MAX_GAP_MS: u64 = 250;
+
+// ---
+
+/// Format of generated records, see `hl generate` command.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum GeneratedFormat {
+    #[default]
+    Json,
+    Logfmt,
+}
+
+/// Relative weights of levels of generated records given as `<level>:<weight>` pairs separated by commas.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LevelWeights(pub Vec<(Level, u32)>);
+
+impl FromStr for LevelWeights {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        s.split(',')
+            .map(|item| {
+                let (level, weight) = item.split_once(':').unwrap_or((item, ""));
+                match (<Level as ValueEnum>::from_str(level.trim(), true), weight.trim().parse()) {
+                    (Ok(level), Ok(weight)) => Ok((level, weight)),
+                    _ => Err(format!("invalid level weight '{}', expected <level>:<weight>", item)),
+                }
+            })
+            .collect::<std::result::Result<_, _>>()
+            .map(Self)
+    }
+}
+
+/// Generator of synthetic records for demos, theme previews and load tests, see `hl generate` command.
+///
+/// The same seed produces the same records, except for timestamps of records paced by a rate.
+pub struct Generator {
+    format: GeneratedFormat,
+    levels: Vec<(Level, u32)>,
+    rate: Option<f64>,
+    count: Option<u64>,
+    start: DateTime<Utc>,
+    rng: Rng,
+}
+
+impl Generator {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            format: GeneratedFormat::default(),
+            levels: vec![
+                (Level::Error, 2),
+                (Level::Warning, 5),
+                (Level::Info, 70),
+                (Level::Debug, 20),
+                (Level::Trace, 3),
+            ],
+            rate: None,
+            count: None,
+            start: Utc::now(),
+            rng: Rng::new(seed),
+        }
+    }
+
+    pub fn with_format(mut self, format: GeneratedFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Sets relative weights of levels of generated records, levels with zero weight are not generated.
+    pub fn with_levels(mut self, levels: Vec<(Level, u32)>) -> Self {
+        self.levels = levels.into_iter().filter(|(_, weight)| *weight != 0).collect();
+        self
+    }
+
+    /// Sets the number of records written per second, timestamps are taken from the clock then.
+    /// Records are written as fast as possible with increasing timestamps otherwise.
+    pub fn with_rate(mut self, rate: Option<f64>) -> Self {
+        self.rate = rate;
+        self
+    }
+
+    /// Sets the number of records to generate, records are generated until the output is closed otherwise.
+    pub fn with_count(mut self, count: Option<u64>) -> Self {
+        self.count = count;
+        self
+    }
+
+    /// Sets the timestamp of the first record generated without a rate.
+    pub fn with_start(mut self, start: DateTime<Utc>) -> Self {
+        self.start = start;
+        self
+    }
+
+    /// Writes generated records to the output, one per line.
+    pub fn run<W: Write>(&mut self, output: &mut W) -> Result<()> {
+        let started = Instant::now();
+        let mut ts = self.start;
+        let mut buf = Vec::new();
+        let mut i = 0;
+        while self.count.map_or(true, |count| i < count) {
+            match self.rate {
+                Some(rate) => {
+                    let due = started + Duration::from_secs_f64(i as f64 / rate);
+                    let now = Instant::now();
+                    if due > now {
+                        output.flush()?;
+                        thread::sleep(due - now);
+                    }
+                    ts = Utc::now();
+                }
+                None => {
+                    if i != 0 {
+                        ts += chrono::Duration::milliseconds(self.rng.below(MAX_GAP_MS) as i64);
+                    }
+                }
+            }
+            buf.clear();
+            self.record(ts, &mut buf);
+            output.write_all(&buf)?;
+            i += 1;
+        }
+        output.flush()?;
+        Ok(())
+    }
+
+    /// Appends the next record with the given timestamp to the buffer.
+    fn record(&mut self, ts: DateTime<Utc>, buf: &mut Vec<u8>) {
+        let level = self.level();
+        let rng = &mut self.rng;
+        let service = *rng.pick(&SERVICES);
+        let mut fields = vec![
+            ("ts", Value::Str(ts.to_rfc3339_opts(SecondsFormat::Millis, true))),
+            ("level", Value::Str(level_name(level))),
+            (
+                "logger",
+                Value::Str(format!("{}.{}", service, rng.pick(&["http", "db", "queue"]))),
+            ),
+        ];
+        let (message, details) = match level {
+            Level::Fatal | Level::Error => (
+                "request failed",
+                vec![("error", Value::Str(rng.pick(&ERRORS).to_string()))],
+            ),
+            Level::Warning => (
+                "slow query",
+                vec![
+                    ("table", Value::Str(rng.pick(&TABLES).to_string())),
+                    (
+                        "duration",
+                        Value::Num(format!("{}.{:03}", 1 + rng.below(5), rng.below(1000))),
+                    ),
+                ],
+            ),
+            Level::Info => (
+                "request completed",
+                vec![
+                    ("method", Value::Str(rng.pick(&METHODS).to_string())),
+                    ("path", Value::Str(rng.pick(&PATHS).to_string())),
+                    (
+                        "status",
+                        Value::Num(rng.pick(&["200", "200", "201", "204", "404"]).to_string()),
+                    ),
+                    ("duration", Value::Num(format!("0.{:03}", rng.below(1000)))),
+                ],
+            ),
+            Level::Debug | Level::Trace => (
+                "cache lookup",
+                vec![
+                    ("key", Value::Str(format!("session:{:08x}", rng.next() as u32))),
+                    ("hit", Value::Num(rng.pick(&["true", "false"]).to_string())),
+                ],
+            ),
+        };
+        fields.push(("msg", Value::Str(message.into())));
+        fields.push(("service", Value::Str(service.into())));
+        fields.push(("host", Value::Str(rng.pick(&HOSTS).to_string())));
+        fields.push(("request-id", Value::Str(format!("{:016x}", rng.next()))));
+        fields.extend(details);
+
+        match self.format {
+            GeneratedFormat::Json => write_json(buf, &fields),
+            GeneratedFormat::Logfmt => write_logfmt(buf, &fields),
+        }
+        buf.push(b'\n');
+    }
+
+    /// Returns a random level according to the weights.
+    fn level(&mut self) -> Level {
+        let total = self.levels.iter().map(|(_, weight)| *weight as u64).sum::<u64>();
+        if total == 0 {
+            return Level::Info;
+        }
+        let mut n = self.rng.below(total);
+        for (level, weight) in &self.levels {
+            if n < *weight as u64 {
+                return *level;
+            }
+            n -= *weight as u64;
+        }
+        Level::Info
+    }
+}
+
+// ---
+
+enum Value {
+    Str(String),
+    /// Number or boolean written as is.
+    Num(String),
+}
+
+fn write_json(buf: &mut Vec<u8>, fields: &[(&str, Value)]) {
+    buf.push(b'{');
+    for (i, (key, value)) in fields.iter().enumerate() {
+        if i != 0 {
+            buf.push(b',');
+        }
+        json::to_writer(&mut *buf, key).ok();
+        buf.push(b':');
+        match value {
+            Value::Str(s) => {
+                json::to_writer(&mut *buf, s).ok();
+            }
+            Value::Num(s) => buf.extend_from_slice(s.as_bytes()),
+        }
+    }
+    buf.push(b'}');
+}
+
+fn write_logfmt(buf: &mut Vec<u8>, fields: &[(&str, Value)]) {
+    for (i, (key, value)) in fields.iter().enumerate() {
+        if i != 0 {
+            buf.push(b' ');
+        }
+        buf.extend_from_slice(key.as_bytes());
+        buf.push(b'=');
+        match value {
+            Value::Str(s) if s.is_empty() || s.contains(|c: char| c == ' ' || c == '"' || c == '=') => {
+                json::to_writer(&mut *buf, s).ok();
+            }
+            Value::Str(s) | Value::Num(s) => buf.extend_from_slice(s.as_bytes()),
+        }
+    }
+}
+
+fn level_name(level: Level) -> String {
+    level
+        .to_possible_value()
+        .map(|value| value.get_name().to_owned())
+        .unwrap_or_default()
+}
+
+// ---
+
+/// Small deterministic pseudo-random number generator, xorshift64*.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed.wrapping_mul(0x9E3779B97F4A7C15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.0 = x;
+        x.wrapping_mul(0x2545F4914F6CDD1D)
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len() as u64) as usize]
+    }
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate(format: GeneratedFormat, levels: Vec<(Level, u32)>) -> String {
+        let start = DateTime::parse_from_rfc3339("2024-01-02T03:04:05Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let mut output = Vec::new();
+        Generator::new(42)
+            .with_format(format)
+            .with_levels(levels)
+            .with_count(Some(20))
+            .with_start(start)
+            .run(&mut output)
+            .unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_generator() {
+        let levels = vec![(Level::Error, 1), (Level::Info, 9), (Level::Debug, 0)];
+        let output = generate(GeneratedFormat::Json, levels.clone());
+        assert_eq!(output, generate(GeneratedFormat::Json, levels.clone()));
+        assert_eq!(output.lines().count(), 20);
+        let mut prev = String::new();
+        for line in output.lines() {
+            let record = json::from_str::<json::Value>(line).unwrap();
+            assert!(matches!(record["level"].as_str(), Some("error" | "info")));
+            let ts = record["ts"].as_str().unwrap().to_owned();
+            assert!(ts >= prev);
+            prev = ts;
+        }
+        assert!(output.starts_with(r#"{"ts":"2024-01-02T03:04:05.000Z","level":"#));
+
+        let output = generate(GeneratedFormat::Logfmt, levels);
+        assert!(output.starts_with("ts=2024-01-02T03:04:05.000Z level="));
+        assert!(output.lines().all(|line| line.contains(" msg=\"request ")));
+    }
+
+    #[test]
+    fn test_level_weights() {
+        assert_eq!(
+            "error:1, info:90".parse::<LevelWeights>(),
+            Ok(LevelWeights(vec![(Level::Error, 1), (Level::Info, 90)]))
+        );
+        assert!("error".parse::<LevelWeights>().is_err());
+        assert!("bogus:1".parse::<LevelWeights>().is_err());
+    }
+}
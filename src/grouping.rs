@@ -0,0 +1,130 @@
+// std imports
+use std::cmp::{Ordering, Reverse};
+use std::net::IpAddr;
+use std::time::Duration;
+
+// third-party imports
+use clap::ValueEnum;
+use serde_json as json;
+
+// ---
+
+/// Order of groups in aggregated output.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum GroupOrder {
+    /// Most frequent groups first, ties are ordered by key.
+    #[default]
+    ByCount,
+    /// Groups ordered by key naturally, numbers numerically, IP addresses by address and durations by magnitude.
+    ByKey,
+}
+
+/// Sorts groups with their counts in the specified order.
+pub fn sort_groups<K: AsRef<str>>(groups: &mut [(K, u64)], order: GroupOrder) {
+    match order {
+        GroupOrder::ByCount => {
+            groups.sort_by_cached_key(|(key, count)| (Reverse(*count), GroupKey::new(key.as_ref())));
+        }
+        GroupOrder::ByKey => {
+            groups.sort_by_cached_key(|(key, _)| GroupKey::new(key.as_ref()));
+        }
+    }
+}
+
+// ---
+
+/// Group key with natural ordering.
+///
+/// Numbers go first, then durations, IP addresses and at last any other text ordered lexicographically.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GroupKey {
+    Number(f64),
+    Duration(Duration),
+    Ip(IpAddr),
+    Text(String),
+}
+
+impl GroupKey {
+    /// Parses a group key from a raw field value, which may be a JSON string.
+    pub fn new(value: &str) -> Self {
+        let text = json::from_str::<String>(value).unwrap_or_else(|_| value.to_string());
+        let trimmed = text.trim();
+        if let Ok(number) = trimmed.parse::<f64>() {
+            if number.is_finite() {
+                return Self::Number(number);
+            }
+        }
+        if let Ok(ip) = trimmed.parse::<IpAddr>() {
+            return Self::Ip(ip);
+        }
+        if let Ok(duration) = humantime::parse_duration(trimmed) {
+            return Self::Duration(duration);
+        }
+        Self::Text(text)
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            Self::Number(_) => 0,
+            Self::Duration(_) => 1,
+            Self::Ip(_) => 2,
+            Self::Text(_) => 3,
+        }
+    }
+}
+
+impl Eq for GroupKey {}
+
+impl Ord for GroupKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Self::Number(a), Self::Number(b)) => a.total_cmp(b),
+            (Self::Duration(a), Self::Duration(b)) => a.cmp(b),
+            (Self::Ip(a), Self::Ip(b)) => a.cmp(b),
+            (Self::Text(a), Self::Text(b)) => a.cmp(b),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+impl PartialOrd for GroupKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(groups: &[(&str, u64)]) -> Vec<String> {
+        groups.iter().map(|(key, _)| key.to_string()).collect()
+    }
+
+    #[test]
+    fn test_sort_by_key() {
+        let mut groups = [
+            ("10", 1),
+            ("9", 1),
+            ("\"10.0.0.10\"", 1),
+            ("\"10.0.0.9\"", 1),
+            ("\"1s\"", 1),
+            ("\"150ms\"", 1),
+            ("\"b\"", 1),
+        ];
+        sort_groups(&mut groups, GroupOrder::ByKey);
+        assert_eq!(
+            keys(&groups),
+            vec!["9", "10", "\"150ms\"", "\"1s\"", "\"10.0.0.9\"", "\"10.0.0.10\"", "\"b\""]
+        );
+    }
+
+    #[test]
+    fn test_sort_by_count() {
+        let mut groups = [("10", 2), ("9", 2), ("1", 5)];
+        sort_groups(&mut groups, GroupOrder::ByCount);
+        assert_eq!(keys(&groups), vec!["1", "9", "10"]);
+    }
+}
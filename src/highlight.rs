@@ -0,0 +1,110 @@
+// std imports
+use std::ops::Range;
+
+// third-party imports
+use regex::Regex;
+
+// local imports
+use crate::model::{FieldFilterSet, Filter};
+
+// ---
+
+/// Finds parts of messages and field values matching the field filters and the query,
+/// to be styled with `match` element of the theme.
+///
+/// Only string values of top-level fields are covered, negated conditions are skipped.
+#[derive(Clone, Debug, Default)]
+pub struct Highlighter {
+    message: Vec<Regex>,
+    fields: Vec<(String, Regex)>,
+}
+
+impl Highlighter {
+    pub fn new(filter: &Filter) -> Self {
+        let mut result = Self::default();
+        result.add(&filter.fields);
+        if let Some(query) = &filter.query {
+            query.for_each_positive_fields(&mut |fields| result.add(fields));
+        }
+        result
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.message.is_empty() && self.fields.is_empty()
+    }
+
+    /// Returns true if values of the field with the given key may have parts to highlight.
+    pub fn has_field(&self, key: &str) -> bool {
+        self.fields.iter().any(|(k, _)| k == key)
+    }
+
+    /// Returns sorted non-overlapping byte ranges of the message text to highlight.
+    pub fn message(&self, text: &str) -> Vec<Range<usize>> {
+        find(self.message.iter(), text)
+    }
+
+    /// Returns sorted non-overlapping byte ranges of the text of the field with the given key to highlight.
+    pub fn field(&self, key: &str, text: &str) -> Vec<Range<usize>> {
+        find(self.fields.iter().filter(|(k, _)| k == key).map(|(_, pattern)| pattern), text)
+    }
+
+    fn add(&mut self, fields: &FieldFilterSet) {
+        for (key, pattern) in fields.highlights() {
+            match key {
+                "msg" | "message" => self.message.push(pattern),
+                "logger" | "caller" => {}
+                key => self.fields.push((key.to_owned(), pattern)),
+            }
+        }
+    }
+}
+
+fn find<'a, I: Iterator<Item = &'a Regex>>(patterns: I, text: &str) -> Vec<Range<usize>> {
+    let mut ranges = patterns
+        .flat_map(|pattern| pattern.find_iter(text).map(|m| m.range()))
+        .filter(|range| !range.is_empty())
+        .collect::<Vec<_>>();
+    ranges.sort_by_key(|range| range.start);
+    let mut result: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match result.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => result.push(range),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::TextFolding;
+    use crate::query::Query;
+    use crate::settings::Settings;
+
+    #[test]
+    fn test_highlighter() {
+        let folding = TextFolding {
+            ignore_case: true,
+            fold_diacritics: false,
+        };
+        let filter = Filter {
+            fields: FieldFilterSet::new_folded(["msg~=fail", "user=admin", "path!~=tmp"], folding).unwrap(),
+            query: Some(
+                Query::parse(r#"msg ~~ "time.?out" or not msg ~ ok"#, folding, &Settings::default().level_aliases).unwrap(),
+            ),
+            ..Default::default()
+        };
+        let highlighter = Highlighter::new(&filter);
+        assert_eq!(highlighter.message("Failed: timeout, will fail"), vec![0..4, 8..15, 22..26]);
+        assert_eq!(highlighter.field("user", "Admin"), vec![0..5]);
+        assert_eq!(highlighter.field("user", "admins"), vec![]);
+        assert!(!highlighter.has_field("path"));
+    }
+
+    #[test]
+    fn test_find_overlapping() {
+        let patterns = [Regex::new("ab").unwrap(), Regex::new("bc").unwrap()];
+        assert_eq!(find(patterns.iter(), "xabcx abx"), vec![1..4, 6..8]);
+    }
+}
@@ -0,0 +1,136 @@
+// std imports
+use std::borrow::Cow;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+// third-party imports
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json as json;
+
+// local imports
+use crate::error::Result;
+
+// ---
+
+/// Invocation of hl recorded to the history file, see `hl history` command.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Entry {
+    pub time: DateTime<Utc>,
+    /// Working directory of the invocation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dir: Option<PathBuf>,
+    /// Command line arguments, starting with the program name.
+    pub args: Vec<String>,
+    /// True if values of the arguments are redacted, see `bugreport::redact`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub redacted: bool,
+    /// Descriptions of the inputs.
+    #[serde(default)]
+    pub inputs: Vec<String>,
+    /// Filtering options in form `<option>=<value>`.
+    #[serde(default)]
+    pub filters: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub records: Option<RecordCounts>,
+}
+
+impl Entry {
+    /// Returns the command line with the arguments quoted for a POSIX shell where needed.
+    pub fn command(&self) -> String {
+        self.args.iter().map(|arg| quote(arg)).collect::<Vec<_>>().join(" ")
+    }
+}
+
+/// Numbers of records processed by an invocation.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RecordCounts {
+    pub parsed: u64,
+    pub matched: u64,
+}
+
+// ---
+
+/// Appends the entry to the history file as a JSON line, creating the file and its directory if needed.
+pub fn append(path: &Path, entry: &Entry) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut line = json::to_vec(entry)?;
+    line.push(b'\n');
+    // a single write to a file opened for appending keeps lines of concurrent invocations intact
+    OpenOptions::new().create(true).append(true).open(path)?.write_all(&line)?;
+    Ok(())
+}
+
+/// Loads entries of the history file in the order they were recorded, skipping lines that cannot be parsed.
+///
+/// Returns no entries if the file does not exist.
+pub fn load(path: &Path) -> Result<Vec<Entry>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err.into()),
+    };
+    let mut entries = Vec::new();
+    for line in BufReader::new(file).lines() {
+        if let Ok(entry) = json::from_str(&line?) {
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+fn quote(arg: &str) -> Cow<str> {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_./=:,@%+".contains(c);
+    if !arg.is_empty() && arg.chars().all(plain) {
+        Cow::Borrowed(arg)
+    } else {
+        Cow::Owned(format!("'{}'", arg.replace('\'', r"'\''")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append_load() {
+        let path = std::env::temp_dir()
+            .join(format!("hl-history-test-{}", std::process::id()))
+            .join("history.jsonl");
+        let entry = |n: u64| Entry {
+            time: Utc::now(),
+            dir: None,
+            args: vec!["hl".into(), "-l".into(), "e".into(), format!("app-{}.log", n)],
+            redacted: n == 2,
+            inputs: vec![format!("file 'app-{}.log'", n)],
+            filters: vec!["level=error".into()],
+            records: Some(RecordCounts { parsed: n, matched: 1 }),
+        };
+        append(&path, &entry(1)).unwrap();
+        append(&path, &entry(2)).unwrap();
+        let entries = load(&path).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].args[3], "app-2.log");
+        assert_eq!((entries[0].redacted, entries[1].redacted), (false, true));
+        assert_eq!(entries[1].records, Some(RecordCounts { parsed: 2, matched: 1 }));
+        fs::remove_dir_all(path.parent().unwrap()).ok();
+        assert_eq!(load(&path).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_command() {
+        let entry = Entry {
+            time: Utc::now(),
+            dir: None,
+            args: vec!["hl".into(), "-q".into(), "msg ~ 'a b'".into(), "".into(), "x.log".into()],
+            redacted: false,
+            inputs: Vec::new(),
+            filters: Vec::new(),
+            records: None,
+        };
+        assert_eq!(entry.command(), r#"hl -q 'msg ~ '\''a b'\''' '' x.log"#);
+    }
+}
@@ -13,19 +13,22 @@
 use std::cmp::{max, min};
 use std::convert::{TryFrom, TryInto};
 use std::fmt::{self, Display};
-use std::fs::File;
-use std::io::{Read, Write};
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
 use std::iter::empty;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 // third-party imports
 use capnp::{message, serialize::read_message};
+use chrono::{DateTime, FixedOffset};
+use clap::ValueEnum;
 use closure::closure;
 use crossbeam_channel as channel;
 use crossbeam_channel::RecvError;
 use crossbeam_utils::thread;
+use enum_map::EnumMap;
 use generic_array::{typenum::U32, GenericArray};
 use itertools::izip;
 use serde::{Deserialize, Serialize};
@@ -33,13 +36,24 @@ use serde_json as json;
 use sha2::{Digest, Sha256};
 
 // local imports
+use crate::binary;
+use crate::bugreport;
+use crate::cache;
+use crate::cancellation::Cancellation;
+use crate::compression::Compression;
+use crate::diagnostics;
 use crate::error::{Error, Result};
 use crate::index_capnp as schema;
-use crate::input::Input;
+use crate::input::{Input, InputStream};
 use crate::level::Level;
+use crate::logfmt;
 use crate::model::{Parser, ParserSettings, RawRecord};
+use crate::progress::{Progress, ProgressReader};
 use crate::scanning::{Scanner, Segment, SegmentBuf, SegmentBufFactory};
-use crate::settings::PredefinedFields;
+use crate::settings::{InputFormat, PredefinedFields};
+use crate::syslog;
+use crate::text::TextFormat;
+use crate::timestamp::TimeFormat;
 
 // types
 pub type Writer = dyn Write + Send + Sync;
@@ -57,6 +71,10 @@ impl Timestamp {
     pub fn add(mut self, interval: std::time::Duration) -> Self {
         self.sec += interval.as_secs() as i64;
         self.nsec += interval.subsec_nanos();
+        if self.nsec >= 1_000_000_000 {
+            self.sec += 1;
+            self.nsec -= 1_000_000_000;
+        }
         self
     }
 
@@ -119,7 +137,16 @@ pub struct Indexer {
     buffer_size: u32,
     max_message_size: u32,
     dir: PathBuf,
+    cache: bool,
     parser: Parser,
+    input_format: InputFormat,
+    text_format: TextFormat,
+    multiline: Option<usize>,
+    max_stream_buffer_size: usize,
+    cancellation: Cancellation,
+    max_runtime: Option<(Instant, Duration)>,
+    rebuild: bool,
+    progress: Option<Arc<Progress>>,
 }
 
 impl Indexer {
@@ -136,19 +163,113 @@ impl Indexer {
             buffer_size,
             max_message_size,
             dir,
+            cache: true,
             parser: Parser::new(ParserSettings::new(&fields, empty(), false)),
+            input_format: InputFormat::default(),
+            text_format: TextFormat::builtin().clone(),
+            multiline: None,
+            max_stream_buffer_size: usize::MAX,
+            cancellation: Cancellation::new(),
+            max_runtime: None,
+            rebuild: false,
+            progress: None,
         }
     }
 
+    /// Enables or disables storing of built indexes in the cache directory.
+    ///
+    /// If disabled, indexes are built in memory each time.
+    pub fn with_cache(mut self, enabled: bool) -> Self {
+        self.cache = enabled;
+        self
+    }
+
+    /// Sets custom timestamp formats tried before the built-in ones.
+    pub fn with_time_formats(mut self, formats: Vec<TimeFormat>) -> Self {
+        self.parser = self.parser.with_time_formats(formats);
+        self
+    }
+
+    /// Sets the format of input lines, which are converted to JSON records if needed before parsing.
+    pub fn with_input_format(mut self, format: InputFormat) -> Self {
+        self.input_format = format;
+        self
+    }
+
+    /// Sets the conversion of plain text lines used in text input format.
+    pub fn with_text_format(mut self, format: TextFormat) -> Self {
+        self.text_format = format;
+        self
+    }
+
+    /// Enables counting of up to the given number of lines that are not records following a record as its valid continuation.
+    ///
+    /// Such lines take the timestamp of the record so they stay attached to it when sorted.
+    pub fn with_multiline(mut self, max_lines: Option<usize>) -> Self {
+        self.multiline = max_lines;
+        self
+    }
+
+    /// Sets the maximum compressed size of a non-seekable stream kept in memory for replaying after indexing,
+    /// the rest of the stream is stored in a temporary file.
+    pub fn with_max_stream_buffer_size(mut self, size: usize) -> Self {
+        self.max_stream_buffer_size = size;
+        self
+    }
+
+    /// Sets the handle that stops indexing with [`Error::Cancelled`] when cancelled.
+    pub fn with_cancellation(mut self, cancellation: Cancellation) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    /// Sets the time budget counted from the given instant, indexing stops with [`Error::TimeBudgetExceeded`]
+    /// once it is exceeded.
+    pub fn with_max_runtime(mut self, started: Instant, budget: Option<Duration>) -> Self {
+        self.max_runtime = budget.map(|budget| (started, budget));
+        self
+    }
+
+    /// Enables building of indexes and decompressed copies of files even if cached ones are up to date.
+    pub fn with_rebuild(mut self, enabled: bool) -> Self {
+        self.rebuild = enabled;
+        self
+    }
+
+    /// Sets the number of threads a file is indexed with.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Counts bytes read from files on disk for indexing them in the given progress, which are the compressed
+    /// bytes for compressed files, streams are not counted.
+    pub fn with_progress(mut self, progress: Arc<Progress>) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    pub fn max_stream_buffer_size(&self) -> usize {
+        self.max_stream_buffer_size
+    }
+
     /// Builds index for the given file.
     ///
     /// Builds the index, saves it to disk and returns it.
     pub fn index(&self, source_path: &PathBuf) -> Result<Index> {
         let source_path = std::fs::canonicalize(source_path)?;
-        let meta = source_path.metadata()?;
+        if !self.cache {
+            return self.build_index(&source_path, None);
+        }
         let hash = hex::encode(sha256(source_path.to_string_lossy().as_bytes()));
-        let index_path = self.dir.join(PathBuf::from(hash));
-        if Path::new(&index_path).exists() {
+        // fingerprint of the beginning of the file tells a replaced file from an appended one having the same size and time
+        let head = head_hash(&source_path)?;
+        let index_path = self.dir.join(format!("{}-{}", hash, head));
+        if Path::new(&index_path).exists() && !self.rebuild {
             let mut file = match File::open(&index_path) {
                 Ok(file) => file,
                 Err(err) => {
@@ -159,13 +280,86 @@ impl Indexer {
                 }
             };
             if let Ok(index) = Index::load(&mut file) {
-                if meta.len() == index.source().size && ts(meta.modified()?) == index.source().modified {
+                if index.is_current(None) {
+                    diagnostics::debug(format_args!("using cached index for '{}'", source_path.display()));
+                    cache::touch(&index_path);
                     return Ok(index);
                 }
             }
+            diagnostics::notice(format_args!("rebuilding outdated index for '{}'", source_path.display()));
+        }
+
+        // remove indexes of previous contents of the file
+        if let Ok(entries) = fs::read_dir(&self.dir) {
+            for entry in entries.flatten() {
+                let file_name = entry.file_name();
+                let file_name = file_name.to_string_lossy();
+                if file_name.starts_with(&hash) && !file_name.ends_with(".unpacked") && !file_name.ends_with(".tmp") {
+                    fs::remove_file(entry.path()).ok();
+                }
+            }
+        }
+
+        self.build_index(&source_path, Some(&index_path))
+    }
+
+    /// Decompresses the given file into the cache directory to make it seekable, reusing a previously decompressed copy if the file is unchanged.
+    ///
+    /// Returns path of the decompressed copy, or None if the cache is disabled.
+    pub fn unpack(&self, source_path: &PathBuf, compression: Compression) -> Result<Option<PathBuf>> {
+        if !self.cache {
+            return Ok(None);
+        }
+        let source_path = std::fs::canonicalize(source_path)?;
+        let meta = source_path.metadata()?;
+        let (sec, nsec) = ts(meta.modified()?);
+        let prefix = format!("{}-", hex::encode(sha256(source_path.to_string_lossy().as_bytes())));
+        let name = format!("{}{:x}-{:x}-{:x}.unpacked", prefix, meta.len(), sec, nsec);
+        let path = self.dir.join(&name);
+        if path.exists() && !self.rebuild {
+            diagnostics::debug(format_args!("using cached decompressed copy of '{}'", source_path.display()));
+            cache::touch(&path);
+            return Ok(Some(path));
+        }
+
+        // remove outdated copies of the same file
+        if let Ok(entries) = fs::read_dir(&self.dir) {
+            for entry in entries.flatten() {
+                let file_name = entry.file_name();
+                let file_name = file_name.to_string_lossy();
+                if file_name.starts_with(&prefix) && file_name.ends_with(".unpacked") {
+                    fs::remove_file(entry.path()).ok();
+                }
+            }
         }
 
-        self.build_index(&source_path, &index_path)
+        diagnostics::notice(format_args!(
+            "decompressing {} file '{}' for indexing",
+            compression.name(),
+            source_path.display()
+        ));
+        let input = File::open(&source_path).map_err(|err| Error::FailedToOpenFileForReading {
+            path: source_path.clone(),
+            source: err,
+        })?;
+        let tmp = self.dir.join(format!("{}.tmp", name));
+        let mut output = File::create(&tmp).map_err(|err| Error::FailedToOpenFileForWriting {
+            path: tmp.clone(),
+            source: err,
+        })?;
+        let input: InputStream = match &self.progress {
+            Some(progress) => Box::new(ProgressReader::new(input, progress.clone())),
+            None => Box::new(input),
+        };
+        let mut input = compression.parallel_decoder(input, self.concurrency)?;
+        let result = self
+            .copy(&mut input, &mut output)
+            .and_then(|()| Ok(fs::rename(&tmp, &path)?));
+        if let Err(err) = result {
+            fs::remove_file(&tmp).ok();
+            return Err(err);
+        }
+        Ok(Some(path))
     }
 
     /// Builds index for the given stream.
@@ -183,7 +377,42 @@ impl Indexer {
         )
     }
 
-    fn build_index(&self, source_path: &PathBuf, index_path: &PathBuf) -> Result<Index> {
+    /// Extends the index of a file to the data appended to it since it was indexed, the result is not cached.
+    ///
+    /// The file is expected to be changed only by appending data to it since it was indexed.
+    pub fn index_appended(&self, index: &Index) -> Result<Index> {
+        let path = PathBuf::from(&index.source.path);
+        let mut input = File::open(&path).map_err(|err| Error::FailedToOpenFileForReading {
+            path: path.clone(),
+            source: err,
+        })?;
+        let metadata = input.metadata().map_err(|err| Error::FailedToGetFileMetadata {
+            path: path.clone(),
+            source: err,
+        })?;
+        let mut metadata = Metadata::try_from(&metadata)?;
+        let (start, end) = (index.source.size, metadata.len.max(index.source.size));
+        input.seek(SeekFrom::Start(start))?;
+        metadata.len = end - start;
+        let tail = self.process_file(&path, metadata, &mut input.take(end - start), &mut io::sink())?;
+        let mut stat = index.source.stat.clone();
+        stat.merge(&tail.source.stat);
+        let blocks = index.source.blocks.iter().cloned().chain(tail.source.blocks.into_iter().map(|mut block| {
+            block.offset += start;
+            block
+        }));
+        Ok(Index {
+            source: SourceFile {
+                size: end,
+                path: index.source.path.clone(),
+                modified: tail.source.modified,
+                stat,
+                blocks: blocks.collect(),
+            },
+        })
+    }
+
+    fn build_index(&self, source_path: &PathBuf, index_path: Option<&PathBuf>) -> Result<Index> {
         let mut input = match Input::open(&source_path) {
             Ok(input) => input,
             Err(err) => {
@@ -202,16 +431,25 @@ impl Indexer {
                 });
             }
         };
-        let mut output = match File::create(&index_path) {
-            Ok(output) => output,
-            Err(err) => {
-                return Err(Error::FailedToOpenFileForReading {
-                    path: index_path.clone(),
-                    source: err,
-                });
+        let mut output: Box<Writer> = match index_path.map(|path| (path, File::create(path))) {
+            Some((_, Ok(output))) => Box::new(output),
+            Some((path, Err(err))) => {
+                diagnostics::warning(format_args!(
+                    "{}, falling back to in-memory indexing",
+                    Error::FailedToOpenFileForWriting {
+                        path: path.clone(),
+                        source: err,
+                    }
+                ));
+                Box::new(std::io::sink())
             }
+            None => Box::new(std::io::sink()),
+        };
+        let mut stream: InputStream = match &self.progress {
+            Some(progress) => Box::new(ProgressReader::new(input.stream, progress.clone())),
+            None => input.stream,
         };
-        self.process_file(&source_path, (&metadata).try_into()?, &mut input.stream, &mut output)
+        self.process_file(&source_path, (&metadata).try_into()?, &mut stream, output.as_mut())
     }
 
     fn process_file(
@@ -223,6 +461,7 @@ impl Indexer {
     ) -> Result<Index> {
         let n = self.concurrency;
         let sfi = Arc::new(SegmentBufFactory::new(self.buffer_size.try_into()?));
+        let now = syslog::reference_time();
         thread::scope(|scope| -> Result<Index> {
             // prepare receive/transmit channels for input data
             let (txi, rxi): (Vec<_>, Vec<_>) = (0..n).map(|_| channel::bounded(1)).unzip();
@@ -234,11 +473,20 @@ impl Indexer {
             // spawn reader thread
             let reader = scope.spawn(closure!(clone sfi, |_| -> Result<()> {
                 let mut sn: usize = 0;
-                let scanner = Scanner::new(sfi, "\n".to_string());
+                let mut offset: u64 = 0;
+                // blocks keep indented continuation lines with their records whether or not they are attached,
+                // because the index may be reused with or without --multiline option
+                let scanner = Scanner::new(sfi, "\n".to_string()).with_continuations(true);
                 for item in scanner.items(input).with_max_segment_size(self.max_message_size.try_into()?) {
-                    if let Err(_) = txi[sn % n].send(item?) {
+                    self.check_stopped()?;
+                    let item = item?;
+                    let size = match &item {
+                        Segment::Complete(segment) | Segment::Incomplete(segment, _) => segment.data().len(),
+                    };
+                    if let Err(_) = txi[sn % n].send((offset, item)) {
                         break;
                     }
+                    offset += size as u64;
                     sn += 1;
                 }
                 Ok(())
@@ -246,9 +494,12 @@ impl Indexer {
             // spawn processing threads
             for (rxi, txo) in izip!(rxi, txo) {
                 scope.spawn(closure!(ref sfi, |_| {
-                    for segment in rxi.iter() {
+                    for (offset, segment) in rxi.iter() {
                         let ((stat, chronology), segment) = match segment {
-                            Segment::Complete(segment) => (self.process_segement(&segment), segment),
+                            Segment::Complete(segment) => {
+                                let _origin = bugreport::origin(path.to_string_lossy().into(), offset);
+                                (self.process_segement(&segment, now), segment)
+                            }
                             Segment::Incomplete(segment, _) => {
                                 let mut stat = Stat::new();
                                 stat.add_invalid();
@@ -294,6 +545,8 @@ impl Indexer {
                     }
                     sn += 1;
                 }
+                // partial index must not be saved as it would be taken for a complete one
+                self.check_stopped()?;
                 index.save(output)?;
                 Ok(index)
             });
@@ -304,46 +557,74 @@ impl Indexer {
         .unwrap()
     }
 
-    fn process_segement(&self, segment: &SegmentBuf) -> (Stat, Chronology) {
+    /// Copies the input to the output like [`io::copy`], stopping once indexing is cancelled or out of time.
+    fn copy<R: Read, W: Write>(&self, input: &mut R, output: &mut W) -> Result<()> {
+        let mut buf = vec![0; 64 * 1024];
+        loop {
+            self.check_stopped()?;
+            match input.read(&mut buf) {
+                Ok(0) => return Ok(()),
+                Ok(n) => output.write_all(&buf[..n])?,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    /// Returns [`Error::Cancelled`] if indexing is cancelled or [`Error::TimeBudgetExceeded`] if it is out of time.
+    fn check_stopped(&self) -> Result<()> {
+        if self.cancellation.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        match self.max_runtime {
+            Some((started, budget)) if started.elapsed() >= budget => Err(Error::TimeBudgetExceeded { budget }),
+            _ => Ok(()),
+        }
+    }
+
+    fn process_segement(&self, segment: &SegmentBuf, now: DateTime<FixedOffset>) -> (Stat, Chronology) {
         let mut stat = Stat::new();
         let mut sorted = true;
         let mut prev_ts = None;
         let mut lines = Vec::<(Option<Timestamp>, u32, u32)>::with_capacity(segment.data().len() / 512);
         let mut offset = 0;
+        let mut normalized = Vec::new();
+        // number of lines attached to the last record, if any
+        let mut continuation = None;
+        let watch = bugreport::watch(segment.data());
         for (i, data) in rtrim(segment.data(), b'\n').split(|c| *c == b'\n').enumerate() {
+            watch.set_offset(offset as usize);
             let data_len = data.len();
             let data = strip(data, b'\r');
             let mut ts = None;
             if data.len() != 0 {
+                let data = logfmt::normalize_with(self.input_format, &self.text_format, now, data, &mut normalized);
                 match json::from_slice::<RawRecord>(data) {
                     Ok(rec) => {
                         let rec = self.parser.parse(rec);
                         let mut flags = 0;
-                        match rec.level {
-                            Some(Level::Debug) => {
-                                flags |= schema::FLAG_LEVEL_DEBUG;
-                            }
-                            Some(Level::Info) => {
-                                flags |= schema::FLAG_LEVEL_INFO;
-                            }
-                            Some(Level::Warning) => {
-                                flags |= schema::FLAG_LEVEL_WARNING;
-                            }
-                            Some(Level::Error) => {
-                                flags |= schema::FLAG_LEVEL_ERROR;
-                            }
-                            None => (),
+                        if let Some(level) = rec.level {
+                            flags |= level_to_flag(level);
                         }
+                        stat.add_record(rec.level);
                         ts = rec.ts.and_then(|ts| ts.unix_utc()).map(|ts| ts.into());
                         if ts < prev_ts {
                             sorted = false;
                         }
                         prev_ts = ts;
+                        continuation = Some(0);
                         stat.add_valid(ts, flags);
                     }
-                    _ => {
-                        stat.add_invalid();
-                    }
+                    _ => match (self.multiline, &mut continuation) {
+                        (Some(max), Some(count)) if *count < max && !binary::is_binary(data) => {
+                            *count += 1;
+                            stat.add_valid(None, 0);
+                        }
+                        _ => {
+                            continuation = None;
+                            stat.add_invalid();
+                        }
+                    },
                 }
             }
             lines.push((ts.or(prev_ts), i as u32, offset));
@@ -399,6 +680,17 @@ impl Index {
         &self.source
     }
 
+    /// Returns true if the indexed file exists and is unchanged, judging by its size, modification time
+    /// and the fingerprint of its beginning if given.
+    pub fn is_current(&self, head: Option<&str>) -> bool {
+        let path = Path::new(&self.source.path);
+        let unchanged = match path.metadata().and_then(|meta| Ok((meta.len(), meta.modified()?))) {
+            Ok((size, modified)) => size == self.source.size && ts(modified) == self.source.modified,
+            Err(_) => false,
+        };
+        unchanged && head.map_or(true, |head| head_hash(path).map_or(false, |actual| actual == head))
+    }
+
     /// Loads the index.
     pub fn load(input: &mut Reader) -> Result<Index> {
         Header::load(input)?.validate()?;
@@ -406,15 +698,14 @@ impl Index {
         let root: schema::root::Reader = message.get_root()?;
         let source = root.get_source()?;
         let modified = source.get_modified();
-        Ok(Index {
-            source: SourceFile {
-                size: source.get_size(),
-                path: source.get_path()?.into(),
-                modified: (modified.get_sec(), modified.get_nsec()),
-                stat: Self::load_stat(source.get_index()?),
-                blocks: Self::load_blocks(source)?,
-            },
-        })
+        let source = SourceFile {
+            size: source.get_size(),
+            path: source.get_path()?.into(),
+            modified: (modified.get_sec(), modified.get_nsec()),
+            stat: Self::load_stat(source.get_index()?)?,
+            blocks: Self::load_blocks(source)?,
+        };
+        Ok(Index { source })
     }
 
     /// Saves the index.
@@ -430,20 +721,26 @@ impl Index {
         modified.set_sec(self.source.modified.0);
         modified.set_nsec(self.source.modified.1);
         let mut index = source.reborrow().init_index();
-        Self::save_stat(index.reborrow(), &self.source.stat);
+        Self::save_stat(index.reborrow(), &self.source.stat)?;
         self.save_blocks(source)?;
         capnp::serialize::write_message(output, &message)?;
         Ok(())
     }
 
-    fn load_stat(index: schema::index::Reader) -> Stat {
+    fn load_stat(index: schema::index::Reader) -> Result<Stat> {
         let lines = index.get_lines();
         let ts = index.get_timestamps();
         let flags = index.get_flags();
-        Stat {
+        let mut levels = EnumMap::default();
+        for ((_, count), value) in levels.iter_mut().zip(index.get_levels()?.iter()) {
+            *count = value;
+        }
+        Ok(Stat {
             flags: flags,
             lines_valid: lines.get_valid(),
             lines_invalid: lines.get_invalid(),
+            records: index.get_records(),
+            levels,
             ts_min_max: if flags & schema::FLAG_HAS_TIMESTAMPS != 0 {
                 Some((
                     Timestamp {
@@ -458,11 +755,16 @@ impl Index {
             } else {
                 None
             },
-        }
+        })
     }
 
-    fn save_stat(mut index: schema::index::Builder, stat: &Stat) {
+    fn save_stat(mut index: schema::index::Builder, stat: &Stat) -> Result<()> {
         index.set_flags(stat.flags);
+        index.set_records(stat.records);
+        let mut levels = index.reborrow().init_levels(stat.levels.len().try_into()?);
+        for (i, count) in stat.levels.values().enumerate() {
+            levels.set(i.try_into()?, *count);
+        }
         let mut lines = index.reborrow().init_lines();
         lines.set_valid(stat.lines_valid);
         lines.set_invalid(stat.lines_invalid);
@@ -475,6 +777,7 @@ impl Index {
             ts_max.set_sec(max.sec);
             ts_max.set_nsec(max.nsec);
         }
+        Ok(())
     }
 
     fn load_blocks(source: schema::source_file::Reader) -> Result<Vec<SourceBlock>> {
@@ -484,7 +787,7 @@ impl Index {
             result.push(SourceBlock {
                 offset: block.get_offset(),
                 size: block.get_size(),
-                stat: Self::load_stat(block.get_index()?),
+                stat: Self::load_stat(block.get_index()?)?,
                 chronology: Self::load_chronology(block.get_chronology()?)?,
             })
         }
@@ -497,7 +800,7 @@ impl Index {
             let mut block = blocks.reborrow().get(i.try_into()?);
             block.set_offset(source_block.offset);
             block.set_size(source_block.size);
-            Self::save_stat(block.reborrow().init_index(), &source_block.stat);
+            Self::save_stat(block.reborrow().init_index(), &source_block.stat)?;
             Self::save_chronology(block.init_chronology(), &source_block.chronology)?;
         }
         Ok(())
@@ -603,7 +906,7 @@ impl SourceBlock {
     /// Returns true if SourceBlock contains at least one line matching the given level or higher level.
     pub fn match_level(&self, level: Level) -> bool {
         let mut flags = 0;
-        for &l in &[Level::Error, Level::Warning, Level::Info, Level::Debug] {
+        for &l in Level::value_variants() {
             flags |= level_to_flag(l);
             if l == level {
                 break;
@@ -612,6 +915,11 @@ impl SourceBlock {
         self.stat.flags & flags != 0
     }
 
+    /// Returns true if SourceBlock contains at least one line with exactly the given level.
+    pub fn has_level(&self, level: Level) -> bool {
+        self.stat.flags & level_to_flag(level) != 0
+    }
+
     /// Returns true if this SourceBlock overlaps by time with other SourceBlock.
     pub fn overlaps_by_time(&self, other: &SourceBlock) -> bool {
         if let (Some(ts1), Some(ts2)) = (self.stat.ts_min_max, other.stat.ts_min_max) {
@@ -630,6 +938,10 @@ pub struct Stat {
     pub flags: u64,
     pub lines_valid: u64,
     pub lines_invalid: u64,
+    /// Number of parsed records, not counting continuation lines attached to them.
+    pub records: u64,
+    /// Numbers of parsed records per level, records without a level are not counted.
+    pub levels: EnumMap<Level, u64>,
     pub ts_min_max: Option<(Timestamp, Timestamp)>,
 }
 
@@ -640,10 +952,20 @@ impl Stat {
             flags: 0,
             lines_valid: 0,
             lines_invalid: 0,
+            records: 0,
+            levels: EnumMap::default(),
             ts_min_max: None,
         }
     }
 
+    /// Counts a parsed record with the given level, the line itself is counted by `add_valid`.
+    pub fn add_record(&mut self, level: Option<Level>) {
+        self.records += 1;
+        if let Some(level) = level {
+            self.levels[level] += 1;
+        }
+    }
+
     /// Adds information about a single valid line.
     pub fn add_valid(&mut self, ts: Option<Timestamp>, flags: u64) {
         self.ts_min_max = min_max_opt(self.ts_min_max, ts.and_then(|ts| Some((ts, ts))));
@@ -663,6 +985,10 @@ impl Stat {
     pub fn merge(&mut self, other: &Self) {
         self.lines_valid += other.lines_valid;
         self.lines_invalid += other.lines_invalid;
+        self.records += other.records;
+        for (level, count) in other.levels {
+            self.levels[level] += count;
+        }
         self.flags |= other.flags;
         self.ts_min_max = min_max_opt(self.ts_min_max, other.ts_min_max);
     }
@@ -797,6 +1123,13 @@ fn ts(ts: SystemTime) -> (i64, u32) {
     }
 }
 
+/// Returns a short hash of the first 4 KiB of the file.
+fn head_hash(path: &Path) -> Result<String> {
+    let mut head = Vec::with_capacity(4096);
+    File::open(path)?.take(4096).read_to_end(&mut head)?;
+    Ok(hex::encode(&sha256(&head)[..8]))
+}
+
 fn sha256(bytes: &[u8]) -> GenericArray<u8, U32> {
     let mut hasher = Sha256::new();
     hasher.update(bytes);
@@ -816,10 +1149,12 @@ fn strip<'a>(slice: &'a [u8], ch: u8) -> &'a [u8] {
 
 fn level_to_flag(level: Level) -> u64 {
     match level {
+        Level::Trace => schema::FLAG_LEVEL_TRACE,
         Level::Debug => schema::FLAG_LEVEL_DEBUG,
         Level::Info => schema::FLAG_LEVEL_INFO,
         Level::Warning => schema::FLAG_LEVEL_WARNING,
         Level::Error => schema::FLAG_LEVEL_ERROR,
+        Level::Fatal => schema::FLAG_LEVEL_FATAL,
     }
 }
 
@@ -832,7 +1167,7 @@ fn rtrim<'a>(s: &'a [u8], c: u8) -> &'a [u8] {
 }
 
 const VALID_MAGIC: u64 = 0x5845444e492d4c48;
-const CURRENT_VERSION: u64 = 1;
+const CURRENT_VERSION: u64 = 4;
 
 /*
 ---
@@ -852,3 +1187,74 @@ const CURRENT_VERSION: u64 = 1;
  xx111111 - [*]seconds in next 64/80/88/96 bits
 ---
  */
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_load() {
+        let mut stat = Stat::new();
+        stat.add_record(Some(Level::Error));
+        stat.add_record(Some(Level::Info));
+        stat.add_record(Some(Level::Info));
+        stat.add_record(None);
+        stat.add_valid(None, level_to_flag(Level::Info));
+        let index = Index {
+            source: SourceFile {
+                size: 100,
+                path: "test.log".into(),
+                modified: (1, 2),
+                stat: stat.clone(),
+                blocks: vec![SourceBlock::new(0, 100, stat, Chronology::default())],
+            },
+        };
+        let mut data = Vec::new();
+        index.save(&mut data).unwrap();
+        let loaded = Index::load(&mut data.as_slice()).unwrap();
+        for stat in [&loaded.source.stat, &loaded.source.blocks[0].stat] {
+            assert_eq!(stat.records, 4);
+            assert_eq!(stat.levels[Level::Error], 1);
+            assert_eq!(stat.levels[Level::Info], 2);
+            assert_eq!(stat.levels[Level::Debug], 0);
+            assert_eq!(stat.lines_valid, 1);
+        }
+    }
+
+    #[test]
+    fn test_unpack() {
+        let dir = std::env::temp_dir().join(format!("hl-index-test-unpack-{}", std::process::id()));
+        fs::create_dir_all(dir.join("cache")).unwrap();
+        let source = dir.join("app.log.gz");
+        let pack = |data: &[u8]| {
+            let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(data).unwrap();
+            fs::write(&source, encoder.finish().unwrap()).unwrap();
+        };
+        let fields = crate::settings::Settings::default().fields.predefined;
+        let indexer = Indexer::new(1, 32, 64, dir.join("cache"), &fields);
+        let unpack = |indexer: &Indexer| indexer.unpack(&source, Compression::Gzip).unwrap();
+
+        pack(b"a\n");
+        let first = unpack(&indexer).unwrap();
+        let first_data = fs::read(&first).unwrap();
+        // the copy is reused while the file is unchanged
+        let reused = unpack(&indexer);
+        // and replaced once it changes
+        pack(b"a\nb\n");
+        let second = unpack(&indexer).unwrap();
+        let second_data = fs::read(&second).unwrap();
+        let first_exists = first.exists();
+        let disabled = unpack(&indexer.with_cache(false));
+        fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(first_data, b"a\n");
+        assert_eq!(reused, Some(first.clone()));
+        assert_ne!(second, first);
+        assert_eq!(second_data, b"a\nb\n");
+        assert!(!first_exists);
+        assert_eq!(disabled, None);
+    }
+}
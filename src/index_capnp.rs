@@ -1196,11 +1196,23 @@ pub mod index {
     pub fn get_timestamps(self) -> crate::index_capnp::index::timestamps::Reader<'a> {
       self.reader.into()
     }
+    #[inline]
+    pub fn get_records(self) -> u64 {
+      self.reader.get_data_field::<u64>(6)
+    }
+    #[inline]
+    pub fn get_levels(self) -> ::capnp::Result<::capnp::primitive_list::Reader<'a,u64>> {
+      ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(0), ::core::option::Option::None)
+    }
+    #[inline]
+    pub fn has_levels(&self) -> bool {
+      !self.reader.get_pointer_field(0).is_null()
+    }
   }
 
   pub struct Builder<'a> { builder: ::capnp::private::layout::StructBuilder<'a> }
   impl <'a,> ::capnp::traits::HasStructSize for Builder<'a,>  {
-    const STRUCT_SIZE: ::capnp::private::layout::StructSize = ::capnp::private::layout::StructSize { data: 6, pointers: 0 };
+    const STRUCT_SIZE: ::capnp::private::layout::StructSize = ::capnp::private::layout::StructSize { data: 7, pointers: 1 };
   }
   impl <'a,> ::capnp::traits::HasTypeId for Builder<'a,>  {
     const TYPE_ID: u64 = _private::TYPE_ID;
@@ -1280,6 +1292,30 @@ pub mod index {
       self.builder.set_data_field::<u32>(9, 0u32);
       self.builder.into()
     }
+    #[inline]
+    pub fn get_records(self) -> u64 {
+      self.builder.get_data_field::<u64>(6)
+    }
+    #[inline]
+    pub fn set_records(&mut self, value: u64)  {
+      self.builder.set_data_field::<u64>(6, value);
+    }
+    #[inline]
+    pub fn get_levels(self) -> ::capnp::Result<::capnp::primitive_list::Builder<'a,u64>> {
+      ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(0), ::core::option::Option::None)
+    }
+    #[inline]
+    pub fn set_levels(&mut self, value: ::capnp::primitive_list::Reader<'a,u64>) -> ::capnp::Result<()> {
+      ::capnp::traits::SetPointerBuilder::set_pointer_builder(self.builder.reborrow().get_pointer_field(0), value, false)
+    }
+    #[inline]
+    pub fn init_levels(self, size: u32) -> ::capnp::primitive_list::Builder<'a,u64> {
+      ::capnp::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(0), size)
+    }
+    #[inline]
+    pub fn has_levels(&self) -> bool {
+      !self.builder.is_pointer_field_null(0)
+    }
   }
 
   pub struct Pipeline { _typeless: ::capnp::any_pointer::Pipeline }
@@ -1297,45 +1333,59 @@ pub mod index {
     }
   }
   mod _private {
-    pub static ENCODED_NODE: [::capnp::Word; 49] = [
+    pub static ENCODED_NODE: [::capnp::Word; 83] = [
       ::capnp::word(0, 0, 0, 0, 5, 0, 6, 0),
       ::capnp::word(28, 102, 52, 52, 108, 182, 120, 255),
-      ::capnp::word(12, 0, 0, 0, 1, 0, 6, 0),
+      ::capnp::word(12, 0, 0, 0, 1, 0, 7, 0),
       ::capnp::word(159, 109, 14, 67, 239, 4, 192, 180),
-      ::capnp::word(0, 0, 7, 0, 0, 0, 0, 0),
+      ::capnp::word(1, 0, 7, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(21, 0, 0, 0, 146, 0, 0, 0),
       ::capnp::word(29, 0, 0, 0, 7, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(25, 0, 0, 0, 175, 0, 0, 0),
+      ::capnp::word(25, 0, 0, 0, 31, 1, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(105, 110, 100, 101, 120, 46, 99, 97),
       ::capnp::word(112, 110, 112, 58, 73, 110, 100, 101),
       ::capnp::word(120, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 1, 0, 1, 0),
-      ::capnp::word(12, 0, 0, 0, 3, 0, 4, 0),
+      ::capnp::word(20, 0, 0, 0, 3, 0, 4, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 1, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(69, 0, 0, 0, 50, 0, 0, 0),
+      ::capnp::word(125, 0, 0, 0, 50, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(64, 0, 0, 0, 3, 0, 1, 0),
-      ::capnp::word(76, 0, 0, 0, 2, 0, 1, 0),
+      ::capnp::word(120, 0, 0, 0, 3, 0, 1, 0),
+      ::capnp::word(132, 0, 0, 0, 2, 0, 1, 0),
       ::capnp::word(1, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(1, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(167, 9, 143, 45, 122, 129, 213, 191),
-      ::capnp::word(73, 0, 0, 0, 50, 0, 0, 0),
+      ::capnp::word(129, 0, 0, 0, 50, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(2, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(1, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(205, 180, 118, 244, 234, 217, 209, 173),
-      ::capnp::word(49, 0, 0, 0, 90, 0, 0, 0),
+      ::capnp::word(105, 0, 0, 0, 90, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(3, 0, 0, 0, 6, 0, 0, 0),
+      ::capnp::word(0, 0, 1, 0, 7, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(85, 0, 0, 0, 66, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(80, 0, 0, 0, 3, 0, 1, 0),
+      ::capnp::word(92, 0, 0, 0, 2, 0, 1, 0),
+      ::capnp::word(4, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 1, 0, 8, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(89, 0, 0, 0, 58, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(84, 0, 0, 0, 3, 0, 1, 0),
+      ::capnp::word(112, 0, 0, 0, 2, 0, 1, 0),
       ::capnp::word(102, 108, 97, 103, 115, 0, 0, 0),
       ::capnp::word(9, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
@@ -1347,12 +1397,34 @@ pub mod index {
       ::capnp::word(108, 105, 110, 101, 115, 0, 0, 0),
       ::capnp::word(116, 105, 109, 101, 115, 116, 97, 109),
       ::capnp::word(112, 115, 0, 0, 0, 0, 0, 0),
-    ];
+      ::capnp::word(114, 101, 99, 111, 114, 100, 115, 0),
+      ::capnp::word(9, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(9, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(108, 101, 118, 101, 108, 115, 0, 0),
+      ::capnp::word(14, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 3, 0, 1, 0),
+      ::capnp::word(9, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(14, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+  ];
     pub fn get_field_types(index: u16) -> ::capnp::introspect::Type {
       match index {
         0 => <u64 as ::capnp::introspect::Introspect>::introspect(),
         1 => <crate::index_capnp::index::lines::Owned as ::capnp::introspect::Introspect>::introspect(),
         2 => <crate::index_capnp::index::timestamps::Owned as ::capnp::introspect::Introspect>::introspect(),
+        3 => <u64 as ::capnp::introspect::Introspect>::introspect(),
+        4 => <::capnp::primitive_list::Owned<u64> as ::capnp::introspect::Introspect>::introspect(),
         _ => panic!("invalid field index {}", index),
       }
     }
@@ -1364,7 +1436,7 @@ pub mod index {
       nonunion_members: NONUNION_MEMBERS,
       members_by_discriminant: MEMBERS_BY_DISCRIMINANT,
     };
-    pub static NONUNION_MEMBERS : &[u16] = &[0,1,2];
+    pub static NONUNION_MEMBERS : &[u16] = &[0,1,2,3,4];
     pub static MEMBERS_BY_DISCRIMINANT : &[u16] = &[];
     pub const TYPE_ID: u64 = 0xff78_b66c_3434_661c;
   }
@@ -1442,7 +1514,7 @@ pub mod index {
 
     pub struct Builder<'a> { builder: ::capnp::private::layout::StructBuilder<'a> }
     impl <'a,> ::capnp::traits::HasStructSize for Builder<'a,>  {
-      const STRUCT_SIZE: ::capnp::private::layout::StructSize = ::capnp::private::layout::StructSize { data: 6, pointers: 0 };
+      const STRUCT_SIZE: ::capnp::private::layout::StructSize = ::capnp::private::layout::StructSize { data: 7, pointers: 1 };
     }
     impl <'a,> ::capnp::traits::HasTypeId for Builder<'a,>  {
       const TYPE_ID: u64 = _private::TYPE_ID;
@@ -1522,9 +1594,9 @@ pub mod index {
       pub static ENCODED_NODE: [::capnp::Word; 46] = [
         ::capnp::word(0, 0, 0, 0, 5, 0, 6, 0),
         ::capnp::word(167, 9, 143, 45, 122, 129, 213, 191),
-        ::capnp::word(18, 0, 0, 0, 1, 0, 6, 0),
+        ::capnp::word(18, 0, 0, 0, 1, 0, 7, 0),
         ::capnp::word(28, 102, 52, 52, 108, 182, 120, 255),
-        ::capnp::word(0, 0, 7, 0, 1, 0, 0, 0),
+        ::capnp::word(1, 0, 7, 0, 1, 0, 0, 0),
         ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
         ::capnp::word(21, 0, 0, 0, 194, 0, 0, 0),
         ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
@@ -1566,7 +1638,7 @@ pub mod index {
         ::capnp::word(9, 0, 0, 0, 0, 0, 0, 0),
         ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
         ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ];
+    ];
       pub fn get_field_types(index: u16) -> ::capnp::introspect::Type {
         match index {
           0 => <u64 as ::capnp::introspect::Introspect>::introspect(),
@@ -1661,7 +1733,7 @@ pub mod index {
 
     pub struct Builder<'a> { builder: ::capnp::private::layout::StructBuilder<'a> }
     impl <'a,> ::capnp::traits::HasStructSize for Builder<'a,>  {
-      const STRUCT_SIZE: ::capnp::private::layout::StructSize = ::capnp::private::layout::StructSize { data: 6, pointers: 0 };
+      const STRUCT_SIZE: ::capnp::private::layout::StructSize = ::capnp::private::layout::StructSize { data: 7, pointers: 1 };
     }
     impl <'a,> ::capnp::traits::HasTypeId for Builder<'a,>  {
       const TYPE_ID: u64 = _private::TYPE_ID;
@@ -1751,9 +1823,9 @@ pub mod index {
       pub static ENCODED_NODE: [::capnp::Word; 33] = [
         ::capnp::word(0, 0, 0, 0, 5, 0, 6, 0),
         ::capnp::word(205, 180, 118, 244, 234, 217, 209, 173),
-        ::capnp::word(18, 0, 0, 0, 1, 0, 6, 0),
+        ::capnp::word(18, 0, 0, 0, 1, 0, 7, 0),
         ::capnp::word(28, 102, 52, 52, 108, 182, 120, 255),
-        ::capnp::word(0, 0, 7, 0, 1, 0, 0, 0),
+        ::capnp::word(1, 0, 7, 0, 1, 0, 0, 0),
         ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
         ::capnp::word(21, 0, 0, 0, 234, 0, 0, 0),
         ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
@@ -1782,7 +1854,7 @@ pub mod index {
         ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
         ::capnp::word(109, 105, 110, 0, 0, 0, 0, 0),
         ::capnp::word(109, 97, 120, 0, 0, 0, 0, 0),
-      ];
+    ];
       pub fn get_field_types(index: u16) -> ::capnp::introspect::Type {
         match index {
           0 => <crate::index_capnp::index::timestamps::min::Owned as ::capnp::introspect::Introspect>::introspect(),
@@ -1876,7 +1948,7 @@ pub mod index {
 
       pub struct Builder<'a> { builder: ::capnp::private::layout::StructBuilder<'a> }
       impl <'a,> ::capnp::traits::HasStructSize for Builder<'a,>  {
-        const STRUCT_SIZE: ::capnp::private::layout::StructSize = ::capnp::private::layout::StructSize { data: 6, pointers: 0 };
+        const STRUCT_SIZE: ::capnp::private::layout::StructSize = ::capnp::private::layout::StructSize { data: 7, pointers: 1 };
       }
       impl <'a,> ::capnp::traits::HasTypeId for Builder<'a,>  {
         const TYPE_ID: u64 = _private::TYPE_ID;
@@ -1956,9 +2028,9 @@ pub mod index {
         pub static ENCODED_NODE: [::capnp::Word; 48] = [
           ::capnp::word(0, 0, 0, 0, 5, 0, 6, 0),
           ::capnp::word(195, 79, 121, 144, 142, 219, 133, 150),
-          ::capnp::word(29, 0, 0, 0, 1, 0, 6, 0),
+          ::capnp::word(29, 0, 0, 0, 1, 0, 7, 0),
           ::capnp::word(205, 180, 118, 244, 234, 217, 209, 173),
-          ::capnp::word(0, 0, 7, 0, 1, 0, 0, 0),
+          ::capnp::word(1, 0, 7, 0, 1, 0, 0, 0),
           ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
           ::capnp::word(21, 0, 0, 0, 10, 1, 0, 0),
           ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
@@ -2002,7 +2074,7 @@ pub mod index {
           ::capnp::word(8, 0, 0, 0, 0, 0, 0, 0),
           ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
           ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-        ];
+      ];
         pub fn get_field_types(index: u16) -> ::capnp::introspect::Type {
           match index {
             0 => <i64 as ::capnp::introspect::Introspect>::introspect(),
@@ -2097,7 +2169,7 @@ pub mod index {
 
       pub struct Builder<'a> { builder: ::capnp::private::layout::StructBuilder<'a> }
       impl <'a,> ::capnp::traits::HasStructSize for Builder<'a,>  {
-        const STRUCT_SIZE: ::capnp::private::layout::StructSize = ::capnp::private::layout::StructSize { data: 6, pointers: 0 };
+        const STRUCT_SIZE: ::capnp::private::layout::StructSize = ::capnp::private::layout::StructSize { data: 7, pointers: 1 };
       }
       impl <'a,> ::capnp::traits::HasTypeId for Builder<'a,>  {
         const TYPE_ID: u64 = _private::TYPE_ID;
@@ -2177,9 +2249,9 @@ pub mod index {
         pub static ENCODED_NODE: [::capnp::Word; 48] = [
           ::capnp::word(0, 0, 0, 0, 5, 0, 6, 0),
           ::capnp::word(120, 234, 101, 182, 119, 235, 169, 185),
-          ::capnp::word(29, 0, 0, 0, 1, 0, 6, 0),
+          ::capnp::word(29, 0, 0, 0, 1, 0, 7, 0),
           ::capnp::word(205, 180, 118, 244, 234, 217, 209, 173),
-          ::capnp::word(0, 0, 7, 0, 1, 0, 0, 0),
+          ::capnp::word(1, 0, 7, 0, 1, 0, 0, 0),
           ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
           ::capnp::word(21, 0, 0, 0, 10, 1, 0, 0),
           ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
@@ -2223,7 +2295,7 @@ pub mod index {
           ::capnp::word(8, 0, 0, 0, 0, 0, 0, 0),
           ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
           ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-        ];
+      ];
         pub fn get_field_types(index: u16) -> ::capnp::introspect::Type {
           match index {
             0 => <i64 as ::capnp::introspect::Introspect>::introspect(),
@@ -2780,6 +2852,8 @@ pub const FLAG_LEVEL_DEBUG: u64 = 1;
 pub const FLAG_LEVEL_INFO: u64 = 2;
 pub const FLAG_LEVEL_WARNING: u64 = 4;
 pub const FLAG_LEVEL_ERROR: u64 = 8;
+pub const FLAG_LEVEL_TRACE: u64 = 16;
+pub const FLAG_LEVEL_FATAL: u64 = 32;
 pub const FLAG_LEVEL_MASK: u64 = 255;
 pub const FLAG_UNSORTED: u64 = 256;
 pub const FLAG_HAS_TIMESTAMPS: u64 = 512;
@@ -1,22 +1,32 @@
 // std imports
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::convert::TryInto;
 use std::fs::File;
-use std::io::{self, stdin, BufReader, Read, Seek, SeekFrom};
+use std::io::{self, stdin, Read, Seek, SeekFrom};
 use std::mem::size_of_val;
-use std::ops::Range;
-use std::path::PathBuf;
+use std::ops::{Deref, Range};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 
 // third-party imports
-use flate2::bufread::GzDecoder;
+use crossbeam_channel as channel;
+use crossbeam_utils::thread;
+use memmap2::Mmap;
 use nu_ansi_term::Color;
 
 // local imports
-use crate::error::{Error::UnsupportedFormatForIndexing, Result};
-use crate::index::{Index, Indexer, SourceBlock};
+use crate::compression::{self, Decompression};
+use crate::encoding::Encoding;
+use crate::error::{Error, Result};
+use crate::exec;
+use crate::index::{Index, Indexer, SourceBlock, Timestamp};
 use crate::iox::ReadFill;
-use crate::pool::SQPool;
-use crate::replay::{ReplayBufCreator, ReplayBufReader};
+use crate::listener::ListenAddress;
+use crate::pool::{Pooled, SQPool};
+use crate::progress::{Progress, ProgressReader};
+use crate::replay::{SpillingReplayBufCreator, SpillingReplayBufReader};
 use crate::tee::TeeReader;
 
 // ---
@@ -28,12 +38,17 @@ pub type InputSeekStream = Box<Mutex<dyn ReadSeek + Send + Sync>>;
 
 pub type BufPool = SQPool<Vec<u8>>;
 
+/// Number of bytes at the beginning of a file compared to tell an appended file from a replaced one, see [IndexedInput::extend].
+const EXTEND_CHECK_SIZE: u64 = 4096;
+
 // ---
 
 #[derive(Clone)]
 pub enum InputReference {
     Stdin,
     File(PathBuf),
+    Listen(ListenAddress),
+    Command(Vec<String>),
 }
 
 impl Into<io::Result<InputHolder>> for InputReference {
@@ -44,17 +59,21 @@ impl Into<io::Result<InputHolder>> for InputReference {
 
 impl InputReference {
     pub fn hold(&self) -> io::Result<InputHolder> {
-        Ok(InputHolder::new(
+        let mut size = None;
+        let holder = InputHolder::new(
             self.clone(),
             match self {
-                InputReference::Stdin => None,
+                InputReference::Stdin | InputReference::Listen(_) | InputReference::Command(_) => None,
                 InputReference::File(path) => {
-                    Some(Box::new(File::open(path).map_err(|e| {
+                    let file = File::open(path).map_err(|e| {
                         io::Error::new(e.kind(), format!("failed to open {}: {}", self.description(), e))
-                    })?))
+                    })?;
+                    size = file.metadata().ok().filter(|meta| meta.is_file()).map(|meta| meta.len());
+                    Some(Box::new(file))
                 }
             },
-        ))
+        );
+        Ok(InputHolder { size, ..holder })
     }
 
     pub fn open(&self) -> io::Result<Input> {
@@ -65,6 +84,8 @@ impl InputReference {
         match self {
             Self::Stdin => "<stdin>".into(),
             Self::File(filename) => format!("file '{}'", Color::Yellow.paint(filename.to_string_lossy())),
+            Self::Listen(address) => format!("listener '{}'", Color::Yellow.paint(address.to_string())),
+            Self::Command(command) => format!("command '{}'", Color::Yellow.paint(command.join(" "))),
         }
     }
 }
@@ -74,28 +95,113 @@ impl InputReference {
 pub struct InputHolder {
     pub reference: InputReference,
     pub stream: Option<Box<dyn ReadSeek + Send + Sync>>,
+    pub tags: Vec<(String, String)>,
+    pub decompression: Decompression,
+    pub encoding: Encoding,
+    pub mmap: bool,
+    pub concurrency: usize,
+    /// Size of the input if it is a regular file.
+    pub size: Option<u64>,
+    pub progress: Option<Arc<Progress>>,
 }
 
 impl InputHolder {
     pub fn new(reference: InputReference, stream: Option<Box<dyn ReadSeek + Send + Sync>>) -> Self {
-        Self { reference, stream }
+        Self {
+            reference,
+            stream,
+            tags: Vec::new(),
+            decompression: Decompression::Auto,
+            encoding: Encoding::Auto,
+            mmap: false,
+            concurrency: 1,
+            size: None,
+            progress: None,
+        }
     }
 
-    pub fn open(self) -> io::Result<Input> {
-        match self.reference {
-            InputReference::Stdin => Ok(Input::new(self.reference, Box::new(stdin()))),
-            InputReference::File(path) => match self.stream {
-                Some(stream) => Input::open_stream(&path, stream),
-                None => Input::open(&path),
+    pub fn with_tags(mut self, tags: Vec<(String, String)>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    pub fn with_decompression(mut self, decompression: Decompression) -> Self {
+        self.decompression = decompression;
+        self
+    }
+
+    /// Sets the character encoding the input is converted from to UTF-8.
+    pub fn with_encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Enables reading blocks of an indexed regular file through a memory mapping instead of copying them into buffers.
+    pub fn with_mmap(mut self, enabled: bool) -> Self {
+        self.mmap = enabled;
+        self
+    }
+
+    /// Sets the number of threads compressed files may be decoded with.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Counts bytes read from the input file in the given progress.
+    pub fn with_progress(mut self, progress: Arc<Progress>) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Returns the stream of the input file, counting bytes read from it if progress is tracked.
+    fn stream(&mut self) -> Option<Box<dyn ReadSeek + Send + Sync>> {
+        let stream = self.stream.take()?;
+        Some(match &self.progress {
+            Some(progress) => Box::new(ProgressReader::new(stream, progress.clone())),
+            None => stream,
+        })
+    }
+
+    pub fn open(mut self) -> io::Result<Input> {
+        let stream = self.stream();
+        let input = match self.reference {
+            InputReference::Stdin => Input::new(self.reference, self.decompression.apply(stdin())?),
+            InputReference::Listen(ref address) => Input::new(self.reference.clone(), address.listen()?),
+            InputReference::Command(ref command) => Input::new(self.reference.clone(), Box::new(exec::spawn(command)?)),
+            InputReference::File(path) => match stream {
+                Some(stream) => Input::new(
+                    InputReference::File(path),
+                    self.decompression.apply_parallel(stream, self.concurrency)?,
+                ),
+                None => Input::open(&path)?,
             },
-        }
+        };
+        Ok(Input::new(input.reference, Box::new(self.encoding.apply(input.stream))))
     }
 
-    pub fn index(self, indexer: &Indexer) -> Result<IndexedInput> {
+    pub fn index(mut self, indexer: &Indexer) -> Result<IndexedInput> {
+        let stream = self.stream();
+        let encoding = self.encoding;
+        let transcode = |stream: InputStream| -> InputStream { Box::new(encoding.apply(stream)) };
         match self.reference {
-            InputReference::Stdin => IndexedInput::open_sequential(self.reference.clone(), Box::new(stdin()), indexer),
-            InputReference::File(path) => match self.stream {
-                Some(stream) => IndexedInput::open_stream(&path, stream, indexer),
+            InputReference::Stdin => IndexedInput::open_sequential(
+                self.reference.clone(),
+                transcode(self.decompression.apply(stdin())?),
+                indexer,
+            ),
+            InputReference::Listen(ref address) => {
+                IndexedInput::open_sequential(self.reference.clone(), transcode(address.listen()?), indexer)
+            }
+            InputReference::Command(ref command) => IndexedInput::open_sequential(
+                self.reference.clone(),
+                transcode(Box::new(exec::spawn(command)?)),
+                indexer,
+            ),
+            InputReference::File(path) => match stream {
+                Some(stream) => {
+                    IndexedInput::open_stream(&path, stream, self.decompression, encoding, indexer, self.mmap)
+                }
                 None => IndexedInput::open(&path, indexer),
             },
         }
@@ -116,21 +222,68 @@ impl Input {
         InputReference::File(path.clone()).hold()?.open()
     }
 
-    pub fn open_stream(path: &PathBuf, stream: Box<dyn ReadSeek + Send + Sync>) -> io::Result<Self> {
-        let stream: InputStream = match path.extension().map(|x| x.to_str()) {
-            Some(Some("gz")) => Box::new(GzDecoder::new(BufReader::new(stream))),
-            _ => Box::new(stream),
-        };
-        Ok(Self::new(InputReference::File(path.clone()), stream))
+    pub fn open_stream(
+        path: &PathBuf,
+        stream: Box<dyn ReadSeek + Send + Sync>,
+        decompression: Decompression,
+    ) -> io::Result<Self> {
+        Ok(Self::new(InputReference::File(path.clone()), decompression.apply(stream)?))
     }
 }
 
+/// Indexes the inputs using the given function, with up to the given number of them at a time,
+/// and returns the indexed inputs in the original order.
+///
+/// Inputs not started yet are skipped after the first failure.
+pub fn index_all<F>(inputs: Vec<InputHolder>, concurrency: usize, index: F) -> Result<Vec<IndexedInput>>
+where
+    F: Fn(usize, InputHolder) -> Result<IndexedInput> + Sync,
+{
+    let n = concurrency.clamp(1, inputs.len().max(1));
+    if n == 1 {
+        return inputs.into_iter().enumerate().map(|(i, input)| index(i, input)).collect();
+    }
+    let (tx, rx) = channel::unbounded();
+    for item in inputs.into_iter().enumerate() {
+        tx.send(item).ok();
+    }
+    drop(tx);
+    let failed = AtomicBool::new(false);
+    let mut results = thread::scope(|scope| {
+        let workers = (0..n)
+            .map(|_| {
+                scope.spawn(|_| {
+                    let mut results = Vec::new();
+                    for (i, input) in rx.iter() {
+                        if failed.load(Ordering::Relaxed) {
+                            break;
+                        }
+                        let result = index(i, input);
+                        if result.is_err() {
+                            failed.store(true, Ordering::Relaxed);
+                        }
+                        results.push((i, result));
+                    }
+                    results
+                })
+            })
+            .collect::<Vec<_>>();
+        workers.into_iter().flat_map(|worker| worker.join().unwrap()).collect::<Vec<_>>()
+    })
+    .unwrap();
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
 // ---
 
 pub struct IndexedInput {
     pub reference: InputReference,
     pub stream: InputSeekStream,
     pub index: Index,
+    mmap: Option<Arc<Mmap>>,
+    /// Beginning of the file indexed in place, see [IndexedInput::extend].
+    head: Option<Vec<u8>>,
 }
 
 impl IndexedInput {
@@ -139,44 +292,140 @@ impl IndexedInput {
             reference,
             stream,
             index,
+            mmap: None,
+            head: None,
+        }
+    }
+
+    /// Keeps the beginning of the file indexed in place to tell whether it is only appended to later.
+    fn with_head(mut self, path: &Path) -> Result<Self> {
+        let mut head = Vec::new();
+        File::open(path)?
+            .take(self.index.source().size.min(EXTEND_CHECK_SIZE))
+            .read_to_end(&mut head)?;
+        self.head = Some(head);
+        Ok(self)
+    }
+
+    /// Maps the file into memory to read blocks from the mapping, blocks beyond the mapped size are read from the stream.
+    ///
+    /// The file is expected not to be truncated while it is mapped.
+    fn with_mmap(mut self, path: &Path) -> Result<Self> {
+        let file = File::open(path).map_err(|err| Error::FailedToOpenFileForReading {
+            path: path.into(),
+            source: err,
+        })?;
+        if file.metadata()?.len() != 0 {
+            // SAFETY: the mapping is only read, but if the file is truncated by another process meanwhile,
+            // reading the pages past its new end raises SIGBUS, so mapping is only used if requested by --mmap
+            // option, which is documented to require files not to be truncated while they are processed.
+            self.mmap = Some(Arc::new(unsafe { Mmap::map(&file)? }));
         }
+        Ok(self)
     }
 
     pub fn open(path: &PathBuf, indexer: &Indexer) -> Result<Self> {
         InputReference::File(path.clone()).hold()?.index(indexer)
     }
 
-    pub fn open_stream(path: &PathBuf, mut stream: Box<dyn ReadSeek + Send + Sync>, indexer: &Indexer) -> Result<Self> {
-        if let Some(Some("gz")) = path.extension().map(|x| x.to_str()) {
-            return Err(UnsupportedFormatForIndexing {
-                path: path.clone(),
-                format: "gzip".into(),
-            });
+    /// Returns the input with the index extended to the lines appended to the file since it was indexed,
+    /// or None if the file was not indexed in place or has been changed in another way.
+    pub fn extend(&self, indexer: &Indexer) -> Result<Option<Self>> {
+        let (path, head) = match (&self.reference, &self.head) {
+            (InputReference::File(path), Some(head)) => (path, head),
+            _ => return Ok(None),
+        };
+        let source = self.index.source();
+        let mut file = File::open(path).map_err(|err| Error::FailedToOpenFileForReading {
+            path: path.clone(),
+            source: err,
+        })?;
+        if file.metadata()?.len() < source.size {
+            return Ok(None);
         }
-
-        if stream.seek(SeekFrom::Current(0)).is_err() {
-            return Self::open_sequential(
-                InputReference::File(path.clone()),
-                Box::new(stream.as_input_stream()),
-                indexer,
-            );
+        // the file still has to start with the indexed data, which has to end with a complete line
+        let mut current = vec![0; head.len()];
+        file.read_exact(&mut current)?;
+        if current != *head {
+            return Ok(None);
+        }
+        if source.size != 0 {
+            let mut last = [0];
+            file.seek(SeekFrom::Start(source.size - 1))?;
+            file.read_exact(&mut last)?;
+            if last[0] != b'\n' {
+                return Ok(None);
+            }
+        }
+        file.seek(SeekFrom::Start(0))?;
+        let input = Self {
+            reference: self.reference.clone(),
+            stream: Box::new(Mutex::new(file)),
+            index: indexer.index_appended(&self.index)?,
+            mmap: self.mmap.clone(),
+            head: None,
+        };
+        Ok(Some(input.with_head(path)?))
+    }
+
+    pub fn open_stream(
+        path: &PathBuf,
+        mut stream: Box<dyn ReadSeek + Send + Sync>,
+        decompression: Decompression,
+        encoding: Encoding,
+        indexer: &Indexer,
+        mmap: bool,
+    ) -> Result<Self> {
+        let reference = InputReference::File(path.clone());
+        let map = |input: Self, path: &Path| if mmap { input.with_mmap(path) } else { Ok(input) };
+        // data that needs to be converted to UTF-8 cannot be read in place, so it is indexed as a stream
+        let transcode = |stream: InputStream| -> InputStream { Box::new(encoding.apply(stream)) };
+        let (compression, head) = match stream.seek(SeekFrom::Current(0)) {
+            Ok(pos) => {
+                let head = compression::read_head(&mut stream)?;
+                stream.seek(SeekFrom::Start(pos))?;
+                (decompression.resolve(&head), head)
+            }
+            Err(_) => return Self::open_sequential(reference, transcode(decompression.apply(stream)?), indexer),
+        };
+        if let Some(compression) = compression {
+            return match indexer.unpack(path, compression)? {
+                Some(unpacked) => {
+                    let mut stream = File::open(&unpacked).map_err(|err| Error::FailedToOpenFileForReading {
+                        path: unpacked.clone(),
+                        source: err,
+                    })?;
+                    let head = compression::read_head(&mut stream)?;
+                    stream.seek(SeekFrom::Start(0))?;
+                    if encoding.transcodes(&head) {
+                        return Self::open_sequential(reference, transcode(Box::new(stream)), indexer);
+                    }
+                    let index = indexer.index(&unpacked)?;
+                    map(Self::new(reference, Box::new(Mutex::new(stream)), index), unpacked.as_path())
+                }
+                None => Self::open_sequential(
+                    reference,
+                    transcode(compression.parallel_decoder(stream, indexer.concurrency())?),
+                    indexer,
+                ),
+            };
+        }
+        if encoding.transcodes(&head) {
+            return Self::open_sequential(reference, transcode(Box::new(stream)), indexer);
         }
 
         let index = indexer.index(&path)?;
-        Ok(Self::new(
-            InputReference::File(path.clone()),
-            Box::new(Mutex::new(stream)),
-            index,
-        ))
+        map(Self::new(reference, Box::new(Mutex::new(stream)), index).with_head(path)?, path.as_path())
     }
 
     pub fn open_sequential(reference: InputReference, stream: InputStream, indexer: &Indexer) -> Result<Self> {
-        let mut tee = TeeReader::new(stream, ReplayBufCreator::new());
+        let buf = SpillingReplayBufCreator::new(indexer.max_stream_buffer_size(), std::env::temp_dir());
+        let mut tee = TeeReader::new(stream, buf);
         let index = indexer.index_from_stream(&mut tee)?;
         let buf = tee.into_writer().result()?;
         Ok(IndexedInput::new(
             reference,
-            Box::new(Mutex::new(ReplayBufReader::new(buf))),
+            Box::new(Mutex::new(SpillingReplayBufReader::new(buf)?)),
             index,
         ))
     }
@@ -234,6 +483,52 @@ impl<II: Iterator<Item = usize>> Iterator for Blocks<IndexedInput, II> {
 
 // ---
 
+/// Merges sorted blocks of several inputs into a single sequence ordered by timestamp range, input number and offset.
+///
+/// Only the next block of each input is held at any time, so the merged sequence can be consumed as a stream.
+pub struct MergedBlocks<II> {
+    inputs: Vec<Blocks<IndexedInput, II>>,
+    heads: Vec<Option<Block<IndexedInput>>>,
+    queue: BinaryHeap<Reverse<(Option<(Timestamp, Timestamp)>, usize, u64)>>,
+}
+
+impl<II: Iterator<Item = usize>> MergedBlocks<II> {
+    /// Returns merged blocks of the given inputs, blocks of each input are expected to be sorted with [Blocks::sorted].
+    pub fn new(inputs: Vec<Blocks<IndexedInput, II>>) -> Self {
+        let n = inputs.len();
+        let mut result = Self {
+            inputs,
+            heads: (0..n).map(|_| None).collect(),
+            queue: BinaryHeap::with_capacity(n),
+        };
+        for i in 0..n {
+            result.advance(i);
+        }
+        result
+    }
+
+    fn advance(&mut self, i: usize) {
+        self.heads[i] = self.inputs[i].next();
+        if let Some(block) = &self.heads[i] {
+            self.queue
+                .push(Reverse((block.source_block().stat.ts_min_max, i, block.offset())));
+        }
+    }
+}
+
+impl<II: Iterator<Item = usize>> Iterator for MergedBlocks<II> {
+    type Item = (Block<IndexedInput>, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse((_, i, _)) = self.queue.pop()?;
+        let block = self.heads[i].take()?;
+        self.advance(i);
+        Some((block, i))
+    }
+}
+
+// ---
+
 pub struct Block<I> {
     input: Arc<I>,
     index: usize,
@@ -282,7 +577,7 @@ impl Block<IndexedInput> {
 
 pub struct BlockLines<I> {
     block: Block<I>,
-    buf: Arc<Vec<u8>>,
+    buf: BlockBuf,
     total: usize,
     current: usize,
     byte: usize,
@@ -293,22 +588,36 @@ impl BlockLines<IndexedInput> {
     pub fn new(mut block: Block<IndexedInput>) -> Result<Self> {
         let (buf, total) = {
             let block = &mut block;
-            let mut buf = if let Some(pool) = &block.buf_pool {
-                pool.checkout() // TODO: implement checkin
-            } else {
-                Vec::new()
-            };
             let source_block = block.source_block();
-            buf.resize(source_block.size.try_into()?, 0);
-            let mut stream = block.input.stream.lock().unwrap();
-            stream.seek(SeekFrom::Start(source_block.offset))?;
-            stream.read_fill(&mut buf)?;
+            let mapped = block.input.mmap.as_ref().and_then(|mmap| BlockBuf::mapped(mmap, source_block));
+            let buf = match mapped {
+                Some(buf) => buf,
+                None => {
+                    let size = source_block.size.try_into()?;
+                    let read = |buf: &mut Vec<u8>| -> Result<()> {
+                        buf.resize(size, 0);
+                        let mut stream = block.input.stream.lock().unwrap();
+                        stream.seek(SeekFrom::Start(source_block.offset))?;
+                        stream.read_fill(buf)?;
+                        Ok(())
+                    };
+                    if let Some(pool) = &block.buf_pool {
+                        let mut buf = Pooled::checkout(pool);
+                        read(&mut buf)?;
+                        BlockBuf::Pooled(Arc::new(buf))
+                    } else {
+                        let mut buf = Vec::new();
+                        read(&mut buf)?;
+                        BlockBuf::Owned(Arc::new(buf))
+                    }
+                }
+            };
             let total = (source_block.stat.lines_valid + source_block.stat.lines_invalid).try_into()?;
             (buf, total)
         };
         Ok(Self {
             block,
-            buf: Arc::new(buf), // TODO: optimize allocations
+            buf,
             total,
             current: 0,
             byte: 0,
@@ -363,13 +672,13 @@ impl Iterator for BlockLines<IndexedInput> {
 // ---
 
 pub struct BlockLine {
-    buf: Arc<Vec<u8>>,
+    buf: BlockBuf,
     range: Range<usize>,
 }
 
 impl BlockLine {
-    pub fn new(buf: Arc<Vec<u8>>, range: Range<usize>) -> Self {
-        Self { buf, range }
+    pub fn new<B: Into<BlockBuf>>(buf: B, range: Range<usize>) -> Self {
+        Self { buf: buf.into(), range }
     }
 
     pub fn bytes(&self) -> &[u8] {
@@ -387,6 +696,47 @@ impl BlockLine {
 
 // ---
 
+/// Shared data of a block, either read into a buffer or referring to a memory mapped file.
+/// Pooled buffers are returned to their pool when the last reference is dropped.
+#[derive(Clone)]
+pub enum BlockBuf {
+    Owned(Arc<Vec<u8>>),
+    Pooled(Arc<Pooled<Vec<u8>, BufPool>>),
+    Mapped(Arc<Mmap>, Range<usize>),
+}
+
+impl BlockBuf {
+    fn mapped(mmap: &Arc<Mmap>, block: &SourceBlock) -> Option<Self> {
+        let start = usize::try_from(block.offset).ok()?;
+        let end = start.checked_add(usize::try_from(block.size).ok()?)?;
+        if end <= mmap.len() {
+            Some(Self::Mapped(mmap.clone(), start..end))
+        } else {
+            None
+        }
+    }
+}
+
+impl Deref for BlockBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Owned(buf) => buf,
+            Self::Pooled(buf) => buf,
+            Self::Mapped(mmap, range) => &mmap[range.clone()],
+        }
+    }
+}
+
+impl From<Arc<Vec<u8>>> for BlockBuf {
+    fn from(buf: Arc<Vec<u8>>) -> Self {
+        Self::Owned(buf)
+    }
+}
+
+// ---
+
 pub struct ConcatReader<I> {
     iter: I,
     item: Option<Input>,
@@ -436,12 +786,132 @@ pub trait ReadSeek: Read + Seek {}
 
 impl<T: Read + Seek> ReadSeek for T {}
 
-trait AsInputStream {
-    fn as_input_stream(self) -> InputStream;
-}
+// ---
 
-impl<T: Read + Send + Sync + 'static> AsInputStream for T {
-    fn as_input_stream(self) -> InputStream {
-        Box::new(self)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Settings;
+
+    fn input(timestamps: &[u32], indexer: &Indexer) -> IndexedInput {
+        let data = timestamps
+            .iter()
+            .map(|ts| format!("{{\"ts\":\"2020-01-01T00:00:{:02}Z\"}}\n", ts))
+            .collect::<String>();
+        IndexedInput::open_sequential(InputReference::Stdin, Box::new(io::Cursor::new(data)), indexer).unwrap()
+    }
+
+    #[test]
+    fn test_merged_blocks() {
+        let indexer = Indexer::new(1, 32, 64, PathBuf::new(), &Settings::default().fields.predefined).with_cache(false);
+        let inputs = vec![input(&[5, 1, 3], &indexer), input(&[4, 2], &indexer)];
+        let merged = MergedBlocks::new(inputs.into_iter().map(|input| input.into_blocks().sorted()).collect())
+            .map(|(block, i)| (block.source_block().stat.ts_min_max.unwrap().0.sec % 60, i))
+            .collect::<Vec<_>>();
+        assert_eq!(merged, vec![(1, 0), (2, 1), (3, 0), (4, 1), (5, 0)]);
+    }
+
+    #[test]
+    fn test_mmap() {
+        let path = std::env::temp_dir().join(format!("hl-mmap-test-{}.log", std::process::id()));
+        std::fs::write(&path, "{\"ts\":\"2020-01-01T00:00:02Z\"}\n{\"ts\":\"2020-01-01T00:00:01Z\"}\n").unwrap();
+        let indexer = Indexer::new(1, 32, 64, PathBuf::new(), &Settings::default().fields.predefined).with_cache(false);
+        let lines = |mmap: bool| {
+            let input = InputReference::File(path.clone()).hold().unwrap().with_mmap(mmap).index(&indexer).unwrap();
+            assert_eq!(input.mmap.is_some(), mmap);
+            input
+                .into_blocks()
+                .flat_map(|block| block.into_lines().unwrap())
+                .map(|line| String::from_utf8(line.bytes().to_vec()).unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(lines(true), lines(false));
+        assert_eq!(lines(true).len(), 2);
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_extend() {
+        use std::io::Write;
+
+        let path = std::env::temp_dir().join(format!("hl-extend-test-{}.log", std::process::id()));
+        std::fs::write(&path, "{\"ts\":\"2020-01-01T00:00:01Z\"}\n").unwrap();
+        let indexer = Indexer::new(1, 32, 64, PathBuf::new(), &Settings::default().fields.predefined).with_cache(false);
+        let lines = |input: IndexedInput| {
+            input
+                .into_blocks()
+                .flat_map(|block| block.into_lines().unwrap())
+                .map(|line| String::from_utf8(line.bytes().to_vec()).unwrap())
+                .collect::<Vec<_>>()
+        };
+        let input = IndexedInput::open(&path, &indexer).unwrap();
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"ts\":\"2020-01-01T00:00:02Z\"}\n").unwrap();
+        let extended = input.extend(&indexer).unwrap().unwrap();
+        assert_eq!(extended.index.source().size, 60);
+        assert_eq!(extended.index.source().stat.lines_valid, 2);
+        assert_eq!(lines(extended).len(), 2);
+
+        // incomplete last line
+        file.write_all(b"{\"ts\"").unwrap();
+        let input = IndexedInput::open(&path, &indexer).unwrap();
+        file.write_all(b":\"2020-01-01T00:00:03Z\"}\n").unwrap();
+        assert!(input.extend(&indexer).unwrap().is_none());
+
+        // replaced with other data
+        let input = IndexedInput::open(&path, &indexer).unwrap();
+        std::fs::write(&path, "{\"ts\":\"2021-01-01T00:00:01Z\"}\n".repeat(4)).unwrap();
+        assert!(input.extend(&indexer).unwrap().is_none());
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_index_all() {
+        let dir = std::env::temp_dir().join(format!("hl-index-all-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let indexer = Indexer::new(1, 32, 64, PathBuf::new(), &Settings::default().fields.predefined).with_cache(false);
+        let inputs = || {
+            (1..=5)
+                .map(|n| {
+                    let path = dir.join(format!("{}.log", n));
+                    std::fs::write(&path, "{\"ts\":\"2020-01-01T00:00:01Z\"}\n".repeat(n)).unwrap();
+                    InputReference::File(path).hold().unwrap()
+                })
+                .collect::<Vec<_>>()
+        };
+        for concurrency in [1, 2, 8] {
+            let indexed = index_all(inputs(), concurrency, |_, x| x.index(&indexer)).unwrap();
+            let lines = indexed.iter().map(|x| x.index.source().stat.lines_valid).collect::<Vec<_>>();
+            assert_eq!(lines, vec![1, 2, 3, 4, 5]);
+        }
+        let result = index_all(inputs(), 2, |i, x| match i {
+            2 => Err(Error::Cancelled),
+            _ => x.index(&indexer),
+        });
+        assert!(matches!(result, Err(Error::Cancelled)));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_buf_pool() {
+        let indexer = Indexer::new(1, 32, 64, PathBuf::new(), &Settings::default().fields.predefined).with_cache(false);
+        let input = Arc::new(input(&[1, 2, 3], &indexer));
+        let pool = Arc::new(BufPool::new());
+        let line = {
+            let mut lines = Block::new(input.clone(), 0).with_buf_pool(pool.clone()).into_lines().unwrap();
+            lines.next().unwrap()
+        };
+        assert_eq!(line.bytes(), b"{\"ts\":\"2020-01-01T00:00:01Z\"}\n");
+        assert_eq!(pool.checkout().capacity(), 0);
+        drop(line);
+        let buf = pool.checkout();
+        assert_ne!(buf.capacity(), 0);
+        pool.checkin(buf);
+
+        let lines = Block::new(input, 1).with_buf_pool(pool.clone()).into_lines().unwrap();
+        assert_eq!(lines.count(), 1);
+        assert_ne!(pool.checkout().capacity(), 0);
     }
 }
@@ -1,5 +1,11 @@
 // std imports
-use std::io::{Read, Result};
+use std::io::{BufRead, BufReader, Read, Result};
+
+// third-party imports
+use crossbeam_channel::{self as channel, Receiver, RecvTimeoutError, Sender};
+
+// local imports
+use crate::cancellation::{Cancellation, POLL_INTERVAL};
 
 // ---
 
@@ -20,3 +26,131 @@ impl<T: Read + ?Sized> ReadFill for T {
         Ok(i)
     }
 }
+
+// ---
+
+/// Reader producing data chunks received from a channel, reaching the end when all senders are dropped.
+pub struct ChannelReader {
+    rx: Receiver<Vec<u8>>,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl ChannelReader {
+    pub fn new(rx: Receiver<Vec<u8>>) -> Self {
+        Self {
+            rx,
+            buf: Vec::new(),
+            pos: 0,
+        }
+    }
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        while self.pos == self.buf.len() {
+            match self.rx.recv() {
+                Ok(data) => {
+                    self.buf = data;
+                    self.pos = 0;
+                }
+                Err(_) => return Ok(0),
+            }
+        }
+        let n = buf.len().min(self.buf.len() - self.pos);
+        buf[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+// ---
+
+/// Reader that reaches the end of data as soon as processing is cancelled, even if the inner reader is blocked,
+/// like when reading from stdin or a socket with no data coming.
+///
+/// The inner reader is read by a separate thread, which stays blocked until the inner read returns
+/// if the reader is dropped after cancellation.
+pub struct CancellableReader {
+    rx: Receiver<Result<Vec<u8>>>,
+    cancellation: Cancellation,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl CancellableReader {
+    pub fn new<R: Read + Send + 'static>(mut inner: R, cancellation: Cancellation) -> Self {
+        let (tx, rx) = channel::bounded(1);
+        std::thread::spawn(move || loop {
+            let mut buf = vec![0; 64 * 1024];
+            let result = inner.read(&mut buf).map(|n| {
+                buf.truncate(n);
+                buf
+            });
+            let end = !matches!(&result, Ok(buf) if !buf.is_empty());
+            if tx.send(result).is_err() || end {
+                break;
+            }
+        });
+        Self {
+            rx,
+            cancellation,
+            buf: Vec::new(),
+            pos: 0,
+        }
+    }
+}
+
+impl Read for CancellableReader {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        while self.pos == self.buf.len() {
+            if self.cancellation.is_cancelled() {
+                return Ok(0);
+            }
+            match self.rx.recv_timeout(POLL_INTERVAL) {
+                Ok(data) => {
+                    self.buf = data?;
+                    self.pos = 0;
+                    if self.buf.is_empty() {
+                        return Ok(0);
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => return Ok(0),
+            }
+        }
+        let n = buf.len().min(self.buf.len() - self.pos);
+        buf[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+// ---
+
+/// Sends complete lines read from the reader to the channel until the end of data or until the receiver is dropped.
+///
+/// The last line is terminated with a newline if it is missing, so that lines of several readers sent to the same channel never get mixed.
+pub fn forward_lines<R: Read>(reader: R, tx: Sender<Vec<u8>>) {
+    let mut reader = BufReader::new(reader);
+    loop {
+        let mut buf = Vec::new();
+        match reader.read_until(b'\n', &mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {
+                if tx.send(terminated(&buf)).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Returns a copy of the data terminated with a newline.
+pub fn terminated(data: &[u8]) -> Vec<u8> {
+    let mut line = data.to_vec();
+    if !line.ends_with(b"\n") {
+        line.push(b'\n');
+    }
+    line
+}
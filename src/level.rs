@@ -1,5 +1,6 @@
 // std imports
 use std::cmp::Ord;
+use std::collections::BTreeMap;
 use std::ops::Deref;
 use std::result::Result;
 
@@ -11,15 +12,66 @@ use clap::{
 use enum_map::Enum;
 use serde::{Deserialize, Serialize};
 
+// local imports
+use crate::error::InvalidLevelError;
+
 // ---
 
 #[derive(ValueEnum, Clone, Copy, Debug, Deserialize, Serialize, Eq, Hash, Ord, PartialEq, PartialOrd, Enum)]
 #[serde(rename_all = "kebab-case")]
 pub enum Level {
+    Fatal,
     Error,
     Warning,
     Info,
     Debug,
+    Trace,
+}
+
+impl Level {
+    /// Parses level name ignoring case, accepting the given aliases the same way as --level option.
+    pub fn parse_relaxed(value: &str, aliases: &LevelAliases) -> Result<Self, InvalidLevelError> {
+        if let Some(level) = aliases.resolve(value) {
+            return Ok(level);
+        }
+        Self::from_str(value, true).map_err(|_| InvalidLevelError {
+            value: value.into(),
+            valid_values: Self::value_variants()
+                .iter()
+                .filter_map(|x| x.to_possible_value())
+                .map(|x| x.get_name().into())
+                .collect(),
+        })
+    }
+
+    /// Returns the closest level to be used in place of this one if it is not configured, e.g. in a theme.
+    pub fn fallback(self) -> Option<Self> {
+        match self {
+            Self::Fatal => Some(Self::Error),
+            Self::Trace => Some(Self::Debug),
+            _ => None,
+        }
+    }
+}
+
+// ---
+
+/// Alternative names of levels, matched ignoring case.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+pub struct LevelAliases(BTreeMap<Level, Vec<String>>);
+
+impl LevelAliases {
+    pub fn new<I: IntoIterator<Item = (Level, Vec<String>)>>(items: I) -> Self {
+        Self(items.into_iter().collect())
+    }
+
+    /// Returns the level having the given alias if any.
+    pub fn resolve(&self, value: &str) -> Option<Level> {
+        self.0
+            .iter()
+            .find(|(_, aliases)| aliases.iter().any(|x| value.eq_ignore_ascii_case(x)))
+            .map(|(level, _)| *level)
+    }
 }
 
 // ---
@@ -44,14 +96,22 @@ impl Deref for RelaxedLevel {
 impl ValueParserFactory for RelaxedLevel {
     type Parser = LevelValueParser;
     fn value_parser() -> Self::Parser {
-        LevelValueParser
+        LevelValueParser::new(LevelAliases::default())
     }
 }
 
 // ---
 
 #[derive(Clone, Debug)]
-pub struct LevelValueParser;
+pub struct LevelValueParser {
+    aliases: LevelAliases,
+}
+
+impl LevelValueParser {
+    pub fn new(aliases: LevelAliases) -> Self {
+        Self { aliases }
+    }
+}
 
 impl TypedValueParser for LevelValueParser {
     type Value = RelaxedLevel;
@@ -62,10 +122,8 @@ impl TypedValueParser for LevelValueParser {
         arg: Option<&clap::Arg>,
         value: &std::ffi::OsStr,
     ) -> Result<RelaxedLevel, clap::Error> {
-        for (level, values) in Self::alternate_values() {
-            if values.iter().cloned().any(|x| value.eq_ignore_ascii_case(x)) {
-                return Ok(RelaxedLevel(*level));
-            }
+        if let Some(level) = value.to_str().and_then(|value| self.aliases.resolve(value)) {
+            return Ok(RelaxedLevel(level));
         }
 
         let inner = EnumValueParser::<Level>::new();
@@ -74,13 +132,23 @@ impl TypedValueParser for LevelValueParser {
     }
 }
 
-impl LevelValueParser {
-    fn alternate_values<'a>() -> &'a [(Level, &'a [&'a str])] {
-        &[
-            (Level::Error, &["err", "e"]),
-            (Level::Warning, &["warn", "wrn", "w"]),
-            (Level::Info, &["inf", "i"]),
-            (Level::Debug, &["dbg", "d"]),
-        ]
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_relaxed() {
+        let aliases = LevelAliases::new([
+            (Level::Fatal, vec!["panic".into()]),
+            (Level::Trace, vec!["trc".into(), "t".into()]),
+        ]);
+        assert_eq!(Level::parse_relaxed("PANIC", &aliases).unwrap(), Level::Fatal);
+        assert_eq!(Level::parse_relaxed("t", &aliases).unwrap(), Level::Trace);
+        assert_eq!(Level::parse_relaxed("Trace", &aliases).unwrap(), Level::Trace);
+        assert_eq!(Level::parse_relaxed("warning", &aliases).unwrap(), Level::Warning);
+        assert!(Level::parse_relaxed("w", &aliases).is_err());
+        assert!(Level::Fatal < Level::Error && Level::Debug < Level::Trace);
     }
 }
@@ -1,33 +1,81 @@
 // public modules
 pub mod app;
+pub mod bugreport;
+pub mod cancellation;
+pub mod checkpoint;
+pub mod clipboard;
+pub mod compression;
 pub mod datefmt;
+pub mod dedup;
+pub mod diagnostics;
+pub mod docker;
+pub mod encoding;
 pub mod error;
+pub mod exec;
 pub mod fmtx;
-pub mod index;
+pub mod generate;
+pub mod grouping;
+pub mod history;
 pub mod index_capnp;
-pub mod input;
 pub mod iox;
 pub mod level;
+pub mod locale;
+pub mod logfmt;
+pub mod listener;
 pub mod output;
+pub mod palette;
+pub mod pattern;
+pub mod pipeline;
+pub mod priority;
+pub mod progress;
+pub mod query;
+pub mod record;
+pub mod schema;
 pub mod settings;
+pub mod snapshot;
+pub mod stats;
+pub mod syslog;
+pub mod terminal;
+pub mod text;
 pub mod theme;
 pub mod themecfg;
 pub mod timeparse;
 pub mod timestamp;
 pub mod timezone;
+pub mod tracking;
 pub mod types;
 
+// internal modules, public only for the binary and benchmarks, use the re-exported types and [Pipeline] instead
+#[doc(hidden)]
+pub mod index;
+#[doc(hidden)]
+pub mod input;
+
 // private modules
+mod binary;
+mod cache;
+mod columns;
 mod console;
+mod context;
+mod diff;
 mod eseq;
+mod export;
 mod filtering;
+mod follow;
 mod formatting;
 mod fsmon;
+mod highlight;
+mod limit;
 mod model;
 mod pool;
+mod prefilter;
 mod replay;
 mod scanning;
+mod summary;
 mod tee;
+mod transform;
+mod ui;
+mod width;
 
 // conditional public modules
 #[cfg_attr(unix, path = "signal_unix.rs")]
@@ -37,10 +85,15 @@ pub mod signal;
 // public uses
 pub use app::{App, FieldOptions, Options, SegmentProcessor};
 pub use datefmt::{DateTimeFormatter, LinuxDateFormat};
-pub use filtering::DefaultNormalizing;
-pub use formatting::RecordFormatter;
-pub use model::{FieldFilterSet, Filter, Level, Parser, ParserSettings};
+pub use filtering::{DefaultNormalizing, FieldSelection};
+pub use formatting::{Lanes, RecordFormatter};
+pub use input::InputReference;
+pub use model::{FieldFilterSet, Filter, Level, LoggerLevels, Parser, ParserSettings, Record, TextFolding};
+pub use palette::{ColorBy, ColorTarget};
+pub use pipeline::Pipeline;
+pub use record::RecordData;
 pub use settings::Settings;
+pub use summary::Summary;
 pub use theme::Theme;
 
 // public uses (platform-specific)
@@ -0,0 +1,99 @@
+// std imports
+use std::collections::VecDeque;
+use std::io::{self, Write};
+
+// local imports
+use crate::app::Limit;
+
+// ---
+
+/// Limits records to show to the first or the last ones, given in output order, see --head and --tail options.
+pub struct RecordLimiter {
+    limit: Limit,
+    count: usize,
+    ring: VecDeque<Vec<u8>>,
+}
+
+impl RecordLimiter {
+    pub fn new(limit: Limit) -> Self {
+        let capacity = match limit {
+            Limit::Head(_) => 0,
+            Limit::Tail(n) => n.min(1024),
+        };
+        Self {
+            limit,
+            count: 0,
+            ring: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Writes the record if it is one of the first ones, or keeps it if it may be one of the last ones.
+    ///
+    /// Returns false if no more records are needed.
+    pub fn push<W: Write + ?Sized>(&mut self, output: &mut W, record: &[u8]) -> io::Result<bool> {
+        match self.limit {
+            Limit::Head(n) => {
+                if self.count < n {
+                    output.write_all(record)?;
+                    self.count += 1;
+                }
+                Ok(self.count < n)
+            }
+            Limit::Tail(n) => {
+                if n != 0 {
+                    let mut buf = if self.ring.len() == n {
+                        self.ring.pop_front().unwrap_or_default()
+                    } else {
+                        Vec::new()
+                    };
+                    buf.clear();
+                    buf.extend_from_slice(record);
+                    self.ring.push_back(buf);
+                }
+                self.count += 1;
+                Ok(true)
+            }
+        }
+    }
+
+    /// Writes the last records kept so far.
+    pub fn finish<W: Write + ?Sized>(&mut self, output: &mut W) -> io::Result<()> {
+        for record in self.ring.drain(..) {
+            output.write_all(&record)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limit(limit: Limit, records: &[&str]) -> (String, usize) {
+        let mut limiter = RecordLimiter::new(limit);
+        let mut output = Vec::new();
+        let mut pushed = 0;
+        for record in records {
+            pushed += 1;
+            if !limiter.push(&mut output, format!("{}\n", record).as_bytes()).unwrap() {
+                break;
+            }
+        }
+        limiter.finish(&mut output).unwrap();
+        (String::from_utf8(output).unwrap(), pushed)
+    }
+
+    #[test]
+    fn test_head() {
+        assert_eq!(limit(Limit::Head(2), &["a", "b", "c", "d"]), ("a\nb\n".into(), 2));
+        assert_eq!(limit(Limit::Head(5), &["a", "b"]), ("a\nb\n".into(), 2));
+        assert_eq!(limit(Limit::Head(0), &["a", "b"]), ("".into(), 1));
+    }
+
+    #[test]
+    fn test_tail() {
+        assert_eq!(limit(Limit::Tail(2), &["a", "b", "c", "d"]), ("c\nd\n".into(), 4));
+        assert_eq!(limit(Limit::Tail(5), &["a", "b"]), ("a\nb\n".into(), 2));
+        assert_eq!(limit(Limit::Tail(0), &["a", "b"]), ("".into(), 2));
+    }
+}
@@ -0,0 +1,226 @@
+// std imports
+use std::fmt;
+use std::io;
+use std::net::{TcpListener, UdpSocket};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::thread;
+
+// third-party imports
+use crossbeam_channel as channel;
+
+// local imports
+use crate::input::InputStream;
+use crate::iox::{forward_lines, terminated, ChannelReader};
+
+// ---
+
+const MAX_DATAGRAM_SIZE: usize = 65536;
+
+// ---
+
+/// Network address to accept log streams on.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ListenAddress {
+    Tcp(String),
+    Udp(String),
+    Unix(PathBuf),
+}
+
+impl ListenAddress {
+    /// Binds to the address and returns a stream with lines received from all connections.
+    ///
+    /// Connections are accepted in background threads and complete lines of concurrent connections are interleaved in the order of arrival.
+    ///
+    /// The file of a unix socket is removed once the stream is dropped, a file left by a previous run that was not
+    /// removed, e.g. because the process was killed, is replaced.
+    pub fn listen(&self) -> io::Result<InputStream> {
+        let (tx, rx) = channel::bounded(64);
+        match self {
+            Self::Tcp(address) => return Ok(serve_tcp(TcpListener::bind(address)?)),
+            Self::Udp(address) => {
+                let socket = UdpSocket::bind(address)?;
+                thread::spawn(move || {
+                    let mut buf = vec![0; MAX_DATAGRAM_SIZE];
+                    while let Ok(n) = socket.recv(&mut buf) {
+                        if n != 0 && tx.send(terminated(&buf[..n])).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            #[cfg(unix)]
+            Self::Unix(path) => {
+                let listener = bind_unix(path)?;
+                thread::spawn(move || {
+                    for connection in listener.incoming().flatten() {
+                        let tx = tx.clone();
+                        thread::spawn(move || forward_lines(connection, tx));
+                    }
+                });
+                return Ok(Box::new(SocketFileReader {
+                    inner: ChannelReader::new(rx),
+                    path: path.clone(),
+                }));
+            }
+            #[cfg(not(unix))]
+            Self::Unix(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "unix sockets are not supported on this platform",
+                ));
+            }
+        }
+        Ok(Box::new(ChannelReader::new(rx)))
+    }
+}
+
+/// Accepts connections of the bound listener in background threads, see [ListenAddress::listen].
+fn serve_tcp(listener: TcpListener) -> InputStream {
+    let (tx, rx) = channel::bounded(64);
+    thread::spawn(move || {
+        for connection in listener.incoming().flatten() {
+            let tx = tx.clone();
+            thread::spawn(move || forward_lines(connection, tx));
+        }
+    });
+    Box::new(ChannelReader::new(rx))
+}
+
+/// Binds a unix socket, replacing the socket file if nobody listens on it anymore.
+#[cfg(unix)]
+fn bind_unix(path: &std::path::Path) -> io::Result<std::os::unix::net::UnixListener> {
+    use std::fs;
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    match UnixListener::bind(path) {
+        Err(err) if err.kind() == io::ErrorKind::AddrInUse => {
+            let socket = fs::symlink_metadata(path).map_or(false, |meta| meta.file_type().is_socket());
+            if !socket || UnixStream::connect(path).is_ok() {
+                return Err(err);
+            }
+            fs::remove_file(path)?;
+            UnixListener::bind(path)
+        }
+        result => result,
+    }
+}
+
+// ---
+
+/// Lines received on a unix socket, the socket file is removed once they are no longer read.
+#[cfg(unix)]
+struct SocketFileReader {
+    inner: ChannelReader,
+    path: PathBuf,
+}
+
+#[cfg(unix)]
+impl io::Read for SocketFileReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        io::Read::read(&mut self.inner, buf)
+    }
+}
+
+#[cfg(unix)]
+impl Drop for SocketFileReader {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.path).ok();
+    }
+}
+
+// ---
+
+impl FromStr for ListenAddress {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once("://") {
+            Some(("tcp", address)) => Ok(Self::Tcp(address.into())),
+            Some(("udp", address)) => Ok(Self::Udp(address.into())),
+            Some(("unix", path)) => Ok(Self::Unix(path.into())),
+            _ => Err(format!(
+                "invalid listen address '{}', expected tcp://<host>:<port>, udp://<host>:<port> or unix://<path>",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ListenAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Tcp(address) => write!(f, "tcp://{}", address),
+            Self::Udp(address) => write!(f, "udp://{}", address),
+            Self::Unix(path) => write!(f, "unix://{}", path.display()),
+        }
+    }
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("tcp://0.0.0.0:5000".parse(), Ok(ListenAddress::Tcp("0.0.0.0:5000".into())));
+        assert_eq!("udp://[::1]:514".parse(), Ok(ListenAddress::Udp("[::1]:514".into())));
+        assert_eq!("unix:///tmp/hl.sock".parse(), Ok(ListenAddress::Unix("/tmp/hl.sock".into())));
+        assert!("localhost:5000".parse::<ListenAddress>().is_err());
+    }
+
+    #[test]
+    fn test_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let stream = serve_tcp(listener);
+        let mut connection = TcpStream::connect(address).unwrap();
+        connection.write_all(b"{\"msg\":\"a\"}\n{\"msg\":\"b\"}").unwrap();
+        drop(connection);
+        let (_, data) = read_at_least(stream, 24);
+        assert_eq!(data, b"{\"msg\":\"a\"}\n{\"msg\":\"b\"}\n");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_unix() {
+        let path = std::env::temp_dir().join(format!("hl-listener-test-{}.sock", std::process::id()));
+        fs::remove_file(&path).ok();
+        // a socket file left by a previous run is replaced
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        let stream = ListenAddress::Unix(path.clone()).listen().unwrap();
+        let mut connection = std::os::unix::net::UnixStream::connect(&path).unwrap();
+        connection.write_all(b"{\"msg\":\"a\"}\n").unwrap();
+        drop(connection);
+        let (stream, data) = read_at_least(stream, 12);
+        drop(stream);
+        let exists = path.exists();
+        fs::remove_file(&path).ok();
+        assert_eq!(data, b"{\"msg\":\"a\"}\n");
+        assert!(!exists);
+    }
+
+    /// Reads at least the given number of bytes from the stream, failing if they are not received in time.
+    fn read_at_least(mut stream: InputStream, n: usize) -> (InputStream, Vec<u8>) {
+        let (tx, rx) = std::sync::mpsc::channel();
+        thread::spawn(move || {
+            let mut buf = vec![0; 64];
+            let mut data = Vec::new();
+            while data.len() < n {
+                match stream.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(k) => data.extend_from_slice(&buf[..k]),
+                }
+            }
+            tx.send((stream, data)).ok();
+        });
+        rx.recv_timeout(std::time::Duration::from_secs(10))
+            .expect("data is not received in time")
+    }
+}
@@ -0,0 +1,244 @@
+// local imports
+use crate::fmtx::Push;
+
+// ---
+
+/// Conventions of a locale used for rendering of month and weekday names and numbers, see --locale option.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Locale {
+    pub name: &'static str,
+    pub months_short: [&'static str; 12],
+    pub months_long: [&'static str; 12],
+    /// Short weekday names starting from Monday.
+    pub weekdays_short: [&'static str; 7],
+    /// Long weekday names starting from Monday.
+    pub weekdays_long: [&'static str; 7],
+    pub decimal_separator: char,
+    pub group_separator: char,
+}
+
+impl Locale {
+    /// Finds a built-in locale by name like `de-DE`, `de_DE.UTF-8` or `de`, falling back to the language part of the name.
+    pub fn find(name: &str) -> Option<&'static Locale> {
+        let name = name.split(['.', '@']).next().unwrap_or_default().replace('_', "-");
+        let language = name.split('-').next().unwrap_or_default().to_ascii_lowercase();
+        LOCALES
+            .iter()
+            .find(|locale| locale.name.eq_ignore_ascii_case(&name))
+            .or_else(|| {
+                LOCALES
+                    .iter()
+                    .find(|locale| locale.name.split('-').next() == Some(language.as_str()))
+            })
+    }
+
+    /// Returns names of all built-in locales.
+    pub fn known() -> impl Iterator<Item = &'static str> {
+        LOCALES.iter().map(|locale| locale.name)
+    }
+
+    /// Writes a JSON number with the locale decimal separator, grouping digits of integer parts having more than 4 digits.
+    /// Numbers having 4 digits or less in the integer part, like years or ports, are kept ungrouped.
+    pub fn format_number<B: Push<u8>>(&self, buf: &mut B, value: &str) {
+        let (sign, value) = match value.strip_prefix('-') {
+            Some(value) => ("-", value),
+            None => ("", value),
+        };
+        let end = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+        let (integer, rest) = value.split_at(end);
+
+        buf.extend_from_slice(sign.as_bytes());
+        if integer.len() > 4 {
+            let mut tmp = [0; 4];
+            let separator = self.group_separator.encode_utf8(&mut tmp).as_bytes();
+            for (i, digit) in integer.bytes().enumerate() {
+                if i != 0 && (integer.len() - i) % 3 == 0 {
+                    buf.extend_from_slice(separator);
+                }
+                buf.push(digit);
+            }
+        } else {
+            buf.extend_from_slice(integer.as_bytes());
+        }
+        match rest.strip_prefix('.') {
+            Some(rest) => {
+                let mut tmp = [0; 4];
+                buf.extend_from_slice(self.decimal_separator.encode_utf8(&mut tmp).as_bytes());
+                buf.extend_from_slice(rest.as_bytes());
+            }
+            None => buf.extend_from_slice(rest.as_bytes()),
+        }
+    }
+}
+
+/// Returns true if numbers in the field with the given key are measurements like sizes, counts or durations,
+/// which are rendered with locale separators, and false for fields named like identifiers or codes,
+/// like `pid`, `user_id`, `port` or `status`, which numbers are rendered as is.
+pub fn is_measurement(key: &str) -> bool {
+    let name = key.rsplit(['.', '_', '-']).next().unwrap_or(key).as_bytes();
+    let id = name.len() >= 2 && name[name.len() - 2..].eq_ignore_ascii_case(b"id");
+    !id && !IDENTIFIERS.iter().any(|x| x.as_bytes().eq_ignore_ascii_case(name))
+}
+
+/// Last parts of field names, besides ones ending with `id`, holding numbers that identify something rather than measure it.
+const IDENTIFIERS: &[&str] = &[
+    "code", "errno", "line", "lineno", "no", "num", "number", "port", "seq", "serial", "status", "version", "year",
+];
+
+// ---
+
+static LOCALES: [Locale; 4] = [
+    Locale {
+        name: "en-US",
+        months_short: [
+            "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+        ],
+        months_long: [
+            "January",
+            "February",
+            "March",
+            "April",
+            "May",
+            "June",
+            "July",
+            "August",
+            "September",
+            "October",
+            "November",
+            "December",
+        ],
+        weekdays_short: ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"],
+        weekdays_long: [
+            "Monday",
+            "Tuesday",
+            "Wednesday",
+            "Thursday",
+            "Friday",
+            "Saturday",
+            "Sunday",
+        ],
+        decimal_separator: '.',
+        group_separator: ',',
+    },
+    Locale {
+        name: "de-DE",
+        months_short: [
+            "Jan", "Feb", "Mär", "Apr", "Mai", "Jun", "Jul", "Aug", "Sep", "Okt", "Nov", "Dez",
+        ],
+        months_long: [
+            "Januar",
+            "Februar",
+            "März",
+            "April",
+            "Mai",
+            "Juni",
+            "Juli",
+            "August",
+            "September",
+            "Oktober",
+            "November",
+            "Dezember",
+        ],
+        weekdays_short: ["Mo", "Di", "Mi", "Do", "Fr", "Sa", "So"],
+        weekdays_long: [
+            "Montag",
+            "Dienstag",
+            "Mittwoch",
+            "Donnerstag",
+            "Freitag",
+            "Samstag",
+            "Sonntag",
+        ],
+        decimal_separator: ',',
+        group_separator: '.',
+    },
+    Locale {
+        name: "fr-FR",
+        months_short: [
+            "janv.", "févr.", "mars", "avril", "mai", "juin", "juil.", "août", "sept.", "oct.", "nov.", "déc.",
+        ],
+        months_long: [
+            "janvier",
+            "février",
+            "mars",
+            "avril",
+            "mai",
+            "juin",
+            "juillet",
+            "août",
+            "septembre",
+            "octobre",
+            "novembre",
+            "décembre",
+        ],
+        weekdays_short: ["lun.", "mar.", "mer.", "jeu.", "ven.", "sam.", "dim."],
+        weekdays_long: ["lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi", "dimanche"],
+        decimal_separator: ',',
+        group_separator: '\u{202f}',
+    },
+    Locale {
+        name: "es-ES",
+        months_short: [
+            "ene", "feb", "mar", "abr", "may", "jun", "jul", "ago", "sep", "oct", "nov", "dic",
+        ],
+        months_long: [
+            "enero",
+            "febrero",
+            "marzo",
+            "abril",
+            "mayo",
+            "junio",
+            "julio",
+            "agosto",
+            "septiembre",
+            "octubre",
+            "noviembre",
+            "diciembre",
+        ],
+        weekdays_short: ["lun", "mar", "mié", "jue", "vie", "sáb", "dom"],
+        weekdays_long: ["lunes", "martes", "miércoles", "jueves", "viernes", "sábado", "domingo"],
+        decimal_separator: ',',
+        group_separator: '.',
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find() {
+        assert_eq!(Locale::find("de-DE").map(|l| l.name), Some("de-DE"));
+        assert_eq!(Locale::find("de_DE.UTF-8").map(|l| l.name), Some("de-DE"));
+        assert_eq!(Locale::find("fr").map(|l| l.name), Some("fr-FR"));
+        assert_eq!(Locale::find("en-GB").map(|l| l.name), Some("en-US"));
+        assert_eq!(Locale::find("xx-YY"), None);
+    }
+
+    #[test]
+    fn test_format_number() {
+        let format = |locale: &str, value: &str| {
+            let mut buf = Vec::new();
+            Locale::find(locale).unwrap().format_number(&mut buf, value);
+            String::from_utf8(buf).unwrap()
+        };
+        assert_eq!(format("de", "1234567"), "1.234.567");
+        assert_eq!(format("de", "-12345.678"), "-12.345,678");
+        assert_eq!(format("de", "2024"), "2024");
+        assert_eq!(format("de", "1.5e10"), "1,5e10");
+        assert_eq!(format("en", "123456"), "123,456");
+        assert_eq!(format("fr", "12345"), "12\u{202f}345");
+    }
+
+    #[test]
+    fn test_is_measurement() {
+        assert!(is_measurement("bytes"));
+        assert!(is_measurement("response.duration"));
+        assert!(is_measurement("valid_count"));
+        assert!(!is_measurement("pid"));
+        assert!(!is_measurement("user_id"));
+        assert!(!is_measurement("requestId"));
+        assert!(!is_measurement("http.status"));
+        assert!(!is_measurement("PORT"));
+    }
+}
@@ -0,0 +1,278 @@
+// third-party imports
+use chrono::{DateTime, FixedOffset};
+use serde_json as json;
+
+// local imports
+use crate::binary;
+use crate::docker;
+use crate::error::{Error, Result};
+use crate::settings::InputFormat;
+use crate::syslog;
+use crate::text::TextFormat;
+
+// ---
+
+/// Number of the first non-empty lines of an input checked to detect plain text, see [is_unstructured].
+const UNSTRUCTURED_SAMPLE_LINES: usize = 16;
+
+// ---
+
+/// Returns the line as a JSON record according to the input format, converting it from logfmt, syslog, Docker json-file
+/// or plain text format to the buffer if needed, with plain text converted by the default settings.
+/// Lines that cannot be converted are returned as is.
+pub fn normalize<'a>(format: InputFormat, line: &'a [u8], buf: &'a mut Vec<u8>) -> &'a [u8] {
+    normalize_with(format, TextFormat::builtin(), syslog::reference_time(), line, buf)
+}
+
+/// Same as [normalize], with plain text converted by the given text format and syslog timestamps without a year
+/// resolved against the given reference time, see [syslog::reference_time].
+pub fn normalize_with<'a>(
+    format: InputFormat,
+    text: &TextFormat,
+    now: DateTime<FixedOffset>,
+    line: &'a [u8],
+    buf: &'a mut Vec<u8>,
+) -> &'a [u8] {
+    let converted = match format {
+        InputFormat::Json => false,
+        InputFormat::Logfmt => to_json(line, buf),
+        InputFormat::DockerJson => docker::unwrap(line, buf),
+        InputFormat::Syslog => syslog::to_json(line, buf, now),
+        InputFormat::Text => text.to_json(line, buf),
+        InputFormat::Auto if docker::is_wrapped(line) => docker::unwrap(line, buf),
+        InputFormat::Auto => {
+            line.iter().find(|b| !b.is_ascii_whitespace()) != Some(&b'{')
+                && (to_json(line, buf) || syslog::to_json_strict(line, buf, now))
+        }
+    };
+    if converted {
+        &buf[..]
+    } else {
+        line
+    }
+}
+
+/// Normalizes all newline-separated lines of the data, see [normalize_with].
+pub fn normalize_all(format: InputFormat, text: &TextFormat, data: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(data.len());
+    let mut buf = Vec::new();
+    let now = syslog::reference_time();
+    for line in data.split(|c| *c == b'\n') {
+        result.extend_from_slice(normalize_with(format, text, now, line, &mut buf));
+        result.push(b'\n');
+    }
+    result
+}
+
+/// Returns true if the sample from the beginning of an input has lines and none of its first lines
+/// is a record in any of the formats detected in auto format, so the input is considered to be plain text.
+pub fn is_unstructured(sample: &[u8]) -> bool {
+    if binary::is_binary(sample) {
+        return false;
+    }
+    let mut buf = Vec::new();
+    let now = syslog::reference_time();
+    let mut lines = sample
+        .split(|c| *c == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+        .filter(|line| !line.iter().all(u8::is_ascii_whitespace))
+        .take(UNSTRUCTURED_SAMPLE_LINES)
+        .peekable();
+    lines.peek().is_some()
+        && lines.all(|line| {
+            let data = normalize_with(InputFormat::Auto, TextFormat::builtin(), now, line, &mut buf);
+            json::from_slice::<&json::value::RawValue>(data).map_or(true, |value| !value.get().starts_with('{'))
+        })
+}
+
+/// Parses a line in logfmt format to a JSON object, entry point for fuzzing, see [to_json].
+pub fn parse(line: &[u8]) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    if to_json(line, &mut buf) {
+        Ok(buf)
+    } else {
+        Err(Error::UnrecognizedLogfmt(String::from_utf8_lossy(line).into()))
+    }
+}
+
+/// Converts a line in logfmt format like `ts=... level=info msg="..."` to a JSON object written to the buffer.
+///
+/// Quoted values become strings, unquoted values that are valid JSON numbers, booleans or null keep their type,
+/// and keys without a value become `true`.
+/// Returns false if the line does not start with a key=value pair or cannot be parsed, leaving the buffer empty.
+pub fn to_json(line: &[u8], buf: &mut Vec<u8>) -> bool {
+    buf.clear();
+    buf.push(b'{');
+    let mut pos = 0;
+    let mut first = true;
+    loop {
+        while pos < line.len() && line[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        if pos == line.len() {
+            break;
+        }
+
+        let start = pos;
+        while pos < line.len() && !line[pos].is_ascii_whitespace() && line[pos] != b'=' && line[pos] != b'"' {
+            pos += 1;
+        }
+        let key = match std::str::from_utf8(&line[start..pos]) {
+            Ok(key) if !key.is_empty() => key,
+            _ => return fail(buf),
+        };
+        let assigned = pos < line.len() && line[pos] == b'=';
+        if first && !assigned {
+            return fail(buf);
+        }
+        if !first {
+            buf.push(b',');
+        }
+        first = false;
+        if json::to_writer(&mut *buf, key).is_err() {
+            return fail(buf);
+        }
+        buf.push(b':');
+
+        if !assigned {
+            if pos < line.len() && !line[pos].is_ascii_whitespace() {
+                return fail(buf);
+            }
+            buf.extend_from_slice(b"true");
+            continue;
+        }
+        pos += 1;
+
+        if pos < line.len() && line[pos] == b'"' {
+            let start = pos;
+            pos += 1;
+            loop {
+                match line.get(pos) {
+                    None => return fail(buf),
+                    Some(b'\\') => pos += 2,
+                    Some(b'"') => break,
+                    Some(_) => pos += 1,
+                }
+            }
+            pos += 1;
+            if pos < line.len() && !line[pos].is_ascii_whitespace() {
+                return fail(buf);
+            }
+            let value = match json::from_slice::<String>(&line[start..pos]) {
+                Ok(value) => value,
+                Err(_) => String::from_utf8_lossy(&line[start + 1..pos - 1]).into_owned(),
+            };
+            if json::to_writer(&mut *buf, &value).is_err() {
+                return fail(buf);
+            }
+        } else {
+            let start = pos;
+            while pos < line.len() && !line[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            let value = &line[start..pos];
+            if is_literal(value) {
+                buf.extend_from_slice(value);
+            } else if json::to_writer(&mut *buf, &String::from_utf8_lossy(value)).is_err() {
+                return fail(buf);
+            }
+        }
+    }
+    if first {
+        return fail(buf);
+    }
+    buf.push(b'}');
+    true
+}
+
+// ---
+
+fn fail(buf: &mut Vec<u8>) -> bool {
+    buf.clear();
+    false
+}
+
+fn is_literal(value: &[u8]) -> bool {
+    match value {
+        b"true" | b"false" | b"null" => true,
+        _ => {
+            value.first().map_or(false, |b| *b == b'-' || b.is_ascii_digit())
+                && json::from_slice::<json::Number>(value).is_ok()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(line: &str) -> Option<String> {
+        let mut buf = Vec::new();
+        if to_json(line.as_bytes(), &mut buf) {
+            Some(String::from_utf8(buf).unwrap())
+        } else {
+            assert!(buf.is_empty());
+            None
+        }
+    }
+
+    #[test]
+    fn test_to_json() {
+        assert_eq!(
+            convert(r#"ts=2023-12-07T20:07:05Z level=info msg="hello \"world\"" n=42 ok=true x=-1.5e3"#).as_deref(),
+            Some(r#"{"ts":"2023-12-07T20:07:05Z","level":"info","msg":"hello \"world\"","n":42,"ok":true,"x":-1.5e3}"#)
+        );
+        assert_eq!(
+            convert("a= b c=0123").as_deref(),
+            Some(r#"{"a":"","b":true,"c":"0123"}"#)
+        );
+        assert_eq!(convert("starting server"), None);
+        assert_eq!(convert(r#"a=1 msg="unterminated"#), None);
+        assert_eq!(convert(r#"{"a":1}"#), None);
+        assert_eq!(convert(""), None);
+        assert_eq!(convert(r#"a="\"#), None);
+        assert_eq!(convert(r#"a="\""#), None);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse(b"a=1 b").unwrap(), br#"{"a":1,"b":true}"#);
+        assert!(matches!(parse(b"a=\"\xff"), Err(Error::UnrecognizedLogfmt(_))));
+    }
+
+    #[test]
+    fn test_normalize() {
+        let mut buf = Vec::new();
+        assert_eq!(normalize(InputFormat::Auto, b"a=1", &mut buf), br#"{"a":1}"#);
+        assert_eq!(
+            normalize(InputFormat::Auto, br#"{"a":"b=1"}"#, &mut buf),
+            br#"{"a":"b=1"}"#
+        );
+        assert_eq!(normalize(InputFormat::Json, b"a=1", &mut buf), b"a=1");
+        assert_eq!(normalize(InputFormat::Auto, b"plain text", &mut buf), b"plain text");
+        assert_eq!(
+            normalize(InputFormat::Auto, b"<11>1 2003-10-11T22:14:15Z host app - - - failed", &mut buf),
+            br#"{"ts":"2003-10-11T22:14:15Z","level":"error","host":"host","logger":"app","msg":"failed"}"#
+        );
+        assert_eq!(
+            normalize(InputFormat::Auto, br#"{"log":"a=1\n","stream":"stdout","time":"t"}"#, &mut buf),
+            br#"{"a":1,"_container_time":"t","_container_stream":"stdout"}"#
+        );
+        assert_eq!(
+            normalize(InputFormat::Json, br#"{"log":"a=1\n","stream":"stdout"}"#, &mut buf),
+            br#"{"log":"a=1\n","stream":"stdout"}"#
+        );
+        assert_eq!(
+            normalize(InputFormat::Text, b"2024-01-02 03:04:05 WARN low disk", &mut buf),
+            br#"{"ts":"2024-01-02 03:04:05","level":"warning","msg":"low disk"}"#
+        );
+    }
+
+    #[test]
+    fn test_is_unstructured() {
+        assert!(is_unstructured(b"2024-01-02 03:04:05 INFO started\n\nfailed\n"));
+        assert!(!is_unstructured(b"started\n{\"msg\":\"ok\"}\n"));
+        assert!(!is_unstructured(b"a=1 b=2\n"));
+        assert!(!is_unstructured(b"\n\n"));
+    }
+}
@@ -1,37 +1,54 @@
 // std imports
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::default::Default;
-use std::io::{stdin, stdout, IsTerminal};
-use std::path::PathBuf;
+use std::io::{stdin, stdout, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 use std::process;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 // third-party imports
-use chrono::Utc;
-use clap::{ArgAction, CommandFactory, Parser, ValueEnum};
+use chrono::{Local, Utc};
+use clap::{ArgAction, CommandFactory, Parser, Subcommand, ValueEnum};
 use itertools::Itertools;
 use nu_ansi_term::Color;
 use once_cell::sync::Lazy;
 use platform_dirs::AppDirs;
+use terminal_size::{terminal_size, Width};
 use std::num::NonZeroUsize;
 
 // local imports
+use hl::checkpoint::Checkpoint;
+use hl::clipboard::CopyFormat;
+use hl::compression::{Compression, Decompression, Encoder};
 use hl::datefmt::LinuxDateFormat;
+use hl::encoding::Encoding;
+use hl::diagnostics::{self, Verbosity};
 use hl::error::*;
-use hl::input::InputReference;
+use hl::generate::{GeneratedFormat, Generator, LevelWeights};
+use hl::grouping::GroupOrder;
+use hl::input::{InputHolder, InputReference};
 use hl::level::{LevelValueParser, RelaxedLevel};
+use hl::locale::Locale;
 use hl::output::{OutputStream, Pager};
-use hl::settings::Settings;
+use hl::pattern::FilePattern;
+use hl::progress::Progress;
+use hl::query::Query;
+use hl::schema::SchemaValidator;
+use hl::settings::{DuplicateKeys, InputFormat, OutputFormat, Settings};
 use hl::signal::SignalHandler;
+use hl::terminal::{Capabilities, ColorDepth};
 use hl::theme::{Theme, ThemeOrigin};
-use hl::timeparse::parse_time;
+use hl::timeparse::{parse_absolute_time, parse_time};
 use hl::timezone::Tz;
-use hl::{IncludeExcludeKeyFilter, KeyMatchOptions};
+use hl::{ColorBy, ColorTarget, FieldSelection, IncludeExcludeKeyFilter, KeyMatchOptions, Lanes};
 
 // ---
 
 const APP_NAME: &str = "hl";
+const PROJECT_CACHE_DIR: &str = ".hl-cache";
+const DEFAULT_SPLIT_SCREEN_WIDTH: usize = 160;
 
 // ---
 
@@ -67,6 +84,51 @@ struct Opt {
     )]
     theme: String,
     //
+    /// Show compact level and source icons instead of textual level tags.
+    #[arg(long, env = "HL_ICONS", overrides_with = "icons")]
+    icons: bool,
+    //
+    /// Highlight timestamps by the gap from the previous message, with thresholds configured in the configuration file, messages are processed by a single thread then.
+    #[arg(long, env = "HL_TIME_GAPS", conflicts_with_all = ["sort", "follow", "replay", "window", "interactive", "split_screen"])]
+    time_gaps: bool,
+    //
+    /// Mark messages with a colored lane by values of the field, e.g. thread, goroutine or pid, so interleaved concurrent execution is easy to follow.
+    #[arg(long, env = "HL_LANES_BY", value_name = "FIELD")]
+    lanes_by: Option<String>,
+    //
+    /// Number of columns each next lane is shifted by.
+    #[arg(long, env = "HL_LANE_INDENT", default_value_t = 0, requires = "lanes_by")]
+    lane_indent: usize,
+    //
+    /// Color records by values of the field, e.g. service, each distinct value gets a stable color from the theme palette.
+    #[arg(long, env = "HL_COLOR_BY", value_name = "FIELD")]
+    color_by: Option<String>,
+    //
+    /// Part of the record colored by values of the --color-by field.
+    #[arg(long, default_value = "gutter", env = "HL_COLOR_BY_TARGET", requires = "color_by")]
+    #[arg(value_enum)]
+    color_by_target: ColorTarget,
+    //
+    /// Terminal width in columns below which the compact layout is used automatically, 0 to disable, disabled by default.
+    #[arg(long, env = "HL_COMPACT_WIDTH", overrides_with = "compact_width")]
+    compact_width: Option<usize>,
+    //
+    /// Configuration profile with transformations applied to messages before filtering and display.
+    #[arg(long, env = "HL_PROFILE", overrides_with = "profile")]
+    profile: Option<String>,
+    //
+    /// Static field added to every message in form of <key>=<value>, may be specified multiple times or per input using <path>?tag=<key>=<value> syntax.
+    #[arg(long, number_of_values = 1, value_parser = parse_tag)]
+    tag: Vec<(String, String)>,
+    //
+    /// Expand messages having an array in the field to one message per array element, each inheriting all other fields of the message.
+    #[arg(long, env = "HL_EXPLODE", value_name = "FIELD", overrides_with = "explode")]
+    explode: Option<String>,
+    //
+    /// Stop processing after the given time budget, e.g. 30s, and exit with code 124 if output was truncated.
+    #[arg(long, env = "HL_MAX_RUNTIME", value_parser = humantime::parse_duration, overrides_with = "max_runtime")]
+    max_runtime: Option<Duration>,
+    //
     /// Disable unescaping and prettifying of field values.
     #[arg(short, long)]
     raw_fields: bool,
@@ -88,23 +150,103 @@ struct Opt {
     #[arg(long, default_value = "64 MiB", env="HL_MAX_MESSAGE_SIZE",  value_parser = parse_non_zero_size, overrides_with="max_message_size")]
     max_message_size: NonZeroUsize,
     //
-    /// Number of processing threads.
-    #[arg(long, short = 'C', env = "HL_CONCURRENCY", overrides_with = "concurrency")]
+    /// Maximum memory used to keep compressed data of each non-seekable input like stdin for sorting, the rest is stored in a temporary file.
+    #[arg(long, default_value = "256 MiB", env="HL_MAX_STREAM_BUFFER_SIZE",  value_parser = parse_non_zero_size, overrides_with="max_stream_buffer_size")]
+    max_stream_buffer_size: NonZeroUsize,
+    //
+    /// Read blocks of local files through memory mapping when sorting, the files must not be truncated meanwhile.
+    #[arg(long, env = "HL_MMAP")]
+    mmap: bool,
+    //
+    /// Report inputs that cannot be opened, read or indexed to stderr and continue with the rest of them, failing at exit if there were any.
+    #[arg(long, env = "HL_SKIP_ERRORS")]
+    skip_errors: bool,
+    //
+    /// Report progress of reading input files to stderr every few seconds, showing byte rate, percentage of their total size and estimated time left.
+    #[arg(long, env = "HL_PROGRESS")]
+    progress: bool,
+    //
+    /// Number of processing threads used for indexing, parsing, formatting and decompression, detected automatically if not specified or zero.
+    #[arg(long, short = 'C', visible_alias = "threads", env = "HL_CONCURRENCY", overrides_with = "concurrency")]
     concurrency: Option<usize>,
     //
-    /// Filtering by field values in one of forms [<key>=<value>, <key>~=<value>, <key>~~=<value>, <key>!=<value>, <key>!~=<value>, <key>!~~=<value>] where ~ denotes substring match and ~~ denotes regular expression match.
+    /// Maximum number of input files indexed at the same time, the number of processing threads if not specified.
+    #[arg(long, value_name = "N", env = "HL_INDEX_CONCURRENCY", overrides_with = "index_concurrency")]
+    index_concurrency: Option<NonZeroUsize>,
+    //
+    /// Run with lowered CPU and I/O priority and a single processing thread unless --concurrency is specified, to avoid competing with other services.
+    #[arg(long, env = "HL_BACKGROUND")]
+    background: bool,
+    //
+    /// Filtering by field values in one of forms [<key>=<value>, <key>~=<value>, <key>~~=<value>, <key>!=<value>, <key>!~=<value>, <key>!~~=<value>] where ~ denotes substring match and ~~ denotes regular expression match, or <key> in <network>[,<network>...] and <key> !in <network>[,<network>...] to match IP addresses against CIDR networks, where a value prefixed with glob: or prefix: is matched as a wildcard pattern or a prefix, with ~= a wildcard pattern may match any part of the value.
     #[arg(short, long, number_of_values = 1)]
     filter: Vec<String>,
     //
-    /// Hide or unhide fields with the specified keys, prefix with ! to unhide, specify !* to unhide all.
-    #[arg(long, short = 'h', number_of_values = 1)]
+    /// Filtering by a query expression like 'level >= warn and (status >= 500 or msg ~ timeout) and duration > 250ms', combining conditions with and, or, not and parentheses, see README for the syntax, multiple queries must all match.
+    #[arg(long, number_of_values = 1)]
+    query: Vec<String>,
+    //
+    /// Load filter expressions from a file, one per line, with support for comments and #include directives.
+    #[arg(long, number_of_values = 1, env = "HL_QUERY_FILE")]
+    query_file: Vec<PathBuf>,
+    //
+    /// Validate messages against a JSON Schema, showing violations after each message that does not conform to it.
+    #[arg(long, env = "HL_VALIDATE", value_name = "SCHEMA")]
+    validate: Option<PathBuf>,
+    //
+    /// Ignore case of letters when matching field values with filters, using Unicode case folding.
+    #[arg(long, short = 'i', env = "HL_IGNORE_CASE")]
+    ignore_case: bool,
+    //
+    /// Ignore diacritical marks when matching field values with filters, so that e.g. 'café' matches 'cafe'.
+    #[arg(long, env = "HL_FOLD_DIACRITICS")]
+    fold_diacritics: bool,
+    //
+    /// Compare values of exact match filters only with values of the same type, failing on values of other types instead of not matching them.
+    #[arg(long, env = "HL_STRICT_TYPES")]
+    strict_types: bool,
+    //
+    /// Hide or unhide fields with the specified keys or wildcard patterns like 'k8s.*', prefix with ! to unhide, specify !* to unhide all.
+    #[arg(long, short = 'h', number_of_values = 1, visible_alias = "hide-fields")]
     hide: Vec<String>,
     //
+    /// Show only top-level fields matching the specified wildcard patterns in the given order, separated by commas, e.g. trace_id,http.*.
+    #[arg(long, env = "HL_OUTPUT_FIELDS", value_delimiter = ',')]
+    output_fields: Vec<String>,
+    //
+    /// Remove the specified prefixes from keys of top-level fields when showing them, separated by commas, e.g. kubernetes.,labels., filters still use full keys.
+    #[arg(long, env = "HL_STRIP_PREFIX", value_delimiter = ',')]
+    strip_prefix: Vec<String>,
+    //
     /// Filtering by level.
-    #[arg(short, long, env = "HL_LEVEL", overrides_with="level", ignore_case=true, value_parser = LevelValueParser)]
+    #[arg(short, long, env = "HL_LEVEL", overrides_with="level", ignore_case=true, value_parser = LevelValueParser::new(CONFIG.level_aliases.clone()))]
     #[arg(value_enum)]
     level: Option<RelaxedLevel>,
     //
+    /// Filtering by level of specific loggers in form <logger>=<level>[,<logger>=<level>...], e.g. 'app.db.*=debug,app.*=info', where each threshold applies to the logger and its descendants, the most specific one takes precedence over --level option, and * denotes any logger.
+    #[arg(long, number_of_values = 1, env = "HL_LOGGER")]
+    logger: Vec<String>,
+    //
+    /// Show the given number of records preceding each matching record, like grep does.
+    #[arg(long, short = 'B', value_name = "N", overrides_with = "before", conflicts_with = "follow")]
+    before: Option<usize>,
+    //
+    /// Show the given number of records following each matching record, like grep does.
+    #[arg(long, short = 'A', value_name = "N", overrides_with = "after", conflicts_with = "follow")]
+    after: Option<usize>,
+    //
+    /// Show the given number of records around each matching record, same as both --before and --after, which take precedence.
+    #[arg(long, value_name = "N", overrides_with = "context", conflicts_with = "follow")]
+    context: Option<usize>,
+    //
+    /// Show only the given number of first matching records.
+    #[arg(long, value_name = "N", overrides_with = "head", conflicts_with_all = ["tail", "before", "after", "context", "follow", "split_screen", "copy_matches"])]
+    head: Option<usize>,
+    //
+    /// Show only the given number of last matching records.
+    #[arg(long, value_name = "N", overrides_with = "tail", conflicts_with_all = ["before", "after", "context", "follow", "split_screen", "copy_matches"])]
+    tail: Option<usize>,
+    //
     /// Filtering by timestamp >= the value (--time-zone and --local options are honored).
     #[arg(long, allow_hyphen_values = true)]
     since: Option<String>,
@@ -125,16 +267,32 @@ struct Opt {
     //
     /// Time zone name, see column "TZ identifier" at https://en.wikipedia.org/wiki/List_of_tz_database_time_zones.
     #[arg(long, short = 'Z', env="HL_TIME_ZONE", default_value = &CONFIG.time_zone.name(), overrides_with="time_zone")]
-    time_zone: chrono_tz::Tz,
+    time_zone: String,
     //
     /// Use local time zone, overrides --time-zone option.
     #[arg(long, short = 'L')]
     local: bool,
     //
-    /// Files to process
+    /// Render month and weekday names and numbers using conventions of the locale, e.g. de-DE.
+    #[arg(long, env = "HL_LOCALE", overrides_with = "locale")]
+    locale: Option<String>,
+    //
+    /// Produce stable output for golden-file tests: use UTC time zone, sort fields by key, accept only absolute times in --since and --until options and disable automatic colors and paging.
+    #[arg(long, env = "HL_DETERMINISTIC")]
+    deterministic: bool,
+    //
+    /// Files to process, directories and wildcard patterns such as 'logs/*.log' are expanded to matching files, and @<name> is replaced with inputs of the workspace with the given name in the configuration file
     #[arg(name = "FILE")]
     files: Vec<PathBuf>,
     //
+    /// Accept log streams on the address in form of tcp://<host>:<port>, udp://<host>:<port> or unix://<path> and process them like standard input, may be specified multiple times.
+    #[arg(long, number_of_values = 1, env = "HL_LISTEN")]
+    listen: Vec<hl::listener::ListenAddress>,
+    //
+    /// Run the command and process its standard output and standard error like standard input, may be specified multiple times, the same as passing cmd://<command> as a file.
+    #[arg(long, number_of_values = 1, value_name = "COMMAND")]
+    exec: Vec<String>,
+    //
     /// Hide empty fields, applies for null, string, object and array fields only.
     #[arg(
         long,
@@ -152,12 +310,43 @@ struct Opt {
         overrides_with = "show_empty_fields"
     )]
     show_empty_fields: bool,
+    //
+    /// Skip lines with binary content instead of showing a short hex preview of them.
+    #[arg(long, env = "HL_SKIP_BINARY")]
+    skip_binary: bool,
+    //
+    /// Handling of duplicate keys in a record, overrides the configuration file setting.
+    #[arg(long, env = "HL_DUPLICATE_KEYS", value_enum)]
+    duplicate_keys: Option<DuplicateKeys>,
 
     /// Show input number and/or input filename before each message.
     #[arg(long, default_value = "auto", overrides_with = "input_info")]
     #[arg(value_enum)]
     input_info: InputInfoOption,
     //
+    /// Show the input filename and the byte offset in it before each message and each line that is not a message, marking each input with its own color.
+    #[arg(long, env = "HL_SHOW_SOURCE", conflicts_with_all = ["follow", "interactive", "split_screen", "stats"])]
+    show_source: bool,
+    //
+    /// Decompression of inputs, detected automatically by the content by default.
+    #[arg(long, default_value = "auto", env = "HL_INPUT_COMPRESSION", overrides_with = "input_compression")]
+    #[arg(value_enum)]
+    input_compression: CompressionOption,
+    //
+    /// Character encoding of inputs converted to UTF-8, detected by a byte order mark or by zero bytes of UTF-16 text by default.
+    #[arg(long, default_value = "auto", env = "HL_INPUT_ENCODING", overrides_with = "input_encoding")]
+    #[arg(value_enum)]
+    input_encoding: Encoding,
+    //
+    /// Format of input lines, JSON with logfmt, syslog and Docker json-file lines detected automatically by default, or plain text for files without records.
+    #[arg(long, default_value = "auto", env = "HL_INPUT_FORMAT", overrides_with = "input_format")]
+    #[arg(value_enum)]
+    input_format: InputFormat,
+    //
+    /// Attach lines that are not messages, like stack traces, to the preceding message, rendering them indented under it.
+    #[arg(long, env = "HL_MULTILINE")]
+    multiline: bool,
+    //
     /// List available themes and exit.
     #[arg(long)]
     list_themes: bool,
@@ -170,24 +359,207 @@ struct Opt {
     #[arg(long, short = 'F')]
     follow: bool,
 
+    /// Browse messages in an interactive viewer with scrolling, search, toggling of levels and following of appended messages.
+    #[arg(long, conflicts_with_all = ["follow", "split_screen", "replay", "head", "tail", "before", "after", "context", "copy_matches"])]
+    interactive: bool,
+
+    /// Collapse consecutive messages that are identical except for timestamp into the first one with a (xN) suffix.
+    #[arg(long, env = "HL_DEDUP", conflicts_with_all = ["follow", "interactive", "split_screen", "before", "after", "context"])]
+    dedup: bool,
+
+    /// Compare only the level, the message and the fields with the given keys, separated by commas, to detect duplicates with --dedup option.
+    #[arg(long, env = "HL_DEDUP_FIELDS", value_delimiter = ',', requires = "dedup")]
+    dedup_fields: Vec<String>,
+
     /// Synchronization interval for live streaming mode enabled by --follow option.
     #[arg(long, default_value = "100")]
     sync_interval_ms: u64,
 
+    /// Write a line with the current time at the given interval, e.g. 30s, in follow mode if any messages were shown since the previous one.
+    #[arg(long, value_name = "INTERVAL", value_parser = humantime::parse_duration)]
+    heartbeat: Option<Duration>,
+
+    /// File keeping offsets input files are processed up to, so that the next run continues from where this one stopped, e.g. for periodic runs over growing files.
+    #[arg(long, value_name = "FILE", env = "HL_CHECKPOINT", conflicts_with_all = ["sort", "replay", "window", "interactive", "split_screen", "stats", "head", "tail"])]
+    checkpoint: Option<PathBuf>,
+
+    /// Number of most recently displayed messages kept in follow mode and dumped to timestamped files in --snapshot-dir on SIGUSR1, 0 to disable.
+    #[arg(long, default_value = "0", env = "HL_SNAPSHOT_SIZE", overrides_with = "snapshot_size")]
+    snapshot_size: usize,
+
+    /// Directory for snapshot files written in follow mode, see --snapshot-size option.
+    #[arg(long, default_value = ".", env = "HL_SNAPSHOT_DIR", overrides_with = "snapshot_dir")]
+    snapshot_dir: PathBuf,
+
+    /// Track values of the numeric field with the given key, showing their minimum, average, maximum and a sparkline per time bucket in the summary and in a status line in follow mode.
+    #[arg(long, env = "HL_TRACK", overrides_with = "track")]
+    track: Option<String>,
+
+    /// Duration of time buckets for --track option.
+    #[arg(long, default_value = "1m", env = "HL_TRACK_INTERVAL", value_parser = humantime::parse_duration, overrides_with = "track_interval")]
+    track_interval: Duration,
+
+    /// Show two inputs side by side in columns aligned by timestamp, messages without timestamps are not shown,
+    /// inputs are expected to be in chronological order.
+    #[arg(long)]
+    split_screen: bool,
+
+    /// Replay messages chronologically with delays between them derived from their timestamps, implies --sort.
+    #[arg(long)]
+    replay: bool,
+
+    /// Replay speed multiplier for --replay option, e.g. 4x.
+    #[arg(long, default_value = "1x", value_parser = parse_speed)]
+    speed: f64,
+
+    /// Split messages into time windows of the given duration, e.g. 1h, and show only the one selected by --page option, implies --sort.
+    #[arg(long, value_parser = humantime::parse_duration)]
+    window: Option<Duration>,
+
+    /// Number of the time window to show, starting from 1, see --window option.
+    #[arg(long, default_value = "1", requires = "window")]
+    page: NonZeroUsize,
+
     /// Output file.
     #[arg(long, short = 'o')]
     output: Option<String>,
 
+    /// Output format, chosen automatically based on the output sink and configuration by default.
+    #[arg(long, default_value = "auto", env = "HL_OUTPUT_FORMAT", overrides_with = "output_format")]
+    #[arg(value_enum)]
+    output_format: OutputFormatOption,
+
+    /// Compression of the output file, detected by its extension like .gz or .zst by default, requires --output.
+    #[arg(long, default_value = "auto", env = "HL_OUTPUT_COMPRESSION", overrides_with = "output_compression")]
+    #[arg(value_enum, requires = "output")]
+    output_compression: CompressionOption,
+
+    /// Compression level of the output file, from 0 or 1 to 9, or to 22 for zstd, 6 by default, or 3 for zstd.
+    #[arg(long, env = "HL_OUTPUT_COMPRESSION_LEVEL", value_name = "LEVEL", requires = "output")]
+    output_compression_level: Option<u32>,
+
+    /// Columns of records exported in json, logfmt or csv output format, separated by commas,
+    /// either field keys with nested keys separated by dots or predefined fields time, level, logger, message and caller.
+    #[arg(long, env = "HL_COLUMNS", value_delimiter = ',')]
+    columns: Vec<String>,
+
+    /// Copy matching messages to the clipboard when processing completes, using OSC 52 terminal sequence that works over SSH as well.
+    #[arg(long, env = "HL_COPY_MATCHES")]
+    copy_matches: bool,
+
+    /// Format of messages copied by --copy-matches option.
+    #[arg(long, default_value = "json", env = "HL_COPY_FORMAT", overrides_with = "copy_format")]
+    #[arg(value_enum)]
+    copy_format: CopyFormatOption,
+
     /// Dump index metadata and exit.
     #[arg(long)]
     dump_index: bool,
 
+    /// Directory for index cache, defaults to the nearest .hl-cache directory of the current directory or its parents if any, or to the platform-specific user cache directory.
+    #[arg(long, env = "HL_CACHE_DIR", overrides_with = "cache_dir")]
+    cache_dir: Option<PathBuf>,
+
+    /// Do not store indexes in the cache directory and build them in memory instead.
+    #[arg(long, env = "HL_NO_INDEX_CACHE")]
+    no_index_cache: bool,
+
+    /// Print summary of processed inputs, bytes, records and covered time range to stderr at exit.
+    #[arg(long, env = "HL_SUMMARY")]
+    summary: bool,
+
+    /// Print statistics of matching records instead of the records: numbers of records per level and per minute and covered time range.
+    #[arg(long, conflicts_with_all = ["follow", "interactive", "split_screen", "replay", "head", "tail", "before", "after", "context", "copy_matches"])]
+    stats: bool,
+
+    /// Also show the most frequent values of the field with the given key in statistics, nested keys are separated by dots.
+    #[arg(long, value_name = "FIELD", requires = "stats")]
+    stats_by: Option<String>,
+
+    /// Number of the most frequent values shown by --stats-by option.
+    #[arg(long, default_value = "10", value_name = "N")]
+    stats_top: usize,
+
+    /// Order of the values shown by --stats-by option, by-key orders numbers, durations and IP addresses naturally.
+    #[arg(long, default_value = "by-count", env = "HL_SORT_GROUPS", value_name = "ORDER")]
+    #[arg(value_enum)]
+    sort_groups: GroupOrder,
+
+    /// Print resolved inputs, their formats, compression and scanning methods, active filters and output, and exit,
+    /// only the beginning of input files is read to detect their format and compression.
+    #[arg(long)]
+    plan: bool,
+
+    /// Suppress warnings of the application itself.
+    #[arg(long, short = 'q', conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Show notices of the application itself, such as index rebuilds and reopened inputs, specify twice for debug messages.
+    #[arg(long, short = 'v', action = ArgAction::Count)]
+    verbose: u8,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+
     //
     /// Print help.
     #[arg(long, default_value_t = false, action = ArgAction::SetTrue)]
     help: bool,
 }
 
+#[derive(Subcommand)]
+enum Command {
+    /// Compare records of two log files and show added, removed and changed ones.
+    Diff {
+        /// Original file.
+        left: PathBuf,
+        /// Modified file.
+        right: PathBuf,
+        /// Field used to align records, records are aligned by their order if not specified.
+        #[arg(long, short = 'k')]
+        key: Option<String>,
+    },
+    /// Show invocations recorded to the history file when history.enabled is set in the configuration file, or rerun one of them.
+    History {
+        /// Number of the invocation to rerun in its working directory.
+        #[arg(long, value_name = "N")]
+        rerun: Option<usize>,
+        /// Show only the given number of the most recent invocations.
+        #[arg(long, short = 'n', value_name = "N")]
+        last: Option<usize>,
+    },
+    /// Write synthetic records for demos, theme previews and load tests, to the output file if specified.
+    Generate {
+        /// Format of generated records.
+        #[arg(long, default_value = "json")]
+        #[arg(value_enum)]
+        format: GeneratedFormat,
+        /// Number of records written per second, e.g. 100/s or 600/m, records are written as fast as possible if not specified.
+        #[arg(long, value_parser = parse_rate)]
+        rate: Option<f64>,
+        /// Relative weights of levels of generated records.
+        #[arg(long, default_value = "error:2,warning:5,info:70,debug:20,trace:3")]
+        levels: LevelWeights,
+        /// Number of records to generate, records are generated until interrupted if not specified.
+        #[arg(long, short = 'n', value_name = "N")]
+        count: Option<u64>,
+        /// Seed of the generator, the same seed produces the same records.
+        #[arg(long, default_value_t = 1)]
+        seed: u64,
+    },
+    /// Build indexes of files in advance and show usage of the index cache, or clean it up.
+    Index {
+        /// Files to index, indexes are built only if missing or outdated.
+        files: Vec<PathBuf>,
+        /// Build indexes of the files even if cached ones are up to date.
+        #[arg(long, requires = "files")]
+        rebuild: bool,
+        /// Remove cached indexes of files that no longer exist or have changed, and the least recently used files exceeding index-cache.max-size setting.
+        #[arg(long)]
+        prune: bool,
+    },
+}
+
 #[derive(ValueEnum, Debug, Clone, Copy)]
 enum ColorOption {
     Auto,
@@ -195,6 +567,21 @@ enum ColorOption {
     Never,
 }
 
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum OutputFormatOption {
+    Auto,
+    Text,
+    Json,
+    Logfmt,
+    Csv,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum CopyFormatOption {
+    Json,
+    Text,
+}
+
 #[derive(ValueEnum, Debug, Clone, Copy)]
 enum PagingOption {
     Auto,
@@ -211,16 +598,110 @@ enum InputInfoOption {
     Minimal,
 }
 
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum CompressionOption {
+    Auto,
+    None,
+    Gzip,
+    Zstd,
+    Bzip2,
+    Xz,
+}
+
 // ---
 
 static CONFIG: Lazy<Settings> = Lazy::new(|| load_config());
 
 // ---
 
+/// Shows invocations recorded to the history file, or reruns the one with the given number.
+fn history(path: &Path, rerun: Option<usize>, last: Option<usize>) -> Result<()> {
+    let entries = hl::history::load(path)?;
+    if let Some(number) = rerun {
+        let entry = match entries.get(number.wrapping_sub(1)).filter(|entry| entry.args.len() != 0) {
+            Some(entry) => entry,
+            None => {
+                return Err(Error::UnknownHistoryEntry {
+                    number,
+                    count: entries.len(),
+                })
+            }
+        };
+        if entry.redacted {
+            return Err(Error::RedactedHistoryEntry { number });
+        }
+        diagnostics::info(format_args!("running {}", entry.command()));
+        let mut command = process::Command::new(std::env::current_exe()?);
+        command.args(&entry.args[1..]);
+        if let Some(dir) = &entry.dir {
+            command.current_dir(dir);
+        }
+        let status = command.status()?;
+        if !status.success() {
+            process::exit(status.code().unwrap_or(1));
+        }
+        return Ok(());
+    }
+
+    let current = std::env::current_dir().ok();
+    let skip = last.map(|n| entries.len().saturating_sub(n)).unwrap_or(0);
+    let mut output = stdout().lock();
+    for (i, entry) in entries.iter().enumerate().skip(skip) {
+        write!(
+            output,
+            "{:>4}  {}  {}",
+            i + 1,
+            entry.time.with_timezone(&Local).format("%Y-%m-%d %T"),
+            entry.command()
+        )?;
+        if let Some(records) = entry.records {
+            write!(output, "  [{} parsed, {} matched]", records.parsed, records.matched)?;
+        }
+        writeln!(output)?;
+        if let Some(dir) = entry.dir.as_ref().filter(|dir| Some(*dir) != current.as_ref()) {
+            writeln!(output, "      in {}", dir.display())?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns filtering options of the invocation in form recorded to the history file,
+/// with values of the logger, field and query filters replaced with a placeholder if `redact` is set.
+fn history_filters(opt: &Opt, redact: bool) -> Vec<String> {
+    let level = opt.level.as_deref().map(|level| format!("level={:?}", level).to_lowercase());
+    let value = |x: &String| if redact { "<redacted>".to_owned() } else { x.clone() };
+    level
+        .into_iter()
+        .chain(opt.logger.iter().map(|x| format!("logger={}", value(x))))
+        .chain(opt.filter.iter().map(|x| format!("filter={}", value(x))))
+        .chain(opt.query.iter().map(|x| format!("query={}", value(x))))
+        .chain(opt.since.iter().map(|x| format!("since={}", x)))
+        .chain(opt.until.iter().map(|x| format!("until={}", x)))
+        .collect()
+}
+
 fn app_dirs() -> AppDirs {
     AppDirs::new(Some(APP_NAME), true).unwrap()
 }
 
+fn cache_dir(opt: &Opt, settings: &Settings, default: PathBuf) -> PathBuf {
+    if let Some(dir) = opt.cache_dir.as_ref().or(settings.cache_dir.as_ref()) {
+        return dir.clone();
+    }
+    if let Ok(current) = std::env::current_dir() {
+        for dir in current.ancestors() {
+            let candidate = dir.join(PROJECT_CACHE_DIR);
+            if candidate.is_dir() {
+                return candidate;
+            }
+        }
+    }
+    match std::env::var_os("XDG_CACHE_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir).join(APP_NAME),
+        None => default,
+    }
+}
+
 fn load_config() -> Settings {
     Settings::load(&app_dirs()).unwrap()
 }
@@ -247,17 +728,171 @@ fn parse_non_zero_size(s: &str) -> std::result::Result<NonZeroUsize, NonZeroSize
 
 // ---
 
+fn parse_speed(s: &str) -> std::result::Result<f64, String> {
+    match s.strip_suffix('x').unwrap_or(s).parse::<f64>() {
+        Ok(speed) if speed > 0.0 && speed.is_finite() => Ok(speed),
+        _ => Err(format!("invalid speed '{}', expected positive number optionally followed by 'x'", s)),
+    }
+}
+
+fn parse_rate(s: &str) -> std::result::Result<f64, String> {
+    let (count, unit) = s.split_once('/').unwrap_or((s, "s"));
+    let seconds = match unit {
+        "s" => 1.0,
+        "m" => 60.0,
+        "h" => 3600.0,
+        _ => 0.0,
+    };
+    match count.parse::<f64>() {
+        Ok(count) if count > 0.0 && count.is_finite() && seconds != 0.0 => Ok(count / seconds),
+        _ => Err(format!("invalid rate '{}', expected positive number optionally followed by /s, /m or /h", s)),
+    }
+}
+
+fn parse_tag(s: &str) -> std::result::Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.into(), value.into())),
+        _ => Err(format!("invalid tag '{}', expected <key>=<value>", s)),
+    }
+}
+
+/// Returns compression of the output file chosen by --output-compression option or by the file extension.
+fn output_compression(opt: &Opt, output: &str) -> Option<Compression> {
+    match opt.output_compression {
+        CompressionOption::Auto => Compression::from_path(Path::new(output)),
+        CompressionOption::None => None,
+        CompressionOption::Gzip => Some(Compression::Gzip),
+        CompressionOption::Zstd => Some(Compression::Zstd),
+        CompressionOption::Bzip2 => Some(Compression::Bzip2),
+        CompressionOption::Xz => Some(Compression::Xz),
+    }
+}
+
+/// Creates the output file, compressing data written to it if needed.
+fn create_output_file(opt: &Opt, output: &str) -> Result<Sink> {
+    let file = std::fs::File::create(PathBuf::from(output))?;
+    Ok(match output_compression(opt, output) {
+        Some(compression) => Sink::Compressed(compression.encoder(file, opt.output_compression_level)?),
+        None => Sink::Stream(Box::new(file)),
+    })
+}
+
+/// Destination of the output, compressed output is finished explicitly to report failures to complete it.
+enum Sink {
+    Stream(OutputStream),
+    Compressed(Encoder<std::fs::File>),
+}
+
+impl Sink {
+    /// Writes the rest of the compressed data if the output is compressed.
+    fn finish(&mut self) -> Result<()> {
+        match self {
+            Self::Stream(stream) => stream.flush()?,
+            Self::Compressed(encoder) => encoder.finish()?,
+        }
+        Ok(())
+    }
+
+    fn as_mut(&mut self) -> &mut (dyn Write + Send + Sync) {
+        match self {
+            Self::Stream(stream) => stream.as_mut(),
+            Self::Compressed(encoder) => encoder,
+        }
+    }
+}
+
+/// Replaces arguments like `@name` with inputs of the workspace with the given name,
+/// unless a file with such name exists, which can also be given as `./@name`.
+fn expand_workspaces(
+    files: &[PathBuf],
+    workspaces: &HashMap<String, Vec<String>>,
+    exists: impl Fn(&Path) -> bool,
+) -> Result<Vec<PathBuf>> {
+    let mut result = Vec::with_capacity(files.len());
+    for file in files {
+        let name = file.to_str().and_then(|name| name.strip_prefix('@'));
+        match name.filter(|_| !exists(file)) {
+            Some(name) => match workspaces.get(name) {
+                Some(inputs) => result.extend(inputs.iter().map(PathBuf::from)),
+                None => {
+                    return Err(Error::UnknownWorkspace {
+                        name: name.into(),
+                        known: workspaces.keys().sorted().cloned().collect(),
+                    })
+                }
+            },
+            None => result.push(file.clone()),
+        }
+    }
+    Ok(result)
+}
+
+fn parse_input(path: &PathBuf) -> (InputReference, Vec<(String, String)>) {
+    if path.to_str() == Some("-") {
+        return (InputReference::Stdin, Vec::new());
+    }
+    if let Some(command) = path.to_str().and_then(|path| path.strip_prefix("cmd://")) {
+        return (InputReference::Command(hl::exec::split(command)), Vec::new());
+    }
+    if !path.exists() {
+        if let Some((base, query)) = path.to_str().and_then(|path| path.rsplit_once('?')) {
+            let tags = query
+                .split('&')
+                .map(|item| item.strip_prefix("tag=").and_then(|tag| parse_tag(tag).ok()))
+                .collect::<Option<Vec<_>>>();
+            if let Some(tags) = tags {
+                let (input, _) = parse_input(&PathBuf::from(base));
+                return (input, tags);
+            }
+        }
+    }
+    (InputReference::File(path.clone()), Vec::new())
+}
+
 fn run() -> Result<()> {
-    let app_dirs = app_dirs();
-    let settings = Settings::load(&app_dirs)?;
+    let mut app_dirs = app_dirs();
+    let mut settings = Settings::load(&app_dirs)?;
     let opt = Opt::parse();
+    diagnostics::set_verbosity(Verbosity::new(opt.quiet, opt.verbose));
+    app_dirs.cache_dir = cache_dir(&opt, &settings, app_dirs.cache_dir);
     if opt.help {
         return Opt::command().print_help().map_err(Error::Io);
     }
+    let started = Utc::now();
+    let history_file = match &settings.history.file {
+        Some(file) => file.clone(),
+        None => app_dirs.data_dir.join("history.jsonl"),
+    };
+    if let Some(Command::History { rerun, last }) = opt.command {
+        return history(&history_file, rerun, last);
+    }
+    if let Some(Command::Generate { format, rate, levels, count, seed }) = &opt.command {
+        let mut output = match &opt.output {
+            Some(output) => create_output_file(&opt, output)?,
+            None => Sink::Stream(Box::new(stdout())),
+        };
+        let result = Generator::new(*seed)
+            .with_format(*format)
+            .with_rate(*rate)
+            .with_levels(levels.0.clone())
+            .with_count(*count)
+            .run(&mut output.as_mut());
+        return match result.and(output.finish()) {
+            Err(Error::Io(ref e)) if e.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+            result => result,
+        };
+    }
+    let record_history = settings.history.enabled;
+    let redact_history = settings.history.redact;
+    if opt.background {
+        if let Err(err) = hl::priority::enter_background_mode() {
+            diagnostics::warning(format_args!("failed to lower process priority: {}", err));
+        }
+    }
 
     let color_supported = if stdout().is_terminal() {
         if let Err(err) = hl::enable_ansi_support() {
-            eprintln!("failed to enable ansi support: {}", err);
+            diagnostics::warning(format_args!("failed to enable ansi support: {}", err));
             false
         } else {
             true
@@ -266,20 +901,39 @@ fn run() -> Result<()> {
         false
     };
 
+    // Configure output format.
+    let output_format = match opt.output_format {
+        OutputFormatOption::Auto => settings
+            .output_format
+            .select(opt.output.as_deref().map(Path::new), stdout().is_terminal()),
+        OutputFormatOption::Text => OutputFormat::Text,
+        OutputFormatOption::Json => OutputFormat::Json,
+        OutputFormatOption::Logfmt => OutputFormat::Logfmt,
+        OutputFormatOption::Csv => OutputFormat::Csv,
+    };
+
     // Configure color scheme.
     let color = if opt.color_always {
         ColorOption::Always
     } else {
         opt.color
     };
+    let terminal = Capabilities::detect().with_overrides(&settings.terminal);
     let use_colors = match color {
-        ColorOption::Auto => stdout().is_terminal() && color_supported,
+        ColorOption::Auto => {
+            !opt.deterministic
+                && stdout().is_terminal()
+                && color_supported
+                && terminal.color_depth != ColorDepth::None
+        }
         ColorOption::Always => true,
         ColorOption::Never => false,
     };
     let theme = if use_colors {
         let theme = &opt.theme;
-        Theme::load(&app_dirs, theme)?
+        // keep colors of the theme as is for stable output regardless of the terminal
+        let depth = if opt.deterministic { ColorDepth::Truecolor } else { terminal.color_depth };
+        Theme::load_downgraded(&app_dirs, theme, depth)?
     } else {
         Theme::none()
     };
@@ -303,40 +957,108 @@ fn run() -> Result<()> {
 
     // Configure concurrency.
     let concurrency = match opt.concurrency.or(settings.concurrency) {
-        None | Some(0) => num_cpus::get(),
+        None | Some(0) if opt.background => 1,
+        None | Some(0) => std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or_else(|_| num_cpus::get()),
         Some(value) => value,
     };
     // Configure timezone.
-    let tz = if opt.local { Tz::Local } else { Tz::IANA(opt.time_zone) };
-    // Configure time format.
-    let time_format = LinuxDateFormat::new(&opt.time_format).compile();
+    let tz = if opt.deterministic {
+        Tz::IANA(chrono_tz::UTC)
+    } else if opt.local {
+        Tz::Local
+    } else {
+        Tz::IANA(hl::timezone::find(&opt.time_zone)?)
+    };
+    // Configure locale.
+    let locale = match &opt.locale {
+        Some(name) => Some(Locale::find(name).ok_or_else(|| Error::UnknownLocale {
+            name: name.clone(),
+            known: Locale::known().map(String::from).collect(),
+        })?),
+        None => None,
+    };
+    // Configure compact layout for narrow terminals.
+    let compact = match opt.compact_width.unwrap_or(settings.compact_layout.width_threshold) {
+        0 => false,
+        threshold => {
+            !opt.deterministic
+                && opt.output.is_none()
+                && stdout().is_terminal()
+                && terminal_size().map(|(Width(width), _)| usize::from(width) < threshold).unwrap_or(false)
+        }
+    };
+    // Configure time format, bounds of --since and --until options are parsed in it regardless of the compact layout.
+    let bound_format = LinuxDateFormat::new(&opt.time_format).compile();
+    let time_format = if compact {
+        LinuxDateFormat::new(&settings.compact_layout.time_format).compile()
+    } else {
+        LinuxDateFormat::new(&opt.time_format).compile()
+    };
+    let parse_bound = |s: &str| -> Result<_> {
+        if opt.deterministic {
+            parse_absolute_time(s, &tz)
+        } else {
+            parse_time(s, &tz, &bound_format)
+        }
+    };
     // Configure filter.
+    let mut filters = opt.filter;
+    for path in &opt.query_file {
+        filters.extend(hl::query::load(path)?);
+    }
+    let folding = hl::TextFolding {
+        ignore_case: opt.ignore_case,
+        fold_diacritics: opt.fold_diacritics,
+    };
+    let mut query: Option<Query> = None;
+    for text in &opt.query {
+        let next = Query::parse(text, folding, &settings.level_aliases)?.with_strict_types(opt.strict_types);
+        query = Some(match query {
+            Some(query) => query.and(next),
+            None => next,
+        });
+    }
     let filter = hl::Filter {
-        fields: hl::FieldFilterSet::new(opt.filter)?,
+        fields: hl::FieldFilterSet::new_folded(filters, folding)?.with_strict_types(opt.strict_types),
         level: opt.level.map(|x| x.into()),
+        loggers: hl::LoggerLevels::new(&opt.logger, &settings.level_aliases)?,
         since: if let Some(v) = &opt.since {
-            Some(parse_time(v, &tz, &time_format)?.with_timezone(&Utc))
+            Some(parse_bound(v)?.with_timezone(&Utc))
         } else {
             None
         },
         until: if let Some(v) = &opt.until {
-            Some(parse_time(v, &tz, &time_format)?.with_timezone(&Utc))
+            Some(parse_bound(v)?.with_timezone(&Utc))
         } else {
             None
         },
+        query,
+    };
+    // Configure schema validation.
+    let schema = match &opt.validate {
+        Some(path) => Some(Arc::new(SchemaValidator::load(path)?)),
+        None => None,
     };
     // Configure hide_empty_fields
     let hide_empty_fields = !opt.show_empty_fields && opt.hide_empty_fields;
+    // Configure handling of duplicate keys.
+    if let Some(policy) = opt.duplicate_keys {
+        settings.fields.duplicate_keys = policy;
+    }
 
     // Configure field filter.
     let all = || IncludeExcludeKeyFilter::new(KeyMatchOptions::default());
     let none = || all().excluded();
     let mut fields = all();
+    let mut hidden = Vec::new();
     for (i, key) in CONFIG.fields.hide.iter().chain(&opt.hide).enumerate() {
         if key == "*" {
             fields = none();
         } else if key == "!*" {
             fields = all();
+            hidden.clear();
         } else if key.starts_with("!") {
             if i == 0 {
                 fields = none();
@@ -346,14 +1068,70 @@ fn run() -> Result<()> {
             fields.entry(&key[1..]).exclude();
         } else if key.starts_with("\\\\") {
             fields.entry(&key[1..]).exclude();
+        } else if key.contains(['*', '?']) {
+            hidden.push(key.clone());
         } else {
             fields.entry(&key).exclude();
         }
     }
+    let shown = if opt.output_fields.is_empty() { &settings.fields.show } else { &opt.output_fields };
+    let selection = FieldSelection::default().with_shown(shown).with_hidden(hidden);
+    if compact {
+        fields = none();
+    }
 
     let max_message_size = opt.max_message_size;
     let buffer_size = std::cmp::min(max_message_size, opt.buffer_size);
 
+    let (transforms, time_formats) = match &opt.profile {
+        Some(name) => match settings.profiles.get(name) {
+            Some(profile) => {
+                if let Some(numbers) = &profile.level_numbers {
+                    for variant in &mut settings.fields.predefined.level.variants {
+                        variant.numbers = numbers.clone();
+                    }
+                }
+                (profile.transforms.clone(), profile.time_formats.clone())
+            }
+            None => {
+                return Err(Error::UnknownProfile {
+                    name: name.clone(),
+                    known: settings.profiles.keys().sorted().cloned().collect(),
+                })
+            }
+        },
+        None => (Vec::new(), Vec::new()),
+    };
+    // formats of the profile are tried before the ones of the configuration
+    let time_formats = time_formats.into_iter().chain(settings.time_formats.iter().cloned()).collect_vec();
+
+    // Expand directories and wildcard patterns in file arguments.
+    let arguments = expand_workspaces(&opt.files, &settings.workspace, Path::exists)?;
+    let mut files = Vec::new();
+    let mut watch = Vec::new();
+    for (input, tags) in arguments.iter().map(parse_input) {
+        let pattern = match &input {
+            InputReference::File(path) => FilePattern::parse(path),
+            _ => None,
+        };
+        match pattern {
+            Some(pattern) => {
+                files.extend(pattern.scan()?.into_iter().map(|path| (InputReference::File(path), tags.clone())));
+                if opt.follow {
+                    let tags = opt.tag.iter().cloned().chain(tags).collect();
+                    watch.push(hl::app::Watch { pattern, tags });
+                }
+            }
+            None => files.push((input, tags)),
+        }
+    }
+
+    // Load offsets of input files saved by the previous run.
+    let checkpoint = match &opt.checkpoint {
+        Some(path) => Some(Arc::new(Mutex::new(Checkpoint::load(path.clone())?))),
+        None => None,
+    };
+
     // Create app.
     let app = hl::App::new(hl::Options {
         theme: Arc::new(theme),
@@ -361,18 +1139,64 @@ fn run() -> Result<()> {
         time_format,
         buffer_size,
         max_message_size,
-        concurrency,
+        max_stream_buffer_size: opt.max_stream_buffer_size,
+        // gaps are measured by the formatter, so messages have to be formatted in the order they are shown
+        concurrency: if opt.time_gaps { 1 } else { concurrency },
         filter,
         fields: hl::FieldOptions {
             settings: settings.fields,
             filter: Arc::new(fields),
+            selection: Arc::new(selection),
+            strip_prefixes: opt.strip_prefix.iter().filter(|prefix| !prefix.is_empty()).cloned().collect(),
         },
         formatting: settings.formatting,
         time_zone: tz,
         hide_empty_fields,
-        sort: opt.sort,
+        sort: opt.sort || opt.replay || opt.window.is_some(),
         follow: opt.follow,
         sync_interval: Duration::from_millis(opt.sync_interval_ms),
+        snapshot: match opt.snapshot_size {
+            0 => None,
+            records => Some(hl::snapshot::SnapshotOptions {
+                records,
+                dir: opt.snapshot_dir.clone(),
+            }),
+        },
+        split_screen: if opt.split_screen {
+            Some(terminal_size().map(|(Width(width), _)| usize::from(width)).unwrap_or(DEFAULT_SPLIT_SCREEN_WIDTH))
+        } else {
+            None
+        },
+        replay: if opt.replay { Some(opt.speed) } else { None },
+        window: opt.window.map(|size| hl::app::TimeWindow {
+            size,
+            page: opt.page.get(),
+        }),
+        transforms,
+        explode: opt.explode.clone(),
+        max_runtime: opt.max_runtime,
+        index_cache: !opt.no_index_cache,
+        index_cache_max_size: settings
+            .index_cache
+            .max_size
+            .as_deref()
+            .map(parse_size)
+            .transpose()?
+            .map(|size| size as u64),
+        index_concurrency: opt.index_concurrency.map_or(concurrency, NonZeroUsize::get),
+        progress: opt.progress && !opt.follow,
+        sort_fields: opt.deterministic,
+        summary: opt.summary,
+        count_records: record_history && settings.history.record_counts,
+        tracking: opt.track.clone().map(|key| hl::tracking::Tracking {
+            key,
+            interval: opt.track_interval,
+        }),
+        skip_binary: opt.skip_binary,
+        skip_errors: opt.skip_errors,
+        schema,
+        output_format,
+        columns: opt.columns.clone(),
         input_info: match opt.input_info {
             InputInfoOption::Auto => Some(hl::app::InputInfo::Auto),
             InputInfoOption::None => None,
@@ -380,47 +1204,120 @@ fn run() -> Result<()> {
             InputInfoOption::Compact => Some(hl::app::InputInfo::Compact),
             InputInfoOption::Minimal => Some(hl::app::InputInfo::Minimal),
         },
+        show_source: opt.show_source,
         dump_index: opt.dump_index,
         app_dirs: Some(app_dirs),
+        icons: opt.icons || compact,
+        time_gaps: if opt.time_gaps { Some(settings.time_gaps) } else { None },
+        lanes: opt.lanes_by.map(|key| Lanes {
+            key,
+            indent: opt.lane_indent,
+        }),
+        color_by: opt.color_by.map(|key| ColorBy {
+            key,
+            target: opt.color_by_target,
+        }),
+        watch,
+        time_formats,
+        locale,
+        input_format: opt.input_format,
+        text_format: hl::text::TextFormat::new(&settings.text_input, &settings.level_aliases)?,
+        multiline: if opt.multiline { Some(settings.multiline) } else { None },
+        terminal,
+        context: match (opt.before.or(opt.context), opt.after.or(opt.context)) {
+            (None, None) => None,
+            (before, after) => Some(hl::app::Context {
+                before: before.unwrap_or(0),
+                after: after.unwrap_or(0),
+            }),
+        },
+        limit: opt.head.map(hl::app::Limit::Head).or(opt.tail.map(hl::app::Limit::Tail)),
+        interactive: opt.interactive,
+        heartbeat: opt.heartbeat.filter(|interval| !interval.is_zero()),
+        dedup: opt.dedup.then(|| hl::dedup::Dedup {
+            fields: opt.dedup_fields.clone(),
+        }),
+        checkpoint: checkpoint.clone(),
+        stats: opt.stats.then(|| hl::stats::StatsOptions {
+            by: opt.stats_by.clone(),
+            top: opt.stats_top,
+            order: opt.sort_groups,
+        }),
+        copy: match (opt.copy_matches, opt.copy_format) {
+            (false, _) => None,
+            (true, CopyFormatOption::Json) => Some(CopyFormat::Json),
+            (true, CopyFormatOption::Text) => Some(CopyFormat::Text),
+        },
     });
 
     // Configure input.
-    let mut inputs = opt
-        .files
-        .iter()
-        .map(|x| {
-            if x.to_str() == Some("-") {
-                InputReference::Stdin
-            } else {
-                InputReference::File(x.clone())
+    let decompression = match opt.input_compression {
+        CompressionOption::Auto => Decompression::Auto,
+        CompressionOption::None => Decompression::Disabled,
+        CompressionOption::Gzip => Decompression::Forced(Compression::Gzip),
+        CompressionOption::Zstd => Decompression::Forced(Compression::Zstd),
+        CompressionOption::Bzip2 => Decompression::Forced(Compression::Bzip2),
+        CompressionOption::Xz => Decompression::Forced(Compression::Xz),
+    };
+    let hold = |(input, tags): (InputReference, Vec<(String, String)>)| -> Result<InputHolder> {
+        Ok(input
+            .hold()?
+            .with_tags(opt.tag.iter().cloned().chain(tags).collect())
+            .with_decompression(decompression)
+            .with_encoding(opt.input_encoding)
+            .with_concurrency(concurrency)
+            .with_mmap(opt.mmap))
+    };
+    // inputs failed to open are skipped with --skip-errors option
+    let hold_all = |inputs: Vec<(InputReference, Vec<(String, String)>)>| -> Result<Vec<InputHolder>> {
+        let mut result = Vec::with_capacity(inputs.len());
+        for input in inputs {
+            let description = input.0.description();
+            match hold(input) {
+                Ok(input) => result.push(input),
+                Err(err) => app.skip_failed_input(description, err)?,
             }
-        })
-        .collect::<Vec<_>>();
-    if inputs.len() == 0 {
+        }
+        Ok(result)
+    };
+    let mut inputs = files;
+    inputs.extend(opt.listen.iter().map(|address| (InputReference::Listen(address.clone()), Vec::new())));
+    inputs.extend(opt.exec.iter().map(|command| (InputReference::Command(hl::exec::split(command)), Vec::new())));
+    if inputs.len() == 0 && opt.files.is_empty() && opt.command.is_none() {
         if stdin().is_terminal() {
             let mut cmd = Opt::command();
             return cmd.print_help().map_err(Error::Io);
         }
-        inputs.push(InputReference::Stdin);
+        inputs.push((InputReference::Stdin, Vec::new()));
     }
 
-    if opt.sort {
-        for input in &inputs {
-            if let InputReference::File(path) = input {
-                if let Some(Some("gz")) = path.extension().map(|x| x.to_str()) {
-                    return Err(Error::UnsupportedFormatForIndexing {
-                        path: path.clone(),
-                        format: "gzip".into(),
-                    });
-                }
-            }
-        }
+    if opt.split_screen && inputs.len() != 2 {
+        return Err(Error::WrongSplitScreenInputCount(inputs.len()));
     }
 
-    let inputs = inputs
-        .into_iter()
-        .map(|input| input.hold().map_err(Error::Io))
-        .collect::<Result<Vec<_>>>()?;
+    let inputs = hold_all(inputs)?;
+    // Continue input files from the offsets saved by the previous run, in follow mode it is done by the app.
+    let inputs = match (&checkpoint, opt.follow) {
+        (Some(checkpoint), false) => {
+            let mut checkpoint = checkpoint.lock().unwrap();
+            inputs.into_iter().map(|input| checkpoint.resume(input)).collect::<Result<Vec<_>>>()?
+        }
+        _ => inputs,
+    };
+    let descriptions = inputs.iter().map(|input| input.reference.description()).collect_vec();
+
+    // Configure progress reporting, inputs indexed before processing report progress of indexing them instead.
+    let sorted = (opt.sort || opt.replay || opt.window.is_some()) && !opt.interactive && !opt.split_screen;
+    let indexed = opt.stats || sorted || matches!(opt.command, Some(Command::Index { .. }));
+    let progress = if opt.progress && !opt.follow && !indexed {
+        Some(Arc::new(Progress::new(inputs.iter().filter_map(|input| input.size).sum())))
+    } else {
+        None
+    };
+    let inputs = match &progress {
+        Some(progress) => inputs.into_iter().map(|input| input.with_progress(progress.clone())).collect(),
+        None => inputs,
+    };
 
     let paging = match opt.paging {
         PagingOption::Auto => {
@@ -433,38 +1330,156 @@ fn run() -> Result<()> {
         PagingOption::Always => true,
         PagingOption::Never => false,
     };
-    let paging = if opt.paging_never || opt.follow { false } else { paging };
-    let mut output: OutputStream = match opt.output {
-        Some(output) => Box::new(std::fs::File::create(PathBuf::from(&output))?),
-        None => {
-            if paging {
-                if let Ok(pager) = Pager::new() {
-                    Box::new(pager)
-                } else {
-                    Box::new(stdout())
-                }
+    let paging = if opt.paging_never || opt.follow || opt.replay || opt.interactive || opt.deterministic {
+        false
+    } else {
+        paging
+    };
+
+    if opt.plan {
+        let sink = match &opt.output {
+            Some(output) => match output_compression(&opt, output) {
+                Some(compression) => format!("file '{}' compressed with {}", output, compression.name()),
+                None => format!("file '{}'", output),
+            },
+            None if paging => "pager".into(),
+            None => "stdout".into(),
+        };
+        return app.plan(&inputs, &sink, &mut stdout());
+    }
+    let mut output = match opt.output {
+        Some(ref output) => create_output_file(&opt, output)?,
+        None => Sink::Stream(if paging {
+            if let Ok(pager) = Pager::new() {
+                Box::new(pager)
             } else {
                 Box::new(stdout())
             }
-        }
+        } else {
+            Box::new(stdout())
+        }),
     };
 
     // Run the app.
-    let run = || match app.run(inputs, output.as_mut()) {
-        Ok(()) => Ok(()),
-        Err(Error::Io(ref e)) if e.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
-        Err(err) => Err(err),
+    let mut output_closed = false;
+    let run = || {
+        let result = match &opt.command {
+            Some(Command::Diff { left, right, key }) => app.diff(
+                hold(parse_input(left))?,
+                hold(parse_input(right))?,
+                key.as_deref(),
+                output.as_mut(),
+            ),
+            Some(Command::Index { files, rebuild, prune }) => app.index(
+                hold_all(files.iter().map(|file| parse_input(file)).collect())?,
+                *rebuild,
+                *prune,
+                output.as_mut(),
+            ),
+            None => app.run(inputs, output.as_mut()),
+            Some(Command::History { .. } | Command::Generate { .. }) => unreachable!(),
+        };
+        match result {
+            Ok(()) => Ok(()),
+            Err(Error::Io(ref e)) if e.kind() == std::io::ErrorKind::BrokenPipe => {
+                output_closed = true;
+                Ok(())
+            }
+            Err(err) => Err(err),
+        }
     };
 
-    let interrupt_ignore_count = if opt.follow { 0 } else { opt.interrupt_ignore_count };
+    let interrupt_ignore_count = if opt.follow || opt.replay { 0 } else { opt.interrupt_ignore_count };
 
     // Run the app with signal handling.
-    SignalHandler::run(interrupt_ignore_count, std::time::Duration::from_secs(1), run)
+    let _reporter = progress.as_ref().map(|progress| progress.report());
+    let result = SignalHandler::run(interrupt_ignore_count, std::time::Duration::from_secs(1), run);
+
+    // Complete compressed output even if processing failed, so that the output produced so far can be read.
+    let finished = match output.finish() {
+        Err(Error::Io(ref e)) if e.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+        finished => finished,
+    };
+    let result = result.and(finished);
+
+    // Save offsets of input files processed up to, unless processing failed, was interrupted, or the pager was quit early.
+    let result = match (&checkpoint, result) {
+        (Some(checkpoint), Ok(())) if !opt.follow && opt.command.is_none() && !output_closed => {
+            checkpoint.lock().unwrap().save()
+        }
+        (_, result) => result,
+    };
+
+    // Record the invocation to the history file.
+    if record_history {
+        // the program name is kept as is, values of the other arguments may contain secrets
+        let mut args = std::env::args_os().map(|arg| arg.to_string_lossy().into_owned());
+        let args = match args.next() {
+            Some(program) if redact_history => Some(program).into_iter().chain(hl::bugreport::redact(args)).collect(),
+            program => program.into_iter().chain(args).collect(),
+        };
+        let entry = hl::history::Entry {
+            time: started,
+            dir: std::env::current_dir().ok(),
+            args,
+            redacted: redact_history,
+            inputs: descriptions,
+            filters: history_filters(&opt, redact_history),
+            records: app
+                .record_counts()
+                .map(|(parsed, matched)| hl::history::RecordCounts { parsed, matched }),
+        };
+        if let Err(err) = hl::history::append(&history_file, &entry) {
+            diagnostics::warning(format_args!(
+                "failed to record invocation to history file '{}': {}",
+                history_file.display(),
+                err
+            ));
+        }
+    }
+
+    result
 }
 
 fn main() {
+    hl::bugreport::install();
     if let Err(err) = run() {
         eprintln!("{}: {}", Color::Red.paint("error"), err);
-        process::exit(1);
+        process::exit(match err {
+            Error::TimeBudgetExceeded { .. } => 124,
+            _ => 1,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_expand_workspaces() {
+        let dir = std::env::temp_dir().join(format!("hl-workspace-test-{}", process::id()));
+        let workspaces = HashMap::from([("app".to_owned(), vec!["a.log".to_owned(), "b.log".to_owned()])]);
+        let exists = |path: &Path| dir.join(path).exists();
+        let expand =
+            |files: &[&Path]| expand_workspaces(&files.iter().map(PathBuf::from).collect_vec(), &workspaces, exists);
+
+        assert_eq!(
+            expand(&[Path::new("@app"), Path::new("c.log")]).unwrap(),
+            vec![PathBuf::from("a.log"), PathBuf::from("b.log"), PathBuf::from("c.log")]
+        );
+        assert!(matches!(
+            expand(&[Path::new("@web")]),
+            Err(Error::UnknownWorkspace { name, .. }) if name == "web"
+        ));
+
+        // an existing file is opened even if its name looks like a workspace
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("@app"), "").unwrap();
+        fs::write(dir.join("@web"), "").unwrap();
+        let result = expand(&[Path::new("@app"), Path::new("@web")]);
+        fs::remove_dir_all(&dir).ok();
+        assert_eq!(result.unwrap(), vec![PathBuf::from("@app"), PathBuf::from("@web")]);
     }
 }
@@ -1,22 +1,34 @@
 // std imports
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::cmp::max;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::iter::IntoIterator;
 use std::marker::PhantomData;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 // third-party imports
 use chrono::{DateTime, Utc};
+use ipnet::IpNet;
 use json::value::RawValue;
-use regex::Regex;
+use once_cell::sync::{Lazy, OnceCell};
+use regex::{Regex, RegexBuilder};
 use serde::de::{Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
 use serde_json as json;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
 use wildmatch::WildMatch;
 
 // local imports
+use crate::diagnostics;
 use crate::error::{Error, Result};
 use crate::level;
-use crate::settings::PredefinedFields;
-use crate::timestamp::Timestamp;
+use crate::prefilter;
+use crate::query::Query;
+use crate::settings::{DuplicateKeys, LevelFieldVariant, PredefinedFields};
+use crate::timestamp::{TimeFormat, Timestamp};
 use crate::types::FieldKind;
 
 // ---
@@ -30,7 +42,7 @@ pub struct Record<'a> {
     pub message: Option<&'a RawValue>,
     pub level: Option<Level>,
     pub logger: Option<&'a str>,
-    pub caller: Option<&'a str>,
+    pub caller: Option<Caller<'a>>,
     pub(crate) extra: heapless::Vec<(&'a str, &'a RawValue), RECORD_EXTRA_CAPACITY>,
     pub(crate) extrax: Vec<(&'a str, &'a RawValue)>,
 }
@@ -40,6 +52,24 @@ impl<'a> Record<'a> {
         self.extra.iter().chain(self.extrax.iter())
     }
 
+    /// Returns numeric value of the field with the given key, where nested keys are separated by dots.
+    pub fn number(&self, key: &str) -> Option<f64> {
+        self.value(key).and_then(parse_number)
+    }
+
+    /// Returns raw value of the field with the given key, where nested keys are separated by dots.
+    pub fn value(&self, key: &str) -> Option<&'a RawValue> {
+        let matcher = KeyMatcher::new(key);
+        for (k, v) in self.fields() {
+            match matcher.match_key(k) {
+                None => continue,
+                Some(KeyMatch::Full) => return Some(v),
+                Some(KeyMatch::Partial(subkey)) => return nested_value(subkey, v),
+            }
+        }
+        None
+    }
+
     pub fn matches(&self, filter: &Filter) -> bool {
         if filter.is_empty() {
             return true;
@@ -60,55 +90,82 @@ impl<'a> Record<'a> {
             }
         }
 
-        if let Some(bound) = &filter.level {
-            if let Some(level) = self.level.as_ref() {
+        if let Some(bound) = filter.loggers.level(self.logger).or(filter.level) {
+            if let Some(level) = self.level {
                 if level > bound {
                     return false;
                 }
             }
         }
 
-        if !filter.fields.0.is_empty() {
-            for field in filter.fields.0.iter() {
-                match &field.key[..] {
-                    "msg" | "message" => {
-                        if !field.match_value(self.message.map(|x| x.get()), true) {
-                            return false;
-                        }
+        if !self.matches_fields(&filter.fields) {
+            return false;
+        }
+
+        if let Some(query) = &filter.query {
+            return query.matches(self);
+        }
+
+        return true;
+    }
+
+    /// Returns true if the record matches all the field filters.
+    ///
+    /// Predefined message, logger and caller fields are matched by their text regardless of strict typing,
+    /// so `msg=404` matches a message `404` even with --strict-types option.
+    pub(crate) fn matches_fields(&self, fields: &FieldFilterSet) -> bool {
+        for field in fields.0.iter() {
+            match &field.key[..] {
+                "msg" | "message" => {
+                    if !field.match_value(self.message.map(|x| x.get()), true, false) {
+                        return false;
                     }
-                    "logger" => {
-                        if !field.match_value(self.logger, false) {
-                            return false;
-                        }
+                }
+                "logger" => {
+                    if !field.match_value(self.logger, false, false) {
+                        return false;
                     }
-                    "caller" => {
-                        if !field.match_value(self.caller, false) {
-                            return false;
-                        }
+                }
+                "caller" => {
+                    if !field.match_value(self.caller.as_ref().map(|x| x.text()).as_deref(), false, false) {
+                        return false;
                     }
-                    _ => {
-                        let mut matched = false;
-                        for (k, v) in self.extra.iter() {
-                            match field.match_key(*k) {
-                                None => {}
-                                Some(KeyMatch::Full) => {
-                                    let escaped = v.get().starts_with('"');
-                                    matched |= field.match_value(Some(v.get()), escaped);
-                                }
-                                Some(KeyMatch::Partial(subkey)) => {
-                                    matched |= field.match_value_partial(subkey, *v);
-                                }
+                }
+                _ => {
+                    let mut matched = false;
+                    for (k, v) in self.extra.iter() {
+                        match field.match_key(*k) {
+                            None => {}
+                            Some(KeyMatch::Full) => {
+                                matched |= field.match_raw_value(*v);
+                            }
+                            Some(KeyMatch::Partial(subkey)) => {
+                                matched |= field.match_value_partial(subkey, *v);
                             }
                         }
-                        if !matched {
-                            return false;
-                        }
+                    }
+                    if !matched {
+                        return false;
                     }
                 }
             }
         }
+        true
+    }
 
-        return true;
+    /// Returns the position of the extra field with the given key among all extra fields.
+    fn extra_position(&self, key: &str) -> Option<usize> {
+        self.extra.iter().chain(self.extrax.iter()).position(|(k, _)| *k == key)
+    }
+
+    /// Returns the value of the extra field with the given position among all extra fields.
+    fn extra_value_mut(&mut self, i: usize) -> &mut &'a RawValue {
+        let n = self.extra.len();
+        if i < n {
+            &mut self.extra[i].1
+        } else {
+            &mut self.extrax[i - n].1
+        }
     }
 
     fn with_capacity(capacity: usize) -> Self {
@@ -130,10 +187,77 @@ impl<'a> Record<'a> {
 
 // ---
 
+/// Source location of a record, given either as a single text like `main.go:42` or as a file name and a line number.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Caller<'a> {
+    pub name: &'a str,
+    pub line: Option<&'a str>,
+}
+
+impl<'a> Caller<'a> {
+    pub fn new(name: &'a str) -> Self {
+        Self { name, line: None }
+    }
+
+    pub fn with_line(mut self, line: Option<&'a str>) -> Self {
+        self.line = line;
+        self
+    }
+
+    /// Returns the location as a single text, with the line number appended to the file name after a colon.
+    pub fn text(&self) -> Cow<'a, str> {
+        match self.line {
+            Some(line) => Cow::Owned(format!("{}:{}", self.name, line)),
+            None => Cow::Borrowed(self.name),
+        }
+    }
+
+    fn parse(value: &'a RawValue) -> Option<Self> {
+        match value.get().as_bytes()[0] {
+            b'"' => json::from_str(value.get()).ok().map(Self::new),
+            b'{' => {
+                let location: SourceLocation = json::from_str(value.get()).ok()?;
+                Some(Self::new(location.file).with_line(location.line.and_then(parse_line)))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Structured source location, e.g. `source` field of Go slog or `src` field of bunyan.
+#[derive(serde::Deserialize)]
+struct SourceLocation<'a> {
+    #[serde(borrow)]
+    file: &'a str,
+    #[serde(borrow)]
+    line: Option<&'a RawValue>,
+}
+
+fn parse_line(value: &RawValue) -> Option<&str> {
+    let s = value.get();
+    let s = if s.starts_with('"') { json::from_str(s).ok()? } else { s };
+    if !s.is_empty() && s.bytes().all(|c| c.is_ascii_digit()) {
+        Some(s)
+    } else {
+        None
+    }
+}
+
+// ---
+
+/// Maximum number of distinct duplicate keys reported with `warn` policy, further ones are not reported.
+const MAX_DUPLICATE_KEY_WARNINGS: usize = 16;
+
+static DUPLICATE_KEYS_WARNED: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+// ---
+
 #[derive(Default)]
 pub struct ParserSettings {
     fields: HashMap<String, (FieldSettings, usize)>,
     ignore: Vec<WildMatch>,
+    duplicate_keys: DuplicateKeys,
+    time_formats: Vec<TimeFormat>,
 }
 
 impl ParserSettings {
@@ -148,12 +272,7 @@ impl ParserSettings {
         }
         let mut j = 0;
         for variant in &predefined.level.variants {
-            let mut mapping = HashMap::new();
-            for (level, values) in &variant.values {
-                for value in values {
-                    mapping.insert(value.clone(), level.clone());
-                }
-            }
+            let mapping = LevelMapping::new(variant);
             for (i, name) in variant.names.iter().enumerate() {
                 fields.insert(name.clone(), (FieldSettings::Level(mapping.clone()), j + i));
             }
@@ -168,36 +287,107 @@ impl ParserSettings {
         for (i, name) in predefined.caller.names.iter().enumerate() {
             fields.insert(name.clone(), (FieldSettings::Caller, i));
         }
+        for (i, name) in predefined.caller_file.names.iter().enumerate() {
+            fields.insert(name.clone(), (FieldSettings::CallerFile, i));
+        }
+        for (i, name) in predefined.caller_line.names.iter().enumerate() {
+            fields.insert(name.clone(), (FieldSettings::CallerLine, i));
+        }
         Self {
             fields,
             ignore: ignore.into_iter().map(|v| WildMatch::new(v)).collect(),
+            duplicate_keys: DuplicateKeys::default(),
+            time_formats: Vec::new(),
         }
     }
 
-    fn apply<'a>(&self, key: &'a str, value: &'a RawValue, to: &mut Record<'a>, ctx: &mut PriorityContext) {
+    pub fn with_duplicate_keys(mut self, policy: DuplicateKeys) -> Self {
+        self.duplicate_keys = policy;
+        self
+    }
+
+    /// Sets custom timestamp formats tried in order before the built-in ones.
+    pub fn with_time_formats(mut self, formats: Vec<TimeFormat>) -> Self {
+        self.time_formats = formats;
+        self
+    }
+
+    fn apply<'a>(&self, key: &'a str, value: &'a RawValue, to: &mut Record<'a>, ctx: &mut PriorityContext<'a>) {
         match self.fields.get(key) {
             Some((field, p)) => {
                 let kind = field.kind();
                 let priority = ctx.priority(kind);
-                if priority.is_none() || Some(*p) <= *priority {
-                    field.apply(value, to);
-                    *priority = Some(*p);
+                let duplicate = Some(*p) == *priority;
+                if duplicate {
+                    self.on_duplicate(key);
                 }
-            }
-            None => {
-                for pattern in &self.ignore {
-                    if pattern.matches(key) {
-                        return;
+                if priority.is_none() || Some(*p) < *priority || (duplicate && self.last_wins()) {
+                    match field {
+                        FieldSettings::CallerFile => ctx.caller_file_field = Some((key, value)),
+                        FieldSettings::CallerLine => ctx.caller_line_field = Some((key, value)),
+                        FieldSettings::Caller => match Caller::parse(value) {
+                            Some(caller) => to.caller = Some(caller),
+                            None => return self.apply_extra(key, value, to),
+                        },
+                        _ => field.apply(value, to, &self.time_formats),
                     }
-                }
-                match to.extra.push((key, value)) {
-                    Ok(_) => {}
-                    Err(value) => to.extrax.push(value),
+                    *priority = Some(*p);
                 }
             }
+            None => self.apply_extra(key, value, to),
         };
     }
 
+    /// Adds the extra field to the record.
+    fn apply_extra<'a>(&self, key: &'a str, value: &'a RawValue, to: &mut Record<'a>) {
+        for pattern in &self.ignore {
+            if pattern.matches(key) {
+                return;
+            }
+        }
+        // duplicate keys are rare, so they are looked up by a linear scan and only if the policy needs it
+        if self.merges_extras() {
+            if let Some(existing) = to.extra_position(key) {
+                self.on_duplicate(key);
+                if self.last_wins() {
+                    *to.extra_value_mut(existing) = value;
+                }
+                return;
+            }
+        }
+        match to.extra.push((key, value)) {
+            Ok(_) => {}
+            Err(value) => to.extrax.push(value),
+        }
+    }
+
+    /// Returns true if values of an extra field with a duplicate key are merged into a single field.
+    fn merges_extras(&self) -> bool {
+        match self.duplicate_keys {
+            DuplicateKeys::KeepAll | DuplicateKeys::KeepBothWithSuffix => false,
+            DuplicateKeys::FirstWins | DuplicateKeys::LastWins | DuplicateKeys::Warn => true,
+        }
+    }
+
+    fn last_wins(&self) -> bool {
+        self.duplicate_keys != DuplicateKeys::FirstWins
+    }
+
+    /// Reports the duplicate key once per key if the policy is `warn`.
+    fn on_duplicate(&self, key: &str) {
+        if self.duplicate_keys != DuplicateKeys::Warn {
+            return;
+        }
+        let mut warned = DUPLICATE_KEYS_WARNED.lock().unwrap();
+        if warned.len() < MAX_DUPLICATE_KEY_WARNINGS && !warned.contains(key) {
+            warned.insert(key.to_owned());
+            diagnostics::warning(format_args!(
+                "duplicate key {:?} found in a record, the last value is used",
+                key
+            ));
+        }
+    }
+
     fn apply_each<'a, 'i, I>(&self, items: I, to: &mut Record<'a>)
     where
         I: IntoIterator<Item = &'i (&'a str, &'a RawValue)>,
@@ -209,24 +399,52 @@ impl ParserSettings {
             logger: None,
             message: None,
             caller: None,
+            caller_file: None,
+            caller_line: None,
+            caller_file_field: None,
+            caller_line_field: None,
         };
         for (key, value) in items {
             self.apply(key, value, to, &mut ctx)
         }
+        self.apply_caller_location(ctx.caller_file_field, ctx.caller_line_field, to);
+    }
+
+    /// Combines separate file name and line number fields into the caller unless it is already known,
+    /// otherwise keeps them as ordinary fields.
+    fn apply_caller_location<'a>(
+        &self,
+        file: Option<(&'a str, &'a RawValue)>,
+        line: Option<(&'a str, &'a RawValue)>,
+        to: &mut Record<'a>,
+    ) {
+        if let (None, Some((_, file)), Some((_, line))) = (&to.caller, file, line) {
+            if let (Ok(file), Some(line)) = (json::from_str(file.get()), parse_line(line)) {
+                to.caller = Some(Caller::new(file).with_line(Some(line)));
+                return;
+            }
+        }
+        for (key, value) in file.into_iter().chain(line) {
+            self.apply_extra(key, value, to);
+        }
     }
 }
 
 // ---
 
-struct PriorityContext {
+struct PriorityContext<'a> {
     time: Option<usize>,
     level: Option<usize>,
     logger: Option<usize>,
     message: Option<usize>,
     caller: Option<usize>,
+    caller_file: Option<usize>,
+    caller_line: Option<usize>,
+    caller_file_field: Option<(&'a str, &'a RawValue)>,
+    caller_line_field: Option<(&'a str, &'a RawValue)>,
 }
 
-impl PriorityContext {
+impl<'a> PriorityContext<'a> {
     fn priority(&mut self, kind: FieldKind) -> &mut Option<usize> {
         match kind {
             FieldKind::Time => &mut self.time,
@@ -234,7 +452,48 @@ impl PriorityContext {
             FieldKind::Logger => &mut self.logger,
             FieldKind::Message => &mut self.message,
             FieldKind::Caller => &mut self.caller,
+            FieldKind::CallerFile => &mut self.caller_file,
+            FieldKind::CallerLine => &mut self.caller_line,
+        }
+    }
+}
+
+// ---
+
+/// Mapping of values of a level field to levels.
+#[derive(Clone)]
+struct LevelMapping {
+    values: HashMap<String, Level>,
+    numbers: Vec<(f64, Level)>,
+}
+
+impl LevelMapping {
+    fn new(variant: &LevelFieldVariant) -> Self {
+        let mut values = HashMap::new();
+        for (level, items) in &variant.values {
+            for value in items {
+                values.insert(value.clone(), *level);
+            }
         }
+        let mut numbers = variant
+            .numbers
+            .iter()
+            .map(|(level, number)| (*number, *level))
+            .collect::<Vec<_>>();
+        numbers.sort_by(|a, b| b.0.total_cmp(&a.0));
+        Self { values, numbers }
+    }
+
+    /// Returns the level of the exactly matching value, otherwise the level with the greatest number not exceeding the numeric value.
+    fn get(&self, value: &str) -> Option<Level> {
+        if let Some(level) = self.values.get(value) {
+            return Some(*level);
+        }
+        let value = value.parse::<f64>().ok()?;
+        self.numbers
+            .iter()
+            .find(|(number, _)| value >= *number)
+            .map(|(_, level)| *level)
     }
 }
 
@@ -242,33 +501,39 @@ impl PriorityContext {
 
 enum FieldSettings {
     Time(bool),
-    Level(HashMap<String, Level>),
+    Level(LevelMapping),
     Logger,
     Message,
     Caller,
+    CallerFile,
+    CallerLine,
 }
 
 impl FieldSettings {
-    fn apply<'a>(&self, value: &'a RawValue, to: &mut Record<'a>) {
+    fn apply<'a>(&self, value: &'a RawValue, to: &mut Record<'a>, time_formats: &[TimeFormat]) {
         match self {
             Self::Time(preparse) => {
                 let s = value.get();
                 let s = if s.as_bytes()[0] == b'"' { &s[1..s.len() - 1] } else { s };
                 let ts = Timestamp::new(s, None);
-                if *preparse {
+                if let Some(parsed) = time_formats.iter().find_map(|format| format.parse(s)) {
+                    to.ts = Some(Timestamp::new(s, Some(Some(parsed))));
+                } else if *preparse {
                     to.ts = Some(Timestamp::new(ts.raw(), Some(ts.parse())));
                 } else {
                     to.ts = Some(ts);
                 }
             }
-            Self::Level(values) => {
-                to.level = json::from_str(value.get())
-                    .ok()
-                    .and_then(|x: &'a str| values.get(x).cloned());
+            Self::Level(mapping) => {
+                to.level = match json::from_str::<&str>(value.get()) {
+                    Ok(text) => mapping.get(text),
+                    Err(_) if ValueKind::of(value) == ValueKind::Number => mapping.get(value.get()),
+                    Err(_) => None,
+                };
             }
             Self::Logger => to.logger = json::from_str(value.get()).ok(),
             Self::Message => to.message = Some(value),
-            Self::Caller => to.caller = json::from_str(value.get()).ok(),
+            Self::Caller | Self::CallerFile | Self::CallerLine => {}
         }
     }
 
@@ -279,6 +544,8 @@ impl FieldSettings {
             Self::Logger => FieldKind::Logger,
             Self::Message => FieldKind::Message,
             Self::Caller => FieldKind::Caller,
+            Self::CallerFile => FieldKind::CallerFile,
+            Self::CallerLine => FieldKind::CallerLine,
         }
     }
 }
@@ -294,6 +561,10 @@ impl Parser {
         Self { settings }
     }
 
+    pub fn with_time_formats(self, formats: Vec<TimeFormat>) -> Self {
+        Self::new(self.settings.with_time_formats(formats))
+    }
+
     pub fn parse<'a>(&self, record: RawRecord<'a>) -> Record<'a> {
         let fields = record.fields();
         let count = fields.size_hint().1.unwrap_or(0);
@@ -303,6 +574,11 @@ impl Parser {
 
         record
     }
+
+    /// Parses a JSON object to a record, entry point for fuzzing.
+    pub fn parse_slice<'a>(&self, data: &'a [u8]) -> Result<Record<'a>> {
+        Ok(self.parse(json::from_slice(data)?))
+    }
 }
 
 // ---
@@ -417,21 +693,219 @@ impl<'a> KeyMatcher<'a> {
 
 // ---
 
+/// Folding of text applied to both filter values and field values before matching.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TextFolding {
+    pub ignore_case: bool,
+    pub fold_diacritics: bool,
+}
+
+impl TextFolding {
+    pub fn is_none(&self) -> bool {
+        !self.ignore_case && !self.fold_diacritics
+    }
+
+    /// Folds the text using Unicode case folding and removal of diacritical marks, as configured.
+    pub fn apply<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut text = Cow::Borrowed(text);
+        if self.fold_diacritics && !text.is_ascii() {
+            text = Cow::Owned(text.nfd().filter(|c| !is_combining_mark(*c)).nfc().collect());
+        }
+        if self.ignore_case {
+            text = if text.is_ascii() {
+                Cow::Owned(text.to_ascii_lowercase())
+            } else {
+                Cow::Owned(caseless::default_case_fold_str(&text))
+            };
+        }
+        text
+    }
+
+    fn without_case(self) -> Self {
+        Self {
+            ignore_case: false,
+            ..self
+        }
+    }
+}
+
+// ---
+
+/// Kind of a JSON value.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ValueKind {
+    Null,
+    Boolean,
+    Number,
+    String,
+    Object,
+    Array,
+}
+
+impl ValueKind {
+    /// Returns kind of the raw JSON value.
+    pub fn of(value: &RawValue) -> Self {
+        match value.get().as_bytes().first() {
+            Some(b'"') => Self::String,
+            Some(b'{') => Self::Object,
+            Some(b'[') => Self::Array,
+            Some(b't' | b'f') => Self::Boolean,
+            Some(b'n') => Self::Null,
+            _ => Self::Number,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Null => "null",
+            Self::Boolean => "boolean",
+            Self::Number => "number",
+            Self::String => "string",
+            Self::Object => "object",
+            Self::Array => "array",
+        }
+    }
+}
+
+// ---
+
+/// Value of an exact match filter like `key=value`, defining how it is compared with values of different kinds.
+///
+/// - `null`, `true` and `false` are null and boolean literals, a valid JSON number is a number literal,
+///   a value enclosed in double quotes is a string literal using JSON escapes, and anything else is a string literal as is.
+/// - Numbers are compared by their numeric value, so `code=200` matches `200`, `200.0` and `2e2`.
+/// - By default, values of other kinds are compared by their text, so `code=200` matches both `200` and `"200"`,
+///   `ok=true` matches both `true` and `"true"`, and a number literal also matches a string containing an equal number.
+/// - With strict typing, only values of the same kind are compared, and meeting a value of another kind is a type mismatch
+///   reported as an error, so `code=200` matches only numbers and `code="200"` matches only strings.
+///
+/// Substring, regular expression, glob, prefix and network filters always match text of values regardless of their kind.
+#[derive(Debug)]
+pub enum Literal {
+    Null,
+    Boolean(bool),
+    Number(f64, String),
+    String(String),
+}
+
+impl Literal {
+    fn parse(value: &str) -> Self {
+        match value {
+            "null" => Self::Null,
+            "true" => Self::Boolean(true),
+            "false" => Self::Boolean(false),
+            _ => {
+                if let Some(number) = json::from_str::<json::Number>(value).ok().and_then(|x| x.as_f64()) {
+                    Self::Number(number, value.into())
+                } else if let Some(text) = Some(value)
+                    .filter(|x| x.starts_with('"'))
+                    .and_then(|x| json::from_str(x).ok())
+                {
+                    Self::String(text)
+                } else {
+                    Self::String(value.into())
+                }
+            }
+        }
+    }
+
+    pub fn kind(&self) -> ValueKind {
+        match self {
+            Self::Null => ValueKind::Null,
+            Self::Boolean(_) => ValueKind::Boolean,
+            Self::Number(..) => ValueKind::Number,
+            Self::String(_) => ValueKind::String,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        match self {
+            Self::Null => "null",
+            Self::Boolean(true) => "true",
+            Self::Boolean(false) => "false",
+            Self::Number(_, text) => text,
+            Self::String(text) => text,
+        }
+    }
+
+    /// Compares the literal with a value of the given kind and text, returns None if kinds differ with strict typing.
+    fn compare(&self, kind: ValueKind, text: &str, strict: bool) -> Option<bool> {
+        if strict && kind != self.kind() {
+            return None;
+        }
+        Some(match self {
+            Self::Number(number, _) if kind == ValueKind::Number || kind == ValueKind::String => {
+                text == self.text() || text.parse::<f64>().map_or(false, |x| x == *number)
+            }
+            _ => text == self.text(),
+        })
+    }
+}
+
+// ---
+
 #[derive(Debug)]
 pub enum ValueMatchPolicy {
-    Exact(String),
+    Exact(Literal),
     SubString(String),
     RegularExpression(Regex),
+    Network(Vec<IpNet>),
+    Glob(WildMatch),
+    Prefix(String),
 }
 
 impl ValueMatchPolicy {
     fn matches(&self, subject: &str) -> bool {
         match self {
-            Self::Exact(pattern) => subject == pattern,
+            Self::Exact(literal) => subject == literal.text(),
             Self::SubString(pattern) => subject.contains(pattern),
             Self::RegularExpression(pattern) => pattern.is_match(subject),
+            Self::Glob(pattern) => pattern.matches(subject),
+            Self::Prefix(pattern) => subject.starts_with(pattern),
+            Self::Network(networks) => match subject.parse::<IpAddr>() {
+                Ok(addr) => networks.iter().any(|network| network.contains(&addr)),
+                Err(_) => false,
+            },
+        }
+    }
+
+    fn subject_folding(&self, folding: TextFolding) -> TextFolding {
+        match self {
+            Self::RegularExpression(_) => folding.without_case(),
+            Self::Network(_) => TextFolding::default(),
+            _ => folding,
         }
     }
+
+    /// Parses a value of `=` or `~=` operator, with `~=` a glob pattern may match any part of the value
+    /// and `prefix:` is not recognized, so the value keeps being matched as a substring.
+    fn parse_plain(value: &str, substring: bool) -> Self {
+        if let Some(pattern) = value.strip_prefix("glob:") {
+            if substring {
+                Self::Glob(WildMatch::new(&format!("*{}*", pattern)))
+            } else {
+                Self::Glob(WildMatch::new(pattern))
+            }
+        } else if substring {
+            Self::SubString(value.into())
+        } else if let Some(pattern) = value.strip_prefix("prefix:") {
+            Self::Prefix(pattern.into())
+        } else {
+            Self::Exact(Literal::parse(value))
+        }
+    }
+
+    fn parse_networks(value: &str) -> Result<Self> {
+        let mut networks = Vec::new();
+        for item in value.split(',').map(|item| item.trim()) {
+            networks.push(match (item.parse::<IpNet>(), item.parse::<IpAddr>()) {
+                (Ok(network), _) => network,
+                (_, Ok(addr)) => addr.into(),
+                _ => return Err(Error::WrongIpNetwork(item.into())),
+            });
+        }
+        Ok(Self::Network(networks))
+    }
 }
 
 // ---
@@ -466,21 +940,39 @@ pub struct FieldFilter {
     match_policy: ValueMatchPolicy,
     op: UnaryBoolOp,
     flat_key: bool,
+    folding: TextFolding,
+    strict: bool,
+    mismatch: OnceCell<(ValueKind, String)>,
+    slow: AtomicBool,
+    literal: Option<String>,
 }
 
 impl FieldFilter {
-    fn parse(text: &str) -> Result<Self> {
-        let parse = |key, value| {
-            let (key, match_policy, op) = Self::parse_mp_op(key, value)?;
+    fn parse(text: &str, folding: TextFolding) -> Result<Self> {
+        let new = |key: &str, match_policy: ValueMatchPolicy, op| {
             let flat_key = key.as_bytes().iter().position(|&x| x == b'.').is_none();
-            Ok(Self {
+            let literal = Self::literal(key, &match_policy, op, folding);
+            Self {
+                literal,
                 key: key.into(),
+                folding: match_policy.subject_folding(folding),
                 match_policy,
                 op,
                 flat_key,
-            })
+                strict: false,
+                mismatch: OnceCell::new(),
+                slow: AtomicBool::new(false),
+            }
+        };
+        let parse = |key, value| {
+            let (key, match_policy, op) = Self::parse_mp_op(key, value, folding)?;
+            Ok(new(key, match_policy, op))
         };
 
+        if let Some((key, op, value)) = Self::split_network_op(text) {
+            return Ok(new(key, ValueMatchPolicy::parse_networks(value)?, op));
+        }
+
         if let Some(index) = text.find('=') {
             return parse(&text[0..index], &text[index + 1..]);
         }
@@ -492,7 +984,60 @@ impl FieldFilter {
         Err(Error::WrongFieldFilter(text.into()))
     }
 
-    fn parse_mp_op<'k>(key: &'k str, value: &str) -> Result<(&'k str, ValueMatchPolicy, UnaryBoolOp)> {
+    /// Returns the longest text a raw line must contain for a value in it to match, see [Prefilter](crate::prefilter::Prefilter).
+    fn literal(key: &str, policy: &ValueMatchPolicy, op: UnaryBoolOp, folding: TextFolding) -> Option<String> {
+        if !matches!(op, UnaryBoolOp::None) || !folding.is_none() || key == "caller" {
+            return None;
+        }
+        let text = match policy {
+            ValueMatchPolicy::Exact(Literal::String(text))
+            | ValueMatchPolicy::SubString(text)
+            | ValueMatchPolicy::Prefix(text) => Cow::Borrowed(text.as_str()),
+            ValueMatchPolicy::RegularExpression(pattern) => Cow::Owned(prefilter::regex_literal(pattern.as_str())?),
+            _ => return None,
+        };
+        prefilter::searchable(&text).map(|text| text.into())
+    }
+
+    /// Returns a pattern finding the parts of values the filter matches, if it matches values by their text.
+    fn highlight(&self) -> Option<Regex> {
+        // offsets in values with folded diacritics do not correspond to offsets in the original values
+        if !matches!(self.op, UnaryBoolOp::None) || self.folding.fold_diacritics {
+            return None;
+        }
+        let pattern = match &self.match_policy {
+            ValueMatchPolicy::RegularExpression(pattern) => return Some(pattern.clone()),
+            ValueMatchPolicy::SubString(text) => regex::escape(text),
+            ValueMatchPolicy::Prefix(text) => format!("^{}", regex::escape(text)),
+            ValueMatchPolicy::Exact(Literal::String(text)) => format!("^{}$", regex::escape(text)),
+            _ => return None,
+        };
+        RegexBuilder::new(&pattern)
+            .case_insensitive(self.folding.ignore_case)
+            .build()
+            .ok()
+    }
+
+    fn split_network_op(text: &str) -> Option<(&str, UnaryBoolOp, &str)> {
+        let (key, rest) = text.trim().split_once(char::is_whitespace)?;
+        if key.contains(['=', ':']) {
+            return None;
+        }
+        let rest = rest.trim_start();
+        if let Some(value) = rest.strip_prefix("in ") {
+            Some((key, UnaryBoolOp::None, value.trim()))
+        } else if let Some(value) = rest.strip_prefix("!in ") {
+            Some((key, UnaryBoolOp::Negate, value.trim()))
+        } else {
+            None
+        }
+    }
+
+    fn parse_mp_op<'k>(
+        key: &'k str,
+        value: &str,
+        folding: TextFolding,
+    ) -> Result<(&'k str, ValueMatchPolicy, UnaryBoolOp)> {
         let key_op = |key: &'k str| {
             if let Some(key) = key.strip_suffix('!') {
                 (key, UnaryBoolOp::Negate)
@@ -503,14 +1048,26 @@ impl FieldFilter {
         Ok(if let Some(key) = key.strip_suffix('~') {
             if let Some(key) = key.strip_suffix('~') {
                 let (key, op) = key_op(key);
-                (key, ValueMatchPolicy::RegularExpression(value.parse()?), op)
+                let pattern = RegexBuilder::new(&folding.without_case().apply(value))
+                    .case_insensitive(folding.ignore_case)
+                    .size_limit(REGEX_SIZE_LIMIT)
+                    .dfa_size_limit(REGEX_DFA_SIZE_LIMIT)
+                    .build()
+                    .map_err(|err| match err {
+                        regex::Error::CompiledTooBig(limit) => Error::RegexTooComplex {
+                            pattern: value.into(),
+                            limit,
+                        },
+                        err => err.into(),
+                    })?;
+                (key, ValueMatchPolicy::RegularExpression(pattern), op)
             } else {
                 let (key, op) = key_op(key);
-                (key, ValueMatchPolicy::SubString(value.into()), op)
+                (key, ValueMatchPolicy::parse_plain(&folding.apply(value), true), op)
             }
         } else {
             let (key, op) = key_op(key);
-            (key, ValueMatchPolicy::Exact(value.into()), op)
+            (key, ValueMatchPolicy::parse_plain(&folding.apply(value), false), op)
         })
     }
 
@@ -522,25 +1079,61 @@ impl FieldFilter {
         KeyMatcher::new(&self.key).match_key(key)
     }
 
-    fn match_value(&self, value: Option<&str>, escaped: bool) -> bool {
-        let apply = |value| self.op.apply(self.match_policy.matches(value));
+    fn match_value(&self, value: Option<&str>, escaped: bool, strict: bool) -> bool {
         if let Some(value) = value {
             if escaped {
                 if let Some(value) = json::from_str::<&str>(value).ok() {
-                    apply(value)
+                    self.match_text(ValueKind::String, value, strict)
                 } else if let Some(value) = json::from_str::<String>(value).ok() {
-                    apply(&value)
+                    self.match_text(ValueKind::String, &value, strict)
                 } else {
                     false
                 }
             } else {
-                apply(value)
+                self.match_text(ValueKind::String, value, strict)
             }
         } else {
             false
         }
     }
 
+    fn match_raw_value(&self, value: &RawValue) -> bool {
+        match ValueKind::of(value) {
+            ValueKind::String => self.match_value(Some(value.get()), true, self.strict),
+            kind => self.match_text(kind, value.get(), self.strict),
+        }
+    }
+
+    fn match_text(&self, kind: ValueKind, text: &str, strict: bool) -> bool {
+        let text = self.folding.apply(text);
+        let matched = match &self.match_policy {
+            ValueMatchPolicy::Exact(literal) => match literal.compare(kind, &text, strict) {
+                Some(matched) => matched,
+                None => {
+                    self.mismatch.get_or_init(|| (kind, text.to_string()));
+                    return false;
+                }
+            },
+            ValueMatchPolicy::RegularExpression(pattern) => {
+                let start = Instant::now();
+                let matched = pattern.is_match(&text);
+                let elapsed = start.elapsed();
+                if elapsed >= SLOW_MATCH_THRESHOLD && !self.slow.swap(true, Ordering::Relaxed) {
+                    diagnostics::warning(format_args!(
+                        "regular expression {:?} of filter on field {:?} took {:?} to match a value of {} bytes, consider simplifying it",
+                        pattern.as_str(),
+                        self.key,
+                        elapsed,
+                        text.len(),
+                    ));
+                }
+                matched
+            }
+            policy => policy.matches(&text),
+        };
+        self.op.apply(matched)
+    }
+
     fn match_value_partial(&self, subkey: KeyMatcher, value: &RawValue) -> bool {
         let bytes = value.get().as_bytes();
         if bytes[0] != b'{' {
@@ -554,7 +1147,7 @@ impl FieldFilter {
                     continue;
                 }
                 Some(KeyMatch::Full) => {
-                    return self.match_value(Some(v.get()), v.get().starts_with('"'));
+                    return self.match_raw_value(*v);
                 }
                 Some(KeyMatch::Partial(subkey)) => {
                     return self.match_value_partial(subkey, *v);
@@ -572,12 +1165,112 @@ pub struct FieldFilterSet(Vec<FieldFilter>);
 
 impl FieldFilterSet {
     pub fn new<T: AsRef<str>, I: IntoIterator<Item = T>>(items: I) -> Result<Self> {
+        Self::new_folded(items, TextFolding::default())
+    }
+
+    pub fn new_folded<T: AsRef<str>, I: IntoIterator<Item = T>>(items: I, folding: TextFolding) -> Result<Self> {
         let mut fields = Vec::new();
         for i in items {
-            fields.push(FieldFilter::parse(i.as_ref())?);
+            fields.push(FieldFilter::parse(i.as_ref(), folding)?);
         }
         Ok(FieldFilterSet(fields))
     }
+
+    /// Returns texts raw lines must contain to match the filters, see [Prefilter](crate::prefilter::Prefilter).
+    pub(crate) fn literals(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().filter_map(|field| field.literal.as_deref())
+    }
+
+    /// Returns keys of the filters along with patterns finding the parts of values they match,
+    /// see [Highlighter](crate::highlight::Highlighter).
+    pub(crate) fn highlights(&self) -> impl Iterator<Item = (&str, Regex)> {
+        self.0.iter().filter_map(|field| Some((field.key.as_str(), field.highlight()?)))
+    }
+
+    /// Makes exact match filters compare values of the same kind only, see [`Literal`] for details.
+    pub fn with_strict_types(mut self, strict: bool) -> Self {
+        for field in self.0.iter_mut() {
+            field.strict = strict;
+        }
+        self
+    }
+
+    /// Returns an error describing the first value met by a filter with strict typing which kind differs from the filter value.
+    pub fn type_mismatch(&self) -> Option<Error> {
+        self.0.iter().find_map(|field| {
+            let (kind, value) = field.mismatch.get()?;
+            let expected = match &field.match_policy {
+                ValueMatchPolicy::Exact(literal) => literal.kind(),
+                _ => return None,
+            };
+            Some(Error::TypeMismatch {
+                key: field.key.clone(),
+                expected: expected.name(),
+                actual: kind.name(),
+                value: value.clone(),
+            })
+        })
+    }
+}
+
+// ---
+
+/// Level thresholds of loggers, each applied to the logger and its descendants, see --logger option.
+#[derive(Debug, Default)]
+pub struct LoggerLevels(Vec<(String, Level)>);
+
+impl LoggerLevels {
+    /// Parses comma-separated list of thresholds in form `<logger>=<level>`, where logger `app.*` is the same as `app`
+    /// and `*` denotes the root logger matching any message.
+    pub fn new<T: AsRef<str>, I: IntoIterator<Item = T>>(items: I, aliases: &level::LevelAliases) -> Result<Self> {
+        let mut result = Self::default();
+        for item in items {
+            for rule in item.as_ref().split(',').map(|x| x.trim()).filter(|x| !x.is_empty()) {
+                let (logger, level) = rule
+                    .split_once('=')
+                    .ok_or_else(|| Error::WrongLoggerLevel(rule.into()))?;
+                let logger = match logger.trim() {
+                    "*" => "",
+                    logger => logger.strip_suffix(".*").unwrap_or(logger),
+                };
+                if logger.contains('*') {
+                    return Err(Error::WrongLoggerLevel(rule.into()));
+                }
+                result.insert(logger, Level::parse_relaxed(level.trim(), aliases)?);
+            }
+        }
+        Ok(result)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the threshold of the most specific matching logger.
+    pub fn level(&self, logger: Option<&str>) -> Option<Level> {
+        if self.0.is_empty() {
+            return None;
+        }
+        let logger = logger.unwrap_or_default();
+        self.0
+            .iter()
+            .find(|(prefix, _)| {
+                prefix.is_empty()
+                    || (logger.starts_with(prefix.as_str())
+                        && matches!(logger.as_bytes().get(prefix.len()), None | Some(b'.' | b':' | b'/')))
+            })
+            .map(|(_, level)| *level)
+    }
+
+    fn insert(&mut self, logger: &str, level: Level) {
+        match self.0.iter_mut().find(|(prefix, _)| prefix == logger) {
+            Some(rule) => rule.1 = level,
+            None => {
+                self.0.push((logger.into(), level));
+                self.0.sort_by(|a, b| b.0.len().cmp(&a.0.len()));
+            }
+        }
+    }
 }
 
 // ---
@@ -586,13 +1279,26 @@ impl FieldFilterSet {
 pub struct Filter {
     pub fields: FieldFilterSet,
     pub level: Option<Level>,
+    pub loggers: LoggerLevels,
     pub since: Option<DateTime<Utc>>,
     pub until: Option<DateTime<Utc>>,
+    pub query: Option<Query>,
 }
 
 impl Filter {
     pub fn is_empty(&self) -> bool {
-        self.fields.0.is_empty() && self.level.is_none() && self.since.is_none() && self.until.is_none()
+        self.fields.0.is_empty()
+            && self.level.is_none()
+            && self.loggers.is_empty()
+            && self.since.is_none()
+            && self.until.is_none()
+            && self.query.is_none()
+    }
+
+    /// Returns the most verbose level a message may have to pass the level thresholds, if it is limited for all loggers.
+    pub fn level_bound(&self) -> Option<Level> {
+        let default = self.loggers.level(None).or(self.level)?;
+        Some(self.loggers.0.iter().map(|(_, level)| *level).fold(default, max))
     }
 }
 
@@ -689,3 +1395,343 @@ impl<'de: 'a, 'a, const N: usize> Deserialize<'de> for Array<'a, N> {
 
 const RECORD_EXTRA_CAPACITY: usize = 32;
 const RAW_RECORD_FIELDS_CAPACITY: usize = RECORD_EXTRA_CAPACITY + 8;
+
+/// Maximum compiled size of a regular expression of a filter.
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+/// Maximum size of the lazy DFA cache of a regular expression of a filter.
+const REGEX_DFA_SIZE_LIMIT: usize = 8 << 20;
+/// Duration of matching a single value by a regular expression after which a warning is shown.
+const SLOW_MATCH_THRESHOLD: Duration = Duration::from_millis(100);
+
+// ---
+
+fn parse_number(value: &RawValue) -> Option<f64> {
+    json::from_str::<f64>(value.get())
+        .ok()
+        .or_else(|| json::from_str::<&str>(value.get()).ok()?.trim().parse().ok())
+}
+
+fn nested_value<'a>(subkey: KeyMatcher, value: &'a RawValue) -> Option<&'a RawValue> {
+    if !value.get().starts_with('{') {
+        return None;
+    }
+    let object = json::from_str::<Object>(value.get()).ok()?;
+    for (k, v) in object.fields.iter() {
+        match subkey.match_key(k) {
+            None => continue,
+            Some(KeyMatch::Full) => return Some(v),
+            Some(KeyMatch::Partial(subkey)) => return nested_value(subkey, v),
+        }
+    }
+    None
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::iter::empty;
+
+    #[test]
+    fn test_regex_limits() {
+        let folding = TextFolding::default();
+        assert!(matches!(
+            FieldFilter::parse("msg~~=a{1000}{1000}", folding),
+            Err(Error::RegexTooComplex { .. })
+        ));
+        let filter = FieldFilter::parse("msg~~=^(a+)+$", folding).unwrap();
+        assert!(!filter.match_value(Some(&format!("\"{}b\"", "a".repeat(64))), true, false));
+        assert!(!filter.slow.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_text_folding() {
+        let folding = TextFolding {
+            ignore_case: true,
+            fold_diacritics: true,
+        };
+        assert_eq!(folding.apply("Café"), "cafe");
+        assert_eq!(folding.apply("STRASSE"), folding.apply("straße"));
+        assert_eq!(TextFolding::default().apply("Café"), "Café");
+
+        let filter = FieldFilter::parse("msg~~=^CAFÉ", folding).unwrap();
+        assert!(filter.match_value(Some("\"café au lait\""), true, false));
+        assert!(!filter.match_value(Some("\"tea\""), true, false));
+    }
+
+    #[test]
+    fn test_parse_slice() {
+        let settings = crate::settings::Settings::default();
+        let parser = Parser::new(ParserSettings::new(&settings.fields.predefined, empty(), false));
+        let record = parser.parse_slice(br#"{"ts":"","level":"info","msg":"hi"}"#).unwrap();
+        assert_eq!(record.level, Some(Level::Info));
+        assert_eq!(record.ts.map(|ts| ts.raw()), Some(""));
+        assert!(matches!(parser.parse_slice(b"[1]"), Err(Error::JsonParseError(_))));
+        assert!(matches!(parser.parse_slice(b"{\"a\":"), Err(Error::JsonParseError(_))));
+    }
+
+    #[test]
+    fn test_custom_time() {
+        let settings = crate::settings::Settings::default();
+        let format = TimeFormat::try_from("%d/%b/%Y:%H:%M:%S %z".to_owned()).unwrap();
+        let parser = Parser::new(
+            ParserSettings::new(&settings.fields.predefined, empty(), false).with_time_formats(vec![format]),
+        );
+        let unix = |data: &str| {
+            parser
+                .parse_slice(data.as_bytes())
+                .unwrap()
+                .ts
+                .and_then(|ts| ts.unix_utc())
+        };
+        assert_eq!(
+            unix(r#"{"@timestamp":"10/Oct/2000:13:55:36 -0700","msg":"hi"}"#),
+            Some((971211336, 0))
+        );
+        assert_eq!(
+            unix(r#"{"@timestamp":971211336250,"msg":"hi"}"#),
+            Some((971211336, 250000000))
+        );
+        assert_eq!(
+            unix(r#"{"ts":971211336.5,"@timestamp":1,"msg":"hi"}"#),
+            Some((971211336, 500000000))
+        );
+    }
+
+    #[test]
+    fn test_typed_comparison() {
+        let settings = crate::settings::Settings::default();
+        let parser = Parser::new(ParserSettings::new(&settings.fields.predefined, empty(), false));
+        let data = r#"{"msg":"404","logger":"1","code":200,"text":"200","ok":true,"none":null,"obj":{"a":1}}"#;
+        let record = parser.parse(json::from_str::<RawRecord>(data).unwrap());
+        let matches = |filter: &str, strict: bool| {
+            let filter = Filter {
+                fields: FieldFilterSet::new([filter]).unwrap().with_strict_types(strict),
+                ..Default::default()
+            };
+            (record.matches(&filter), filter.fields.type_mismatch().is_some())
+        };
+
+        assert_eq!(matches("code=200", false), (true, false));
+        assert_eq!(matches("code=2e2", false), (true, false));
+        assert_eq!(matches("code=200.0", true), (true, false));
+        assert_eq!(matches("code=201", true), (false, false));
+        assert_eq!(matches("text=200.0", false), (true, false));
+        assert_eq!(matches(r#"code="200""#, false), (true, false));
+        assert_eq!(matches("ok=true", false), (true, false));
+        assert_eq!(matches("none=null", true), (true, false));
+        assert_eq!(matches("none!=null", true), (false, false));
+        assert_eq!(matches("obj.a=1", true), (true, false));
+
+        assert_eq!(matches("text=200", true), (false, true));
+        assert_eq!(matches(r#"code="200""#, true), (false, true));
+        assert_eq!(matches(r#"text="200""#, true), (true, false));
+        assert_eq!(matches("ok=yes", true), (false, true));
+        assert_eq!(matches("code~=20", true), (true, false));
+        assert_eq!(matches("missing=1", true), (false, false));
+
+        assert_eq!(matches("msg=404", true), (true, false));
+        assert_eq!(matches("msg=true", true), (false, false));
+        assert_eq!(matches("logger=1.0", true), (true, false));
+    }
+
+    #[test]
+    fn test_value_matchers() {
+        let settings = crate::settings::Settings::default();
+        let parser = Parser::new(ParserSettings::new(&settings.fields.predefined, empty(), false));
+        let data = r#"{"msg":"m","path":"/api/v1/users","service":"checkout-eu"}"#;
+        let record = parser.parse(json::from_str::<RawRecord>(data).unwrap());
+        let matches = |filter: &str| {
+            let filter = Filter {
+                fields: FieldFilterSet::new([filter]).unwrap(),
+                ..Default::default()
+            };
+            record.matches(&filter)
+        };
+
+        assert!(matches("path=glob:/api/v1/*"));
+        assert!(matches("path=glob:/api/v?/users"));
+        assert!(!matches("path=glob:/api/v2/*"));
+        assert!(!matches("path=glob:v1/*"));
+        assert!(matches("path~=glob:v1/*"));
+        assert!(!matches("path~=glob:v2/*"));
+        assert!(matches("path!=glob:/api/v2/*"));
+
+        assert!(matches("service=prefix:checkout-"));
+        assert!(!matches("service=prefix:eu"));
+        assert!(matches("service!=prefix:billing-"));
+        assert!(!matches("service~=prefix:checkout-"));
+        assert!(matches("service~=checkout-"));
+    }
+
+    #[test]
+    fn test_networks() {
+        let settings = crate::settings::Settings::default();
+        let parser = Parser::new(ParserSettings::new(&settings.fields.predefined, empty(), false));
+        let data = r#"{"msg":"m","service":"checkout-eu","addr":"10.1.2.3","ip6":"fd00::1"}"#;
+        let record = parser.parse(json::from_str::<RawRecord>(data).unwrap());
+        let matches = |filter: &str| {
+            let filter = Filter {
+                fields: FieldFilterSet::new([filter]).unwrap(),
+                ..Default::default()
+            };
+            record.matches(&filter)
+        };
+
+        assert!(matches("addr in 10.0.0.0/8"));
+        assert!(matches("addr in 192.168.0.0/16, 10.1.2.0/24"));
+        assert!(matches("addr in 10.1.2.3"));
+        assert!(!matches("addr in 10.1.3.0/24"));
+        assert!(matches("addr !in 192.168.0.0/16"));
+        assert!(!matches("addr !in 10.0.0.0/8"));
+        assert!(matches("ip6 in fd00::/8"));
+        assert!(!matches("ip6 in 10.0.0.0/8"));
+        assert!(!matches("service in 10.0.0.0/8"));
+        assert!(matches!(
+            FieldFilter::parse("addr in 10.0.0.0/33", TextFolding::default()),
+            Err(Error::WrongIpNetwork(_))
+        ));
+
+        assert!(matches!(
+            FieldFilter::split_network_op(" addr  in 10.0.0.0/8 "),
+            Some(("addr", UnaryBoolOp::None, "10.0.0.0/8"))
+        ));
+        assert!(matches!(
+            FieldFilter::split_network_op("addr !in 10.0.0.0/8,::1"),
+            Some(("addr", UnaryBoolOp::Negate, "10.0.0.0/8,::1"))
+        ));
+        assert!(FieldFilter::split_network_op("msg=user in 10.0.0.0/8").is_none());
+        assert!(FieldFilter::split_network_op("msg:in 10.0.0.0/8").is_none());
+        assert!(FieldFilter::split_network_op("addr into 10.0.0.0/8").is_none());
+        assert!(FieldFilter::split_network_op("addr").is_none());
+    }
+
+    #[test]
+    fn test_caller() {
+        let mut settings = crate::settings::Settings::default();
+        let parser = Parser::new(ParserSettings::new(&settings.fields.predefined, empty(), false));
+        let parse = |data: &'static str| parser.parse(json::from_str::<RawRecord>(data).unwrap());
+
+        let record = parse(r#"{"caller":"main.go:42"}"#);
+        assert_eq!(record.caller, Some(Caller::new("main.go:42")));
+
+        // not claimed by default
+        let record = parse(r#"{"source":{"file":"main.go","line":42},"file":"app.py","line":7}"#);
+        assert_eq!(record.caller, None);
+        assert_eq!(record.fields().count(), 3);
+
+        // not recognized as a source location
+        let record = parse(r#"{"caller":42,"msg":"m"}"#);
+        assert_eq!(record.caller, None);
+        assert_eq!(record.fields().map(|(k, v)| (*k, v.get())).collect::<Vec<_>>(), vec![("caller", "42")]);
+
+        let predefined = &mut settings.fields.predefined;
+        predefined.caller.0.names.push("source".into());
+        predefined.caller_file.0.names.push("file".into());
+        predefined.caller_line.0.names.push("line".into());
+        let parser = Parser::new(ParserSettings::new(&settings.fields.predefined, empty(), false));
+        let parse = |data: &'static str| parser.parse(json::from_str::<RawRecord>(data).unwrap());
+
+        let record = parse(r#"{"source":{"function":"main.main","file":"main.go","line":42}}"#);
+        assert_eq!(record.caller, Some(Caller::new("main.go").with_line(Some("42"))));
+        assert_eq!(record.caller.unwrap().text(), "main.go:42");
+
+        let record = parse(r#"{"file":"app.py","msg":"m","line":"7"}"#);
+        assert_eq!(record.caller, Some(Caller::new("app.py").with_line(Some("7"))));
+        assert_eq!(record.fields().count(), 0);
+
+        let record = parse(r#"{"file":"upload.txt"}"#);
+        assert_eq!(record.caller, None);
+        assert_eq!(record.fields().map(|(k, _)| *k).collect::<Vec<_>>(), vec!["file"]);
+    }
+
+    #[test]
+    fn test_numeric_levels() {
+        let settings = crate::settings::Settings::default();
+        let parser = Parser::new(ParserSettings::new(&settings.fields.predefined, empty(), false));
+        let parse = |data: &'static str| parser.parse(json::from_str::<RawRecord>(data).unwrap()).level;
+
+        assert_eq!(parse(r#"{"level":30}"#), Some(Level::Info));
+        assert_eq!(parse(r#"{"level":45}"#), Some(Level::Warning));
+        assert_eq!(parse(r#"{"level":"50"}"#), Some(Level::Error));
+        assert_eq!(parse(r#"{"level":60}"#), Some(Level::Fatal));
+        assert_eq!(parse(r#"{"level":5}"#), None);
+        assert_eq!(parse(r#"{"level":"warn"}"#), Some(Level::Warning));
+        assert_eq!(parse(r#"{"PRIORITY":6}"#), Some(Level::Info));
+    }
+
+    #[test]
+    fn test_logger_levels() {
+        let aliases = crate::settings::Settings::default().level_aliases;
+        let loggers = LoggerLevels::new(["app.db.*=debug, app.*=info", "app.http=e"], &aliases).unwrap();
+        assert_eq!(loggers.level(Some("app.db")), Some(Level::Debug));
+        assert_eq!(loggers.level(Some("app.db.pool")), Some(Level::Debug));
+        assert_eq!(loggers.level(Some("app.dbx")), Some(Level::Info));
+        assert_eq!(loggers.level(Some("app.http")), Some(Level::Error));
+        assert_eq!(loggers.level(Some("other")), None);
+        assert_eq!(loggers.level(None), None);
+
+        let filter = Filter {
+            level: Some(Level::Warning),
+            loggers,
+            ..Default::default()
+        };
+        assert_eq!(filter.level_bound(), Some(Level::Debug));
+
+        let loggers = LoggerLevels::new(["*=warn"], &aliases).unwrap();
+        assert_eq!(loggers.level(Some("other")), Some(Level::Warning));
+        assert_eq!(loggers.level(None), Some(Level::Warning));
+
+        assert!(LoggerLevels::new(["app"], &aliases).is_err());
+        assert!(LoggerLevels::new(["app=verbose"], &aliases).is_err());
+    }
+
+    #[test]
+    fn test_duplicate_keys() {
+        let settings = crate::settings::Settings::default();
+        let padding = (0..RECORD_EXTRA_CAPACITY)
+            .map(|i| format!(r#""f{}":{},"#, i, i))
+            .collect::<String>();
+        let data = format!(
+            r#"{{"msg":"a","a":1,"b":2,{}"x":3,"msg":"b","a":4,"x":5}}"#,
+            padding
+        );
+        let parse = |policy: DuplicateKeys| {
+            let parser = Parser::new(
+                ParserSettings::new(&settings.fields.predefined, empty(), false).with_duplicate_keys(policy),
+            );
+            let record = parser.parse_slice(data.as_bytes()).unwrap();
+            let fields = record
+                .fields()
+                .filter(|(k, _)| !k.starts_with('f'))
+                .map(|(k, v)| format!("{}={}", k, v.get()))
+                .collect::<Vec<_>>();
+            (record.message.map(|m| m.get().to_owned()), fields)
+        };
+
+        // the last value of a predefined field is used and all values of other fields are kept by default
+        assert_eq!(settings.fields.duplicate_keys, DuplicateKeys::default());
+        let (message, fields) = parse(DuplicateKeys::default());
+        assert_eq!(message.as_deref(), Some(r#""b""#));
+        assert_eq!(fields, ["a=1", "b=2", "x=3", "a=4", "x=5"]);
+
+        let (message, fields) = parse(DuplicateKeys::FirstWins);
+        assert_eq!(message.as_deref(), Some(r#""a""#));
+        assert_eq!(fields, ["a=1", "b=2", "x=3"]);
+
+        let (message, fields) = parse(DuplicateKeys::LastWins);
+        assert_eq!(message.as_deref(), Some(r#""b""#));
+        assert_eq!(fields, ["a=4", "b=2", "x=5"]);
+
+        let (message, fields) = parse(DuplicateKeys::KeepBothWithSuffix);
+        assert_eq!(message.as_deref(), Some(r#""b""#));
+        assert_eq!(fields, ["a=1", "b=2", "x=3", "a=4", "x=5"]);
+
+        let (message, fields) = parse(DuplicateKeys::Warn);
+        assert_eq!(message.as_deref(), Some(r#""b""#));
+        assert_eq!(fields, ["a=4", "b=2", "x=5"]);
+        let warned = DUPLICATE_KEYS_WARNED.lock().unwrap();
+        assert!(warned.contains("msg") && warned.contains("a") && warned.contains("x"));
+    }
+}
@@ -0,0 +1,62 @@
+// third-party imports
+use clap::ValueEnum;
+
+// local imports
+use crate::model::Record;
+
+// ---
+
+/// Returns the palette slot of the value out of `n` slots, which is the same for the same value in any run.
+pub fn slot(value: &str, n: usize) -> usize {
+    let hash = value
+        .bytes()
+        .fold(0xcbf29ce484222325u64, |h, b| (h ^ b as u64).wrapping_mul(0x100000001b3));
+    (hash % n.max(1) as u64) as usize
+}
+
+// ---
+
+/// Part of the formatted record colored by values of the field, see --color-by option.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ColorTarget {
+    /// Colored block at the start of each line.
+    #[default]
+    Gutter,
+    /// Value of the field itself.
+    Value,
+}
+
+/// Settings of coloring of records by values of a field, e.g. service name, see --color-by option.
+#[derive(Clone, Debug)]
+pub struct ColorBy {
+    /// Key of the top-level field which values define the colors.
+    pub key: String,
+    pub target: ColorTarget,
+}
+
+impl ColorBy {
+    /// Returns the palette slot of the record out of `n` slots, or None if the record has no such field.
+    pub(crate) fn slot(&self, rec: &Record, n: usize) -> Option<usize> {
+        let (_, value) = rec.fields().find(|(k, _)| *k == self.key)?;
+        Some(slot(value.get(), n))
+    }
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slot() {
+        assert_eq!(slot(r#""api""#, 12), slot(r#""api""#, 12));
+        assert!(slot(r#""api""#, 12) < 12);
+        let slots = [r#""api""#, r#""auth""#, r#""billing""#, r#""search""#]
+            .iter()
+            .map(|value| slot(value, 1024))
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(slots.len(), 4);
+        assert_eq!(slot("x", 0), 0);
+    }
+}
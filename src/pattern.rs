@@ -0,0 +1,95 @@
+// std imports
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// third-party imports
+use wildmatch::WildMatch;
+
+// ---
+
+/// Pattern matching files of a directory, such as `/var/log/app/*.log`.
+///
+/// Wildcards `*` and `?` are supported in the file name only.
+#[derive(Clone, Debug)]
+pub struct FilePattern {
+    dir: PathBuf,
+    name: String,
+    matcher: WildMatch,
+}
+
+impl FilePattern {
+    /// Recognizes a path of an existing directory, matching all its files, or a path with wildcards in the file name.
+    pub fn parse(path: &Path) -> Option<Self> {
+        if path.is_dir() {
+            return Some(Self::new(path.into(), "*".into()));
+        }
+        let name = path.file_name()?.to_str()?;
+        if !name.contains(['*', '?']) {
+            return None;
+        }
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.into(),
+            _ => PathBuf::from("."),
+        };
+        Some(Self::new(dir, name.into()))
+    }
+
+    /// Returns directory containing the matched files.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the pattern as a path.
+    pub fn path(&self) -> PathBuf {
+        self.dir.join(&self.name)
+    }
+
+    /// Checks whether the file name matches the pattern.
+    pub fn matches(&self, name: &str) -> bool {
+        self.matcher.matches(name)
+    }
+
+    /// Lists files of the directory matching the pattern, sorted by path.
+    pub fn scan(&self) -> io::Result<Vec<PathBuf>> {
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let matched = entry.file_name().to_str().map(|name| self.matches(name)).unwrap_or(false);
+            if matched && entry.path().is_file() {
+                paths.push(entry.path());
+            }
+        }
+        paths.sort_unstable();
+        Ok(paths)
+    }
+
+    fn new(dir: PathBuf, name: String) -> Self {
+        Self {
+            matcher: WildMatch::new(&name),
+            dir,
+            name,
+        }
+    }
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let pattern = FilePattern::parse(Path::new("/var/log/app/*.log")).unwrap();
+        assert_eq!(pattern.dir(), Path::new("/var/log/app"));
+        assert!(pattern.matches("api.log"));
+        assert!(!pattern.matches("api.log.1"));
+
+        let pattern = FilePattern::parse(Path::new("app-?.log")).unwrap();
+        assert_eq!(pattern.dir(), Path::new("."));
+        assert!(pattern.matches("app-1.log"));
+
+        assert!(FilePattern::parse(Path::new("/var/log/app/api.log")).is_none());
+    }
+}
@@ -0,0 +1,330 @@
+// std imports
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader};
+use std::sync::Arc;
+
+// third-party imports
+use chrono::{DateTime, FixedOffset};
+use serde_json as json;
+
+// local imports
+use crate::datefmt::{DateTimeFormatter, LinuxDateFormat};
+use crate::error::Result;
+use crate::formatting::RecordFormatter;
+use crate::input::{InputReference, InputStream};
+use crate::logfmt;
+use crate::model::{Filter, Parser, ParserSettings, RawRecord};
+use crate::record::RecordData;
+use crate::settings::{InputFormat, Settings};
+use crate::syslog;
+use crate::text::TextFormat;
+use crate::theme::Theme;
+use crate::timezone::Tz;
+use crate::IncludeExcludeKeyFilter;
+
+// ---
+
+/// Parsing, filtering and formatting of records for using hl as a library.
+///
+/// The pipeline depends neither on command line options nor on the configuration files of the user,
+/// it starts with the embedded default settings and plain output without colors.
+///
+/// Records of an input are yielded in the order they appear in it, see [Pipeline::sorted] for records of several inputs
+/// in chronological order.
+///
+/// ```no_run
+/// use hl::{pipeline::Pipeline, Filter, Level};
+///
+/// let filter = Filter { level: Some(Level::Warning), ..Default::default() };
+/// let pipeline = Pipeline::new().with_filter(filter);
+/// for entry in pipeline.records(std::io::stdin().lock())? {
+///     let entry = entry?;
+///     println!("{:?} {}", entry.data.level, entry.text);
+/// }
+/// # Ok::<(), hl::error::Error>(())
+/// ```
+pub struct Pipeline {
+    settings: Settings,
+    filter: Filter,
+    fields: Arc<IncludeExcludeKeyFilter>,
+    theme: Arc<Theme>,
+    time_format: String,
+    time_zone: Tz,
+    input_format: InputFormat,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the settings, which also resets the time format and the time zone to the ones of the settings.
+    pub fn with_settings(mut self, settings: Settings) -> Self {
+        self.time_format = settings.time_format.clone();
+        self.time_zone = settings.time_zone;
+        self.settings = settings;
+        self
+    }
+
+    /// Sets the filter records have to match to be yielded, all records are yielded by default.
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Sets the filter of fields shown in formatted records, all fields are shown by default.
+    pub fn with_field_filter(mut self, fields: Arc<IncludeExcludeKeyFilter>) -> Self {
+        self.fields = fields;
+        self
+    }
+
+    /// Sets the theme of formatted records, see [Theme::embedded] and [Theme::load].
+    pub fn with_theme(mut self, theme: Arc<Theme>) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Sets the format of timestamps of formatted records in strftime-like notation, see --time-format option.
+    pub fn with_time_format(mut self, format: &str) -> Self {
+        self.time_format = format.into();
+        self
+    }
+
+    pub fn with_time_zone(mut self, tz: Tz) -> Self {
+        self.time_zone = tz;
+        self
+    }
+
+    pub fn with_input_format(mut self, format: InputFormat) -> Self {
+        self.input_format = format;
+        self
+    }
+
+    /// Returns the iterator over records of the input, lines without records are skipped.
+    pub fn records<R: BufRead>(&self, input: R) -> Result<Records<'_, R>> {
+        let fields = &self.settings.fields;
+        let parser = Parser::new(
+            ParserSettings::new(
+                &fields.predefined,
+                &fields.ignore,
+                self.filter.since.is_some() || self.filter.until.is_some(),
+            )
+            .with_duplicate_keys(fields.duplicate_keys)
+            .with_time_formats(self.settings.time_formats.clone()),
+        );
+        let formatter = RecordFormatter::new(
+            self.theme.clone(),
+            DateTimeFormatter::new(LinuxDateFormat::new(&self.time_format).compile(), self.time_zone),
+            false,
+            self.fields.clone(),
+            self.settings.formatting.clone(),
+        );
+        Ok(Records {
+            pipeline: self,
+            input,
+            parser,
+            formatter,
+            text_format: TextFormat::new(&self.settings.text_input, &self.settings.level_aliases)?,
+            now: syslog::reference_time(),
+            offset: 0,
+            line: Vec::new(),
+            normalized: Vec::new(),
+            buf: Vec::new(),
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Opens the input, decompressing it if needed, and returns the iterator over its records.
+    pub fn open(&self, input: &InputReference) -> Result<Records<'_, BufReader<InputStream>>> {
+        self.records(BufReader::new(input.open()?.stream))
+    }
+
+    /// Returns the records of the inputs in chronological order, the same way as --sort option orders them.
+    ///
+    /// A record without a timestamp follows the previous record of the same input, records preceding the first record
+    /// with a timestamp in an input are skipped. Unlike --sort option, all matching records are kept in memory.
+    pub fn sorted<I>(&self, inputs: I) -> Result<Vec<Entry>>
+    where
+        I: IntoIterator,
+        I::Item: BufRead,
+    {
+        let mut entries = Vec::new();
+        for input in inputs {
+            let mut time = None;
+            for entry in self.records(input)? {
+                let entry = entry?;
+                time = entry.data.time.or(time);
+                if let Some(time) = time {
+                    entries.push((time, entry));
+                }
+            }
+        }
+        entries.sort_by_key(|(time, _)| *time);
+        Ok(entries.into_iter().map(|(_, entry)| entry).collect())
+    }
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        let settings = Settings::default();
+        Self {
+            filter: Filter::default(),
+            fields: Arc::new(IncludeExcludeKeyFilter::default()),
+            theme: Arc::new(Theme::none()),
+            time_format: settings.time_format.clone(),
+            time_zone: settings.time_zone,
+            input_format: InputFormat::default(),
+            settings,
+        }
+    }
+}
+
+// ---
+
+/// Record yielded by [Pipeline], owning its data.
+#[derive(Clone, Debug)]
+pub struct Entry {
+    /// Parsed record, with its span being the byte range of the line of the input it was parsed from.
+    pub data: RecordData,
+    /// Record formatted according to the settings and the theme, without a trailing newline.
+    pub text: String,
+}
+
+/// Iterator over records of an input, see [Pipeline::records].
+pub struct Records<'a, R> {
+    pipeline: &'a Pipeline,
+    input: R,
+    parser: Parser,
+    formatter: RecordFormatter,
+    text_format: TextFormat,
+    now: DateTime<FixedOffset>,
+    /// Offset of the current line in the input.
+    offset: u64,
+    line: Vec<u8>,
+    normalized: Vec<u8>,
+    buf: Vec<u8>,
+    pending: VecDeque<Entry>,
+}
+
+impl<R: BufRead> Records<'_, R> {
+    /// Parses records of the current line, queueing the matching ones.
+    fn process(&mut self) {
+        let span = self.offset..self.offset + self.line.len() as u64;
+        self.offset = span.end;
+        let line = self.line.strip_suffix(b"\n").unwrap_or(&self.line);
+        if line.is_empty() {
+            return;
+        }
+        let data = logfmt::normalize_with(
+            self.pipeline.input_format,
+            &self.text_format,
+            self.now,
+            line,
+            &mut self.normalized,
+        );
+        let mut stream = json::Deserializer::from_slice(data).into_iter::<RawRecord>();
+        while let Some(Ok(record)) = stream.next() {
+            let record = self.parser.parse(record);
+            if record.matches(&self.pipeline.filter) {
+                self.buf.clear();
+                self.formatter.format_record(&mut self.buf, &record);
+                let text = self.buf.strip_suffix(b"\n").unwrap_or(&self.buf);
+                self.pending.push_back(Entry {
+                    data: RecordData::from(&record).with_span(span.clone()),
+                    text: String::from_utf8_lossy(text).into_owned(),
+                });
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Records<'_, R> {
+    type Item = Result<Entry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() {
+            self.line.clear();
+            match self.input.read_until(b'\n', &mut self.line) {
+                Ok(0) => return None,
+                Ok(_) => self.process(),
+                Err(err) => return Some(Err(err.into())),
+            }
+        }
+        self.pending.pop_front().map(Ok)
+    }
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::Level;
+
+    #[test]
+    fn test_pipeline() {
+        let input = concat!(
+            r#"{"ts":"2024-01-02T03:04:05Z","level":"info","msg":"started","port":8080}"#,
+            "\n\nnot a record\n",
+            "ts=2024-01-02T03:04:06Z level=error logger=db msg=\"query failed\" table=users\n",
+        );
+        let filter = Filter {
+            level: Some(Level::Error),
+            ..Default::default()
+        };
+        let pipeline = Pipeline::new()
+            .with_filter(filter)
+            .with_time_format("%H:%M:%S")
+            .with_time_zone(Tz::IANA(chrono_tz::UTC));
+        let entries = pipeline
+            .records(input.as_bytes())
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        let entry = &entries[0];
+        assert_eq!(entry.data.level, Some(Level::Error));
+        assert_eq!(entry.data.logger.as_deref(), Some("db"));
+        assert_eq!(entry.data.message.as_ref().map(|message| message.get()), Some(r#""query failed""#));
+        assert_eq!(entry.data.fields.len(), 1);
+        assert_eq!(entry.data.field("table").map(|value| value.get()), Some(r#""users""#));
+        assert_eq!(entry.data.time.map(|time| time.timestamp()), Some(1704164646));
+        assert_eq!(entry.data.span, Some(87..164));
+        assert!(
+            entry.text.starts_with("03:04:06 |ERR| db: query failed"),
+            "{}",
+            entry.text
+        );
+        assert!(!entry.text.ends_with('\n'));
+
+        let entries = Pipeline::new().records(input.as_bytes()).unwrap().count();
+        assert_eq!(entries, 2);
+    }
+
+    #[test]
+    fn test_sorted() {
+        let first = concat!(
+            "no timestamp yet\n",
+            r#"{"ts":"2024-01-02T03:04:01Z","msg":"a"}"#,
+            "\n",
+            r#"{"msg":"b"}"#,
+            "\n",
+            r#"{"ts":"2024-01-02T03:04:04Z","msg":"c"}"#,
+            "\n",
+        );
+        let second = concat!(
+            r#"{"msg":"x"}"#,
+            "\n",
+            r#"{"ts":"2024-01-02T03:04:02Z","msg":"d"}"#,
+            "\n",
+            r#"{"ts":"2024-01-02T03:04:03Z","msg":"e"}"#,
+            "\n",
+        );
+        let entries = Pipeline::new().sorted([first.as_bytes(), second.as_bytes()]).unwrap();
+        let messages = entries
+            .iter()
+            .map(|entry| entry.data.message.as_ref().unwrap().get())
+            .collect::<Vec<_>>();
+        assert_eq!(messages, [r#""a""#, r#""b""#, r#""d""#, r#""e""#, r#""c""#]);
+    }
+}
@@ -1,3 +1,7 @@
+// std imports
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+
 // third-party imports
 use crossbeam_queue::SegQueue;
 
@@ -172,3 +176,68 @@ where
         self.checkin(item)
     }
 }
+
+// ---
+
+/// Item checked out from a pool, checked in back to the pool when dropped.
+pub struct Pooled<T, P: Checkin<T>> {
+    item: Option<T>,
+    pool: Arc<P>,
+}
+
+impl<T, P: Pool<T>> Pooled<T, P> {
+    /// Checks out a new or recycled item from the given pool.
+    #[inline]
+    pub fn checkout(pool: &Arc<P>) -> Self {
+        Self {
+            item: Some(pool.checkout()),
+            pool: pool.clone(),
+        }
+    }
+}
+
+impl<T, P: Checkin<T>> Deref for Pooled<T, P> {
+    type Target = T;
+
+    #[inline(always)]
+    fn deref(&self) -> &T {
+        self.item.as_ref().unwrap()
+    }
+}
+
+impl<T, P: Checkin<T>> DerefMut for Pooled<T, P> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut T {
+        self.item.as_mut().unwrap()
+    }
+}
+
+impl<T, P: Checkin<T>> Drop for Pooled<T, P> {
+    #[inline]
+    fn drop(&mut self) {
+        if let Some(item) = self.item.take() {
+            self.pool.checkin(item)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pooled() {
+        let pool = Arc::new(SQPool::<Vec<u8>>::new());
+        let mut item = Pooled::checkout(&pool);
+        item.extend_from_slice(b"test");
+        let capacity = item.capacity();
+        drop(item);
+
+        let item = Pooled::checkout(&pool);
+        assert_eq!(&item[..], b"test");
+        assert_eq!(item.capacity(), capacity);
+        assert!(pool.recycled.is_empty());
+        drop(item);
+        assert_eq!(pool.recycled.len(), 1);
+    }
+}
@@ -0,0 +1,191 @@
+// third-party imports
+use aho_corasick::AhoCorasick;
+
+// local imports
+use crate::model::Filter;
+
+// ---
+
+/// Minimum length of a literal worth searching for, shorter ones are too common to skip anything.
+const MIN_LITERAL_LEN: usize = 3;
+
+/// Maximum number of literals a line is checked for.
+const MAX_LITERALS: usize = 64;
+
+/// Marker of a JSON unicode escape, which may hide any character of a literal.
+const UNICODE_ESCAPE: &str = "\\u";
+
+// ---
+
+/// Checks raw lines for literals required by the field filters to skip lines that cannot match them without parsing.
+///
+/// Each positive field filter comparing values with a text, like `key=value`, `key~=value` or `key~~=regex`,
+/// contributes the longest text that any matching value must contain, so a line missing any of them is skipped.
+/// Lines containing unicode escapes are never skipped, because escaped text cannot be found as is.
+pub struct Prefilter {
+    matcher: AhoCorasick,
+    escape: usize,
+    all: u64,
+}
+
+impl Prefilter {
+    /// Returns None if the filter has no literals to search for.
+    pub fn new(filter: &Filter) -> Option<Self> {
+        let mut literals: Vec<&str> = Vec::new();
+        for literal in filter.fields.literals() {
+            if !literals.contains(&literal) && literals.len() < MAX_LITERALS {
+                literals.push(literal);
+            }
+        }
+        if literals.is_empty() {
+            return None;
+        }
+        let escape = literals.len();
+        let matcher = AhoCorasick::new(literals.iter().copied().chain([UNICODE_ESCAPE])).ok()?;
+        Some(Self {
+            matcher,
+            escape,
+            all: u64::MAX >> (u64::BITS as usize - escape),
+        })
+    }
+
+    /// Returns false if the line cannot match the filter.
+    pub fn matches(&self, line: &[u8]) -> bool {
+        let mut found = 0u64;
+        for m in self.matcher.find_overlapping_iter(line) {
+            let id = m.pattern().as_usize();
+            if id == self.escape {
+                return true;
+            }
+            found |= 1 << id;
+            if found == self.all {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+// ---
+
+/// Returns the longest part of the text that can be found as is in a JSON, logfmt or syslog line containing the text,
+/// i.e. not containing characters that may be escaped.
+pub(crate) fn searchable(text: &str) -> Option<&str> {
+    text.split(|c: char| c == '"' || c == '\\' || c == '/' || c.is_control())
+        .max_by_key(|part| part.len())
+        .filter(|part| part.len() >= MIN_LITERAL_LEN)
+}
+
+/// Returns the longest literal text that any text matching the regular expression must contain, if it is easy to tell.
+pub(crate) fn regex_literal(pattern: &str) -> Option<String> {
+    if pattern.contains('|') || pattern.contains("(?") {
+        return None;
+    }
+    let mut best = String::new();
+    let mut run = String::new();
+    let mut chars = pattern.chars().peekable();
+    let end = |run: &mut String, best: &mut String| {
+        if run.len() > best.len() {
+            *best = std::mem::take(run);
+        } else {
+            run.clear();
+        }
+    };
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '\\' => match chars.next() {
+                Some(c) if !c.is_ascii_alphanumeric() => c,
+                // character classes, assertions and escapes of arbitrary characters are not followed further
+                _ => break,
+            },
+            '(' | '[' | '{' => {
+                end(&mut run, &mut best);
+                let close = match c {
+                    '(' => ')',
+                    '[' => ']',
+                    _ => '}',
+                };
+                let mut depth = 1;
+                let mut first = true;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => {
+                            chars.next();
+                        }
+                        _ if c == close && !(close == ']' && first) => {
+                            depth -= 1;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        _ if c == '(' && close == ')' => depth += 1,
+                        _ => {}
+                    }
+                    first = first && c == '^';
+                }
+                continue;
+            }
+            '.' | '^' | '$' | '*' | '+' | '?' | ')' | ']' | '}' => {
+                end(&mut run, &mut best);
+                continue;
+            }
+            c => c,
+        };
+        match chars.peek() {
+            Some('?' | '*' | '{') => end(&mut run, &mut best),
+            Some('+') => {
+                run.push(c);
+                end(&mut run, &mut best);
+            }
+            _ => run.push(c),
+        }
+    }
+    end(&mut run, &mut best);
+    Some(best).filter(|best| !best.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::FieldFilterSet;
+
+    fn prefilter(filters: &[&str]) -> Option<Prefilter> {
+        Prefilter::new(&Filter {
+            fields: FieldFilterSet::new(filters).unwrap(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_regex_literal() {
+        assert_eq!(regex_literal("timeout").as_deref(), Some("timeout"));
+        assert_eq!(regex_literal(r"^conn(ection)? refused: \d+ms$").as_deref(), Some(" refused: "));
+        assert_eq!(regex_literal(r"user-\w+@example\.com").as_deref(), Some("user-"));
+        assert_eq!(regex_literal(r"[a-z]+ab?cde{2}xyz").as_deref(), Some("xyz"));
+        assert_eq!(regex_literal(r"a+bcd").as_deref(), Some("bcd"));
+        assert_eq!(regex_literal(r"[]abcd]efg").as_deref(), Some("efg"));
+        assert_eq!(regex_literal("foo|bar"), None);
+        assert_eq!(regex_literal("(?i)foo"), None);
+        assert_eq!(regex_literal(".*"), None);
+    }
+
+    #[test]
+    fn test_searchable() {
+        assert_eq!(searchable("GET /api/users"), Some("users"));
+        assert_eq!(searchable("say \"hello\""), Some("hello"));
+        assert_eq!(searchable("a/b"), None);
+    }
+
+    #[test]
+    fn test_prefilter() {
+        assert!(prefilter(&[]).is_none());
+        assert!(prefilter(&["code!=500", "x~=ab", "n in 10.0.0.0/8"]).is_none());
+
+        let prefilter = prefilter(&["user=alice", "msg~=timeout", "code=500", "path~~=^/api/v\\d+/orders"]).unwrap();
+        assert!(prefilter.matches(br#"{"msg":"request timeout","user":"alice","path":"/api/v2/orders/1"}"#));
+        assert!(!prefilter.matches(br#"{"msg":"request timeout","user":"bob","path":"/api/v2/orders/1"}"#));
+        assert!(!prefilter.matches(br#"{"msg":"ok","user":"alice","path":"/api/v2/orders/1"}"#));
+        assert!(prefilter.matches(br#"{"msg":"request timeout","user":"\u0061lice","path":"/api/v2/orders/1"}"#));
+        assert!(prefilter.matches(b"msg=\"request timeout\" user=alice path=/api/v1/orders"));
+    }
+}
@@ -0,0 +1,45 @@
+// std imports
+use std::io;
+
+/// Lowers CPU and I/O scheduling priority of the current process.
+///
+/// Should be called before any threads are spawned so that they inherit the priority.
+#[cfg(unix)]
+pub fn enter_background_mode() -> io::Result<()> {
+    const NICENESS: libc::c_int = 10;
+
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS as _, 0, NICENESS) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        const IOPRIO_WHO_PROCESS: libc::c_long = 1;
+        const IOPRIO_CLASS_IDLE: libc::c_long = 3;
+        const IOPRIO_CLASS_SHIFT: libc::c_long = 13;
+
+        let priority = IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT;
+        if unsafe { libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, priority) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(windows)]
+pub fn enter_background_mode() -> io::Result<()> {
+    use winapi::um::processthreadsapi::{GetCurrentProcess, SetPriorityClass};
+    use winapi::um::winbase::PROCESS_MODE_BACKGROUND_BEGIN;
+
+    if unsafe { SetPriorityClass(GetCurrentProcess(), PROCESS_MODE_BACKGROUND_BEGIN) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn enter_background_mode() -> io::Result<()> {
+    Ok(())
+}
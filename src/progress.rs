@@ -0,0 +1,246 @@
+// std imports
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+// local imports
+use crate::diagnostics;
+
+// ---
+
+/// Interval between progress reports.
+const INTERVAL: Duration = Duration::from_secs(5);
+
+const SIZE_UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+// ---
+
+/// Counter of bytes read from input files, see --progress option.
+pub struct Progress {
+    total: u64,
+    read: AtomicU64,
+    started: Instant,
+    files: usize,
+    files_done: AtomicUsize,
+    parent: Option<Arc<Progress>>,
+}
+
+impl Progress {
+    /// Creates a counter for inputs of the given total size, zero if it is unknown.
+    pub fn new(total: u64) -> Self {
+        Self {
+            total,
+            read: AtomicU64::new(0),
+            started: Instant::now(),
+            files: 0,
+            files_done: AtomicUsize::new(0),
+            parent: None,
+        }
+    }
+
+    /// Makes the counter a part of the given one, which counts the bytes counted by this one up to its total size.
+    pub fn with_parent(mut self, parent: Arc<Progress>) -> Self {
+        self.parent = Some(parent);
+        self
+    }
+
+    /// Sets the number of files to process, which makes the status show how many of them are done.
+    pub fn with_files(mut self, files: usize) -> Self {
+        self.files = files;
+        self
+    }
+
+    /// Counts a file that is done, see `with_files`.
+    pub fn file_done(&self) {
+        self.files_done.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn add(&self, bytes: u64) {
+        let read = self.read.fetch_add(bytes, Ordering::Relaxed);
+        if let Some(parent) = &self.parent {
+            if self.total == 0 {
+                parent.add(bytes);
+            } else {
+                parent.add((read + bytes).min(self.total).saturating_sub(read));
+            }
+        }
+    }
+
+    /// Counts the rest of the total size as read, e.g. for a file that was not read in full because it had
+    /// an up to date cached index.
+    pub fn finish(&self) {
+        let read = self.read();
+        if self.total > read {
+            self.add(self.total - read);
+        }
+    }
+
+    pub fn read(&self) -> u64 {
+        self.read.load(Ordering::Relaxed)
+    }
+
+    /// Returns a line describing the progress, with the byte rate, percentage of the total size and ETA if it is known.
+    pub fn status(&self) -> String {
+        let status = status(self.read(), self.total, self.started.elapsed());
+        if self.files == 0 {
+            return status;
+        }
+        format!("{} of {} files, {}", self.files_done.load(Ordering::Relaxed), self.files, status)
+    }
+
+    /// Starts writing the progress to stderr periodically until the returned reporter is dropped.
+    pub fn report(self: &Arc<Self>) -> Reporter {
+        let stop = Arc::new((Mutex::new(false), Condvar::new()));
+        let thread = thread::spawn({
+            let progress = self.clone();
+            let stop = stop.clone();
+            move || {
+                let (stopped, cvar) = &*stop;
+                let mut stopped = stopped.lock().unwrap();
+                loop {
+                    stopped = cvar.wait_timeout(stopped, INTERVAL).unwrap().0;
+                    if *stopped {
+                        break;
+                    }
+                    diagnostics::progress(format_args!("{}", progress.status()));
+                }
+            }
+        });
+        Reporter {
+            progress: self.clone(),
+            stop,
+            thread: Some(thread),
+        }
+    }
+}
+
+// ---
+
+/// Writes the progress periodically, and the final state when dropped.
+pub struct Reporter {
+    progress: Arc<Progress>,
+    stop: Arc<(Mutex<bool>, Condvar)>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for Reporter {
+    fn drop(&mut self) {
+        let (stopped, cvar) = &*self.stop;
+        *stopped.lock().unwrap() = true;
+        cvar.notify_all();
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+        diagnostics::progress(format_args!("{}", self.progress.status()));
+    }
+}
+
+// ---
+
+/// Reader counting bytes read from the inner reader.
+pub struct ProgressReader<R> {
+    inner: R,
+    progress: Arc<Progress>,
+}
+
+impl<R> ProgressReader<R> {
+    pub fn new(inner: R, progress: Arc<Progress>) -> Self {
+        Self { inner, progress }
+    }
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.progress.add(n as u64);
+        Ok(n)
+    }
+}
+
+impl<R: Seek> Seek for ProgressReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+// ---
+
+fn status(read: u64, total: u64, elapsed: Duration) -> String {
+    let rate = read as f64 / elapsed.as_secs_f64().max(0.001);
+    let mut result = format_size(read as f64);
+    if total != 0 {
+        let percent = (read as f64 * 100.0 / total as f64).min(100.0);
+        result += &format!(" of {} ({:.1}%)", format_size(total as f64), percent);
+    }
+    result += &format!(", {}/s", format_size(rate));
+    if total > read && rate >= 1.0 {
+        let eta = ((total - read) as f64 / rate).ceil() as u64;
+        result += &format!(", ETA {:02}:{:02}:{:02}", eta / 3600, eta / 60 % 60, eta % 60);
+    }
+    result
+}
+
+fn format_size(mut value: f64) -> String {
+    let mut unit = 0;
+    while value >= 1024.0 && unit < SIZE_UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", value as u64, SIZE_UNITS[unit])
+    } else {
+        format!("{:.1} {}", value, SIZE_UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_status() {
+        assert_eq!(
+            status(3 << 30, 12 << 30, Duration::from_secs(10)),
+            "3.0 GiB of 12.0 GiB (25.0%), 307.2 MiB/s, ETA 00:00:30"
+        );
+        assert_eq!(status(512, 0, Duration::from_secs(2)), "512 B, 256 B/s");
+        assert_eq!(status(100, 100, Duration::from_secs(1)), "100 B of 100 B (100.0%), 100 B/s");
+        assert_eq!(
+            status(1 << 20, 10 << 30, Duration::from_secs(1)),
+            "1.0 MiB of 10.0 GiB (0.0%), 1.0 MiB/s, ETA 02:50:39"
+        );
+    }
+
+    #[test]
+    fn test_progress_reader() {
+        let progress = Arc::new(Progress::new(11));
+        let mut reader = ProgressReader::new(Cursor::new(b"hello world".to_vec()), progress.clone());
+        let mut buf = [0; 5];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(progress.read(), 5);
+        reader.seek(SeekFrom::Start(0)).unwrap();
+        reader.read_to_end(&mut Vec::new()).unwrap();
+        assert_eq!(progress.read(), 16);
+    }
+
+    #[test]
+    fn test_parent() {
+        let total = Arc::new(Progress::new(30));
+        let first = Progress::new(10).with_parent(total.clone());
+        let second = Progress::new(20).with_parent(total.clone());
+        first.add(4);
+        second.add(5);
+        assert_eq!(total.read(), 9);
+        // re-read data is not counted beyond the size of the file
+        first.add(8);
+        assert_eq!(first.read(), 12);
+        assert_eq!(total.read(), 15);
+        second.finish();
+        assert_eq!(total.read(), 30);
+        first.finish();
+        assert_eq!(total.read(), 30);
+    }
+}
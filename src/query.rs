@@ -0,0 +1,652 @@
+// std imports
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// third-party imports
+use clap::ValueEnum;
+use serde_json::{self as json, value::RawValue};
+
+// local imports
+use crate::error::{Error, Result};
+use crate::level::LevelAliases;
+use crate::model::{FieldFilterSet, Level, Record, TextFolding};
+
+// ---
+
+const INCLUDE_DIRECTIVE: &str = "#include";
+
+// ---
+
+/// Loads filter expressions from a query file.
+///
+/// Each non-empty line of the file contains a filter expression in the same form as accepted by --filter option.
+/// Lines starting with `#` are comments, except for `#include <path>` directives which insert
+/// expressions from another query file, resolved relative to the including file.
+pub fn load(path: &Path) -> Result<Vec<String>> {
+    let mut result = Vec::new();
+    load_into(path, &mut Vec::new(), &mut result)?;
+    Ok(result)
+}
+
+fn load_into(path: &Path, stack: &mut Vec<PathBuf>, result: &mut Vec<String>) -> Result<()> {
+    let text = fs::read_to_string(path).map_err(|err| Error::FailedToOpenFileForReading {
+        path: path.into(),
+        source: err,
+    })?;
+    stack.push(fs::canonicalize(path)?);
+
+    for (i, line) in text.lines().enumerate() {
+        let located = |source| Error::InvalidQuery {
+            path: path.into(),
+            line: i + 1,
+            source: Box::new(source),
+        };
+        let line = line.trim();
+        if let Some(include) = line.strip_prefix(INCLUDE_DIRECTIVE) {
+            let include = include.trim().trim_matches('"');
+            if include.is_empty() {
+                return Err(located(Error::WrongFieldFilter(line.into())));
+            }
+            let include = path.parent().unwrap_or(Path::new("")).join(include);
+            if let Ok(canonical) = fs::canonicalize(&include) {
+                if stack.contains(&canonical) {
+                    return Err(located(Error::RecursiveQueryInclude { path: include }));
+                }
+            }
+            load_into(&include, stack, result).map_err(located)?;
+        } else if line.is_empty() || line.starts_with('#') {
+            continue;
+        } else {
+            FieldFilterSet::new([line]).map_err(located)?;
+            result.push(line.into());
+        }
+    }
+
+    stack.pop();
+    Ok(())
+}
+
+// ---
+
+/// Query expression combining conditions with `and`, `or`, `not` and parentheses, see --query option.
+///
+/// Conditions are:
+/// - `<key> = <value>` and `<key> != <value>` comparing values the same way as `<key>=<value>` filter,
+/// - `<key> ~ <value>` and `<key> ~~ <value>` matching substrings and regular expressions like `~=` and `~~=` filters,
+/// - `<key> < <number>`, `<=`, `>` and `>=` comparing numbers, where a number may also be a duration like `250ms` or `1h30m`
+///   compared with numbers of seconds and duration strings, or a size like `10KiB` or `5MB` compared with numbers of bytes and size strings,
+/// - `level <op> <level>` comparing levels by severity, so `level >= warn` matches warnings, errors and fatal errors,
+/// - `exists(<key>)` checking presence of a field.
+///
+/// Values containing spaces or operator characters are put in double quotes, using JSON escapes.
+#[derive(Debug)]
+pub struct Query(Node);
+
+impl Query {
+    pub fn parse(text: &str, folding: TextFolding, aliases: &LevelAliases) -> Result<Self> {
+        let mut parser = QueryParser {
+            text,
+            tokens: tokenize(text)?,
+            pos: 0,
+            folding,
+            aliases,
+        };
+        let node = parser.or()?;
+        match parser.tokens.get(parser.pos) {
+            Some((offset, _)) => Err(invalid(text, *offset, "unexpected token")),
+            None => Ok(Self(node)),
+        }
+    }
+
+    /// Combines the queries so that both of them must match.
+    pub fn and(self, other: Self) -> Self {
+        Self(Node::And(vec![self.0, other.0]))
+    }
+
+    /// Makes exact match conditions compare values of the same kind only, like --strict-types option does for filters.
+    pub fn with_strict_types(mut self, strict: bool) -> Self {
+        self.0.for_each_fields_mut(&mut |fields| *fields = std::mem::take(fields).with_strict_types(strict));
+        self
+    }
+
+    pub fn matches(&self, record: &Record) -> bool {
+        self.0.matches(record)
+    }
+
+    /// Returns false if no record can match the query, given which levels records with a level may have.
+    pub fn may_match_levels<F: Fn(Level) -> bool>(&self, present: F) -> bool {
+        self.0.bounds(&present).0
+    }
+
+    /// Calls the function for each set of field filters that is not negated.
+    pub(crate) fn for_each_positive_fields(&self, f: &mut dyn FnMut(&FieldFilterSet)) {
+        self.0.for_each_positive_fields(f)
+    }
+
+    /// Returns an error describing the first value met by a condition with strict typing which kind differs from the expected one.
+    pub fn type_mismatch(&self) -> Option<Error> {
+        let mut result = None;
+        self.0.for_each_fields(&mut |fields| {
+            if result.is_none() {
+                result = fields.type_mismatch();
+            }
+        });
+        result
+    }
+}
+
+// ---
+
+#[derive(Debug)]
+enum Node {
+    And(Vec<Node>),
+    Or(Vec<Node>),
+    Not(Box<Node>),
+    Fields(FieldFilterSet),
+    Compare { key: String, op: Operator, value: f64, unit: Unit },
+    Level(Operator, Level),
+    Exists(String),
+}
+
+impl Node {
+    fn matches(&self, record: &Record) -> bool {
+        match self {
+            Self::And(nodes) => nodes.iter().all(|node| node.matches(record)),
+            Self::Or(nodes) => nodes.iter().any(|node| node.matches(record)),
+            Self::Not(node) => !node.matches(record),
+            Self::Fields(fields) => record.matches_fields(fields),
+            Self::Compare { key, op, value, unit } => record
+                .value(key)
+                .and_then(|v| unit.value_of(v))
+                .and_then(|v| v.partial_cmp(value))
+                .map_or(false, |ordering| op.test(ordering)),
+            Self::Level(op, level) => record.level.map_or(false, |l| op.test(level.cmp(&l))),
+            Self::Exists(key) => match key.as_str() {
+                "msg" | "message" => record.message.is_some(),
+                "logger" => record.logger.is_some(),
+                "caller" => record.caller.is_some(),
+                "level" => record.level.is_some(),
+                key => record.value(key).is_some(),
+            },
+        }
+    }
+
+    /// Returns whether the node may be true and whether it may be false for records having one of the present levels or no level.
+    fn bounds(&self, present: &dyn Fn(Level) -> bool) -> (bool, bool) {
+        match self {
+            Self::And(nodes) => nodes
+                .iter()
+                .map(|node| node.bounds(present))
+                .fold((true, false), |(t, f), (nt, nf)| (t && nt, f || nf)),
+            Self::Or(nodes) => nodes
+                .iter()
+                .map(|node| node.bounds(present))
+                .fold((false, true), |(t, f), (nt, nf)| (t || nt, f && nf)),
+            Self::Not(node) => {
+                let (t, f) = node.bounds(present);
+                (f, t)
+            }
+            Self::Level(op, level) => (
+                Level::value_variants().iter().any(|&l| present(l) && op.test(level.cmp(&l))),
+                true,
+            ),
+            _ => (true, true),
+        }
+    }
+
+    fn for_each_fields(&self, f: &mut dyn FnMut(&FieldFilterSet)) {
+        match self {
+            Self::And(nodes) | Self::Or(nodes) => nodes.iter().for_each(|node| node.for_each_fields(f)),
+            Self::Not(node) => node.for_each_fields(f),
+            Self::Fields(fields) => f(fields),
+            _ => {}
+        }
+    }
+
+    fn for_each_positive_fields(&self, f: &mut dyn FnMut(&FieldFilterSet)) {
+        match self {
+            Self::And(nodes) | Self::Or(nodes) => nodes.iter().for_each(|node| node.for_each_positive_fields(f)),
+            Self::Fields(fields) => f(fields),
+            _ => {}
+        }
+    }
+
+    fn for_each_fields_mut(&mut self, f: &mut dyn FnMut(&mut FieldFilterSet)) {
+        match self {
+            Self::And(nodes) | Self::Or(nodes) => nodes.iter_mut().for_each(|node| node.for_each_fields_mut(f)),
+            Self::Not(node) => node.for_each_fields_mut(f),
+            Self::Fields(fields) => f(fields),
+            _ => {}
+        }
+    }
+}
+
+// ---
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Operator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+    Matches,
+}
+
+impl Operator {
+    /// Tests ordering of a value relative to the operand.
+    fn test(self, ordering: Ordering) -> bool {
+        match self {
+            Self::Eq => ordering == Ordering::Equal,
+            Self::Ne => ordering != Ordering::Equal,
+            Self::Lt => ordering == Ordering::Less,
+            Self::Le => ordering != Ordering::Greater,
+            Self::Gt => ordering == Ordering::Greater,
+            Self::Ge => ordering != Ordering::Less,
+            Self::Contains | Self::Matches => false,
+        }
+    }
+}
+
+// ---
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Unit {
+    Number,
+    Duration,
+    Size,
+}
+
+impl Unit {
+    /// Parses a number literal like `42`, `250ms` or `10KiB`, durations are converted to seconds and sizes to bytes.
+    fn parse(text: &str) -> Option<(f64, Self)> {
+        if let Ok(number) = text.parse() {
+            Some((number, Self::Number))
+        } else if let Some(seconds) = parse_duration(text) {
+            Some((seconds, Self::Duration))
+        } else {
+            parse_size(text).map(|bytes| (bytes, Self::Size))
+        }
+    }
+
+    fn value_of(self, value: &RawValue) -> Option<f64> {
+        let text = value.get();
+        if !text.starts_with('"') {
+            return text.parse().ok();
+        }
+        let text = json::from_str::<Cow<str>>(text).ok()?;
+        let text = text.trim();
+        text.parse().ok().or_else(|| match self {
+            Self::Number => None,
+            Self::Duration => parse_duration(text),
+            Self::Size => parse_size(text),
+        })
+    }
+}
+
+/// Parses a duration like `250ms`, `1.5s` or `1h30m` to seconds.
+fn parse_duration(text: &str) -> Option<f64> {
+    if text.is_empty() {
+        return None;
+    }
+    let mut rest = text;
+    let mut result = 0.0;
+    while !rest.is_empty() {
+        let n = rest.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(rest.len());
+        let number: f64 = rest[..n].parse().ok()?;
+        rest = &rest[n..];
+        let n = rest.find(|c: char| c.is_ascii_digit() || c == '.').unwrap_or(rest.len());
+        let scale = match &rest[..n] {
+            "ns" => 1e-9,
+            "us" | "µs" => 1e-6,
+            "ms" => 1e-3,
+            "s" => 1.0,
+            "m" => 60.0,
+            "h" => 3600.0,
+            "d" => 86400.0,
+            _ => return None,
+        };
+        result += number * scale;
+        rest = &rest[n..];
+    }
+    Some(result)
+}
+
+/// Parses a size like `512B`, `10KB` or `1.5MiB` to bytes.
+fn parse_size(text: &str) -> Option<f64> {
+    let n = text.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(text.len());
+    let number: f64 = text[..n].parse().ok()?;
+    let scale = match text[n..].trim_start().to_ascii_lowercase().as_str() {
+        "b" => 1.0,
+        "kb" => 1e3,
+        "mb" => 1e6,
+        "gb" => 1e9,
+        "tb" => 1e12,
+        "kib" => 1024.0,
+        "mib" => 1024f64.powi(2),
+        "gib" => 1024f64.powi(3),
+        "tib" => 1024f64.powi(4),
+        _ => return None,
+    };
+    Some(number * scale)
+}
+
+// ---
+
+/// Words that cannot be used as field keys without quoting.
+const KEYWORDS: [&str; 3] = ["and", "or", "not"];
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Op(Operator),
+    Str(String),
+    Word(String),
+}
+
+impl Token {
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self, Self::Word(word) if word.eq_ignore_ascii_case(keyword))
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<(usize, Token)>> {
+    let bytes = text.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let pair = bytes.get(i..i + 2).unwrap_or_default();
+        let (token, len) = match bytes[i] {
+            b if b.is_ascii_whitespace() => {
+                i += 1;
+                continue;
+            }
+            b'(' => (Token::Open, 1),
+            b')' => (Token::Close, 1),
+            _ if pair == b"&&" => (Token::And, 2),
+            _ if pair == b"||" => (Token::Or, 2),
+            _ if pair == b"!=" => (Token::Op(Operator::Ne), 2),
+            _ if pair == b"==" => (Token::Op(Operator::Eq), 2),
+            _ if pair == b"<=" => (Token::Op(Operator::Le), 2),
+            _ if pair == b">=" => (Token::Op(Operator::Ge), 2),
+            _ if pair == b"~~" => (Token::Op(Operator::Matches), 2),
+            b'!' => (Token::Not, 1),
+            b'=' => (Token::Op(Operator::Eq), 1),
+            b'<' => (Token::Op(Operator::Lt), 1),
+            b'>' => (Token::Op(Operator::Gt), 1),
+            b'~' => (Token::Op(Operator::Contains), 1),
+            b'"' => {
+                let mut end = i + 1;
+                while end < bytes.len() && bytes[end] != b'"' {
+                    end += if bytes[end] == b'\\' { 2 } else { 1 };
+                }
+                if end >= bytes.len() {
+                    return Err(invalid(text, i, "unterminated string"));
+                }
+                let value = json::from_str(&text[i..=end]).map_err(|_| invalid(text, i, "invalid string"))?;
+                (Token::Str(value), end + 1 - i)
+            }
+            _ => {
+                let len = text[i..]
+                    .find(|c: char| c.is_whitespace() || "()!=<>~\"&|".contains(c))
+                    .unwrap_or(text.len() - i);
+                if len == 0 {
+                    return Err(invalid(text, i, "unexpected character"));
+                }
+                (Token::Word(text[i..i + len].into()), len)
+            }
+        };
+        tokens.push((i, token));
+        i += len;
+    }
+    Ok(tokens)
+}
+
+fn invalid(text: &str, offset: usize, message: &str) -> Error {
+    Error::InvalidQueryExpression {
+        query: text.into(),
+        position: text[..offset].chars().count() + 1,
+        message: message.into(),
+    }
+}
+
+// ---
+
+struct QueryParser<'a> {
+    text: &'a str,
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    folding: TextFolding,
+    aliases: &'a LevelAliases,
+}
+
+impl<'a> QueryParser<'a> {
+    fn or(&mut self) -> Result<Node> {
+        let mut nodes = vec![self.and()?];
+        while self.next_if(|token| *token == Token::Or || token.is_keyword("or")) {
+            nodes.push(self.and()?);
+        }
+        Ok(if nodes.len() == 1 { nodes.remove(0) } else { Node::Or(nodes) })
+    }
+
+    fn and(&mut self) -> Result<Node> {
+        let mut nodes = vec![self.not()?];
+        while self.next_if(|token| *token == Token::And || token.is_keyword("and")) {
+            nodes.push(self.not()?);
+        }
+        Ok(if nodes.len() == 1 { nodes.remove(0) } else { Node::And(nodes) })
+    }
+
+    fn not(&mut self) -> Result<Node> {
+        if self.next_if(|token| *token == Token::Not || token.is_keyword("not")) {
+            Ok(Node::Not(Box::new(self.not()?)))
+        } else {
+            self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Result<Node> {
+        match self.next("condition")? {
+            (_, Token::Open) => {
+                let node = self.or()?;
+                self.expect(Token::Close, "closing parenthesis")?;
+                Ok(node)
+            }
+            (_, Token::Word(word)) if word == "exists" && self.tokens.get(self.pos).map(|x| &x.1) == Some(&Token::Open) => {
+                self.pos += 1;
+                let key = match self.next("field key")? {
+                    (_, Token::Word(key)) => key,
+                    (offset, _) => return Err(invalid(self.text, offset, "expected field key")),
+                };
+                self.expect(Token::Close, "closing parenthesis")?;
+                Ok(Node::Exists(key))
+            }
+            (_, Token::Word(key)) if !KEYWORDS.iter().any(|k| key.eq_ignore_ascii_case(k)) => {
+                let op = match self.next("operator")? {
+                    (_, Token::Op(op)) => op,
+                    (offset, _) => return Err(invalid(self.text, offset, "expected operator")),
+                };
+                match self.next("value")? {
+                    (offset, Token::Str(value)) => self.condition(key, op, value, true, offset),
+                    (offset, Token::Word(value)) => self.condition(key, op, value, false, offset),
+                    (offset, _) => Err(invalid(self.text, offset, "expected value")),
+                }
+            }
+            (offset, _) => Err(invalid(self.text, offset, "expected condition")),
+        }
+    }
+
+    fn condition(&self, key: String, op: Operator, value: String, quoted: bool, offset: usize) -> Result<Node> {
+        if key == "level" {
+            return match op {
+                Operator::Contains | Operator::Matches => Err(invalid(self.text, offset, "levels can only be compared")),
+                op => Ok(Node::Level(op, Level::parse_relaxed(&value, self.aliases)?)),
+            };
+        }
+        let quantity = if quoted { None } else { Unit::parse(&value) };
+        match op {
+            Operator::Contains => self.fields(format!("{}~={}", key, value)),
+            Operator::Matches => self.fields(format!("{}~~={}", key, value)),
+            Operator::Eq | Operator::Ne => match quantity {
+                Some((number, unit)) if unit != Unit::Number => Ok(Node::Compare {
+                    key,
+                    op,
+                    value: number,
+                    unit,
+                }),
+                _ => {
+                    let value = if quoted { json::to_string(&value)? } else { value };
+                    let negation = if op == Operator::Ne { "!" } else { "" };
+                    self.fields(format!("{}{}={}", key, negation, value))
+                }
+            },
+            op => match quantity {
+                Some((number, unit)) => Ok(Node::Compare {
+                    key,
+                    op,
+                    value: number,
+                    unit,
+                }),
+                None => Err(invalid(self.text, offset, "expected number, duration or size")),
+            },
+        }
+    }
+
+    fn fields(&self, filter: String) -> Result<Node> {
+        Ok(Node::Fields(FieldFilterSet::new_folded([filter], self.folding)?))
+    }
+
+    fn next(&mut self, expected: &str) -> Result<(usize, Token)> {
+        match self.tokens.get(self.pos) {
+            Some(token) => {
+                self.pos += 1;
+                Ok(token.clone())
+            }
+            None => Err(invalid(self.text, self.text.len(), &format!("expected {}", expected))),
+        }
+    }
+
+    fn next_if<F: Fn(&Token) -> bool>(&mut self, f: F) -> bool {
+        match self.tokens.get(self.pos) {
+            Some((_, token)) if f(token) => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect(&mut self, expected: Token, name: &str) -> Result<()> {
+        match self.next(name)? {
+            (_, token) if token == expected => Ok(()),
+            (offset, _) => Err(invalid(self.text, offset, &format!("expected {}", name))),
+        }
+    }
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Parser, ParserSettings};
+    use crate::settings::Settings;
+    use std::iter::empty;
+
+    fn query(text: &str) -> Result<Query> {
+        Query::parse(text, TextFolding::default(), &Settings::default().level_aliases)
+    }
+
+    fn matches(text: &str, source: &str) -> bool {
+        let settings = Settings::default();
+        let parser = Parser::new(ParserSettings::new(&settings.fields.predefined, empty(), false));
+        let record = parser.parse_slice(source.as_bytes()).unwrap();
+        query(text).unwrap().matches(&record)
+    }
+
+    const SOURCE: &str = r#"{"level":"warning","msg":"request timeout","status":504,"duration":"1.5s","size":"2KiB","user":{"id":"42"}}"#;
+
+    #[test]
+    fn test_query_matches() {
+        assert!(matches("level >= warn", SOURCE));
+        assert!(!matches("level > warn", SOURCE));
+        assert!(matches("level != error", SOURCE));
+        assert!(matches("level >= warn and (status >= 500 or msg ~ timeout) and duration > 250ms", SOURCE));
+        assert!(!matches("status < 500 || duration >= 2s", SOURCE));
+        assert!(matches("not status = 200 && user.id = \"42\"", SOURCE));
+        assert!(matches("msg ~~ \"^request \\\\w+$\"", SOURCE));
+        assert!(matches("size = 2048B and size < 2.1kb", SOURCE));
+        assert!(matches("exists(user.id) and exists(msg) and !exists(caller)", SOURCE));
+        assert!(!matches("exists(missing) or missing > 1", SOURCE));
+    }
+
+    #[test]
+    fn test_query_errors() {
+        for (text, position) in [
+            ("", 1),
+            ("status >", 9),
+            ("(status = 1", 12),
+            ("status = 1)", 11),
+            ("status > abc", 10),
+            ("level ~ warn", 9),
+            ("msg = \"x", 7),
+            ("status 1", 8),
+        ] {
+            match query(text) {
+                Err(Error::InvalidQueryExpression { position: p, .. }) => assert_eq!(p, position, "{}", text),
+                result => panic!("unexpected result for {:?}: {:?}", text, result),
+            }
+        }
+        assert!(matches!(query("level = loud"), Err(Error::InvalidLevel(_))));
+    }
+
+    #[test]
+    fn test_units() {
+        assert_eq!(Unit::parse("42"), Some((42.0, Unit::Number)));
+        assert_eq!(Unit::parse("1h30m"), Some((5400.0, Unit::Duration)));
+        assert_eq!(Unit::parse("250ms"), Some((0.25, Unit::Duration)));
+        assert_eq!(Unit::parse("1.5KiB"), Some((1536.0, Unit::Size)));
+        assert_eq!(Unit::parse("5MB"), Some((5e6, Unit::Size)));
+        assert_eq!(Unit::parse("5x"), None);
+        assert_eq!(Unit::parse("ms"), None);
+    }
+
+    #[test]
+    fn test_may_match_levels() {
+        let query = query("level >= error or status >= 500 and level = info").unwrap();
+        assert!(query.may_match_levels(|level| level == Level::Error));
+        assert!(query.may_match_levels(|level| level == Level::Info));
+        assert!(!query.may_match_levels(|level| level == Level::Debug));
+        assert!(self::query("not level < warn").unwrap().may_match_levels(|level| level == Level::Debug));
+    }
+
+    #[test]
+    fn test_load_with_include() {
+        let dir = std::env::temp_dir().join(format!("hl-query-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("common.hql"), "# common filters\nservice=checkout\n").unwrap();
+        fs::write(dir.join("main.hql"), "#include common.hql\n\nstatus!=200\n").unwrap();
+        fs::write(dir.join("cycle.hql"), "#include cycle.hql\n").unwrap();
+        fs::write(dir.join("invalid.hql"), "service=checkout\nbroken\n").unwrap();
+
+        assert_eq!(load(&dir.join("main.hql")).unwrap(), vec!["service=checkout", "status!=200"]);
+        assert!(matches!(
+            load(&dir.join("cycle.hql")),
+            Err(Error::InvalidQuery { line: 1, .. })
+        ));
+        assert!(matches!(
+            load(&dir.join("invalid.hql")),
+            Err(Error::InvalidQuery { line: 2, .. })
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,182 @@
+// std imports
+use std::fmt;
+use std::ops::Range;
+
+// third-party imports
+use chrono::{DateTime, TimeZone, Utc};
+use serde::de::{Deserializer, MapAccess, Visitor};
+use serde::ser::{SerializeMap, Serializer};
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+
+// local imports
+use crate::model::{Level, Record};
+
+// ---
+
+/// Parsed record detached from the source data, to be passed between processing stages and tools,
+/// see [Pipeline](crate::Pipeline) yielding records with their formatted text.
+///
+/// Exporting by --output-format option works on the borrowed [Record] instead, to avoid copying each record.
+///
+/// Serializes to a JSON object with the predefined fields recognized by the parser and the other fields as they appear in the source,
+/// so it can be restored without loss.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecordData {
+    /// Timestamp as it appears in the source.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ts: Option<String>,
+    /// Timestamp converted to UTC, if it is recognized.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<Level>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logger: Option<String>,
+    /// Message as a raw JSON value, usually a string.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<Box<RawValue>>,
+    /// Source location of the message in form `file:line` or `file`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub caller: Option<String>,
+    /// Fields other than the predefined ones.
+    #[serde(skip_serializing_if = "Fields::is_empty")]
+    pub fields: Fields,
+    /// Byte range of the record in its source, if known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub span: Option<Range<u64>>,
+}
+
+impl RecordData {
+    pub fn with_span(mut self, span: Range<u64>) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    /// Returns raw value of the first field with the given key.
+    pub fn field(&self, key: &str) -> Option<&RawValue> {
+        self.fields.get(key)
+    }
+}
+
+impl<'a> From<&Record<'a>> for RecordData {
+    fn from(record: &Record<'a>) -> Self {
+        Self {
+            ts: record.ts.as_ref().map(|ts| ts.raw().into()),
+            time: record
+                .ts
+                .as_ref()
+                .and_then(|ts| ts.unix_utc())
+                .and_then(|(sec, nsec)| Utc.timestamp_opt(sec, nsec).single()),
+            level: record.level,
+            logger: record.logger.map(|logger| logger.into()),
+            message: record.message.map(|message| message.to_owned()),
+            caller: record.caller.as_ref().map(|caller| caller.text().into_owned()),
+            fields: Fields(
+                record
+                    .fields()
+                    .map(|(key, value)| (key.to_string(), (*value).to_owned()))
+                    .collect(),
+            ),
+            span: None,
+        }
+    }
+}
+
+// ---
+
+/// Fields of a record in the source order, possibly with duplicate keys, serialized as a JSON object.
+#[derive(Clone, Debug, Default)]
+pub struct Fields(Vec<(String, Box<RawValue>)>);
+
+impl Fields {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &RawValue)> {
+        self.0.iter().map(|(key, value)| (key.as_str(), value.as_ref()))
+    }
+
+    /// Returns raw value of the first field with the given key.
+    pub fn get(&self, key: &str) -> Option<&RawValue> {
+        self.iter().find(|(k, _)| *k == key).map(|(_, value)| value)
+    }
+
+    pub fn push(&mut self, key: String, value: Box<RawValue>) {
+        self.0.push((key, value));
+    }
+}
+
+impl Serialize for Fields {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (key, value) in &self.0 {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Fields {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(FieldsVisitor)
+    }
+}
+
+struct FieldsVisitor;
+
+impl<'de> Visitor<'de> for FieldsVisitor {
+    type Value = Fields;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("object with fields of a record")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+        let mut fields = Fields(Vec::with_capacity(access.size_hint().unwrap_or(0)));
+        while let Some((key, value)) = access.next_entry()? {
+            fields.push(key, value);
+        }
+        Ok(fields)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Parser, ParserSettings};
+    use crate::settings::Settings;
+    use serde_json as json;
+    use std::iter::empty;
+
+    #[test]
+    fn test_record_data() {
+        let settings = Settings::default();
+        let parser = Parser::new(ParserSettings::new(&settings.fields.predefined, empty(), false));
+        let source = r#"{"ts":"2023-12-07T20:07:05.5+01:00","level":"warn","logger":"app","msg":"hi","caller":"main.go:42","x":{"y":1},"n":2}"#;
+        let record = parser.parse_slice(source.as_bytes()).unwrap();
+        let data = RecordData::from(&record).with_span(10..20);
+
+        assert_eq!(data.level, Some(Level::Warning));
+        assert_eq!(data.time.unwrap().to_rfc3339(), "2023-12-07T19:07:05.500+00:00");
+        assert_eq!(data.caller.as_deref(), Some("main.go:42"));
+        assert_eq!(data.fields.len(), 2);
+        assert_eq!(data.field("x").map(|v| v.get()), Some(r#"{"y":1}"#));
+
+        let text = json::to_string(&data).unwrap();
+        assert_eq!(
+            text,
+            r#"{"ts":"2023-12-07T20:07:05.5+01:00","time":"2023-12-07T19:07:05.500Z","level":"warning","logger":"app","message":"hi","caller":"main.go:42","fields":{"x":{"y":1},"n":2},"span":{"start":10,"end":20}}"#
+        );
+        let restored: RecordData = json::from_str(&text).unwrap();
+        assert_eq!(json::to_string(&restored).unwrap(), text);
+
+        assert_eq!(json::to_string(&RecordData::default()).unwrap(), "{}");
+    }
+}
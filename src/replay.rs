@@ -3,10 +3,14 @@ use std::{
     cmp::min,
     collections::{btree_map::Entry as BTreeEntry, hash_map::Entry, BTreeMap, HashMap},
     convert::{TryFrom, TryInto},
+    fs::{self, File, OpenOptions},
     hash::Hash,
     io::{Error, ErrorKind, Read, Result, Seek, SeekFrom, Write},
     mem::replace,
     num::{NonZeroU64, NonZeroUsize},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
     time::Instant,
 };
 
@@ -38,6 +42,7 @@ pub struct ReplayBuf {
     segment_size: NonZeroUsize,
     segments: Vec<CompressedBuf>,
     size: usize,
+    compressed_size: usize,
 }
 
 impl ReplayBuf {
@@ -46,6 +51,7 @@ impl ReplayBuf {
             segment_size,
             segments: Vec::new(),
             size: 0,
+            compressed_size: 0,
         }
     }
 }
@@ -84,7 +90,9 @@ impl ReplayBufCreator {
     fn prepare(&mut self) -> Result<()> {
         if self.buf.size % self.buf.segment_size != 0 {
             assert_eq!(self.scratch.len(), 0);
-            self.buf.segments.pop().unwrap().decode(self.scratch.backstage())?;
+            let segment = self.buf.segments.pop().unwrap();
+            self.buf.compressed_size -= segment.0.len();
+            segment.decode(self.scratch.backstage())?;
         }
         Ok(())
     }
@@ -113,7 +121,9 @@ impl Write for ReplayBufCreator {
     fn flush(&mut self) -> Result<()> {
         if self.scratch.len() != 0 {
             let buf = self.scratch.clear();
-            self.buf.segments.push(CompressedBuf::try_from(buf)?);
+            let segment = CompressedBuf::try_from(buf)?;
+            self.buf.compressed_size += segment.0.len();
+            self.buf.segments.push(segment);
             self.buf.size += buf.len();
         }
         Ok(())
@@ -247,6 +257,298 @@ impl<C: Cache> From<ReplayBufReaderBuilder<C>> for ReplayBufReader<C> {
 
 // ---
 
+/// Replay buffer keeping data in memory up to the limit of its compressed size and the rest in a temporary file.
+pub struct SpillingReplayBuf {
+    memory: ReplayBuf,
+    memory_size: u64,
+    spill: Option<SpillFile>,
+}
+
+// ---
+
+/// Writer of [SpillingReplayBuf], stores data compressed in memory until the limit is reached.
+pub struct SpillingReplayBufCreator {
+    memory: ReplayBufCreator,
+    memory_size: u64,
+    limit: usize,
+    dir: PathBuf,
+    spill: Option<SpillFile>,
+}
+
+impl SpillingReplayBufCreator {
+    pub fn new(limit: usize, dir: PathBuf) -> Self {
+        Self::with_memory(ReplayBufCreator::new(), limit, dir)
+    }
+
+    fn with_memory(memory: ReplayBufCreator, limit: usize, dir: PathBuf) -> Self {
+        Self {
+            memory,
+            memory_size: 0,
+            limit,
+            dir,
+            spill: None,
+        }
+    }
+
+    pub fn result(mut self) -> Result<SpillingReplayBuf> {
+        if let Some(spill) = &mut self.spill {
+            spill.finish()?;
+        }
+        Ok(SpillingReplayBuf {
+            memory: self.memory.result()?,
+            memory_size: self.memory_size,
+            spill: self.spill,
+        })
+    }
+}
+
+impl Write for SpillingReplayBufCreator {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        if let Some(spill) = &mut self.spill {
+            return spill.write(buf);
+        }
+        let n = self.memory.write(buf)?;
+        self.memory_size += n as u64;
+        if self.memory.buf.compressed_size >= self.limit {
+            self.memory.flush()?;
+            self.spill = Some(SpillFile::new(&self.dir, self.memory.buf.segment_size)?);
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        match &mut self.spill {
+            Some(spill) => spill.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+// ---
+
+/// Reader of [SpillingReplayBuf].
+pub struct SpillingReplayBufReader {
+    memory: ReplayBufReader<MinimalCache<usize>>,
+    boundary: u64,
+    spill: Option<SpillFile>,
+    size: u64,
+    position: u64,
+}
+
+impl SpillingReplayBufReader {
+    pub fn new(buf: SpillingReplayBuf) -> Result<Self> {
+        let boundary = buf.memory_size;
+        let size = boundary + buf.spill.as_ref().map_or(0, |spill| spill.size);
+        Ok(Self {
+            memory: ReplayBufReader::new(buf.memory),
+            boundary,
+            spill: buf.spill,
+            size,
+            position: 0,
+        })
+    }
+}
+
+impl Read for SpillingReplayBufReader {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let mut n = 0;
+        if self.position < self.boundary {
+            let limit = min(buf.len() as u64, self.boundary - self.position) as usize;
+            n = self.memory.read(&mut buf[..limit])?;
+            self.position += n as u64;
+        }
+        if n < buf.len() && self.position >= self.boundary {
+            if let Some(spill) = &mut self.spill {
+                let k = spill.read_at(self.position - self.boundary, &mut buf[n..])?;
+                self.position += k as u64;
+                n += k;
+            }
+        }
+        Ok(n)
+    }
+}
+
+impl Seek for SpillingReplayBufReader {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::Current(pos) => self.position.checked_add_signed(pos),
+            SeekFrom::End(pos) => self.size.checked_add_signed(pos),
+        };
+        let pos = pos.ok_or_else(|| Error::new(ErrorKind::InvalidInput, "position out of range"))?;
+        let pos = min(pos, self.size);
+        if pos < self.boundary {
+            self.memory.seek(SeekFrom::Start(pos))?;
+        }
+        self.position = pos;
+        Ok(pos)
+    }
+}
+
+// ---
+
+/// Temporary file storing data in compressed segments of a fixed size, which are read back on demand.
+///
+/// Each segment is verified by the checksum of its compressed data when read, so a damaged file is reported
+/// as an error instead of producing wrong output.
+struct SpillFile {
+    file: TempFile,
+    segment_size: usize,
+    segments: Vec<SpilledSegment>,
+    pending: Buf,
+    size: u64,
+    cache: MinimalCache<usize>,
+}
+
+impl SpillFile {
+    fn new(dir: &Path, segment_size: NonZeroUsize) -> Result<Self> {
+        Ok(Self {
+            file: TempFile::new(dir)?,
+            segment_size: segment_size.get(),
+            segments: Vec::new(),
+            pending: Buf::with_capacity(segment_size.get()),
+            size: 0,
+            cache: MinimalCache::new(),
+        })
+    }
+
+    /// Stores the last incomplete segment, no data can be written after that.
+    fn finish(&mut self) -> Result<()> {
+        self.store()?;
+        self.file.flush()
+    }
+
+    /// Reads data at the given position from a single segment, returns the number of bytes read.
+    fn read_at(&mut self, pos: u64, buf: &mut [u8]) -> Result<usize> {
+        if pos >= self.size || buf.is_empty() {
+            return Ok(0);
+        }
+        let index = (pos / self.segment_size as u64) as usize;
+        let offset = (pos % self.segment_size as u64) as usize;
+        let (file, segments) = (&mut self.file, &self.segments);
+        let data = self.cache.cache(index, || segments[index].load(file, index))?;
+        let n = min(buf.len(), data.len() - offset);
+        buf[..n].copy_from_slice(&data[offset..offset + n]);
+        Ok(n)
+    }
+
+    fn store(&mut self) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let compressed = CompressedBuf::new(&self.pending)?;
+        let offset = self.segments.last().map_or(0, |s| s.offset + s.compressed as u64);
+        self.file.write_all(&compressed.0)?;
+        self.segments.push(SpilledSegment {
+            offset,
+            compressed: compressed.0.len(),
+            size: self.pending.len(),
+            checksum: crc32fast::hash(&compressed.0),
+        });
+        self.size += self.pending.len() as u64;
+        self.pending.clear();
+        Ok(())
+    }
+}
+
+impl Write for SpillFile {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let n = min(buf.len(), self.segment_size - self.pending.len());
+        self.pending.extend_from_slice(&buf[..n]);
+        if self.pending.len() == self.segment_size {
+            self.store()?;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Location and checksum of a segment stored in [SpillFile].
+struct SpilledSegment {
+    offset: u64,
+    compressed: usize,
+    size: usize,
+    checksum: u32,
+}
+
+impl SpilledSegment {
+    fn load(&self, file: &mut TempFile, index: usize) -> Result<Buf> {
+        let mut compressed = vec![0; self.compressed];
+        file.seek(SeekFrom::Start(self.offset))?;
+        file.read_exact(&mut compressed)?;
+        if crc32fast::hash(&compressed) != self.checksum {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("segment {} of temporary file {} is corrupted", index, file.path.display()),
+            ));
+        }
+        let mut buf = vec![0; self.size];
+        CompressedBuf(compressed).decode(&mut buf)?;
+        Ok(buf)
+    }
+}
+
+// ---
+
+/// Temporary file removed when dropped.
+struct TempFile {
+    file: File,
+    path: PathBuf,
+}
+
+impl TempFile {
+    fn new(dir: &Path) -> Result<Self> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        loop {
+            let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+            let path = dir.join(format!("hl-replay-{}-{}.tmp", process::id(), n));
+            match OpenOptions::new().read(true).write(true).create_new(true).open(&path) {
+                Ok(file) => return Ok(Self { file, path }),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+                Err(e) => {
+                    return Err(Error::new(
+                        e.kind(),
+                        format!("failed to create temporary file {}: {}", path.display(), e),
+                    ))
+                }
+            }
+        }
+    }
+}
+
+impl Read for TempFile {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl Write for TempFile {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Seek for TempFile {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        fs::remove_file(&self.path).ok();
+    }
+}
+
+// ---
+
 pub struct ReplayBufReaderBuilder<C> {
     buf: ReplayBuf,
     cache: C,
@@ -662,6 +964,45 @@ mod tests {
         assert_eq!(r.read(&mut buf3).unwrap(), 0);
     }
 
+    #[test]
+    fn test_spilling_replay_buf() {
+        test_rewinding_reader(|segment_size, data| {
+            let memory = ReplayBufCreator::build()
+                .segment_size(segment_size.try_into().unwrap())
+                .result();
+            let mut creator = SpillingReplayBufCreator::with_memory(memory, 1, std::env::temp_dir());
+            for chunk in data.as_bytes().chunks(5) {
+                creator.write_all(chunk).unwrap();
+            }
+            let buf = creator.result().unwrap();
+            assert_eq!(buf.memory_size, 5);
+            assert!(buf.spill.is_some());
+            Box::new(SpillingReplayBufReader::new(buf).unwrap())
+        });
+    }
+
+    #[test]
+    fn test_spilling_replay_buf_corrupted() {
+        let memory = ReplayBufCreator::build().segment_size(4.try_into().unwrap()).result();
+        let mut creator = SpillingReplayBufCreator::with_memory(memory, 1, std::env::temp_dir());
+        for chunk in b"Lorem ipsum dolor sit amet.".chunks(5) {
+            creator.write_all(chunk).unwrap();
+        }
+        let buf = creator.result().unwrap();
+        let path = buf.spill.as_ref().unwrap().file.path.clone();
+        let mut data = fs::read(&path).unwrap();
+        let last = data.len() - 1;
+        data[last] ^= 0xff;
+        fs::write(&path, data).unwrap();
+
+        let mut reader = SpillingReplayBufReader::new(buf).unwrap();
+        let mut head = vec![0; 20];
+        reader.read_exact(&mut head).unwrap();
+        assert_eq!(head, b"Lorem ipsum dolor si");
+        let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
     #[test]
     fn test_rewinding_reader_default() {
         test_rewinding_reader(|block_size, data| {
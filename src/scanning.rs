@@ -17,6 +17,7 @@ use crate::error::*;
 pub struct Scanner {
     delimiter: String,
     sf: Arc<SegmentBufFactory>,
+    continuations: bool,
 }
 
 impl Scanner {
@@ -25,9 +26,17 @@ impl Scanner {
         Self {
             delimiter: delimiter.clone(),
             sf,
+            continuations: false,
         }
     }
 
+    /// Makes segments end before a token not starting with a space or a tab whenever the buffer allows,
+    /// so indented continuation lines like stack traces stay in the same segment with the line they follow.
+    pub fn with_continuations(mut self, enabled: bool) -> Self {
+        self.continuations = enabled;
+        self
+    }
+
     /// Returns an iterator over segments found in the input.
     pub fn items<'a, 'b>(&'a self, input: &'b mut dyn Read) -> ScannerIter<'a, 'b> {
         return ScannerIter::new(self, input);
@@ -241,22 +250,31 @@ impl<'a, 'b> ScannerIter<'a, 'b> {
             return None;
         }
 
-        for i in (0..self.next.size - k + 1).rev() {
-            if self.next.data[i..].starts_with(self.scanner.delimiter.as_bytes()) {
-                let n = self.next.size - i - k;
-                let mut result = self.scanner.sf.new_segment();
-                if result.data.len() < n {
-                    result.data.resize(n, 0);
-                }
-                if n > 0 {
-                    result.data[..n].copy_from_slice(&self.next.data[i + k..i + k + n]);
-                    result.size = n;
-                    self.next.size -= n;
-                }
-                return Some(result);
-            }
+        let data = &self.next.data[..self.next.size];
+        let delimiter = self.scanner.delimiter.as_bytes();
+        let mut found = (0..data.len() - k + 1).rev().filter(|&i| data[i..].starts_with(delimiter));
+        let last = found.next()?;
+        let i = if self.scanner.continuations {
+            // data ending with a delimiter is split there so that streamed tokens are not held until more data arrives
+            std::iter::once(last)
+                .chain(found)
+                .find(|&i| data.get(i + k).map_or(true, |&b| b != b' ' && b != b'\t'))
+                .unwrap_or(last)
+        } else {
+            last
+        };
+
+        let n = self.next.size - i - k;
+        let mut result = self.scanner.sf.new_segment();
+        if result.data.len() < n {
+            result.data.resize(n, 0);
+        }
+        if n > 0 {
+            result.data[..n].copy_from_slice(&self.next.data[i + k..i + k + n]);
+            result.size = n;
+            self.next.size -= n;
         }
-        None
+        Some(result)
     }
 }
 
@@ -447,6 +465,24 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_continuations() {
+        let sf = Arc::new(SegmentBufFactory::new(12));
+        let tokens = |continuations| {
+            let scanner = Scanner::new(sf.clone(), "\n".into()).with_continuations(continuations);
+            let mut data = std::io::Cursor::new(b"ab\n  c\nde\n  f\n");
+            scanner.items(&mut data).collect::<Result<Vec<_>>>().unwrap()
+        };
+        assert_eq!(
+            tokens(false),
+            vec![Segment::Complete(b"ab\n  c\nde\n".into()), Segment::Complete(b"  f\n".into())]
+        );
+        assert_eq!(
+            tokens(true),
+            vec![Segment::Complete(b"ab\n  c\n".into()), Segment::Complete(b"de\n  f\n".into())]
+        );
+    }
+
     #[test]
     fn test_jumbo_1() {
         let sf = Arc::new(SegmentBufFactory::new(2));
@@ -0,0 +1,78 @@
+// std imports
+use std::fs;
+use std::path::Path;
+
+// third-party imports
+use jsonschema::Validator;
+use serde_json as json;
+
+// local imports
+use crate::error::{Error, Result};
+
+// ---
+
+/// Validates records against a JSON Schema.
+pub struct SchemaValidator {
+    validator: Validator,
+}
+
+impl SchemaValidator {
+    /// Loads a JSON Schema from the file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let invalid = |message: String| Error::InvalidSchema {
+            path: path.into(),
+            message,
+        };
+        let data = fs::read(path).map_err(|err| Error::FailedToOpenFileForReading {
+            path: path.into(),
+            source: err,
+        })?;
+        let schema = json::from_slice(&data).map_err(|err| invalid(err.to_string()))?;
+        let validator = jsonschema::validator_for(&schema).map_err(|err| invalid(err.to_string()))?;
+        Ok(Self { validator })
+    }
+
+    /// Validates a record and writes a description of each violation to the buffer, one per line.
+    ///
+    /// Returns the number of violations found.
+    pub fn validate(&self, record: &[u8], buf: &mut Vec<u8>) -> usize {
+        let instance = match json::from_slice::<json::Value>(record) {
+            Ok(instance) => instance,
+            Err(_) => return 0,
+        };
+        let mut count = 0;
+        for error in self.validator.iter_errors(&instance) {
+            count += 1;
+            let path = error.instance_path.to_string();
+            let path = if path.is_empty() { "/" } else { &path };
+            buf.extend_from_slice(format!("    ! schema violation at {}: {}\n", path, error).as_bytes());
+        }
+        count
+    }
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let validator = SchemaValidator {
+            validator: jsonschema::validator_for(&json::json!({
+                "type": "object",
+                "required": ["msg"],
+                "properties": {"level": {"enum": ["info", "error"]}},
+            }))
+            .unwrap(),
+        };
+        let mut buf = Vec::new();
+        assert_eq!(validator.validate(br#"{"msg":"a","level":"info"}"#, &mut buf), 0);
+        assert!(buf.is_empty());
+        assert_eq!(validator.validate(br#"{"level":"fatal"}"#, &mut buf), 2);
+        let text = String::from_utf8(buf).unwrap();
+        assert!(text.contains("schema violation at /level:"));
+        assert!(text.contains("schema violation at /:"));
+    }
+}
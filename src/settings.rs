@@ -1,17 +1,23 @@
 // std imports
 use std::collections::{BTreeMap, HashMap};
 use std::include_str;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 // third-party imports
 use chrono_tz::Tz;
+use clap::ValueEnum;
 use config::{Config, File, FileFormat};
 use derive_deref::Deref;
 use platform_dirs::AppDirs;
-use serde::{Deserialize, Serialize, Serializer};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
 // local imports
+use crate::compression::Compression;
 use crate::error::Error;
-use crate::level::Level;
+use crate::level::{Level, LevelAliases};
+use crate::terminal;
+use crate::timestamp::TimeFormat;
 
 // ---
 
@@ -23,11 +29,31 @@ static DEFAULT_SETTINGS: &str = include_str!("../etc/defaults/config.yaml");
 #[serde(rename_all = "kebab-case")]
 pub struct Settings {
     pub fields: Fields,
+    #[serde(default)]
+    pub level_aliases: LevelAliases,
     pub concurrency: Option<usize>,
+    pub cache_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub index_cache: IndexCache,
     pub time_format: String,
+    #[serde(default)]
+    pub time_formats: Vec<TimeFormat>,
     pub time_zone: Tz,
     pub formatting: Formatting,
     pub theme: String,
+    pub output_format: OutputFormatRules,
+    pub compact_layout: CompactLayout,
+    pub time_gaps: TimeGaps,
+    pub multiline: Multiline,
+    pub text_input: TextInput,
+    #[serde(default)]
+    pub history: History,
+    #[serde(default)]
+    pub terminal: terminal::Overrides,
+    #[serde(default)]
+    pub profiles: HashMap<String, Profile>,
+    #[serde(default)]
+    pub workspace: HashMap<String, Vec<String>>,
 }
 
 impl Settings {
@@ -56,22 +82,223 @@ impl Default for Settings {
 
 // ---
 
+/// Compact layout used automatically on narrow terminals.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CompactLayout {
+    pub width_threshold: usize,
+    pub time_format: String,
+}
+
+// ---
+
+/// Thresholds of gaps between timestamps of adjacent messages highlighted by --time-gaps option.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TimeGaps {
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub warning: Duration,
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub critical: Duration,
+}
+
+// ---
+
+/// Attaching of lines that are not messages, like stack traces, to the preceding message, see --multiline option.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Multiline {
+    /// Maximum number of lines attached to a message.
+    pub max_lines: usize,
+    /// Number of spaces attached lines are indented by.
+    pub indent: usize,
+}
+
+// ---
+
+/// Conversion of plain text lines to messages, see `text` input format.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TextInput {
+    /// Regular expressions finding timestamps in lines, tried in order.
+    pub time_patterns: Vec<String>,
+    /// Regular expression finding the level, resolved the same way as values of --level option.
+    pub level_pattern: String,
+}
+
+// ---
+
+/// Limits of the index cache managed by `hl index` command.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct IndexCache {
+    /// Maximum total size of cached files, like `2 GiB`, not limited if not specified.
+    pub max_size: Option<String>,
+}
+
+// ---
+
+/// Recording of invocations to the history file recalled by `hl history` command.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct History {
+    pub enabled: bool,
+    /// Record the command line with values of the arguments redacted, such invocations cannot be rerun.
+    pub redact: bool,
+    /// Record numbers of parsed and matching records, which requires collecting processing statistics.
+    pub record_counts: bool,
+    /// History file, `history.jsonl` in the user data directory if not specified.
+    pub file: Option<PathBuf>,
+}
+
+// ---
+
+/// Rules choosing the output format automatically based on the output sink.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct OutputFormatRules {
+    pub terminal: OutputFormat,
+    pub pipe: OutputFormat,
+    #[serde(default)]
+    pub files: HashMap<String, OutputFormat>,
+}
+
+impl OutputFormatRules {
+    /// Returns the output format for the output file if specified, or for the standard output otherwise.
+    pub fn select(&self, output: Option<&Path>, terminal: bool) -> OutputFormat {
+        match output {
+            Some(output) => self.file(output),
+            None if terminal => self.terminal,
+            None => self.pipe,
+        }
+    }
+
+    /// Returns the output format for a file based on its extension, or text format if there is no matching rule.
+    /// Extension of a compressed file is taken before the compression one, e.g. `jsonl` for `errors.jsonl.gz`.
+    pub fn file(&self, path: &Path) -> OutputFormat {
+        let path = match Compression::from_path(path) {
+            Some(_) => Path::new(path.file_stem().unwrap_or_default()),
+            None => path,
+        };
+        path.extension()
+            .and_then(|ext| self.files.get(&ext.to_string_lossy().to_lowercase()))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, Serialize, Eq, PartialEq)]
+pub enum InputFormat {
+    /// JSON records, or logfmt and syslog records detected in lines not starting with an object, unwrapping Docker json-file lines.
+    #[default]
+    Auto,
+    /// JSON records only.
+    Json,
+    /// Logfmt records, or JSON records in lines that are not valid logfmt.
+    Logfmt,
+    /// Docker json-file lines with JSON, logfmt or plain text records in the `log` field.
+    DockerJson,
+    /// Syslog records in RFC 5424 or RFC 3164 format, or JSON records in lines that are not valid syslog.
+    Syslog,
+    /// Plain text lines with timestamps and levels found by patterns of `text-input` settings, also detected in auto format for files without records.
+    Text,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    /// Human-readable text, styled with the theme if colors are enabled.
+    #[default]
+    Text,
+    /// Original JSON records, one per line, or objects of the selected columns.
+    Json,
+    /// Records in logfmt format, one per line.
+    Logfmt,
+    /// Values of the selected columns in CSV format, with a header row.
+    Csv,
+}
+
+// ---
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Profile {
+    #[serde(default)]
+    pub transforms: Vec<Transform>,
+    #[serde(default)]
+    pub time_formats: Vec<TimeFormat>,
+    /// Lowest numeric values of levels replacing `numbers` of all level field variants.
+    #[serde(default)]
+    pub level_numbers: Option<HashMap<Level, f64>>,
+}
+
+// ---
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Transform {
+    Rename { from: String, to: String },
+    Drop(Vec<String>),
+    ParseJson(String),
+    Coerce { field: String, to: ValueType },
+    Tag { key: String, value: String },
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ValueType {
+    String,
+    Number,
+    Boolean,
+}
+
+// ---
+
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct Fields {
     pub predefined: PredefinedFields,
     pub ignore: Vec<String>,
     pub hide: Vec<String>,
+    #[serde(default)]
+    pub show: Vec<String>,
+    #[serde(default)]
+    pub duplicate_keys: DuplicateKeys,
+}
+
+// ---
+
+/// Policy of handling duplicate keys in a record.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum DuplicateKeys {
+    /// The last value of a predefined field such as the level or the time is used, all values of other fields are kept.
+    #[default]
+    KeepAll,
+    /// The first value of the key is used.
+    FirstWins,
+    /// The last value of the key is used.
+    LastWins,
+    /// All values are kept, with a numeric suffix added to the key of each subsequent duplicate, e.g. level#2.
+    KeepBothWithSuffix,
+    /// The last value of the key is used, and a warning is shown once for each key.
+    Warn,
 }
 
 // ---
 
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct PredefinedFields {
     pub time: TimeField,
     pub level: LevelField,
     pub message: MessageField,
     pub logger: LoggerField,
     pub caller: CallerField,
+    #[serde(default)]
+    pub caller_file: CallerFileField,
+    #[serde(default)]
+    pub caller_line: CallerLineField,
 }
 
 // ---
@@ -93,6 +320,9 @@ pub struct LevelFieldVariant {
     pub names: Vec<String>,
     #[serde(serialize_with = "ordered_map_serialize")]
     pub values: HashMap<Level, Vec<String>>,
+    /// Lowest numeric values of levels used for numeric values not listed in `values`.
+    #[serde(default, serialize_with = "ordered_map_serialize")]
+    pub numbers: HashMap<Level, f64>,
 }
 
 // ---
@@ -108,11 +338,23 @@ pub struct LoggerField(Field);
 // ---
 
 #[derive(Debug, Serialize, Deserialize, Deref)]
-pub struct CallerField(Field);
+pub struct CallerField(pub Field);
 
 // ---
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Field containing the source file name of a record, used as the caller along with the line number field.
+#[derive(Debug, Default, Serialize, Deserialize, Deref)]
+pub struct CallerFileField(pub Field);
+
+// ---
+
+/// Field containing the source line number of a record, used as the caller along with the file name field.
+#[derive(Debug, Default, Serialize, Deserialize, Deref)]
+pub struct CallerLineField(pub Field);
+
+// ---
+
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Field {
     pub names: Vec<String>,
 }
@@ -120,8 +362,63 @@ pub struct Field {
 // ---
 
 #[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct Formatting {
     pub punctuation: Punctuation,
+    #[serde(default)]
+    pub caller_position: CallerPosition,
+    #[serde(default)]
+    pub empty_values: EmptyValues,
+}
+
+// ---
+
+/// Rendering of null and empty string values of fields and messages.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct EmptyValues {
+    pub null: EmptyValueDisplay,
+    pub empty_string: EmptyValueDisplay,
+    pub symbol: String,
+}
+
+impl Default for EmptyValues {
+    fn default() -> Self {
+        Self {
+            null: EmptyValueDisplay::Literal,
+            empty_string: EmptyValueDisplay::Literal,
+            symbol: "∅".into(),
+        }
+    }
+}
+
+// ---
+
+/// Rendering of an empty value.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum EmptyValueDisplay {
+    /// As is, i.e. `null` or empty quotes.
+    #[default]
+    Literal,
+    /// As the configured symbol styled as null.
+    Symbol,
+    /// Not shown, fields having such values are omitted entirely.
+    Hidden,
+}
+
+// ---
+
+/// Position of the caller in a formatted message.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CallerPosition {
+    /// At the end of the line, after all fields.
+    #[default]
+    End,
+    /// In the gutter right after the level, before the logger name and the message.
+    Gutter,
 }
 
 // ---
@@ -191,6 +488,10 @@ impl Punctuation {
     }
 }
 
+fn deserialize_duration<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    humantime::parse_duration(&String::deserialize(deserializer)?).map_err(de::Error::custom)
+}
+
 fn ordered_map_serialize<K: Eq + PartialEq + Ord + PartialOrd + Serialize, V: Serialize, S>(
     value: &HashMap<K, V>,
     serializer: S,
@@ -201,3 +502,33 @@ where
     let ordered: BTreeMap<_, _> = value.iter().collect();
     ordered.serialize(serializer)
 }
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_format_rules() {
+        let rules = Settings::default().output_format;
+        let select = |output: Option<&str>, terminal| rules.select(output.map(Path::new), terminal);
+        assert_eq!(select(None, true), OutputFormat::Text);
+        assert_eq!(select(None, false), OutputFormat::Text);
+        assert_eq!(select(Some("errors.log"), true), OutputFormat::Text);
+        assert_eq!(select(Some("errors"), true), OutputFormat::Text);
+        assert_eq!(select(Some("errors.jsonl"), true), OutputFormat::Json);
+        assert_eq!(select(Some("out/errors.NDJSON"), false), OutputFormat::Json);
+        assert_eq!(select(Some("errors.jsonl.gz"), true), OutputFormat::Json);
+        assert_eq!(select(Some("errors.csv"), true), OutputFormat::Csv);
+
+        let rules = OutputFormatRules {
+            terminal: OutputFormat::Text,
+            pipe: OutputFormat::Json,
+            files: HashMap::new(),
+        };
+        assert_eq!(rules.select(None, true), OutputFormat::Text);
+        assert_eq!(rules.select(None, false), OutputFormat::Json);
+        assert_eq!(rules.select(Some(Path::new("errors.jsonl")), false), OutputFormat::Text);
+    }
+}
@@ -0,0 +1,124 @@
+// std imports
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+// third-party imports
+use chrono::Utc;
+
+// local imports
+use crate::error::*;
+
+// ---
+
+/// Settings of snapshots of recently displayed messages in follow mode.
+#[derive(Clone, Debug)]
+pub struct SnapshotOptions {
+    /// Number of most recently displayed messages to keep.
+    pub records: usize,
+    /// Directory where snapshot files are written.
+    pub dir: PathBuf,
+}
+
+// ---
+
+/// Keeps the most recently displayed messages and dumps them to files on request.
+///
+/// A dump is requested by sending SIGUSR1 to the process on Unix-like systems.
+pub struct Snapshot {
+    options: SnapshotOptions,
+    records: VecDeque<(Vec<u8>, Vec<u8>)>, // (formatted, raw)
+    requested: Arc<AtomicBool>,
+    #[cfg(unix)]
+    signal: signal_hook::SigId,
+}
+
+impl Snapshot {
+    pub fn new(options: SnapshotOptions) -> Result<Self> {
+        let requested = Arc::new(AtomicBool::new(false));
+        #[cfg(unix)]
+        let signal = signal_hook::flag::register(signal_hook::consts::SIGUSR1, requested.clone())?;
+        Ok(Self {
+            records: VecDeque::with_capacity(options.records),
+            options,
+            requested,
+            #[cfg(unix)]
+            signal,
+        })
+    }
+
+    /// Remembers a displayed message, forgetting the oldest one if the limit is reached.
+    pub fn push(&mut self, formatted: &[u8], raw: &[u8]) {
+        if self.options.records == 0 {
+            return;
+        }
+        if self.records.len() == self.options.records {
+            self.records.pop_front();
+        }
+        self.records.push_back((formatted.to_vec(), raw.to_vec()));
+    }
+
+    /// Returns true once after a dump has been requested.
+    pub fn requested(&self) -> bool {
+        self.requested.swap(false, Ordering::Relaxed)
+    }
+
+    /// Writes formatted messages to a timestamped .log file and raw messages to a .jsonl file next to it.
+    ///
+    /// Returns path of the file with formatted messages.
+    pub fn save(&self) -> Result<PathBuf> {
+        let name = format!("hl-snapshot-{}", Utc::now().format("%Y%m%dT%H%M%S%.3fZ"));
+        let formatted = self.options.dir.join(format!("{}.log", name));
+        let raw = self.options.dir.join(format!("{}.jsonl", name));
+        write(&formatted, self.records.iter().map(|record| &record.0[..]))?;
+        write(&raw, self.records.iter().map(|record| &record.1[..]))?;
+        Ok(formatted)
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Snapshot {
+    fn drop(&mut self) {
+        signal_hook::low_level::unregister(self.signal);
+    }
+}
+
+fn write<'a, I: Iterator<Item = &'a [u8]>>(path: &Path, lines: I) -> Result<()> {
+    let mut data = Vec::new();
+    for line in lines {
+        data.extend_from_slice(line);
+        if !line.ends_with(b"\n") {
+            data.push(b'\n');
+        }
+    }
+    fs::write(path, data).map_err(|err| Error::FailedToOpenFileForWriting {
+        path: path.into(),
+        source: err,
+    })
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push() {
+        let mut snapshot = Snapshot::new(SnapshotOptions {
+            records: 2,
+            dir: PathBuf::from("."),
+        })
+        .unwrap();
+        snapshot.push(b"a\n", b"{\"msg\":\"a\"}");
+        snapshot.push(b"b\n", b"{\"msg\":\"b\"}");
+        snapshot.push(b"c\n", b"{\"msg\":\"c\"}");
+        assert_eq!(
+            snapshot.records.iter().map(|record| &record.0[..]).collect::<Vec<_>>(),
+            vec![&b"b\n"[..], &b"c\n"[..]]
+        );
+        assert!(!snapshot.requested());
+    }
+}
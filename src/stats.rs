@@ -0,0 +1,303 @@
+// std imports
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+
+// third-party imports
+use chrono::{TimeZone, Utc};
+use clap::ValueEnum;
+use enum_map::EnumMap;
+
+// local imports
+use crate::grouping::{sort_groups, GroupOrder};
+use crate::index::{Stat, Timestamp};
+use crate::level::Level;
+
+// ---
+
+const HISTOGRAM_ROWS: u64 = 30;
+const HISTOGRAM_WIDTH: usize = 40;
+
+// ---
+
+/// Settings of statistics shown instead of messages, see --stats option.
+#[derive(Clone, Debug)]
+pub struct StatsOptions {
+    pub by: Option<String>,
+    pub top: usize,
+    /// Order of the shown values, which are the most frequent ones in any order.
+    pub order: GroupOrder,
+}
+
+// ---
+
+/// Statistics of matching records: counts per level, per minute and per value of a selected field.
+#[derive(Clone, Debug, Default)]
+pub struct Stats {
+    records: u64,
+    levels: EnumMap<Level, u64>,
+    unknown_level: u64,
+    minutes: BTreeMap<i64, u64>,
+    values: HashMap<String, u64>,
+    ts_min_max: Option<(Timestamp, Timestamp)>,
+    /// Set if counts per minute are estimated from counts per block taken from the index.
+    estimated: bool,
+}
+
+impl Stats {
+    /// Adds a record with the given level, timestamp and value of the field selected by --stats-by option.
+    pub fn add(&mut self, level: Option<Level>, ts: Option<Timestamp>, value: Option<&str>) {
+        self.records += 1;
+        match level {
+            Some(level) => self.levels[level] += 1,
+            None => self.unknown_level += 1,
+        }
+        if let Some(ts) = ts {
+            *self.minutes.entry(ts.sec.div_euclid(60) * 60).or_default() += 1;
+            self.ts_min_max = match self.ts_min_max {
+                Some((min, max)) => Some((min.min(ts), max.max(ts))),
+                None => Some((ts, ts)),
+            };
+        }
+        if let Some(value) = value {
+            match self.values.get_mut(value) {
+                Some(count) => *count += 1,
+                None => {
+                    self.values.insert(value.to_owned(), 1);
+                }
+            }
+        }
+    }
+
+    /// Adds records of a block counted by the index, spreading them evenly over minutes of its time range.
+    pub fn add_indexed(&mut self, stat: &Stat) {
+        let leveled = stat.levels.values().sum::<u64>();
+        self.records += stat.records;
+        for (level, count) in stat.levels {
+            self.levels[level] += count;
+        }
+        self.unknown_level += stat.records.saturating_sub(leveled);
+        if let Some((min, max)) = stat.ts_min_max {
+            let first = min.sec.div_euclid(60);
+            let n = (max.sec.div_euclid(60) - first + 1) as u64;
+            for i in 0..n {
+                let count = stat.records / n + u64::from(i < stat.records % n);
+                if count != 0 {
+                    *self.minutes.entry((first + i as i64) * 60).or_default() += count;
+                }
+            }
+            self.estimated = true;
+            self.ts_min_max = match self.ts_min_max {
+                Some((a, b)) => Some((a.min(min), b.max(max))),
+                None => Some((min, max)),
+            };
+        }
+    }
+
+    pub fn records(&self) -> u64 {
+        self.records
+    }
+
+    /// Returns non-zero counts per level, like `error 1, info 2, unknown 1`.
+    pub fn format_levels(&self) -> String {
+        let mut result = Vec::new();
+        for level in Level::value_variants() {
+            if let Some(name) = level.to_possible_value().filter(|_| self.levels[*level] != 0) {
+                result.push(format!("{} {}", name.get_name(), self.levels[*level]));
+            }
+        }
+        if self.unknown_level != 0 {
+            result.push(format!("unknown {}", self.unknown_level));
+        }
+        result.join(", ")
+    }
+
+    pub fn merge(&mut self, other: Stats) {
+        self.records += other.records;
+        for (level, count) in other.levels {
+            self.levels[level] += count;
+        }
+        self.unknown_level += other.unknown_level;
+        self.estimated |= other.estimated;
+        for (minute, count) in other.minutes {
+            *self.minutes.entry(minute).or_default() += count;
+        }
+        for (value, count) in other.values {
+            *self.values.entry(value).or_default() += count;
+        }
+        if let Some((min, max)) = other.ts_min_max {
+            self.ts_min_max = match self.ts_min_max {
+                Some((a, b)) => Some((a.min(min), b.max(max))),
+                None => Some((min, max)),
+            };
+        }
+    }
+
+    pub fn print<W: Write>(&self, output: &mut W, options: &StatsOptions) -> io::Result<()> {
+        writeln!(output, "records: {}", self.records)?;
+        match self.ts_min_max {
+            Some((min, max)) => writeln!(output, "time range: {} to {}", format_ts(min.sec), format_ts(max.sec))?,
+            None => writeln!(output, "time range: none")?,
+        }
+        writeln!(output, "levels:")?;
+        for level in Level::value_variants() {
+            if let Some(name) = level.to_possible_value() {
+                writeln!(output, "  {}: {}", name.get_name(), self.levels[*level])?;
+            }
+        }
+        if self.unknown_level != 0 {
+            writeln!(output, "  unknown: {}", self.unknown_level)?;
+        }
+        self.print_histogram(output)?;
+        if let Some(key) = &options.by {
+            self.print_top(output, key, options.top, options.order)?;
+        }
+        Ok(())
+    }
+
+    fn print_histogram<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        let (first, last) = match (self.minutes.keys().next(), self.minutes.keys().next_back()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => return Ok(()),
+        };
+        // consecutive minutes are grouped to keep the number of rows limited
+        let minutes = ((last - first) / 60 + 1) as u64;
+        let step = minutes.div_ceil(HISTOGRAM_ROWS) as i64 * 60;
+        let rows = (first..=last)
+            .step_by(step as usize)
+            .map(|start| (start, self.minutes.range(start..start + step).map(|(_, count)| *count).sum::<u64>()))
+            .map(|(start, count)| (start, count as f64 * 60.0 / step as f64))
+            .collect::<Vec<_>>();
+        let max = rows.iter().map(|(_, rate)| *rate).fold(0.0, f64::max);
+        let estimated = if self.estimated { ", estimated from the index" } else { "" };
+        if step == 60 {
+            writeln!(output, "records per minute{}:", estimated)?;
+        } else {
+            writeln!(output, "records per minute, averaged over {} minutes{}:", step / 60, estimated)?;
+        }
+        for (start, rate) in rows {
+            let width = if max > 0.0 { (rate / max * HISTOGRAM_WIDTH as f64).round() as usize } else { 0 };
+            let row = format!("  {} {:>8.0} {}", format_ts(start), rate, "█".repeat(width));
+            writeln!(output, "{}", row.trim_end())?;
+        }
+        Ok(())
+    }
+
+    fn print_top<W: Write>(&self, output: &mut W, key: &str, top: usize, order: GroupOrder) -> io::Result<()> {
+        let mut values = self.values.iter().map(|(value, count)| (value, *count)).collect::<Vec<_>>();
+        sort_groups(&mut values, GroupOrder::ByCount);
+        let top = top.min(values.len());
+        sort_groups(&mut values[..top], order);
+        writeln!(output, "top values of {}:", key)?;
+        for (value, count) in &values[..top] {
+            writeln!(output, "  {}: {}", value, count)?;
+        }
+        if values.len() > top {
+            let rest = values[top..].iter().map(|(_, count)| *count).sum::<u64>();
+            writeln!(output, "  others ({} values): {}", values.len() - top, rest)?;
+        }
+        let missing = self.records - self.values.values().sum::<u64>();
+        if missing != 0 {
+            writeln!(output, "  no value: {}", missing)?;
+        }
+        Ok(())
+    }
+}
+
+fn format_ts(sec: i64) -> String {
+    match Utc.timestamp_opt(sec, 0).single() {
+        Some(ts) => ts.to_rfc3339(),
+        None => sec.to_string(),
+    }
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats() {
+        let ts = |sec| Some(Timestamp { sec, nsec: 0 });
+        let mut stats = Stats::default();
+        stats.add(Some(Level::Info), ts(0), Some("api"));
+        stats.add(Some(Level::Error), ts(30), Some("db"));
+        let mut other = Stats::default();
+        other.add(Some(Level::Info), ts(150), Some("api"));
+        other.add(None, None, None);
+        stats.merge(other);
+        let mut buf = Vec::new();
+        stats
+            .print(
+                &mut buf,
+                &StatsOptions {
+                    by: Some("service".into()),
+                    top: 1,
+                    order: GroupOrder::ByCount,
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            concat!(
+                "records: 4\n",
+                "time range: 1970-01-01T00:00:00+00:00 to 1970-01-01T00:02:30+00:00\n",
+                "levels:\n",
+                "  fatal: 0\n",
+                "  error: 1\n",
+                "  warning: 0\n",
+                "  info: 2\n",
+                "  debug: 0\n",
+                "  trace: 0\n",
+                "  unknown: 1\n",
+                "records per minute:\n",
+                "  1970-01-01T00:00:00+00:00        2 ████████████████████████████████████████\n",
+                "  1970-01-01T00:01:00+00:00        0\n",
+                "  1970-01-01T00:02:00+00:00        1 ████████████████████\n",
+                "top values of service:\n",
+                "  api: 2\n",
+                "  others (1 values): 1\n",
+                "  no value: 1\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_top_order() {
+        let mut stats = Stats::default();
+        for (value, count) in [("10", 2), ("9", 1), ("100", 3), ("2", 1)] {
+            for _ in 0..count {
+                stats.add(None, None, Some(value));
+            }
+        }
+        let top = |order| {
+            let mut buf = Vec::new();
+            stats.print_top(&mut buf, "code", 3, order).unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+        assert_eq!(
+            top(GroupOrder::ByCount),
+            "top values of code:\n  100: 3\n  10: 2\n  2: 1\n  others (1 values): 1\n"
+        );
+        assert_eq!(
+            top(GroupOrder::ByKey),
+            "top values of code:\n  2: 1\n  10: 2\n  100: 3\n  others (1 values): 1\n"
+        );
+    }
+
+    #[test]
+    fn test_add_indexed() {
+        let ts = |sec| Timestamp { sec, nsec: 0 };
+        let mut stat = Stat::new();
+        stat.records = 5;
+        stat.levels[Level::Info] = 3;
+        stat.levels[Level::Error] = 1;
+        stat.ts_min_max = Some((ts(30), ts(150)));
+        let mut stats = Stats::default();
+        stats.add_indexed(&stat);
+        assert_eq!(stats.records(), 5);
+        assert_eq!(stats.format_levels(), "error 1, info 3, unknown 1");
+        assert_eq!(stats.minutes.into_iter().collect::<Vec<_>>(), vec![(0, 2), (60, 2), (120, 1)]);
+        assert_eq!(stats.ts_min_max, Some((ts(30), ts(150))));
+    }
+}
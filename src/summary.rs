@@ -0,0 +1,164 @@
+// std imports
+use std::io::{self, Write};
+use std::time::Duration;
+
+// third-party imports
+use chrono::{TimeZone, Utc};
+
+// local imports
+use crate::index::{Stat, Timestamp};
+use crate::stats::Stats;
+use crate::tracking::Series;
+
+// ---
+
+/// Processing statistics shown at exit by --summary option.
+#[derive(Clone, Debug, Default)]
+pub struct Summary {
+    pub inputs: usize,
+    pub bytes: u64,
+    pub parsed: u64,
+    pub skipped: u64,
+    pub invalid: u64,
+    pub matched: u64,
+    pub rendered: u64,
+    pub ts_min_max: Option<(Timestamp, Timestamp)>,
+    pub violations: Option<u64>,
+    pub series: Option<Series>,
+    /// Records of the inputs counted by the index, regardless of the filter.
+    pub indexed: Option<Stats>,
+    /// Descriptions of inputs skipped due to errors, see --skip-errors option.
+    pub failed: Vec<String>,
+}
+
+impl Summary {
+    pub fn add_ts(&mut self, ts: Timestamp) {
+        self.ts_min_max = match self.ts_min_max {
+            Some((min, max)) => Some((min.min(ts), max.max(ts))),
+            None => Some((ts, ts)),
+        };
+    }
+
+    pub fn add_violations(&mut self, count: usize) {
+        *self.violations.get_or_insert(0) += count as u64;
+    }
+
+    /// Adds records of an indexed input counted by the index.
+    pub fn add_indexed(&mut self, stat: &Stat) {
+        self.indexed.get_or_insert_with(Stats::default).add_indexed(stat);
+    }
+
+    pub fn merge(&mut self, other: &Summary) {
+        self.inputs += other.inputs;
+        self.bytes += other.bytes;
+        self.parsed += other.parsed;
+        self.skipped += other.skipped;
+        self.invalid += other.invalid;
+        self.matched += other.matched;
+        self.rendered += other.rendered;
+        if let Some((min, max)) = other.ts_min_max {
+            self.add_ts(min);
+            self.add_ts(max);
+        }
+        if let Some(violations) = other.violations {
+            self.add_violations(violations as usize);
+        }
+        if let Some(series) = &other.series {
+            match &mut self.series {
+                Some(this) => this.merge(series),
+                None => self.series = Some(series.clone()),
+            }
+        }
+        if let Some(indexed) = &other.indexed {
+            self.indexed.get_or_insert_with(Stats::default).merge(indexed.clone());
+        }
+        self.failed.extend(other.failed.iter().cloned());
+    }
+
+    pub fn print<W: Write>(&self, output: &mut W, elapsed: Duration) -> io::Result<()> {
+        writeln!(output, "summary:")?;
+        writeln!(output, "  inputs: {}", self.inputs)?;
+        if !self.failed.is_empty() {
+            writeln!(output, "  failed inputs: {}", self.failed.join(", "))?;
+        }
+        writeln!(output, "  bytes read: {}", self.bytes)?;
+        writeln!(
+            output,
+            "  records: {} parsed, {} invalid, {} matched, {} rendered",
+            self.parsed, self.invalid, self.matched, self.rendered
+        )?;
+        if self.skipped != 0 {
+            writeln!(output, "  lines skipped without parsing: {}", self.skipped)?;
+        }
+        if let Some(indexed) = &self.indexed {
+            writeln!(output, "  indexed records: {} ({})", indexed.records(), indexed.format_levels())?;
+        }
+        match self.ts_min_max {
+            Some((min, max)) => writeln!(output, "  time range: {} to {}", format_ts(min), format_ts(max))?,
+            None => writeln!(output, "  time range: none")?,
+        }
+        if let Some(violations) = self.violations {
+            writeln!(output, "  schema violations: {}", violations)?;
+        }
+        if let Some(series) = &self.series {
+            series.print(output)?;
+        }
+        writeln!(output, "  elapsed: {:.3}s", elapsed.as_secs_f64())
+    }
+}
+
+fn format_ts(ts: Timestamp) -> String {
+    match Utc.timestamp_opt(ts.sec, ts.nsec).single() {
+        Some(ts) => ts.to_rfc3339(),
+        None => ts.to_string(),
+    }
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_and_print() {
+        let mut summary = Summary {
+            inputs: 1,
+            bytes: 100,
+            parsed: 3,
+            matched: 2,
+            rendered: 2,
+            ..Default::default()
+        };
+        summary.add_ts((1704067202, 0).into());
+        summary.add_ts((1704067201, 0).into());
+
+        let mut other = Summary {
+            inputs: 1,
+            bytes: 50,
+            parsed: 2,
+            invalid: 1,
+            matched: 1,
+            rendered: 1,
+            failed: vec!["missing.log".into()],
+            ..Default::default()
+        };
+        other.add_ts((1704067200, 0).into());
+        summary.merge(&other);
+
+        let mut output = Vec::new();
+        summary.print(&mut output, Duration::from_millis(1500)).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&output).unwrap(),
+            concat!(
+                "summary:\n",
+                "  inputs: 2\n",
+                "  failed inputs: missing.log\n",
+                "  bytes read: 150\n",
+                "  records: 5 parsed, 1 invalid, 3 matched, 3 rendered\n",
+                "  time range: 2024-01-01T00:00:00+00:00 to 2024-01-01T00:00:02+00:00\n",
+                "  elapsed: 1.500s\n",
+            )
+        );
+    }
+}
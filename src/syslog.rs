@@ -0,0 +1,353 @@
+// third-party imports
+use chrono::{DateTime, Datelike, Duration, FixedOffset, Local, NaiveDate, SecondsFormat, TimeZone};
+use serde_json as json;
+
+// local imports
+use crate::timestamp::rfc3339;
+
+// ---
+
+const MONTHS: [&[u8]; 12] = [
+    b"Jan", b"Feb", b"Mar", b"Apr", b"May", b"Jun", b"Jul", b"Aug", b"Sep", b"Oct", b"Nov", b"Dec",
+];
+
+/// Level names by severity, matching the default level values.
+const SEVERITIES: [&str; 8] = ["fatal", "fatal", "fatal", "error", "warning", "info", "info", "debug"];
+
+// ---
+
+/// Converts a syslog line in RFC 5424 or RFC 3164 format to a JSON object written to the buffer.
+///
+/// The timestamp becomes `ts`, the severity becomes `level`, the app name becomes `logger`,
+/// and the hostname, process id and message id become `host`, `pid` and `msgid` fields.
+/// RFC 3164 timestamps without a year are considered to be in the year and the time zone of the reference time,
+/// see [reference_time].
+/// Returns false if the line is not a syslog line, leaving the buffer empty.
+pub fn to_json(line: &[u8], buf: &mut Vec<u8>, now: DateTime<FixedOffset>) -> bool {
+    convert(line, buf, now, false)
+}
+
+/// Same as [to_json], but converts RFC 3164 lines only if they have a priority header or a timestamp without a year,
+/// so that other lines starting with an RFC 3339 timestamp are not taken for syslog lines when the format is detected.
+pub fn to_json_strict(line: &[u8], buf: &mut Vec<u8>, now: DateTime<FixedOffset>) -> bool {
+    convert(line, buf, now, true)
+}
+
+/// Returns the current local time, which is taken once per input as the reference time of its RFC 3164 timestamps.
+pub fn reference_time() -> DateTime<FixedOffset> {
+    let now = Local::now();
+    now.with_timezone(now.offset())
+}
+
+// ---
+
+fn convert(line: &[u8], buf: &mut Vec<u8>, now: DateTime<FixedOffset>, strict: bool) -> bool {
+    buf.clear();
+    let mut p = Reader { line, pos: 0 };
+    let pri = p.priority();
+    let ok = if pri.is_some() && p.version() {
+        rfc5424(&mut p, pri, buf)
+    } else {
+        rfc3164(&mut p, pri, buf, now, strict)
+    };
+    if !ok {
+        buf.clear();
+    }
+    ok
+}
+
+fn rfc5424(p: &mut Reader, pri: Option<u8>, buf: &mut Vec<u8>) -> bool {
+    let (ts, host, app, pid, msgid) = match (p.token(), p.token(), p.token(), p.token(), p.token()) {
+        (Some(ts), Some(host), Some(app), Some(pid), Some(msgid)) => (ts, host, app, pid, msgid),
+        _ => return false,
+    };
+    if ts != b"-" && rfc3339::Timestamp::parse(lossy(ts).as_ref()).is_none() {
+        return false;
+    }
+    let sd = match p.structured_data() {
+        Some(sd) => sd,
+        None => return false,
+    };
+
+    let mut obj = Object::new(buf);
+    obj.field("ts", ts);
+    obj.level(pri);
+    obj.field("host", host);
+    obj.field("logger", app);
+    obj.field("pid", pid);
+    obj.field("msgid", msgid);
+    obj.field("sd", sd);
+    let msg = p.rest();
+    obj.field("msg", msg.strip_prefix(b"\xef\xbb\xbf").unwrap_or(msg));
+    obj.end();
+    true
+}
+
+fn rfc3164(p: &mut Reader, pri: Option<u8>, buf: &mut Vec<u8>, now: DateTime<FixedOffset>, strict: bool) -> bool {
+    let ts = match p.rfc3164_timestamp(now) {
+        Some(ts) => ts,
+        None if strict && pri.is_none() => return false,
+        None => match p.token() {
+            Some(ts) if rfc3339::Timestamp::parse(lossy(ts).as_ref()).is_some() => lossy(ts).into_owned(),
+            _ => return false,
+        },
+    };
+    let host = match p.token() {
+        Some(host) => host,
+        None => return false,
+    };
+    let (app, pid) = p.tag();
+
+    let mut obj = Object::new(buf);
+    obj.field("ts", ts.as_bytes());
+    obj.level(pri);
+    obj.field("host", host);
+    if let Some(app) = app {
+        obj.field("logger", app);
+    }
+    if let Some(pid) = pid {
+        obj.field("pid", pid);
+    }
+    obj.field("msg", p.rest());
+    obj.end();
+    true
+}
+
+fn lossy(v: &[u8]) -> std::borrow::Cow<str> {
+    String::from_utf8_lossy(v)
+}
+
+// ---
+
+struct Reader<'a> {
+    line: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    /// Parses priority like `<34>`.
+    fn priority(&mut self) -> Option<u8> {
+        let rest = self.line.strip_prefix(b"<")?;
+        let end = rest.iter().position(|b| *b == b'>')?;
+        if end == 0 || end > 3 || !rest[..end].iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+        let pri = std::str::from_utf8(&rest[..end]).ok()?.parse::<u8>().ok().filter(|pri| *pri <= 191)?;
+        self.pos = end + 2;
+        Some(pri)
+    }
+
+    /// Parses RFC 5424 version followed by a space.
+    fn version(&mut self) -> bool {
+        let rest = &self.line[self.pos..];
+        let end = rest.iter().position(|b| !b.is_ascii_digit()).unwrap_or(rest.len());
+        if end == 0 || end > 2 || rest.get(end) != Some(&b' ') {
+            return false;
+        }
+        self.pos += end + 1;
+        true
+    }
+
+    /// Parses a token followed by a space or the end of the line.
+    fn token(&mut self) -> Option<&'a [u8]> {
+        let rest = &self.line[self.pos..];
+        let end = rest.iter().position(|b| *b == b' ').unwrap_or(rest.len());
+        if end == 0 {
+            return None;
+        }
+        self.pos += (end + 1).min(rest.len());
+        Some(&rest[..end])
+    }
+
+    /// Parses RFC 5424 structured data, either `-` or a sequence of elements like `[id key="value"]`.
+    fn structured_data(&mut self) -> Option<&'a [u8]> {
+        let rest = &self.line[self.pos..];
+        if rest.starts_with(b"-") {
+            return self.token();
+        }
+        let mut i = 0;
+        while rest.get(i) == Some(&b'[') {
+            let mut quoted = false;
+            loop {
+                i += 1;
+                match rest.get(i)? {
+                    b'\\' if quoted => i += 1,
+                    b'"' => quoted = !quoted,
+                    b']' if !quoted => break,
+                    _ => {}
+                }
+            }
+            i += 1;
+        }
+        if i == 0 || !matches!(rest.get(i), None | Some(b' ')) {
+            return None;
+        }
+        self.pos += (i + 1).min(rest.len());
+        Some(&rest[..i])
+    }
+
+    /// Parses RFC 3164 timestamp like `Dec  7 20:07:05`, returning it in RFC 3339 format.
+    fn rfc3164_timestamp(&mut self, now: DateTime<FixedOffset>) -> Option<String> {
+        let rest = &self.line[self.pos..];
+        if rest.len() < 15 || rest[3] != b' ' || rest[6] != b' ' || rest[9] != b':' || rest[12] != b':' {
+            return None;
+        }
+        let month = MONTHS.iter().position(|m| *m == &rest[0..3])? as u32 + 1;
+        let day = number(if rest[4] == b' ' { &rest[5..6] } else { &rest[4..6] })?;
+        let (hour, minute, second) = (number(&rest[7..9])?, number(&rest[10..12])?, number(&rest[13..15])?);
+        if !matches!(rest.get(15), None | Some(b' ')) {
+            return None;
+        }
+
+        let ts = |year| {
+            let ts = NaiveDate::from_ymd_opt(year, month, day)?.and_hms_opt(hour, minute, second)?;
+            now.offset().from_local_datetime(&ts).single()
+        };
+        let mut result = ts(now.year())?;
+        // messages dated in the future are from the previous year, e.g. logged in December and read in January
+        if result > now + Duration::days(1) {
+            result = ts(now.year() - 1)?;
+        }
+        self.pos += 16.min(rest.len());
+        Some(result.to_rfc3339_opts(SecondsFormat::Secs, true))
+    }
+
+    /// Parses RFC 3164 tag like `app[42]: ` or `app: `, returning app name and process id.
+    fn tag(&mut self) -> (Option<&'a [u8]>, Option<&'a [u8]>) {
+        let rest = &self.line[self.pos..];
+        let end = match rest.iter().position(|b| *b == b':' || *b == b' ') {
+            Some(end) if end != 0 && rest[end] == b':' => end,
+            _ => return (None, None),
+        };
+        let tag = &rest[..end];
+        let (app, pid) = match tag.iter().position(|b| *b == b'[') {
+            Some(i) if tag.ends_with(b"]") => (&tag[..i], Some(&tag[i + 1..tag.len() - 1])),
+            _ => (tag, None),
+        };
+        self.pos += end + 1;
+        if self.line.get(self.pos) == Some(&b' ') {
+            self.pos += 1;
+        }
+        (Some(app).filter(|app| !app.is_empty()), pid)
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        &self.line[self.pos..]
+    }
+}
+
+fn number(v: &[u8]) -> Option<u32> {
+    if !v.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    std::str::from_utf8(v).ok()?.parse().ok()
+}
+
+// ---
+
+/// Writer of a JSON object with string fields, skipping RFC 5424 nil values.
+struct Object<'a> {
+    buf: &'a mut Vec<u8>,
+    first: bool,
+}
+
+impl<'a> Object<'a> {
+    fn new(buf: &'a mut Vec<u8>) -> Self {
+        buf.push(b'{');
+        Self { buf, first: true }
+    }
+
+    fn field(&mut self, key: &str, value: &[u8]) {
+        if value == b"-" {
+            return;
+        }
+        if !self.first {
+            self.buf.push(b',');
+        }
+        self.first = false;
+        json::to_writer(&mut *self.buf, key).ok();
+        self.buf.push(b':');
+        json::to_writer(&mut *self.buf, &lossy(value)).ok();
+    }
+
+    fn level(&mut self, pri: Option<u8>) {
+        if let Some(pri) = pri {
+            self.field("level", SEVERITIES[(pri % 8) as usize].as_bytes());
+        }
+    }
+
+    fn end(self) {
+        self.buf.push(b'}');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Option<String> {
+        parse_with(line, false)
+    }
+
+    fn parse_with(line: &str, strict: bool) -> Option<String> {
+        let now = DateTime::parse_from_rfc3339("2023-01-02T10:00:00+01:00").unwrap();
+        let mut buf = Vec::new();
+        if convert(line.as_bytes(), &mut buf, now, strict) {
+            Some(String::from_utf8(buf).unwrap())
+        } else {
+            assert!(buf.is_empty());
+            None
+        }
+    }
+
+    #[test]
+    fn test_rfc5424() {
+        assert_eq!(
+            parse(r#"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 [exampleSDID@32473 iut="3" eventID="1011"] An application event"#).as_deref(),
+            Some(r#"{"ts":"2003-10-11T22:14:15.003Z","level":"info","host":"mymachine.example.com","logger":"evntslog","msgid":"ID47","sd":"[exampleSDID@32473 iut=\"3\" eventID=\"1011\"]","msg":"An application event"}"#)
+        );
+        assert_eq!(
+            parse("<34>1 2003-10-11T22:14:15.003Z host su 42 - - \u{feff}'su root' failed").as_deref(),
+            Some(r#"{"ts":"2003-10-11T22:14:15.003Z","level":"fatal","host":"host","logger":"su","pid":"42","msg":"'su root' failed"}"#)
+        );
+        assert_eq!(parse("<34>1 yesterday host su 42 - - failed"), None);
+        assert_eq!(parse("<34>1 - host su 42 - [unterminated"), None);
+    }
+
+    #[test]
+    fn test_rfc3164() {
+        assert_eq!(
+            parse("<11>Dec  7 20:07:05 web01 nginx[1234]: connect() failed").as_deref(),
+            Some(r#"{"ts":"2022-12-07T20:07:05+01:00","level":"error","host":"web01","logger":"nginx","pid":"1234","msg":"connect() failed"}"#)
+        );
+        assert_eq!(
+            parse("Jan  2 09:30:00 web01 kernel: eth0: link up").as_deref(),
+            Some(r#"{"ts":"2023-01-02T09:30:00+01:00","host":"web01","logger":"kernel","msg":"eth0: link up"}"#)
+        );
+        assert_eq!(
+            parse("2023-01-02T09:30:00.123456+01:00 web01 systemd[1]: Started Session 1.").as_deref(),
+            Some(r#"{"ts":"2023-01-02T09:30:00.123456+01:00","host":"web01","logger":"systemd","pid":"1","msg":"Started Session 1."}"#)
+        );
+        assert_eq!(
+            parse("Jan 12 09:30:00 web01 no tag here").as_deref(),
+            Some(r#"{"ts":"2022-01-12T09:30:00+01:00","host":"web01","msg":"no tag here"}"#)
+        );
+        assert_eq!(parse("Jan 32 09:30:00 web01 app: x"), None);
+        assert_eq!(parse("starting server"), None);
+        assert_eq!(parse("<300>Dec  7 20:07:05 web01 app: x"), None);
+        assert_eq!(parse(""), None);
+    }
+
+    #[test]
+    fn test_strict() {
+        assert_eq!(parse_with("2023-01-02T09:30:00Z web01 started", true), None);
+        assert_eq!(
+            parse_with("<14>2023-01-02T09:30:00Z web01 app: started", true).as_deref(),
+            Some(r#"{"ts":"2023-01-02T09:30:00Z","level":"info","host":"web01","logger":"app","msg":"started"}"#)
+        );
+        assert_eq!(
+            parse_with("Jan  2 09:30:00 web01 app: started", true).as_deref(),
+            Some(r#"{"ts":"2023-01-02T09:30:00+01:00","host":"web01","logger":"app","msg":"started"}"#)
+        );
+    }
+}
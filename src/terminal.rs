@@ -0,0 +1,332 @@
+// std imports
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+// third-party imports
+use serde::Deserialize;
+
+// ---
+
+/// Number of colors supported by a terminal.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorDepth {
+    /// Escape sequences are not supported at all, e.g. by `dumb` terminal.
+    None,
+    /// 16 basic colors.
+    Ansi16,
+    /// 256 colors of the xterm palette.
+    Ansi256,
+    /// 24-bit RGB colors.
+    Truecolor,
+}
+
+// ---
+
+/// Terminal multiplexer which requires escape sequences unknown to it to be wrapped into a passthrough sequence.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Multiplexer {
+    Tmux,
+    Screen,
+}
+
+impl Multiplexer {
+    /// Wraps the escape sequence into a passthrough sequence of the multiplexer.
+    pub fn wrap(&self, sequence: &[u8]) -> Vec<u8> {
+        let mut result = Vec::with_capacity(sequence.len() + 16);
+        match self {
+            Self::Tmux => {
+                result.extend(b"\x1bPtmux;");
+                for &b in sequence {
+                    if b == b'\x1b' {
+                        result.push(b);
+                    }
+                    result.push(b);
+                }
+            }
+            Self::Screen => {
+                result.extend(b"\x1bP");
+                result.extend(sequence);
+            }
+        }
+        result.extend(b"\x1b\\");
+        result
+    }
+}
+
+// ---
+
+/// Explicit overrides of detected terminal capabilities, see `terminal` section of the configuration file.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct Overrides {
+    pub color_depth: Option<ColorDepth>,
+    pub clipboard: Option<bool>,
+    pub multiplexer: Option<MultiplexerOverride>,
+}
+
+/// Explicitly configured terminal multiplexer.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum MultiplexerOverride {
+    None,
+    Tmux,
+    Screen,
+}
+
+// ---
+
+/// Features supported by the terminal of the current process.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Capabilities {
+    pub color_depth: ColorDepth,
+    /// Setting clipboard contents, OSC 52 escape sequence.
+    pub clipboard: bool,
+    pub multiplexer: Option<Multiplexer>,
+}
+
+impl Capabilities {
+    /// Detects capabilities using environment variables and the terminfo database.
+    pub fn detect() -> Self {
+        Self::from_env(|name| env::var(name).ok(), load_terminfo, cfg!(windows))
+    }
+
+    /// Replaces detected capabilities with the explicitly configured ones.
+    pub fn with_overrides(mut self, overrides: &Overrides) -> Self {
+        self.color_depth = overrides.color_depth.unwrap_or(self.color_depth);
+        self.clipboard = overrides.clipboard.unwrap_or(self.clipboard);
+        self.multiplexer = match overrides.multiplexer {
+            Some(MultiplexerOverride::None) => None,
+            Some(MultiplexerOverride::Tmux) => Some(Multiplexer::Tmux),
+            Some(MultiplexerOverride::Screen) => Some(Multiplexer::Screen),
+            None => self.multiplexer,
+        };
+        self
+    }
+
+    /// Wraps the escape sequence into a passthrough sequence if running inside a terminal multiplexer.
+    pub fn passthrough(&self, sequence: Vec<u8>) -> Vec<u8> {
+        match self.multiplexer {
+            Some(multiplexer) => multiplexer.wrap(&sequence),
+            None => sequence,
+        }
+    }
+
+    fn from_env<V, T>(var: V, terminfo: T, windows: bool) -> Self
+    where
+        V: Fn(&str) -> Option<String>,
+        T: Fn(&str) -> Option<Terminfo>,
+    {
+        let term = var("TERM").unwrap_or_default();
+        let program = var("TERM_PROGRAM").unwrap_or_default();
+        if term == "dumb" {
+            return Self {
+                color_depth: ColorDepth::None,
+                clipboard: false,
+                multiplexer: None,
+            };
+        }
+
+        let multiplexer = if var("TMUX").is_some() || term.starts_with("tmux") {
+            Some(Multiplexer::Tmux)
+        } else if var("STY").is_some() || term.starts_with("screen") {
+            Some(Multiplexer::Screen)
+        } else {
+            None
+        };
+        let windows_terminal = var("WT_SESSION").is_some();
+        // legacy Windows console without TERM is still a ConPTY host supporting 24-bit colors
+        let conpty = windows && term.is_empty();
+        let vte = var("VTE_VERSION").and_then(|v| v.parse::<u32>().ok());
+        let info = terminfo(&term);
+
+        let color_depth = if matches!(var("COLORTERM").as_deref(), Some("truecolor" | "24bit"))
+            || term.ends_with("-direct")
+            || info.as_ref().map_or(false, |info| info.truecolor)
+            || matches!(
+                program.as_str(),
+                "iTerm.app" | "WezTerm" | "vscode" | "Hyper" | "ghostty"
+            )
+            || windows_terminal
+            || conpty
+        {
+            ColorDepth::Truecolor
+        } else if term.contains("256color")
+            // most terminal emulators announcing themselves as xterm support at least 256 colors
+            || term == "xterm"
+            || info.as_ref().map_or(false, |info| info.colors >= 256)
+            || program == "Apple_Terminal"
+        {
+            ColorDepth::Ansi256
+        } else {
+            ColorDepth::Ansi16
+        };
+        // screen does not pass 24-bit colors through
+        let color_depth = match multiplexer {
+            Some(Multiplexer::Screen) => color_depth.min(ColorDepth::Ansi256),
+            _ => color_depth,
+        };
+
+        let clipboard = program != "Apple_Terminal" && vte.is_none() && (!conpty || windows_terminal);
+
+        Self {
+            color_depth,
+            clipboard,
+            multiplexer,
+        }
+    }
+}
+
+// ---
+
+/// Capabilities of a terminal found in the terminfo database.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+struct Terminfo {
+    colors: u32,
+    truecolor: bool,
+}
+
+impl Terminfo {
+    /// Parses a compiled terminfo entry, see term(5).
+    fn parse(data: &[u8]) -> Option<Self> {
+        let short = |i: usize| Some(u16::from_le_bytes([*data.get(i * 2)?, *data.get(i * 2 + 1)?]) as usize);
+        let number_size = match short(0)? {
+            0o432 => 2,
+            0o1036 => 4,
+            _ => return None,
+        };
+        let (names_size, bools, numbers) = (short(1)?, short(2)?, short(3)?);
+        let mut offset = 12 + names_size + bools;
+        offset += offset % 2;
+        // max_colors is the numeric capability #13
+        let colors = if numbers > 13 {
+            let n = data.get(offset + 13 * number_size..offset + 14 * number_size)?;
+            match number_size {
+                2 => i16::from_le_bytes([n[0], n[1]]) as i32,
+                _ => i32::from_le_bytes([n[0], n[1], n[2], n[3]]),
+            }
+        } else {
+            -1
+        };
+        // extended capabilities Tc and RGB are used to announce 24-bit color support
+        let truecolor = data.windows(4).any(|w| w == b"\0Tc\0") || data.windows(5).any(|w| w == b"\0RGB\0");
+        Some(Self {
+            colors: colors.max(0) as u32,
+            truecolor,
+        })
+    }
+}
+
+fn load_terminfo(term: &str) -> Option<Terminfo> {
+    let first = term.chars().next()?;
+    let mut dirs = Vec::new();
+    if let Some(dir) = env::var_os("TERMINFO") {
+        dirs.push(PathBuf::from(dir));
+    }
+    if let Some(home) = env::var_os("HOME") {
+        dirs.push(PathBuf::from(home).join(".terminfo"));
+    }
+    if let Some(list) = env::var_os("TERMINFO_DIRS") {
+        dirs.extend(env::split_paths(&list).filter(|dir| !dir.as_os_str().is_empty()));
+    }
+    dirs.extend(["/etc/terminfo", "/lib/terminfo", "/usr/share/terminfo"].map(PathBuf::from));
+
+    dirs.iter()
+        .flat_map(|dir| {
+            [
+                dir.join(first.to_string()).join(term),
+                dir.join(format!("{:x}", first as u32)).join(term),
+            ]
+        })
+        .find_map(|path| fs::read(path).ok())
+        .and_then(|data| Terminfo::parse(&data))
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(vars: &[(&str, &str)], windows: bool) -> Capabilities {
+        let var = |name: &str| vars.iter().find(|(k, _)| *k == name).map(|(_, v)| v.to_string());
+        Capabilities::from_env(var, |_| None, windows)
+    }
+
+    #[test]
+    fn test_detect() {
+        let caps = detect(&[("TERM", "xterm-256color"), ("COLORTERM", "truecolor")], false);
+        assert_eq!(caps.color_depth, ColorDepth::Truecolor);
+        assert_eq!(caps.multiplexer, None);
+        assert!(caps.clipboard);
+
+        let caps = detect(
+            &[("TERM", "screen-256color"), ("TMUX", "/tmp/tmux-1000/default,1,0")],
+            false,
+        );
+        assert_eq!(caps.color_depth, ColorDepth::Ansi256);
+        assert_eq!(caps.multiplexer, Some(Multiplexer::Tmux));
+
+        let caps = detect(
+            &[("TERM", "screen"), ("STY", "1.pts-0"), ("COLORTERM", "truecolor")],
+            false,
+        );
+        assert_eq!(caps.color_depth, ColorDepth::Ansi256);
+        assert_eq!(caps.multiplexer, Some(Multiplexer::Screen));
+
+        let caps = detect(&[("TERM", "xterm"), ("TERM_PROGRAM", "Apple_Terminal")], false);
+        assert_eq!(caps.color_depth, ColorDepth::Ansi256);
+        assert!(!caps.clipboard);
+
+        assert_eq!(detect(&[("TERM", "dumb")], false).color_depth, ColorDepth::None);
+        assert_eq!(detect(&[], true).color_depth, ColorDepth::Truecolor);
+        assert!(!detect(&[], true).clipboard);
+        assert_eq!(detect(&[("TERM", "xterm")], false).color_depth, ColorDepth::Ansi256);
+        assert_eq!(detect(&[("TERM", "vt100")], false).color_depth, ColorDepth::Ansi16);
+
+        let caps = caps.with_overrides(&Overrides {
+            clipboard: Some(true),
+            multiplexer: Some(MultiplexerOverride::Tmux),
+            ..Default::default()
+        });
+        assert!(caps.clipboard);
+        assert_eq!(caps.multiplexer, Some(Multiplexer::Tmux));
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(
+            Multiplexer::Tmux.wrap(b"\x1b]8;;\x1b\\"),
+            b"\x1bPtmux;\x1b\x1b]8;;\x1b\x1b\\\x1b\\"
+        );
+        assert_eq!(
+            Multiplexer::Screen.wrap(b"\x1b]52;c;\x07"),
+            b"\x1bP\x1b]52;c;\x07\x1b\\"
+        );
+    }
+
+    #[test]
+    fn test_terminfo() {
+        let mut data = Vec::new();
+        for n in [0o432u16, 5, 1, 14, 0, 0] {
+            data.extend(n.to_le_bytes());
+        }
+        data.extend(b"test\0");
+        data.push(1);
+        for i in 0..14u16 {
+            data.extend(if i == 13 { 256u16 } else { 0xffff }.to_le_bytes());
+        }
+        data.extend(b"\0RGB\0");
+        assert_eq!(
+            Terminfo::parse(&data),
+            Some(Terminfo {
+                colors: 256,
+                truecolor: true
+            })
+        );
+        assert_eq!(Terminfo::parse(b"garbage"), None);
+    }
+}
@@ -0,0 +1,148 @@
+// std imports
+use std::ops::Range;
+
+// third-party imports
+use clap::ValueEnum;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json as json;
+
+// local imports
+use crate::error::Result;
+use crate::level::{Level, LevelAliases};
+use crate::settings::{Settings, TextInput};
+
+// ---
+
+/// Maximum number of words preceding the level in a line after the timestamp is removed,
+/// so that words like `info` or `error` further in the message are not taken for the level.
+const MAX_WORDS_BEFORE_LEVEL: usize = 2;
+
+static BUILTIN: Lazy<TextFormat> = Lazy::new(|| {
+    let settings = Settings::default();
+    TextFormat::new(&settings.text_input, &settings.level_aliases).unwrap()
+});
+
+// ---
+
+/// Conversion of plain text lines to records with a timestamp and a level found by regular expressions,
+/// see `text` input format.
+#[derive(Clone, Debug)]
+pub struct TextFormat {
+    time: Vec<Regex>,
+    level: Regex,
+    aliases: LevelAliases,
+}
+
+impl TextFormat {
+    pub fn new(settings: &TextInput, aliases: &LevelAliases) -> Result<Self> {
+        Ok(Self {
+            time: settings
+                .time_patterns
+                .iter()
+                .map(|pattern| Regex::new(pattern))
+                .collect::<std::result::Result<_, _>>()?,
+            level: Regex::new(&settings.level_pattern)?,
+            aliases: aliases.clone(),
+        })
+    }
+
+    /// Returns the conversion configured by the default settings.
+    pub fn builtin() -> &'static Self {
+        &BUILTIN
+    }
+
+    /// Converts a plain text line to a JSON object with `ts`, `level` and `msg` fields written to the buffer.
+    ///
+    /// The timestamp is the first match of the time patterns tried in order, and the level is the first match
+    /// of the level pattern resolved the same way as values of --level option, if it is preceded by no more than
+    /// [MAX_WORDS_BEFORE_LEVEL] words. Both are removed from the message if they lead it, along with surrounding brackets
+    /// and separators.
+    /// Returns false for empty lines and lines starting with a space, like lines of stack traces, leaving the buffer empty.
+    pub fn to_json(&self, line: &[u8], buf: &mut Vec<u8>) -> bool {
+        buf.clear();
+        if line.first().map_or(true, |c| c.is_ascii_whitespace()) {
+            return false;
+        }
+        let line = String::from_utf8_lossy(line);
+        let mut rest = line.as_ref();
+
+        let ts = self.time.iter().find_map(|pattern| pattern.find(rest)).map(|m| {
+            if let Some(tail) = strip_leading(rest, m.range()) {
+                rest = tail;
+            }
+            m.as_str()
+        });
+        let level = self.level.find(rest).and_then(|m| {
+            if rest[..m.start()].split_whitespace().count() > MAX_WORDS_BEFORE_LEVEL {
+                return None;
+            }
+            let level = Level::parse_relaxed(m.as_str(), &self.aliases).ok()?;
+            if let Some(tail) = strip_leading(rest, m.range()) {
+                rest = tail;
+            }
+            Some(level)
+        });
+
+        buf.push(b'{');
+        if let Some(ts) = ts {
+            buf.extend_from_slice(b"\"ts\":");
+            json::to_writer(&mut *buf, ts).ok();
+            buf.push(b',');
+        }
+        if let Some(name) = level.and_then(|level| level.to_possible_value()) {
+            buf.extend_from_slice(b"\"level\":");
+            json::to_writer(&mut *buf, name.get_name()).ok();
+            buf.push(b',');
+        }
+        buf.extend_from_slice(b"\"msg\":");
+        json::to_writer(&mut *buf, rest.trim_end()).ok();
+        buf.push(b'}');
+        true
+    }
+}
+
+/// Returns the text following the match if only spaces and opening brackets precede it,
+/// skipping closing brackets and separators after it.
+fn strip_leading(text: &str, range: Range<usize>) -> Option<&str> {
+    if !text[..range.start].chars().all(|c| c.is_whitespace() || "[(<".contains(c)) {
+        return None;
+    }
+    Some(text[range.end..].trim_start_matches(|c: char| c.is_whitespace() || "])>:|-".contains(c)))
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(line: &str) -> Option<String> {
+        let mut buf = Vec::new();
+        TextFormat::builtin()
+            .to_json(line.as_bytes(), &mut buf)
+            .then(|| String::from_utf8(buf).unwrap())
+    }
+
+    #[test]
+    fn test_to_json() {
+        assert_eq!(
+            convert("2024-01-02 03:04:05.678 ERROR [main] failed to connect").as_deref(),
+            Some(r#"{"ts":"2024-01-02 03:04:05.678","level":"error","msg":"[main] failed to connect"}"#)
+        );
+        assert_eq!(
+            convert("[2024-01-02T03:04:05Z] [warn] disk is \"almost\" full").as_deref(),
+            Some(r#"{"ts":"2024-01-02T03:04:05Z","level":"warning","msg":"disk is \"almost\" full"}"#)
+        );
+        assert_eq!(
+            convert("request done, see info below").as_deref(),
+            Some(r#"{"msg":"request done, see info below"}"#)
+        );
+        assert_eq!(
+            convert("2024-01-02 03:04:05 [main] app WARN low disk, see error log").as_deref(),
+            Some(r#"{"ts":"2024-01-02 03:04:05","level":"warning","msg":"[main] app WARN low disk, see error log"}"#)
+        );
+        assert_eq!(convert("    at com.example.Main.run(Main.java:42)"), None);
+        assert_eq!(convert(""), None);
+    }
+}
@@ -10,13 +10,15 @@ use crate::{
     error::*,
     eseq::{Brightness, Color, ColorCode, Mode, Sequence, StyleCode},
     fmtx::Push,
-    level, themecfg,
+    level,
+    terminal::ColorDepth,
+    themecfg,
 };
 
 // ---
 
 pub use level::Level;
-pub use themecfg::{Element, ThemeInfo, ThemeOrigin};
+pub use themecfg::{Element, SourceKind, ThemeInfo, ThemeOrigin};
 
 // ---
 
@@ -24,6 +26,8 @@ pub trait StylingPush<B: Push<u8>> {
     fn element<R, F: FnOnce(&mut Self) -> R>(&mut self, element: Element, f: F) -> R;
     fn batch<F: FnOnce(&mut B)>(&mut self, f: F);
     fn space(&mut self);
+    /// Appends data having its own styling, the current style is applied again to the data that follows.
+    fn styled<F: FnOnce(&mut B)>(&mut self, f: F);
 }
 
 // ---
@@ -32,6 +36,9 @@ pub struct Theme {
     packs: EnumMap<Level, StylePack>,
     default: StylePack,
     pub indicators: IndicatorPack,
+    pub icons: IconPack,
+    pub lanes: LanePack,
+    pub palette: PalettePack,
 }
 
 impl Theme {
@@ -40,6 +47,9 @@ impl Theme {
             packs: EnumMap::default(),
             default: StylePack::default(),
             indicators: IndicatorPack::default(),
+            icons: IconPack::from(&themecfg::IconPack::default()),
+            lanes: LanePack::plain(&themecfg::LanePack::default()),
+            palette: PalettePack::plain(&themecfg::PalettePack::default()),
         }
     }
 
@@ -47,6 +57,13 @@ impl Theme {
         Ok(themecfg::Theme::load(app_dirs, name)?.into())
     }
 
+    /// Loads the theme replacing colors not supported by the terminal with the nearest supported ones.
+    pub fn load_downgraded(app_dirs: &AppDirs, name: &str, depth: ColorDepth) -> Result<Self> {
+        let mut theme = themecfg::Theme::load(app_dirs, name)?;
+        theme.downgrade(depth);
+        Ok(theme.into())
+    }
+
     pub fn embedded(name: &str) -> Result<Self> {
         Ok(themecfg::Theme::embedded(name)?.into())
     }
@@ -79,14 +96,17 @@ impl<S: Borrow<themecfg::Theme>> From<S> for Theme {
     fn from(s: S) -> Self {
         let s = s.borrow();
         let default = StylePack::load(&s.elements);
-        let mut packs = EnumMap::default();
-        for (level, pack) in &s.levels {
-            packs[*level] = StylePack::load(&s.elements.clone().merged(pack.clone()));
-        }
+        let packs = EnumMap::from_fn(|level| match by_level(&s.levels, level) {
+            Some(pack) => StylePack::load(&s.elements.clone().merged(pack.clone())),
+            None => StylePack::default(),
+        });
         Self {
             default,
             packs,
             indicators: IndicatorPack::from(&s.indicators),
+            icons: IconPack::from(&s.icons),
+            lanes: LanePack::from(&s.lanes),
+            palette: PalettePack::from(&s.palette),
         }
     }
 }
@@ -240,6 +260,11 @@ impl<'a, B: Push<u8>> StylingPush<B> for Styler<'a, B> {
         self.sync();
         f(self.buf)
     }
+    #[inline(always)]
+    fn styled<F: FnOnce(&mut B)>(&mut self, f: F) {
+        f(self.buf);
+        self.synced = None;
+    }
 }
 
 // ---
@@ -282,12 +307,14 @@ impl StylePack {
 #[derive(Default)]
 pub struct IndicatorPack {
     pub sync: SyncIndicatorPack,
+    pub diff: DiffIndicatorPack,
 }
 
 impl From<&themecfg::IndicatorPack> for IndicatorPack {
     fn from(indicator: &themecfg::IndicatorPack) -> Self {
         Self {
             sync: SyncIndicatorPack::from(&indicator.sync),
+            diff: DiffIndicatorPack::from(&indicator.diff),
         }
     }
 }
@@ -311,6 +338,34 @@ impl From<&themecfg::SyncIndicatorPack> for SyncIndicatorPack {
 
 // ---
 
+pub struct DiffIndicatorPack {
+    pub added: Indicator,
+    pub removed: Indicator,
+    pub changed: Indicator,
+}
+
+impl Default for DiffIndicatorPack {
+    fn default() -> Self {
+        Self {
+            added: Indicator { value: "+".into() },
+            removed: Indicator { value: "-".into() },
+            changed: Indicator { value: "~".into() },
+        }
+    }
+}
+
+impl From<&themecfg::DiffIndicatorPack> for DiffIndicatorPack {
+    fn from(indicator: &themecfg::DiffIndicatorPack) -> Self {
+        Self {
+            added: Indicator::from(&indicator.added),
+            removed: Indicator::from(&indicator.removed),
+            changed: Indicator::from(&indicator.changed),
+        }
+    }
+}
+
+// ---
+
 #[derive(Default)]
 pub struct Indicator {
     pub value: String,
@@ -340,6 +395,159 @@ impl From<&themecfg::Indicator> for Indicator {
 
 // ---
 
+/// Pre-rendered markers of lanes, see --lanes-by option.
+pub struct LanePack {
+    markers: Vec<String>,
+    blank: String,
+}
+
+impl LanePack {
+    /// Returns the marker of the lane, the same lane always gets the same marker.
+    pub fn marker(&self, lane: usize) -> &str {
+        &self.markers[lane % self.markers.len()]
+    }
+
+    /// Returns a blank string of the same width as the markers.
+    pub fn blank(&self) -> &str {
+        &self.blank
+    }
+
+    fn plain(lanes: &themecfg::LanePack) -> Self {
+        Self {
+            markers: vec![lanes.marker.clone()],
+            blank: " ".repeat(lanes.marker.chars().count()),
+        }
+    }
+}
+
+impl From<&themecfg::LanePack> for LanePack {
+    fn from(lanes: &themecfg::LanePack) -> Self {
+        if lanes.styles.is_empty() {
+            return Self::plain(lanes);
+        }
+        let markers = lanes
+            .styles
+            .iter()
+            .map(|style| {
+                let mut buf = Vec::new();
+                Style::from(style).with(&mut buf, |buf| buf.extend(lanes.marker.as_bytes()));
+                String::from_utf8(buf).unwrap()
+            })
+            .collect();
+        Self {
+            markers,
+            ..Self::plain(lanes)
+        }
+    }
+}
+
+// ---
+
+/// Pre-rendered gutter blocks and styles of field values, see --color-by option.
+pub struct PalettePack {
+    styles: Vec<Style>,
+    gutters: Vec<String>,
+    blank: String,
+}
+
+impl PalettePack {
+    /// Returns the number of distinct colors.
+    pub fn colors(&self) -> usize {
+        self.styles.len().max(1)
+    }
+
+    /// Returns the gutter block of the slot, or a blank string of the same width if there is no slot.
+    pub fn gutter(&self, slot: Option<usize>) -> &str {
+        match slot {
+            Some(slot) => &self.gutters[slot % self.gutters.len()],
+            None => &self.blank,
+        }
+    }
+
+    /// Appends data produced by `f` styled with the color of the slot.
+    pub fn paint<B: Push<u8>, F: FnOnce(&mut B)>(&self, slot: usize, buf: &mut B, f: F) {
+        match self.styles.get(slot % self.colors()) {
+            Some(style) => style.with(buf, f),
+            None => f(buf),
+        }
+    }
+
+    fn plain(palette: &themecfg::PalettePack) -> Self {
+        Self {
+            styles: Vec::new(),
+            gutters: vec![palette.gutter.clone()],
+            blank: " ".repeat(palette.gutter.chars().count()),
+        }
+    }
+}
+
+impl From<&themecfg::PalettePack> for PalettePack {
+    fn from(palette: &themecfg::PalettePack) -> Self {
+        if palette.styles.is_empty() {
+            return Self::plain(palette);
+        }
+        let styles: Vec<Style> = palette.styles.iter().map(Style::from).collect();
+        let gutters = styles
+            .iter()
+            .map(|style| {
+                let mut buf = Vec::new();
+                style.with(&mut buf, |buf| buf.extend(palette.gutter.as_bytes()));
+                String::from_utf8(buf).unwrap()
+            })
+            .collect();
+        Self {
+            styles,
+            gutters,
+            ..Self::plain(palette)
+        }
+    }
+}
+
+// ---
+
+pub struct IconPack {
+    levels: EnumMap<Level, String>,
+    sources: EnumMap<SourceKind, String>,
+    unknown: String,
+}
+
+impl IconPack {
+    pub fn level(&self, level: Option<Level>) -> &str {
+        match level {
+            Some(level) => &self.levels[level],
+            None => &self.unknown,
+        }
+    }
+
+    pub fn source(&self, source: SourceKind) -> &str {
+        &self.sources[source]
+    }
+}
+
+impl From<&themecfg::IconPack> for IconPack {
+    fn from(icons: &themecfg::IconPack) -> Self {
+        let levels = EnumMap::from_fn(|level| by_level(&icons.levels, level).cloned().unwrap_or_default());
+        let mut sources = EnumMap::default();
+        for (source, icon) in &icons.sources {
+            sources[*source] = icon.clone();
+        }
+        Self {
+            levels,
+            sources,
+            unknown: icons.unknown.clone(),
+        }
+    }
+}
+
+// ---
+
+/// Returns the item configured for the level or for its fallback level if the level itself is not configured.
+fn by_level<T>(items: &HashMap<Level, T>, level: Level) -> Option<&T> {
+    items.get(&level).or_else(|| level.fallback().and_then(|level| items.get(&level)))
+}
+
+// ---
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -13,10 +13,10 @@ use derive_deref::Deref;
 use enum_map::Enum;
 use platform_dirs::AppDirs;
 use rust_embed::RustEmbed;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer, Serialize};
 
 // local imports
-use crate::{error::*, level::Level};
+use crate::{error::*, level::Level, terminal::ColorDepth};
 
 // ---
 
@@ -26,12 +26,15 @@ pub struct Theme {
     pub elements: StylePack,
     pub levels: HashMap<Level, StylePack>,
     pub indicators: IndicatorPack,
+    pub icons: IconPack,
+    pub lanes: LanePack,
+    pub palette: PalettePack,
 }
 
 impl Theme {
     pub fn load(app_dirs: &AppDirs, name: &str) -> Result<Self> {
         let filename = Self::filename(name);
-        match Self::load_from(Self::themes_dir(app_dirs), &filename) {
+        match Self::load_from(name, Self::themes_dir(app_dirs), &filename) {
             Err(Error::Io(e)) => match e.kind() {
                 ErrorKind::NotFound => match Self::load_embedded::<Assets>(name, &filename) {
                     Err(Error::UnknownTheme { name, mut known }) => {
@@ -56,6 +59,35 @@ impl Theme {
         Self::load_embedded::<Assets>(name, &Self::filename(name))
     }
 
+    /// Replaces colors not supported by a terminal with the nearest supported ones.
+    pub fn downgrade(&mut self, depth: ColorDepth) {
+        let sync = &mut self.indicators.sync;
+        let diff = &mut self.indicators.diff;
+        let indicators = [
+            &mut sync.synced,
+            &mut sync.failed,
+            &mut diff.added,
+            &mut diff.removed,
+            &mut diff.changed,
+        ];
+        let styles = self
+            .elements
+            .0
+            .values_mut()
+            .chain(self.levels.values_mut().flat_map(|pack| pack.0.values_mut()))
+            .chain(
+                indicators
+                    .into_iter()
+                    .flat_map(|i| [&mut i.outer.style, &mut i.inner.style]),
+            )
+            .chain(self.lanes.styles.iter_mut())
+            .chain(self.palette.styles.iter_mut());
+        for style in styles {
+            style.foreground = style.foreground.map(|color| color.downgraded(depth));
+            style.background = style.background.map(|color| color.downgraded(depth));
+        }
+    }
+
     pub fn list(app_dirs: &AppDirs) -> Result<HashMap<String, ThemeInfo>> {
         let mut result = HashMap::new();
 
@@ -63,7 +95,12 @@ impl Theme {
             result.insert(name, ThemeOrigin::Stock.into());
         }
 
-        for name in Self::custom_names(app_dirs)? {
+        let names = match Self::custom_names(app_dirs) {
+            Ok(names) => names,
+            Err(Error::Io(e)) if e.kind() == ErrorKind::NotFound => return Ok(result),
+            Err(e) => return Err(e),
+        };
+        for name in names {
             result.insert(name?, ThemeOrigin::Custom.into());
         }
 
@@ -72,6 +109,7 @@ impl Theme {
 
     fn load_embedded<S: RustEmbed>(name: &str, filename: &str) -> Result<Self> {
         Self::from_buf(
+            name,
             S::get(&filename)
                 .ok_or_else(|| Error::UnknownTheme {
                     name: name.to_string(),
@@ -82,13 +120,32 @@ impl Theme {
         )
     }
 
-    fn from_buf(data: &[u8]) -> Result<Self> {
-        Ok(serde_yaml::from_str(std::str::from_utf8(data)?)?)
+    fn from_buf(name: &str, data: &[u8]) -> Result<Self> {
+        Self::parse(name, serde_yaml::from_str(std::str::from_utf8(data)?)?)
     }
 
-    fn load_from(dir: PathBuf, filename: &str) -> Result<Self> {
+    fn load_from(name: &str, dir: PathBuf, filename: &str) -> Result<Self> {
         let f = std::fs::File::open(dir.join(filename))?;
-        Ok(serde_yaml::from_reader(f)?)
+        Self::parse(name, serde_yaml::from_reader(f)?)
+    }
+
+    /// Deserializes the theme, reporting keys of `elements` and `levels` sections that are not known elements.
+    fn parse(name: &str, value: serde_yaml::Value) -> Result<Self> {
+        let levels = value.get("levels").and_then(|levels| levels.as_mapping());
+        let packs = value
+            .get("elements")
+            .into_iter()
+            .chain(levels.into_iter().flat_map(|levels| levels.values()));
+        for key in packs.filter_map(|pack| pack.as_mapping()).flat_map(|pack| pack.keys()) {
+            if Element::deserialize(key.clone()).is_err() {
+                return Err(Error::UnknownThemeElement {
+                    theme: name.to_string(),
+                    element: key.as_str().map(String::from).unwrap_or_else(|| format!("{:?}", key)),
+                    known: Element::names(),
+                });
+            }
+        }
+        Ok(serde_yaml::from_value(value)?)
     }
 
     fn filename(name: &str) -> String {
@@ -176,7 +233,7 @@ impl<I: Into<HashMap<Element, Style>>> From<I> for StylePack {
 // ---
 
 #[repr(u8)]
-#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq, Ord, PartialOrd, Enum, Deserialize)]
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq, Ord, PartialOrd, Enum, Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum Element {
     Input,
@@ -185,8 +242,11 @@ pub enum Element {
     InputName,
     InputNameInner,
     Time,
+    TimeGapWarning,
+    TimeGapCritical,
     Level,
     LevelInner,
+    Icon,
     Logger,
     LoggerInner,
     Caller,
@@ -201,6 +261,16 @@ pub enum Element {
     Boolean,
     Null,
     Ellipsis,
+    Match,
+}
+
+impl Element {
+    /// Returns names of all elements as used in theme files.
+    pub fn names() -> Vec<String> {
+        (0..Self::LENGTH)
+            .filter_map(|i| serde_yaml::to_value(Self::from_usize(i)).ok()?.as_str().map(String::from))
+            .collect()
+    }
 }
 
 // ---
@@ -241,6 +311,24 @@ pub enum Color {
     RGB(RGB),
 }
 
+impl Color {
+    /// Returns the nearest color supported with the given color depth.
+    pub fn downgraded(self, depth: ColorDepth) -> Self {
+        match (self, depth) {
+            (_, ColorDepth::Truecolor) => self,
+            (Self::RGB(rgb), ColorDepth::Ansi256) => Self::Palette(rgb.nearest_palette()),
+            (Self::RGB(rgb), _) => Self::Plain(rgb.nearest_plain()),
+            (Self::Palette(code), ColorDepth::Ansi16 | ColorDepth::None) if code < 16 => {
+                Self::Plain(PLAIN[code as usize])
+            }
+            (Self::Palette(code), ColorDepth::Ansi16 | ColorDepth::None) => {
+                Self::Plain(RGB::palette(code).nearest_plain())
+            }
+            _ => self,
+        }
+    }
+}
+
 // ---
 
 #[derive(Clone, Copy, Debug, Deserialize)]
@@ -271,6 +359,81 @@ pub enum PlainColor {
 #[serde(try_from = "String")]
 pub struct RGB(pub u8, pub u8, pub u8);
 
+impl RGB {
+    /// Returns the color of the xterm 256-color palette entry.
+    pub fn palette(code: u8) -> Self {
+        match code {
+            0..=15 => PLAIN_RGB[code as usize],
+            16..=231 => {
+                let level = |i: u8| if i == 0 { 0 } else { 55 + 40 * i };
+                let i = code - 16;
+                RGB(level(i / 36), level(i / 6 % 6), level(i % 6))
+            }
+            _ => {
+                let v = 8 + 10 * (code - 232);
+                RGB(v, v, v)
+            }
+        }
+    }
+
+    /// Returns the nearest entry of the xterm 256-color palette, excluding the 16 basic colors which differ between terminals.
+    pub fn nearest_palette(&self) -> u8 {
+        (16..=255)
+            .min_by_key(|&code| self.distance(&Self::palette(code)))
+            .unwrap_or(16)
+    }
+
+    /// Returns the nearest basic color.
+    pub fn nearest_plain(&self) -> PlainColor {
+        let i = (0..16).min_by_key(|&i| self.distance(&PLAIN_RGB[i])).unwrap_or(0);
+        PLAIN[i]
+    }
+
+    fn distance(&self, other: &Self) -> u32 {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+        d(self.0, other.0) + d(self.1, other.1) + d(self.2, other.2)
+    }
+}
+
+const PLAIN: [PlainColor; 16] = [
+    PlainColor::Black,
+    PlainColor::Red,
+    PlainColor::Green,
+    PlainColor::Yellow,
+    PlainColor::Blue,
+    PlainColor::Magenta,
+    PlainColor::Cyan,
+    PlainColor::White,
+    PlainColor::BrightBlack,
+    PlainColor::BrightRed,
+    PlainColor::BrightGreen,
+    PlainColor::BrightYellow,
+    PlainColor::BrightBlue,
+    PlainColor::BrightMagenta,
+    PlainColor::BrightCyan,
+    PlainColor::BrightWhite,
+];
+
+/// Typical xterm colors of the basic colors.
+const PLAIN_RGB: [RGB; 16] = [
+    RGB(0, 0, 0),
+    RGB(205, 0, 0),
+    RGB(0, 205, 0),
+    RGB(205, 205, 0),
+    RGB(0, 0, 238),
+    RGB(205, 0, 205),
+    RGB(0, 205, 205),
+    RGB(229, 229, 229),
+    RGB(127, 127, 127),
+    RGB(255, 0, 0),
+    RGB(0, 255, 0),
+    RGB(255, 255, 0),
+    RGB(92, 92, 255),
+    RGB(255, 0, 255),
+    RGB(0, 255, 255),
+    RGB(255, 255, 255),
+];
+
 impl FromStr for RGB {
     type Err = String;
 
@@ -314,6 +477,7 @@ impl fmt::Display for RGB {
 #[serde(default)]
 pub struct IndicatorPack {
     pub sync: SyncIndicatorPack,
+    pub diff: DiffIndicatorPack,
 }
 
 // ---
@@ -352,6 +516,40 @@ impl Default for SyncIndicatorPack {
 
 // ---
 
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct DiffIndicatorPack {
+    pub added: Indicator,
+    pub removed: Indicator,
+    pub changed: Indicator,
+}
+
+impl Default for DiffIndicatorPack {
+    fn default() -> Self {
+        let indicator = |text: &str, color| Indicator {
+            outer: IndicatorStyle::default(),
+            inner: IndicatorStyle {
+                prefix: String::default(),
+                suffix: String::default(),
+                style: Style {
+                    modes: vec![Mode::Bold],
+                    background: None,
+                    foreground: Some(Color::Plain(color)),
+                },
+            },
+            text: text.into(),
+        };
+        Self {
+            added: indicator("+", PlainColor::Green),
+            removed: indicator("-", PlainColor::Red),
+            changed: indicator("~", PlainColor::Yellow),
+        }
+    }
+}
+
+// ---
+
 #[derive(Clone, Debug, Default, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 #[serde(default)]
@@ -374,6 +572,127 @@ pub struct IndicatorStyle {
 
 // ---
 
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct IconPack {
+    #[serde(deserialize_with = "IconPack::levels_over_defaults")]
+    pub levels: HashMap<Level, String>,
+    #[serde(deserialize_with = "IconPack::sources_over_defaults")]
+    pub sources: HashMap<SourceKind, String>,
+    pub unknown: String,
+}
+
+impl IconPack {
+    /// Overrides icons of the default levels by the given ones, so that a theme may override only some of them.
+    fn levels_over_defaults<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<HashMap<Level, String>, D::Error> {
+        let mut levels = Self::default().levels;
+        levels.extend(HashMap::<Level, String>::deserialize(deserializer)?);
+        Ok(levels)
+    }
+
+    /// Overrides icons of the default sources by the given ones, so that a theme may override only some of them.
+    fn sources_over_defaults<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<HashMap<SourceKind, String>, D::Error> {
+        let mut sources = Self::default().sources;
+        sources.extend(HashMap::<SourceKind, String>::deserialize(deserializer)?);
+        Ok(sources)
+    }
+}
+
+impl Default for IconPack {
+    fn default() -> Self {
+        Self {
+            levels: HashMap::from([
+                (Level::Fatal, "💀".into()),
+                (Level::Error, "🛑".into()),
+                (Level::Warning, "🔶".into()),
+                (Level::Info, "🔷".into()),
+                (Level::Debug, "🐛".into()),
+                (Level::Trace, "🔍".into()),
+            ]),
+            sources: HashMap::from([
+                (SourceKind::K8s, "⎈".into()),
+                (SourceKind::Docker, "🐳".into()),
+                (SourceKind::Syslog, "📜".into()),
+            ]),
+            unknown: "❔".into(),
+        }
+    }
+}
+
+// ---
+
+/// 256-color palette codes of the default styles of lanes and palette, distinct enough to tell values apart.
+pub const DEFAULT_PALETTE: [u8; 12] = [39, 208, 113, 170, 220, 75, 204, 150, 141, 167, 44, 186];
+
+// ---
+
+/// Marker and its styles assigned to lanes of messages split by --lanes-by option.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct LanePack {
+    pub marker: String,
+    pub styles: Vec<Style>,
+}
+
+impl Default for LanePack {
+    fn default() -> Self {
+        Self {
+            marker: "┃".into(),
+            styles: DEFAULT_PALETTE
+                .into_iter()
+                .map(|code| Style {
+                    foreground: Some(Color::Palette(code)),
+                    ..Default::default()
+                })
+                .collect(),
+        }
+    }
+}
+
+// ---
+
+/// Gutter block and styles assigned to values of the field chosen by --color-by option.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct PalettePack {
+    pub gutter: String,
+    pub styles: Vec<Style>,
+}
+
+impl Default for PalettePack {
+    fn default() -> Self {
+        Self {
+            gutter: "▌".into(),
+            styles: DEFAULT_PALETTE
+                .into_iter()
+                .map(|code| Style {
+                    foreground: Some(Color::Palette(code)),
+                    ..Default::default()
+                })
+                .collect(),
+        }
+    }
+}
+
+// ---
+
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq, Ord, PartialOrd, Enum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SourceKind {
+    K8s,
+    Docker,
+    Syslog,
+}
+
+// ---
+
 #[derive(RustEmbed)]
 #[folder = "etc/defaults/themes/"]
 struct Assets;
@@ -429,4 +748,51 @@ mod tests {
         let b: RGB = serde_json::from_str(r##""#102030""##).unwrap();
         assert_eq!(b, RGB(16, 32, 48));
     }
+
+    #[test]
+    fn test_unknown_element() {
+        let data = b"elements:\n  time:\n    foreground: red\nlevels:\n  info:\n    levle:\n      modes: [bold]\n";
+        match Theme::from_buf("custom", data) {
+            Err(Error::UnknownThemeElement { theme, element, known }) => {
+                assert_eq!(theme, "custom");
+                assert_eq!(element, "levle");
+                assert!(known.contains(&"level-inner".to_string()));
+                assert_eq!(known.len(), Element::LENGTH);
+            }
+            result => panic!("unexpected result: {:?}", result.map(|_| ())),
+        }
+        assert!(Theme::from_buf("custom", b"elements:\n  time:\n    foreground: '#102030'\n").is_ok());
+    }
+
+    #[test]
+    fn test_icons() {
+        let theme = Theme::from_buf("custom", b"icons:\n  levels:\n    error: E\n  sources:\n    k8s: K\n").unwrap();
+        let defaults = IconPack::default();
+        assert_eq!(theme.icons.levels[&Level::Error], "E");
+        assert_eq!(theme.icons.levels[&Level::Info], defaults.levels[&Level::Info]);
+        assert_eq!(theme.icons.levels.len(), defaults.levels.len());
+        assert_eq!(theme.icons.sources[&SourceKind::K8s], "K");
+        assert_eq!(theme.icons.sources[&SourceKind::Docker], defaults.sources[&SourceKind::Docker]);
+        assert_eq!(theme.icons.unknown, defaults.unknown);
+    }
+
+    #[test]
+    fn test_downgrade() {
+        assert_eq!(RGB::palette(196), RGB(255, 0, 0));
+        assert_eq!(RGB::palette(244), RGB(128, 128, 128));
+        assert_eq!(RGB(250, 10, 5).nearest_palette(), 196);
+        assert_eq!(RGB(130, 130, 128).nearest_palette(), 244);
+
+        let downgrade = |color: Color, depth| match color.downgraded(depth) {
+            Color::Plain(color) => format!("{:?}", color),
+            Color::Palette(code) => code.to_string(),
+            Color::RGB(rgb) => rgb.to_string(),
+        };
+        assert_eq!(downgrade(Color::RGB(RGB(250, 10, 5)), ColorDepth::Truecolor), "#fa0a05");
+        assert_eq!(downgrade(Color::RGB(RGB(250, 10, 5)), ColorDepth::Ansi256), "196");
+        assert_eq!(downgrade(Color::RGB(RGB(250, 10, 5)), ColorDepth::Ansi16), "BrightRed");
+        assert_eq!(downgrade(Color::Palette(2), ColorDepth::Ansi16), "Green");
+        assert_eq!(downgrade(Color::Palette(39), ColorDepth::Ansi16), "Cyan");
+        assert_eq!(downgrade(Color::Plain(PlainColor::Cyan), ColorDepth::Ansi16), "Cyan");
+    }
 }
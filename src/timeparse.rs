@@ -1,5 +1,5 @@
 // third-party imports
-use chrono::{DateTime, Datelike, Duration, NaiveDateTime, Offset, Utc};
+use chrono::{DateTime, Datelike, Duration, NaiveDateTime, NaiveTime, Offset, TimeZone, Utc};
 use humantime::parse_duration;
 
 // local imports
@@ -11,6 +11,8 @@ pub fn parse_time(s: &str, tz: &Tz, format: &DateTimeFormat) -> Result<DateTime<
     let s = s.trim();
     None.or_else(|| relative_past(s))
         .or_else(|| relative_future(s))
+        .or_else(|| relative_ago(s))
+        .or_else(|| relative_day(s, &Utc::now().with_timezone(tz)))
         .or_else(|| use_custom_format(s, format, &Utc::now().with_timezone(tz), tz))
         .or_else(|| rfc3339(s, tz))
         .or_else(|| rfc3339_weak(s, tz))
@@ -18,10 +20,18 @@ pub fn parse_time(s: &str, tz: &Tz, format: &DateTimeFormat) -> Result<DateTime<
         .ok_or(Error::UnrecognizedTime(s.into()))
 }
 
+/// Parses time that does not depend on current time, i.e. only in RFC 3339 format or its weakened form.
+pub fn parse_absolute_time(s: &str, tz: &Tz) -> Result<DateTime<Tz>> {
+    let s = s.trim();
+    None.or_else(|| rfc3339(s, tz))
+        .or_else(|| rfc3339_weak(s, tz))
+        .ok_or(Error::UnrecognizedTime(s.into()))
+}
+
 fn relative_past(s: &str) -> Option<DateTime<Tz>> {
     if s.starts_with('-') {
         let d = parse_duration(&s[1..]).ok()?;
-        let ts = Utc::now() - Duration::from_std(d).ok()?;
+        let ts = Utc::now().checked_sub_signed(Duration::from_std(d).ok()?)?;
         Some(ts.with_timezone(&ts.timezone().into()))
     } else {
         None
@@ -31,13 +41,45 @@ fn relative_past(s: &str) -> Option<DateTime<Tz>> {
 fn relative_future(s: &str) -> Option<DateTime<Tz>> {
     if s.starts_with('+') {
         let d = parse_duration(&s[1..]).ok()?;
-        let ts = Utc::now() + Duration::from_std(d).ok()?;
+        let ts = Utc::now().checked_add_signed(Duration::from_std(d).ok()?)?;
         Some(ts.with_timezone(&ts.timezone().into()))
     } else {
         None
     }
 }
 
+/// Parses a duration like `15m` or `2h 30m ago` as the time that long ago.
+fn relative_ago(s: &str) -> Option<DateTime<Tz>> {
+    relative_past(&format!("-{}", s.strip_suffix(" ago").unwrap_or(s).trim_end()))
+}
+
+/// Parses `now`, or `today`, `yesterday` or `tomorrow` optionally followed by a time of day like `14:00` or `at 14:00:30`.
+fn relative_day(s: &str, now: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+    let mut words = s.split_whitespace();
+    let days = match words.next()?.to_ascii_lowercase().as_str() {
+        "now" => return words.next().is_none().then(|| now.clone()),
+        "today" => 0,
+        "yesterday" => -1,
+        "tomorrow" => 1,
+        _ => return None,
+    };
+    let time = match words.next() {
+        Some(word) if word.eq_ignore_ascii_case("at") => Some(words.next()?),
+        word => word,
+    };
+    if words.next().is_some() {
+        return None;
+    }
+    let time = match time {
+        Some(time) => NaiveTime::parse_from_str(time, "%H:%M:%S")
+            .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M"))
+            .ok()?,
+        None => NaiveTime::from_hms_opt(0, 0, 0)?,
+    };
+    let date = now.date_naive().checked_add_signed(Duration::days(days))?;
+    now.timezone().from_local_datetime(&date.and_time(time)).earliest()
+}
+
 fn human(s: &str, tz: &Tz) -> Option<DateTime<Tz>> {
     htp::parse(s, Utc::now().with_timezone(tz)).ok()
 }
@@ -49,9 +91,9 @@ fn rfc3339(s: &str, tz: &Tz) -> Option<DateTime<Tz>> {
 fn rfc3339_weak(s: &str, tz: &Tz) -> Option<DateTime<Tz>> {
     let time = DateTime::<Utc>::from(humantime::parse_rfc3339_weak(s).ok()?).with_timezone(tz);
     let fix1 = time.offset().fix().local_minus_utc();
-    let time = time - Duration::seconds(fix1 as i64);
+    let time = time.checked_sub_signed(Duration::seconds(fix1 as i64))?;
     let fix2 = time.offset().fix().local_minus_utc();
-    let time = time - Duration::seconds((fix2 - fix1) as i64);
+    let time = time.checked_sub_signed(Duration::seconds((fix2 - fix1) as i64))?;
     Some(time)
 }
 
@@ -434,4 +476,34 @@ mod tests {
             Some(ts("2022-10-30T03:00:00+01:00"))
         );
     }
+
+    #[test]
+    fn test_relative_overflow() {
+        assert!(relative_past("-100000000years").is_none());
+        assert!(relative_future("+100000000years").is_none());
+        assert!(relative_past("-1h").is_some());
+    }
+
+    #[test]
+    fn test_relative_ago() {
+        let ago = |s: &str| relative_ago(s).map(|ts| (Utc::now() - ts.with_timezone(&Utc)).num_minutes());
+        assert_eq!(ago("15m"), Some(15));
+        assert_eq!(ago("2h 30m ago"), Some(150));
+        assert_eq!(ago("15"), None);
+        assert_eq!(ago("yesterday"), None);
+    }
+
+    #[test]
+    fn test_relative_day() {
+        let tz = Tz::FixedOffset(FixedOffset::east_opt(3600).unwrap());
+        let now = ts("2023-03-15T10:20:30+01:00", &tz);
+        assert_eq!(relative_day("now", &now), Some(now.clone()));
+        assert_eq!(relative_day("today", &now), Some(ts("2023-03-15T00:00:00+01:00", &tz)));
+        assert_eq!(relative_day("Yesterday 14:00", &now), Some(ts("2023-03-14T14:00:00+01:00", &tz)));
+        assert_eq!(relative_day("tomorrow at 08:15:30", &now), Some(ts("2023-03-16T08:15:30+01:00", &tz)));
+        assert_eq!(relative_day("now 14:00", &now), None);
+        assert_eq!(relative_day("yesterday noon", &now), None);
+        assert_eq!(relative_day("today 14:00 UTC", &now), None);
+        assert_eq!(relative_day("Jun 19", &now), None);
+    }
 }
@@ -1,6 +1,14 @@
+// std imports
+use std::convert::TryFrom;
+
 // third-party imports
+use chrono::format::{self, Item, Parsed, StrftimeItems};
 use chrono::naive::NaiveDateTime;
 use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+use serde::Deserialize;
+
+// local imports
+use crate::error::Error;
 
 // ---
 
@@ -21,15 +29,8 @@ impl<'a> Timestamp<'a> {
             return parsed;
         }
 
-        if let Ok(ts) = self.0.parse::<i64>() {
-            let (ts, nsec) = if ts < 100000000000 {
-                (ts, 0)
-            } else if ts < 100000000000000 {
-                (ts / 1000, (ts % 1000) * 1000000)
-            } else {
-                (ts / 1000000, (ts % 1000000) * 1000)
-            };
-            let ts = NaiveDateTime::from_timestamp_opt(ts, nsec as u32)?;
+        if let Some((ts, nsec)) = parse_unix(self.0) {
+            let ts = NaiveDateTime::from_timestamp_opt(ts, nsec)?;
             Some(DateTime::from_naive_utc_and_offset(ts, FixedOffset::east_opt(0)?))
         } else if let Ok(ts) = self.0.parse() {
             Some(ts)
@@ -50,6 +51,39 @@ impl<'a> Timestamp<'a> {
     }
 }
 
+/// Parses a unix timestamp into seconds and nanoseconds.
+///
+/// Integer timestamps are taken as seconds, milliseconds, microseconds or nanoseconds depending on their magnitude,
+/// and timestamps with a fractional part are taken as seconds.
+fn parse_unix(value: &str) -> Option<(i64, u32)> {
+    if let Some((sec, frac)) = value.split_once('.') {
+        let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+        if !digits(sec) || !digits(frac) || frac.len() > 9 {
+            return None;
+        }
+        return Some((sec.parse().ok()?, frac.parse::<u32>().ok()? * 10u32.pow(9 - frac.len() as u32)));
+    }
+    let ts = value.parse::<i64>().ok()?;
+    let (ts, nsec) = if ts < 100000000000 {
+        (ts, 0)
+    } else if ts < 100000000000000 {
+        (ts / 1000, (ts % 1000) * 1000000)
+    } else if ts < 100000000000000000 {
+        (ts / 1000000, (ts % 1000000) * 1000)
+    } else {
+        (ts / 1000000000, ts % 1000000000)
+    };
+    Some((ts, nsec as u32))
+}
+
+/// Parses a timestamp in any of the supported formats, entry point for fuzzing.
+pub fn parse(v: &[u8]) -> Result<DateTime<FixedOffset>, Error> {
+    let s = std::str::from_utf8(v)?;
+    Timestamp::new(s, None)
+        .parse()
+        .ok_or_else(|| Error::UnrecognizedTime(s.into()))
+}
+
 // ---
 
 pub mod rfc3339 {
@@ -246,7 +280,7 @@ pub mod rfc3339 {
                     b'+' | b'-' => {
                         if v[3] == b':' {
                             let _ = Number::parse(&v[1..3])?;
-                            let _ = Number::parse(&v[4..5])?;
+                            let _ = Number::parse(&v[4..6])?;
                             Some(v)
                         } else {
                             None
@@ -293,7 +327,7 @@ pub mod rfc3339 {
         #[inline]
         pub fn minute(&self) -> Option<Number<'a>> {
             if self.v.len() > 1 {
-                Some(Number { v: &self.v[4..5] })
+                Some(Number { v: &self.v[4..6] })
             } else {
                 None
             }
@@ -394,6 +428,55 @@ pub mod rfc3339 {
 
 // ---
 
+/// Custom timestamp format given as a chrono format string, compiled once when loaded.
+///
+/// Timestamps without time zone are considered to be in UTC.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "String")]
+pub struct TimeFormat {
+    format: String,
+    items: Vec<Item<'static>>,
+}
+
+impl TimeFormat {
+    pub fn as_str(&self) -> &str {
+        &self.format
+    }
+
+    pub fn parse(&self, value: &str) -> Option<DateTime<FixedOffset>> {
+        let mut parsed = Parsed::new();
+        format::parse(&mut parsed, value, self.items.iter()).ok()?;
+        match parsed.to_datetime() {
+            Ok(ts) => Some(ts),
+            Err(_) => {
+                let ts = parsed.to_naive_datetime_with_offset(0).ok()?;
+                Some(DateTime::from_naive_utc_and_offset(ts, FixedOffset::east_opt(0)?))
+            }
+        }
+    }
+}
+
+impl TryFrom<String> for TimeFormat {
+    type Error = String;
+
+    fn try_from(format: String) -> Result<Self, Self::Error> {
+        let items = StrftimeItems::new(&format)
+            .map(|item| match item {
+                Item::Literal(s) => Ok(Item::OwnedLiteral(s.into())),
+                Item::OwnedLiteral(s) => Ok(Item::OwnedLiteral(s)),
+                Item::Space(s) => Ok(Item::OwnedSpace(s.into())),
+                Item::OwnedSpace(s) => Ok(Item::OwnedSpace(s)),
+                Item::Numeric(numeric, pad) => Ok(Item::Numeric(numeric, pad)),
+                Item::Fixed(fixed) => Ok(Item::Fixed(fixed)),
+                Item::Error => Err(format!("invalid time format {:?}", format)),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { format, items })
+    }
+}
+
+// ---
+
 fn only_digits(b: &[u8]) -> bool {
     b.iter().map(|&b| b.is_ascii_digit()).position(|x| x == false).is_none()
 }
@@ -455,5 +538,46 @@ mod tests {
             "",
             "+03:00",
         );
+        let ts = Timestamp::parse("2020-08-21T07:20:48+05:30").unwrap();
+        assert_eq!(ts.timezone().hour().unwrap().as_str(), "05");
+        assert_eq!(ts.timezone().minute().unwrap().as_str(), "30");
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse(b"2020-08-21T07:20:48.092+03:00").unwrap().to_rfc3339(),
+            "2020-08-21T07:20:48.092+03:00"
+        );
+        assert_eq!(parse(b"1597994448").unwrap().to_rfc3339(), "2020-08-21T07:20:48+00:00");
+        assert_eq!(parse(b"1597994448123").unwrap().to_rfc3339(), "2020-08-21T07:20:48.123+00:00");
+        assert_eq!(parse(b"1597994448123456").unwrap().to_rfc3339(), "2020-08-21T07:20:48.123456+00:00");
+        assert_eq!(
+            parse(b"1597994448123456789").unwrap().to_rfc3339(),
+            "2020-08-21T07:20:48.123456789+00:00"
+        );
+        assert_eq!(parse(b"1597994448.25").unwrap().to_rfc3339(), "2020-08-21T07:20:48.250+00:00");
+        assert_eq!(
+            parse(b"9223372036854775807").unwrap().to_rfc3339(),
+            "2262-04-11T23:47:16.854775807+00:00"
+        );
+        assert!(matches!(parse(b"99999999999999999999"), Err(Error::UnrecognizedTime(_))));
+        assert!(matches!(parse(b"1597994448.1234567890"), Err(Error::UnrecognizedTime(_))));
+        assert!(matches!(parse(b"\xff"), Err(Error::Utf8Error(_))));
+        assert!(rfc3339::Timestamp::parse("2020-08-21T07:20:48.+03:00").is_none());
+    }
+
+    #[test]
+    fn test_time_format() {
+        let format = TimeFormat::try_from("%d/%b/%Y:%H:%M:%S %z".to_owned()).unwrap();
+        let ts = format.parse("10/Oct/2000:13:55:36 -0700").unwrap();
+        assert_eq!(ts.to_rfc3339(), "2000-10-10T13:55:36-07:00");
+        assert!(format.parse("2000-10-10T13:55:36Z").is_none());
+
+        let format = TimeFormat::try_from("%d.%m.%Y %H:%M:%S%.3f".to_owned()).unwrap();
+        let ts = format.parse("10.10.2000 13:55:36.250").unwrap();
+        assert_eq!(ts.to_rfc3339(), "2000-10-10T13:55:36.250+00:00");
+
+        assert!(TimeFormat::try_from("%Y-%m-%d %Q".to_owned()).is_err());
     }
 }
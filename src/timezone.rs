@@ -1,8 +1,68 @@
 use chrono::{FixedOffset, Local, LocalResult, NaiveDate, NaiveDateTime, Offset, TimeZone, Utc};
-use chrono_tz::OffsetName;
+use chrono_tz::{OffsetName, TZ_VARIANTS};
 
 use std::{convert::From, fmt};
 
+use crate::error::{Error, Result};
+
+// ---
+
+/// Maximum number of similar time zone names suggested for an unknown one.
+const MAX_SUGGESTIONS: usize = 5;
+
+// ---
+
+/// Finds a time zone by name in the time zone database bundled into the executable, ignoring case.
+///
+/// Fails with a list of the most similar known names if there is no such time zone.
+pub fn find(name: &str) -> Result<chrono_tz::Tz> {
+    if let Ok(tz) = name.parse() {
+        return Ok(tz);
+    }
+    if let Some(tz) = TZ_VARIANTS.iter().find(|tz| tz.name().eq_ignore_ascii_case(name)) {
+        return Ok(*tz);
+    }
+    Err(Error::UnknownTimeZone {
+        name: name.into(),
+        known: similar(name),
+    })
+}
+
+/// Returns known time zone names closest to the given one, comparing both full names and names of locations.
+fn similar(name: &str) -> Vec<String> {
+    let name = name.to_ascii_lowercase().replace(' ', "_");
+    let mut known = TZ_VARIANTS
+        .iter()
+        .map(|tz| {
+            let known = tz.name().to_ascii_lowercase();
+            let location = known.rsplit('/').next().unwrap_or(&known);
+            (distance(&name, &known).min(distance(&name, location)), tz.name())
+        })
+        .collect::<Vec<_>>();
+    known.sort();
+    known.into_iter().take(MAX_SUGGESTIONS).map(|(_, name)| name.into()).collect()
+}
+
+/// Returns edit distance between the strings.
+fn distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == cb {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
 // ---
 
 #[derive(Clone, Debug, Copy)]
@@ -165,3 +225,33 @@ impl fmt::Display for Tz {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find() {
+        assert_eq!(find("Europe/Berlin").unwrap(), chrono_tz::Europe::Berlin);
+        assert_eq!(find("america/new_york").unwrap(), chrono_tz::America::New_York);
+        match find("Europe/Berlim") {
+            Err(Error::UnknownTimeZone { name, known }) => {
+                assert_eq!(name, "Europe/Berlim");
+                assert_eq!(known[0], "Europe/Berlin");
+                assert_eq!(known.len(), MAX_SUGGESTIONS);
+            }
+            _ => panic!("expected unknown time zone error"),
+        }
+        match find("new york") {
+            Err(Error::UnknownTimeZone { known, .. }) => assert_eq!(known[0], "America/New_York"),
+            _ => panic!("expected unknown time zone error"),
+        }
+    }
+
+    #[test]
+    fn test_distance() {
+        assert_eq!(distance("kitten", "sitting"), 3);
+        assert_eq!(distance("", "abc"), 3);
+        assert_eq!(distance("abc", "abc"), 0);
+    }
+}
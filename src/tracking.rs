@@ -0,0 +1,207 @@
+// std imports
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::time::Duration;
+
+// third-party imports
+use chrono::{TimeZone, Utc};
+
+// local imports
+use crate::index::Timestamp;
+
+// ---
+
+const SPARKLINE_LEN: usize = 20;
+const SPARKLINE_BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+// ---
+
+/// Settings of tracking of a numeric field, see --track option.
+#[derive(Clone, Debug)]
+pub struct Tracking {
+    pub key: String,
+    pub interval: Duration,
+}
+
+// ---
+
+/// Statistics of values of a numeric field grouped into time buckets.
+#[derive(Clone, Debug)]
+pub struct Series {
+    key: String,
+    interval: i64,
+    buckets: BTreeMap<i64, Stat>,
+}
+
+impl Series {
+    pub fn new(tracking: &Tracking) -> Self {
+        Self {
+            key: tracking.key.clone(),
+            interval: (tracking.interval.as_secs() as i64).max(1),
+            buckets: BTreeMap::new(),
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn add(&mut self, ts: Timestamp, value: f64) {
+        let bucket = ts.sec.div_euclid(self.interval) * self.interval;
+        self.buckets.entry(bucket).or_insert_with(Stat::new).add(value);
+    }
+
+    pub fn merge(&mut self, other: &Series) {
+        for (bucket, stat) in &other.buckets {
+            self.buckets.entry(*bucket).or_insert_with(Stat::new).merge(stat);
+        }
+    }
+
+    /// Returns start of the latest bucket that is considered complete because a later bucket has values.
+    pub fn completed(&self) -> Option<i64> {
+        self.buckets.keys().rev().nth(1).copied()
+    }
+
+    /// Returns a status line with statistics of the bucket and a sparkline of averages of the preceding buckets.
+    pub fn status(&self, bucket: i64) -> String {
+        let stat = self.buckets.get(&bucket).copied().unwrap_or_else(Stat::new);
+        format!(
+            "{} at {}: {} {}",
+            self.key,
+            format_ts(bucket),
+            stat,
+            self.sparkline(bucket)
+        )
+    }
+
+    pub fn print<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        let mut total = Stat::new();
+        for stat in self.buckets.values() {
+            total.merge(stat);
+        }
+        writeln!(output, "  {}: {}", self.key, total)?;
+        if let Some(last) = self.buckets.keys().next_back() {
+            writeln!(output, "  {} trend: {}", self.key, self.sparkline(*last))?;
+        }
+        Ok(())
+    }
+
+    fn sparkline(&self, last: i64) -> String {
+        let first = last - self.interval * (SPARKLINE_LEN as i64 - 1);
+        let first = self.buckets.range(first..).next().map(|(bucket, _)| *bucket).unwrap_or(last);
+        let averages: Vec<_> = (first..=last)
+            .step_by(self.interval as usize)
+            .map(|bucket| self.buckets.get(&bucket).and_then(|stat| stat.avg()))
+            .collect();
+        let min = averages.iter().flatten().copied().fold(f64::INFINITY, f64::min);
+        let max = averages.iter().flatten().copied().fold(f64::NEG_INFINITY, f64::max);
+        averages
+            .iter()
+            .map(|avg| match avg {
+                Some(avg) if max > min => {
+                    SPARKLINE_BARS[((avg - min) / (max - min) * (SPARKLINE_BARS.len() - 1) as f64).round() as usize]
+                }
+                Some(_) => SPARKLINE_BARS[0],
+                None => ' ',
+            })
+            .collect()
+    }
+}
+
+// ---
+
+#[derive(Clone, Copy, Debug)]
+struct Stat {
+    min: f64,
+    max: f64,
+    sum: f64,
+    count: u64,
+}
+
+impl Stat {
+    fn new() -> Self {
+        Self {
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn add(&mut self, value: f64) {
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn merge(&mut self, other: &Stat) {
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.sum += other.sum;
+        self.count += other.count;
+    }
+
+    fn avg(&self) -> Option<f64> {
+        if self.count != 0 {
+            Some(self.sum / self.count as f64)
+        } else {
+            None
+        }
+    }
+}
+
+impl std::fmt::Display for Stat {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.avg() {
+            Some(avg) => write!(
+                f,
+                "min {} avg {} max {} ({} values)",
+                format_number(self.min),
+                format_number(avg),
+                format_number(self.max),
+                self.count
+            ),
+            None => write!(f, "no values"),
+        }
+    }
+}
+
+// ---
+
+fn format_number(value: f64) -> String {
+    let text = format!("{:.3}", value);
+    text.trim_end_matches('0').trim_end_matches('.').to_owned()
+}
+
+fn format_ts(sec: i64) -> String {
+    match Utc.timestamp_opt(sec, 0).single() {
+        Some(ts) => ts.to_rfc3339(),
+        None => sec.to_string(),
+    }
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_series() {
+        let mut series = Series::new(&Tracking {
+            key: "latency".into(),
+            interval: Duration::from_secs(60),
+        });
+        let ts = |sec| Timestamp { sec, nsec: 0 };
+        series.add(ts(0), 1.0);
+        series.add(ts(30), 3.0);
+        series.add(ts(180), 10.0);
+        assert_eq!(series.completed(), Some(0));
+        assert_eq!(
+            series.status(0),
+            "latency at 1970-01-01T00:00:00+00:00: min 1 avg 2 max 3 (2 values) ▁"
+        );
+        assert_eq!(series.sparkline(180), "▁  █");
+    }
+}
@@ -0,0 +1,314 @@
+// std imports
+use std::borrow::Cow;
+
+// third-party imports
+use json::value::RawValue;
+use serde_json as json;
+use wildmatch::WildMatch;
+
+// local imports
+use crate::model::RawRecord;
+use crate::settings::{self, ValueType};
+
+// ---
+
+type Fields<'a> = Vec<(Cow<'a, str>, Cow<'a, str>)>;
+
+// ---
+
+/// Ordered chain of record transformations applied before filtering and formatting.
+#[derive(Default)]
+pub struct Transformer {
+    steps: Vec<Step>,
+    explode: Option<String>,
+}
+
+impl Transformer {
+    pub fn new<'a, I: IntoIterator<Item = &'a settings::Transform>>(transforms: I) -> Self {
+        Self {
+            steps: transforms.into_iter().map(Step::from).collect(),
+            explode: None,
+        }
+    }
+
+    /// Adds a numeric suffix to the keys of duplicate fields, e.g. `level#2`, so that all of them are kept.
+    pub fn with_duplicate_suffixes(mut self, enabled: bool) -> Self {
+        if enabled {
+            self.steps.push(Step::SuffixDuplicates);
+        }
+        self
+    }
+
+    /// Expands records having an array in the given field to one record per array element,
+    /// each inheriting all other fields of the record and having the element as the field value.
+    pub fn with_explode(mut self, key: Option<String>) -> Self {
+        self.explode = key;
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty() && self.explode.is_none()
+    }
+
+    /// Transforms a line containing a JSON object and writes the resulting object to the buffer.
+    /// If the record is exploded, the resulting objects are written separated by newlines.
+    ///
+    /// Returns false if the line does not contain a JSON object, leaving the buffer empty.
+    pub fn apply(&self, line: &[u8], buf: &mut Vec<u8>) -> bool {
+        buf.clear();
+        let record = match json::from_slice::<RawRecord>(line) {
+            Ok(record) => record,
+            Err(_) => return false,
+        };
+
+        let mut fields: Fields = record
+            .fields()
+            .map(|(key, value)| (Cow::Borrowed(*key), Cow::Borrowed(value.get())))
+            .collect();
+        for step in &self.steps {
+            step.apply(&mut fields);
+        }
+
+        let exploded = self.explode.as_ref().and_then(|key| fields.iter().rposition(|field| field.0 == *key));
+        let value = exploded.map(|i| fields[i].1.clone());
+        let elements = value
+            .as_deref()
+            .and_then(|value| json::from_str::<Vec<&RawValue>>(value).ok())
+            .filter(|elements| !elements.is_empty());
+
+        match exploded.zip(elements) {
+            Some((i, elements)) => {
+                for (j, element) in elements.into_iter().enumerate() {
+                    if j != 0 {
+                        buf.push(b'\n');
+                    }
+                    fields[i].1 = Cow::Owned(element.get().to_string());
+                    write(&fields, buf);
+                }
+            }
+            None => write(&fields, buf),
+        }
+        true
+    }
+
+    /// Transforms all lines of the data and returns the result, leaving lines that are not JSON objects as is.
+    pub fn apply_all(&self, data: &[u8]) -> Vec<u8> {
+        let mut result = Vec::with_capacity(data.len());
+        let mut buf = Vec::new();
+        for line in data.split(|c| *c == b'\n') {
+            if self.apply(line, &mut buf) {
+                result.extend_from_slice(&buf);
+            } else {
+                result.extend_from_slice(line);
+            }
+            result.push(b'\n');
+        }
+        result
+    }
+}
+
+// ---
+
+enum Step {
+    Rename { from: String, to: String },
+    Drop(Vec<WildMatch>),
+    ParseJson(String),
+    Coerce { field: String, to: ValueType },
+    Tag { key: String, value: String },
+    SuffixDuplicates,
+}
+
+impl Step {
+    fn apply<'a>(&'a self, fields: &mut Fields<'a>) {
+        match self {
+            Self::Rename { from, to } => {
+                for field in fields.iter_mut().filter(|field| field.0 == *from) {
+                    field.0 = Cow::Borrowed(to);
+                }
+            }
+            Self::Drop(patterns) => {
+                fields.retain(|field| !patterns.iter().any(|pattern| pattern.matches(&field.0)));
+            }
+            Self::ParseJson(key) => {
+                for field in fields.iter_mut().filter(|field| field.0 == *key) {
+                    if let Some(value) = json::from_str::<String>(&field.1)
+                        .ok()
+                        .filter(|value| json::from_str::<&RawValue>(value).is_ok())
+                    {
+                        field.1 = Cow::Owned(value);
+                    }
+                }
+            }
+            Self::Coerce { field: key, to } => {
+                for field in fields.iter_mut().filter(|field| field.0 == *key) {
+                    if let Some(value) = coerce(&field.1, *to) {
+                        field.1 = Cow::Owned(value);
+                    }
+                }
+            }
+            Self::Tag { key, value } => {
+                fields.retain(|field| field.0 != *key);
+                fields.push((Cow::Borrowed(key), Cow::Borrowed(value)));
+            }
+            Self::SuffixDuplicates => {
+                for i in (1..fields.len()).rev() {
+                    let n = fields[..i].iter().filter(|field| field.0 == fields[i].0).count();
+                    if n != 0 {
+                        let key = format!("{}#{}", fields[i].0, n + 1);
+                        fields[i].0 = Cow::Owned(key);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl From<&settings::Transform> for Step {
+    fn from(transform: &settings::Transform) -> Self {
+        match transform {
+            settings::Transform::Rename { from, to } => Self::Rename {
+                from: from.clone(),
+                to: to.clone(),
+            },
+            settings::Transform::Drop(keys) => Self::Drop(keys.iter().map(|key| WildMatch::new(key)).collect()),
+            settings::Transform::ParseJson(key) => Self::ParseJson(key.clone()),
+            settings::Transform::Coerce { field, to } => Self::Coerce {
+                field: field.clone(),
+                to: *to,
+            },
+            settings::Transform::Tag { key, value } => Self::Tag {
+                key: key.clone(),
+                value: json::to_string(value).unwrap(),
+            },
+        }
+    }
+}
+
+// ---
+
+fn write(fields: &Fields, buf: &mut Vec<u8>) {
+    buf.push(b'{');
+    for (i, (key, value)) in fields.iter().enumerate() {
+        if i != 0 {
+            buf.push(b',');
+        }
+        json::to_writer(&mut *buf, key).ok();
+        buf.push(b':');
+        buf.extend_from_slice(value.as_bytes());
+    }
+    buf.push(b'}');
+}
+
+fn coerce(value: &str, to: ValueType) -> Option<String> {
+    let text = json::from_str::<String>(value).ok();
+    match to {
+        ValueType::String => match text {
+            Some(_) => None,
+            None => json::to_string(value).ok(),
+        },
+        ValueType::Number => {
+            let text = text?;
+            let text = text.trim();
+            json::from_str::<json::Number>(text).ok().map(|_| text.to_string())
+        }
+        ValueType::Boolean => match text?.trim() {
+            "true" => Some("true".into()),
+            "false" => Some("false".into()),
+            _ => None,
+        },
+    }
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transform(transforms: Vec<settings::Transform>, line: &str) -> String {
+        let transformer = Transformer::new(&transforms);
+        let mut buf = Vec::new();
+        assert!(transformer.apply(line.as_bytes(), &mut buf));
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn test_rename_and_drop() {
+        let result = transform(
+            vec![
+                settings::Transform::Rename {
+                    from: "text".into(),
+                    to: "msg".into(),
+                },
+                settings::Transform::Drop(vec!["secret-*".into()]),
+            ],
+            r#"{"text":"hello","secret-a":1,"x":2}"#,
+        );
+        assert_eq!(result, r#"{"msg":"hello","x":2}"#);
+    }
+
+    #[test]
+    fn test_parse_json_coerce_and_tag() {
+        let result = transform(
+            vec![
+                settings::Transform::ParseJson("payload".into()),
+                settings::Transform::Coerce {
+                    field: "status".into(),
+                    to: ValueType::Number,
+                },
+                settings::Transform::Coerce {
+                    field: "ok".into(),
+                    to: ValueType::Boolean,
+                },
+                settings::Transform::Tag {
+                    key: "env".into(),
+                    value: "prod".into(),
+                },
+            ],
+            r#"{"payload":"{\"a\":1}","status":"200","ok":"true"}"#,
+        );
+        assert_eq!(result, r#"{"payload":{"a":1},"status":200,"ok":true,"env":"prod"}"#);
+    }
+
+    #[test]
+    fn test_suffix_duplicates() {
+        let transformer = Transformer::default().with_duplicate_suffixes(true);
+        let mut buf = Vec::new();
+        assert!(transformer.apply(br#"{"level":"info","x":1,"level":"error","level":"debug"}"#, &mut buf));
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            r#"{"level":"info","x":1,"level#2":"error","level#3":"debug"}"#
+        );
+    }
+
+    #[test]
+    fn test_explode() {
+        let transformer = Transformer::default().with_explode(Some("batch".into()));
+        let mut buf = Vec::new();
+        assert!(transformer.apply(
+            br#"{"msg":"done","batch":[{"id":1,"status":"ok"},{"id":2,"status":"failed"}],"n":2}"#,
+            &mut buf
+        ));
+        assert_eq!(
+            String::from_utf8(buf.clone()).unwrap(),
+            concat!(
+                r#"{"msg":"done","batch":{"id":1,"status":"ok"},"n":2}"#,
+                "\n",
+                r#"{"msg":"done","batch":{"id":2,"status":"failed"},"n":2}"#,
+            )
+        );
+
+        for line in [r#"{"msg":"done","batch":[]}"#, r#"{"msg":"done","batch":{"id":1}}"#, r#"{"msg":"done"}"#] {
+            assert!(transformer.apply(line.as_bytes(), &mut buf));
+            assert_eq!(String::from_utf8(buf.clone()).unwrap(), line);
+        }
+    }
+
+    #[test]
+    fn test_not_an_object() {
+        let transformer = Transformer::default();
+        let mut buf = Vec::new();
+        assert!(!transformer.apply(b"plain text", &mut buf));
+        assert!(buf.is_empty());
+    }
+}
@@ -0,0 +1,897 @@
+// std imports
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// third-party imports
+use chrono::{DateTime, FixedOffset};
+use clap::ValueEnum;
+use enum_map::EnumMap;
+use serde_json as json;
+use terminal_size::{terminal_size, Height, Width};
+
+// local imports
+use crate::error::{Error, Result};
+use crate::formatting::RecordFormatter;
+use crate::input::{Block, BlockLine, IndexedInput, InputReference};
+use crate::logfmt;
+use crate::model::{Filter, Level, Parser, RawRecord};
+use crate::settings::InputFormat;
+use crate::syslog;
+use crate::text::TextFormat;
+use crate::width::char_width;
+
+// ---
+
+/// Number of blocks kept loaded by a document.
+const CACHED_BLOCKS: usize = 16;
+
+/// Interval between checks for changes of the inputs while following.
+const SYNC_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Number of lines scanned between checks whether the user interrupts the scan with Esc or Ctrl-C.
+const INTERRUPT_CHECK_LINES: usize = 1024;
+
+const ENTER_SCREEN: &[u8] = b"\x1b[?1049h\x1b[?25l";
+const LEAVE_SCREEN: &[u8] = b"\x1b[?25h\x1b[?1049l";
+const HOME: &[u8] = b"\x1b[H";
+const CLEAR_LINE: &[u8] = b"\x1b[K";
+const REVERSE: &[u8] = b"\x1b[7m";
+const RESET: &[u8] = b"\x1b[m";
+
+// ---
+
+/// Lines of indexed inputs in file order, addressed by their number and loaded block by block on demand.
+pub struct Document {
+    inputs: Vec<Arc<IndexedInput>>,
+    // input, block and number of the first line of each block having lines
+    blocks: Vec<(usize, usize, usize)>,
+    len: usize,
+    cache: VecDeque<(usize, Vec<BlockLine>)>,
+}
+
+impl Document {
+    pub fn new(inputs: Vec<Arc<IndexedInput>>) -> Self {
+        let mut blocks = Vec::new();
+        let mut len = 0;
+        for (i, input) in inputs.iter().enumerate() {
+            for (j, block) in input.index.source().blocks.iter().enumerate() {
+                let lines = (block.stat.lines_valid + block.stat.lines_invalid) as usize;
+                if lines != 0 {
+                    blocks.push((i, j, len));
+                    len += lines;
+                }
+            }
+        }
+        Self {
+            inputs,
+            blocks,
+            len,
+            cache: VecDeque::with_capacity(CACHED_BLOCKS),
+        }
+    }
+
+    /// Returns the total number of lines.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn inputs(&self) -> &[Arc<IndexedInput>] {
+        &self.inputs
+    }
+
+    /// Returns the line with the given number, loading its block if needed.
+    pub fn line(&mut self, n: usize) -> Result<Option<&[u8]>> {
+        if n >= self.len {
+            return Ok(None);
+        }
+        let b = self.blocks.partition_point(|&(_, _, first)| first <= n) - 1;
+        match self.cache.iter().position(|(k, _)| *k == b) {
+            Some(pos) => {
+                let entry = self.cache.remove(pos).unwrap();
+                self.cache.push_front(entry);
+            }
+            None => {
+                let (i, j, _) = self.blocks[b];
+                let mut lines = Block::new(self.inputs[i].clone(), j).into_lines()?.collect::<Vec<_>>();
+                // lines of unsorted blocks come in chronological order
+                lines.sort_by_key(|line| line.offset());
+                if self.cache.len() == CACHED_BLOCKS {
+                    self.cache.pop_back();
+                }
+                self.cache.push_front((b, lines));
+            }
+        }
+        Ok(self.cache[0].1.get(n - self.blocks[b].2).map(|line| line.bytes()))
+    }
+
+    /// Returns true if size of any of the input files has changed since it was indexed.
+    pub fn outdated(&self) -> bool {
+        self.inputs.iter().any(|input| match &input.reference {
+            InputReference::File(path) => fs::metadata(path).map_or(false, |meta| meta.len() != input.index.source().size),
+            _ => false,
+        })
+    }
+}
+
+// ---
+
+/// Interactive viewer of a document, see --interactive option.
+pub struct Viewer<'a> {
+    document: Document,
+    renderer: Renderer<'a>,
+    top: usize,
+    bottom: usize,
+    search: Option<String>,
+    // pattern being entered and the top line before it was started
+    prompt: Option<(String, usize)>,
+    follow: bool,
+    message: Option<String>,
+    size: (usize, usize),
+    buf: Vec<u8>,
+    tty: Option<tty::Tty>,
+    // keys pressed while scanning, handled when the scan completes
+    keys: VecDeque<Key>,
+}
+
+impl<'a> Viewer<'a> {
+    pub fn new(
+        document: Document,
+        parser: Parser,
+        formatter: RecordFormatter,
+        filter: &'a Filter,
+        input_format: InputFormat,
+        text_format: TextFormat,
+    ) -> Self {
+        Self {
+            document,
+            renderer: Renderer {
+                parser,
+                formatter,
+                filter,
+                input_format,
+                text_format,
+                now: syslog::reference_time(),
+                levels: EnumMap::from_fn(|_| true),
+                normalized: Vec::new(),
+            },
+            top: 0,
+            bottom: 0,
+            search: None,
+            prompt: None,
+            follow: false,
+            message: None,
+            size: (80, 24),
+            buf: Vec::new(),
+            tty: None,
+            keys: VecDeque::new(),
+        }
+    }
+
+    /// Shows the document in the alternate screen of the terminal until the user quits.
+    ///
+    /// While following, the document is replaced with the one returned by `reload` for the current one
+    /// each time the inputs change.
+    ///
+    /// Long scans for shown or matching lines are interrupted with Esc or Ctrl-C.
+    pub fn run<W, R>(mut self, output: &mut W, reload: R) -> Result<()>
+    where
+        W: Write + ?Sized,
+        R: FnMut(&Document) -> Result<Document>,
+    {
+        self.tty = Some(tty::Tty::open()?);
+        output.write_all(ENTER_SCREEN)?;
+        let result = self.interact(output, reload);
+        output.write_all(LEAVE_SCREEN)?;
+        output.flush()?;
+        result
+    }
+
+    fn interact<W, R>(&mut self, output: &mut W, mut reload: R) -> Result<()>
+    where
+        W: Write + ?Sized,
+        R: FnMut(&Document) -> Result<Document>,
+    {
+        self.top = match self.visible(0, true) {
+            Ok(top) => top.unwrap_or(0),
+            Err(Error::Cancelled) => 0,
+            Err(err) => return Err(err),
+        };
+        let mut synced = Instant::now();
+        let mut dirty = true;
+        loop {
+            let size = terminal_size()
+                .map(|(Width(width), Height(height))| (usize::from(width), usize::from(height)))
+                .unwrap_or(self.size);
+            if size != self.size {
+                self.size = size;
+                dirty = true;
+            }
+            if dirty {
+                self.draw(output)?;
+                dirty = false;
+            }
+            if self.keys.is_empty() {
+                self.read_keys()?;
+            }
+            while let Some(key) = self.keys.pop_front() {
+                match self.handle(key) {
+                    Ok(true) => {}
+                    Ok(false) => return Ok(()),
+                    Err(Error::Cancelled) => self.message = Some("interrupted".into()),
+                    Err(err) => return Err(err),
+                }
+                dirty = true;
+            }
+            if self.follow && synced.elapsed() >= SYNC_INTERVAL {
+                synced = Instant::now();
+                if self.document.outdated() {
+                    self.document = reload(&self.document)?;
+                    if let Err(err) = self.end() {
+                        self.follow = false;
+                        match err {
+                            Error::Cancelled => self.message = Some("interrupted".into()),
+                            err => return Err(err),
+                        }
+                    }
+                    dirty = true;
+                }
+            }
+        }
+    }
+
+    /// Handles the key, returns false if the user quits.
+    fn handle(&mut self, key: Key) -> Result<bool> {
+        self.message = None;
+        if let Some((mut pattern, origin)) = self.prompt.take() {
+            match key {
+                Key::Enter => {
+                    self.search = Some(pattern).filter(|pattern| !pattern.is_empty()).or(self.search.take());
+                }
+                Key::Esc | Key::Ctrl('c') => self.top = origin,
+                Key::Backspace | Key::Char(_) => {
+                    if let Key::Char(c) = key {
+                        pattern.push(c);
+                    } else {
+                        pattern.pop();
+                    }
+                    self.prompt = Some((pattern.clone(), origin));
+                    // search is incremental, starting from where it was started each time the pattern changes
+                    self.top = match self.find(&pattern, origin, true)? {
+                        Some(n) => n,
+                        None => origin,
+                    };
+                }
+                _ => self.prompt = Some((pattern, origin)),
+            }
+            return Ok(true);
+        }
+
+        if key != Key::Char('F') {
+            self.follow = false;
+        }
+        match key {
+            Key::Char('q') | Key::Ctrl('c') => return Ok(false),
+            Key::Char('j') | Key::Down | Key::Enter => self.down()?,
+            Key::Char('k') | Key::Up => self.up()?,
+            Key::Char(' ') | Key::PageDown => {
+                if self.bottom < self.document.len() {
+                    self.top = self.bottom;
+                }
+            }
+            Key::Char('b') | Key::PageUp => self.top = self.back(self.top, self.rows())?,
+            Key::Char('g') | Key::Home => self.top = self.visible(0, true)?.unwrap_or(0),
+            Key::Char('G') | Key::End => self.end()?,
+            Key::Char('/') => self.prompt = Some((String::new(), self.top)),
+            Key::Char('n') => self.next_match(true)?,
+            Key::Char('N') => self.next_match(false)?,
+            Key::Char('F') => {
+                self.follow = !self.follow;
+                if self.follow {
+                    self.end()?;
+                }
+            }
+            Key::Char(c @ '1'..='6') => {
+                let level = Level::value_variants()[c as usize - '1' as usize];
+                self.renderer.levels[level] = !self.renderer.levels[level];
+                // keep the position if the top line is hidden
+                if !self.render(self.top)? {
+                    self.top = match self.visible(self.top, true)? {
+                        Some(n) => n,
+                        None => self.back(self.document.len(), self.rows())?,
+                    };
+                }
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn down(&mut self) -> Result<()> {
+        if self.bottom < self.document.len() {
+            if let Some(n) = self.visible(self.top + 1, true)? {
+                self.top = n;
+            }
+        }
+        Ok(())
+    }
+
+    fn up(&mut self) -> Result<()> {
+        if self.top != 0 {
+            if let Some(n) = self.visible(self.top - 1, false)? {
+                self.top = n;
+            }
+        }
+        Ok(())
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.top = self.back(self.document.len(), self.rows())?;
+        Ok(())
+    }
+
+    fn next_match(&mut self, forward: bool) -> Result<()> {
+        let pattern = match self.search.clone() {
+            Some(pattern) => pattern,
+            None => return Ok(()),
+        };
+        let from = match (forward, self.top) {
+            (true, top) => Some(top + 1),
+            (false, 0) => None,
+            (false, top) => Some(top - 1),
+        };
+        match from.map(|from| self.find(&pattern, from, forward)).transpose()?.flatten() {
+            Some(n) => self.top = n,
+            None => self.message = Some(format!("pattern not found: {}", pattern)),
+        }
+        Ok(())
+    }
+
+    /// Returns number of the first shown line containing the pattern, ignoring case, starting from the given line.
+    fn find(&mut self, pattern: &str, from: usize, forward: bool) -> Result<Option<usize>> {
+        let pattern = pattern.as_bytes();
+        let mut n = from;
+        let mut scanned = 0;
+        while n < self.document.len() {
+            self.check_interrupt(&mut scanned)?;
+            let found = match self.document.line(n)? {
+                Some(line) => contains(line, pattern),
+                None => false,
+            };
+            if found && self.render(n)? {
+                return Ok(Some(n));
+            }
+            if !forward && n == 0 {
+                break;
+            }
+            n = if forward { n + 1 } else { n - 1 };
+        }
+        Ok(None)
+    }
+
+    /// Returns number of the first shown line starting from the given line.
+    fn visible(&mut self, from: usize, forward: bool) -> Result<Option<usize>> {
+        let mut n = from.min(self.document.len());
+        let mut scanned = 0;
+        if forward {
+            while n < self.document.len() {
+                self.check_interrupt(&mut scanned)?;
+                if self.render(n)? {
+                    return Ok(Some(n));
+                }
+                n += 1;
+            }
+        } else {
+            while n != 0 {
+                self.check_interrupt(&mut scanned)?;
+                n -= 1;
+                if self.render(n)? {
+                    return Ok(Some(n));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns number of the top line of the screen filled with up to the given number of rows just above the given line.
+    fn back(&mut self, from: usize, rows: usize) -> Result<usize> {
+        let mut top = from;
+        let mut used = 0;
+        let mut n = from.min(self.document.len());
+        let mut scanned = 0;
+        while n != 0 && used < rows {
+            self.check_interrupt(&mut scanned)?;
+            n -= 1;
+            if self.render(n)? {
+                let count = self.buf.iter().filter(|&&b| b == b'\n').count().max(1);
+                if used != 0 && used + count > rows {
+                    break;
+                }
+                used += count;
+                top = n;
+            }
+        }
+        Ok(top)
+    }
+
+    /// Reads the pressed keys, returns after a short timeout if no keys are pressed.
+    fn read_keys(&mut self) -> Result<()> {
+        let tty = match &mut self.tty {
+            Some(tty) => tty,
+            None => return Ok(()),
+        };
+        let mut keys = [0; 64];
+        let n = match tty.read(&mut keys) {
+            Ok(n) => n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => 0,
+            Err(err) => return Err(err.into()),
+        };
+        self.keys.extend(parse_keys(&keys[..n]));
+        Ok(())
+    }
+
+    /// Counts a scanned line and checks once in a while whether the user interrupts the scan with Esc or Ctrl-C,
+    /// in which case it fails with [Error::Cancelled], other keys pressed meanwhile are kept to be handled later.
+    fn check_interrupt(&mut self, scanned: &mut usize) -> Result<()> {
+        *scanned += 1;
+        if *scanned % INTERRUPT_CHECK_LINES != 0 {
+            return Ok(());
+        }
+        match &self.tty {
+            Some(tty) if tty.ready()? => {}
+            _ => return Ok(()),
+        }
+        let n = self.keys.len();
+        self.read_keys()?;
+        if self.keys.range(n..).any(|key| matches!(key, Key::Esc | Key::Ctrl('c'))) {
+            self.keys.clear();
+            return Err(Error::Cancelled);
+        }
+        Ok(())
+    }
+
+    /// Renders the line into the buffer, returns false if it is not shown.
+    fn render(&mut self, n: usize) -> Result<bool> {
+        self.buf.clear();
+        Ok(match self.document.line(n)? {
+            Some(line) => self.renderer.render(line, &mut self.buf),
+            None => false,
+        })
+    }
+
+    fn rows(&self) -> usize {
+        self.size.1.saturating_sub(1).max(1)
+    }
+
+    fn draw<W: Write + ?Sized>(&mut self, output: &mut W) -> Result<()> {
+        let (width, rows) = (self.size.0, self.rows());
+        let mut screen = Vec::new();
+        screen.extend_from_slice(HOME);
+        let mut used = 0;
+        let mut n = self.top;
+        let mut scanned = 0;
+        while used < rows && n < self.document.len() {
+            match self.check_interrupt(&mut scanned) {
+                Ok(()) => {}
+                Err(Error::Cancelled) => {
+                    self.message = Some("interrupted".into());
+                    break;
+                }
+                Err(err) => return Err(err),
+            }
+            if self.render(n)? {
+                let text = self.buf.strip_suffix(b"\n").unwrap_or(&self.buf);
+                let count = text.split(|&b| b == b'\n').count();
+                if used != 0 && used + count > rows {
+                    // the line is shown at the top of the next page
+                    break;
+                }
+                for row in text.split(|&b| b == b'\n').take(rows - used) {
+                    fit(&mut screen, row, width);
+                    screen.extend_from_slice(CLEAR_LINE);
+                    screen.extend_from_slice(b"\r\n");
+                    used += 1;
+                }
+            }
+            n += 1;
+        }
+        self.bottom = n;
+        for _ in used..rows {
+            screen.extend_from_slice(CLEAR_LINE);
+            screen.extend_from_slice(b"\r\n");
+        }
+        screen.extend_from_slice(REVERSE);
+        let status = self.status();
+        let shown = fit(&mut screen, status.as_bytes(), width);
+        screen.extend(std::iter::repeat(b' ').take(width.saturating_sub(shown)));
+        screen.extend_from_slice(RESET);
+        output.write_all(&screen)?;
+        output.flush()?;
+        Ok(())
+    }
+
+    fn status(&self) -> String {
+        if let Some((pattern, _)) = &self.prompt {
+            return format!("/{}", pattern);
+        }
+        let levels: String = Level::value_variants()
+            .iter()
+            .map(|&level| match level.to_possible_value().filter(|_| self.renderer.levels[level]) {
+                Some(value) => value.get_name().chars().next().unwrap_or('?').to_ascii_uppercase(),
+                None => '-',
+            })
+            .collect();
+        let len = self.document.len();
+        let mut status = format!(" line {} of {}, levels {}", (self.top + 1).min(len), len, levels);
+        if self.follow {
+            status.push_str(", following");
+        }
+        match &self.message {
+            Some(message) => {
+                status.push_str(" | ");
+                status.push_str(message);
+            }
+            None => status.push_str(" | q quit, / search, n/N next/previous match, 1-6 toggle levels, F follow"),
+        }
+        status
+    }
+}
+
+// ---
+
+struct Renderer<'a> {
+    parser: Parser,
+    formatter: RecordFormatter,
+    filter: &'a Filter,
+    input_format: InputFormat,
+    text_format: TextFormat,
+    now: DateTime<FixedOffset>,
+    levels: EnumMap<Level, bool>,
+    normalized: Vec<u8>,
+}
+
+impl<'a> Renderer<'a> {
+    /// Formats the line if it is shown, lines that cannot be parsed are shown as is unless filtering is enabled.
+    fn render(&mut self, line: &[u8], buf: &mut Vec<u8>) -> bool {
+        let line = line.strip_suffix(b"\n").unwrap_or(line);
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let data = logfmt::normalize_with(self.input_format, &self.text_format, self.now, line, &mut self.normalized);
+        match json::from_slice::<RawRecord>(data) {
+            Ok(record) => {
+                let record = self.parser.parse(record);
+                if !record.matches(self.filter) || record.level.map_or(false, |level| !self.levels[level]) {
+                    return false;
+                }
+                self.formatter.format_record(buf, &record);
+                true
+            }
+            Err(_) => {
+                if !self.filter.is_empty() {
+                    return false;
+                }
+                buf.extend_from_slice(line);
+                buf.push(b'\n');
+                true
+            }
+        }
+    }
+}
+
+// ---
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Key {
+    Char(char),
+    Ctrl(char),
+    Enter,
+    Esc,
+    Backspace,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+}
+
+/// Parses keys read from the terminal, skipping unknown escape sequences.
+fn parse_keys(mut data: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    while let Some(&b) = data.first() {
+        let (key, n) = match b {
+            0x1b => match data.get(1) {
+                Some(b'[') | Some(b'O') => {
+                    // parameters are followed by a final byte
+                    let n = data[2..].iter().position(|b| (0x40..=0x7e).contains(b)).map_or(data.len(), |i| i + 3);
+                    let key = match &data[1..n] {
+                        b"[A" | b"OA" => Some(Key::Up),
+                        b"[B" | b"OB" => Some(Key::Down),
+                        b"[5~" => Some(Key::PageUp),
+                        b"[6~" => Some(Key::PageDown),
+                        b"[H" | b"OH" | b"[1~" | b"[7~" => Some(Key::Home),
+                        b"[F" | b"OF" | b"[4~" | b"[8~" => Some(Key::End),
+                        _ => None,
+                    };
+                    (key, n)
+                }
+                _ => (Some(Key::Esc), 1),
+            },
+            b'\r' | b'\n' => (Some(Key::Enter), 1),
+            0x08 | 0x7f => (Some(Key::Backspace), 1),
+            0x01..=0x1a => (Some(Key::Ctrl((b'a' + b - 1) as char)), 1),
+            _ => {
+                let n = utf8_len(b).min(data.len());
+                let key = std::str::from_utf8(&data[..n]).ok().and_then(|s| s.chars().next()).map(Key::Char);
+                (key, n)
+            }
+        };
+        keys.extend(key);
+        data = &data[n..];
+    }
+    keys
+}
+
+/// Returns the length of UTF-8 encoded character by its first byte, invalid bytes are taken as single characters.
+fn utf8_len(b: u8) -> usize {
+    match b {
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => 1,
+    }
+}
+
+/// Writes the beginning of the styled line that fits the given number of columns, returns the number of columns taken.
+///
+/// Wide characters take two columns and combining characters take none, invalid bytes take a column each.
+fn fit(output: &mut Vec<u8>, line: &[u8], width: usize) -> usize {
+    let mut columns = 0;
+    let mut styled = false;
+    let mut i = 0;
+    while i < line.len() {
+        let b = line[i];
+        if b == 0x1b && line.get(i + 1) == Some(&b'[') {
+            let n = line[i + 2..].iter().position(|b| (0x40..=0x7e).contains(b)).map_or(line.len(), |k| i + k + 3);
+            output.extend_from_slice(&line[i..n]);
+            styled = true;
+            i = n;
+            continue;
+        }
+        let n = utf8_len(b).min(line.len() - i);
+        let w = match std::str::from_utf8(&line[i..i + n]).ok().and_then(|s| s.chars().next()) {
+            Some(c) => char_width(c),
+            None => 1,
+        };
+        if columns + w > width {
+            break;
+        }
+        columns += w;
+        if b == b'\t' {
+            output.push(b' ');
+        } else {
+            output.extend_from_slice(&line[i..i + n]);
+        }
+        i += n;
+    }
+    if styled {
+        output.extend_from_slice(RESET);
+    }
+    columns
+}
+
+fn contains(text: &[u8], pattern: &[u8]) -> bool {
+    pattern.is_empty() || text.windows(pattern.len()).any(|w| w.eq_ignore_ascii_case(pattern))
+}
+
+// ---
+
+#[cfg(unix)]
+mod tty {
+    // std imports
+    use std::fs::{File, OpenOptions};
+    use std::io::{self, Read};
+    use std::mem::MaybeUninit;
+    use std::os::unix::io::AsRawFd;
+
+    /// Controlling terminal switched to raw mode with reads returning after a short timeout, restored when dropped.
+    pub struct Tty {
+        file: File,
+        saved: libc::termios,
+    }
+
+    impl Tty {
+        pub fn open() -> io::Result<Self> {
+            let file = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+            let fd = file.as_raw_fd();
+            let mut saved = MaybeUninit::uninit();
+            // SAFETY: fd is an open file descriptor owned by file, and saved points to memory for a termios structure.
+            if unsafe { libc::tcgetattr(fd, saved.as_mut_ptr()) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            // SAFETY: tcgetattr succeeded, so it has initialized the structure.
+            let saved = unsafe { saved.assume_init() };
+            let mut raw = saved;
+            // SAFETY: raw is a valid initialized termios structure, which is only modified in place.
+            unsafe { libc::cfmakeraw(&mut raw) };
+            raw.c_cc[libc::VMIN] = 0;
+            raw.c_cc[libc::VTIME] = 2;
+            // SAFETY: fd is an open file descriptor owned by file, and raw is a valid termios structure.
+            if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &raw) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Self { file, saved })
+        }
+
+        /// Returns true if there are pressed keys to be read without waiting.
+        pub fn ready(&self) -> io::Result<bool> {
+            let mut fd = libc::pollfd {
+                fd: self.file.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            // SAFETY: fd points to a single valid pollfd structure, and the zero timeout makes the call return at once.
+            match unsafe { libc::poll(&mut fd, 1, 0) } {
+                -1 => Err(io::Error::last_os_error()),
+                n => Ok(n > 0),
+            }
+        }
+    }
+
+    impl Read for Tty {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.file.read(buf)
+        }
+    }
+
+    impl Drop for Tty {
+        fn drop(&mut self) {
+            // SAFETY: the file descriptor is still open, and saved is the structure filled in by tcgetattr.
+            unsafe { libc::tcsetattr(self.file.as_raw_fd(), libc::TCSANOW, &self.saved) };
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod tty {
+    // std imports
+    use std::io::{self, Read};
+
+    pub struct Tty;
+
+    impl Tty {
+        pub fn open() -> io::Result<Self> {
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "interactive mode is not supported on this platform",
+            ))
+        }
+
+        pub fn ready(&self) -> io::Result<bool> {
+            Ok(false)
+        }
+    }
+
+    impl Read for Tty {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Ok(0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_keys() {
+        assert_eq!(
+            parse_keys(b"q\x1b[A\x1b[6~\x1bOH\x1b/\r\x7f\x03\xc3\xa9"),
+            vec![
+                Key::Char('q'),
+                Key::Up,
+                Key::PageDown,
+                Key::Home,
+                Key::Esc,
+                Key::Char('/'),
+                Key::Enter,
+                Key::Backspace,
+                Key::Ctrl('c'),
+                Key::Char('é'),
+            ]
+        );
+        assert_eq!(parse_keys(b"\x1b[1;5Cx"), vec![Key::Char('x')]);
+        assert_eq!(parse_keys(b"\x1b"), vec![Key::Esc]);
+    }
+
+    #[test]
+    fn test_fit() {
+        let mut output = Vec::new();
+        assert_eq!(fit(&mut output, b"\x1b[1mhello\x1b[m world", 7), 7);
+        assert_eq!(output, b"\x1b[1mhello\x1b[m w\x1b[m");
+
+        let mut output = Vec::new();
+        assert_eq!(fit(&mut output, "h\u{e9}\tx".as_bytes(), 10), 4);
+        assert_eq!(output, "h\u{e9} x".as_bytes());
+
+        let mut output = Vec::new();
+        assert_eq!(fit(&mut output, "\u{65e5}\u{672c}\u{8a9e}".as_bytes(), 5), 4);
+        assert_eq!(output, "\u{65e5}\u{672c}".as_bytes());
+
+        let mut output = Vec::new();
+        assert_eq!(fit(&mut output, "cafe\u{301}!".as_bytes(), 4), 4);
+        assert_eq!(output, "cafe\u{301}".as_bytes());
+    }
+
+    #[test]
+    fn test_viewer() {
+        use crate::datefmt::{DateTimeFormatter, LinuxDateFormat};
+        use crate::index::Indexer;
+        use crate::model::ParserSettings;
+        use crate::settings::Settings;
+        use crate::theme::Theme;
+        use crate::timezone::Tz;
+        use crate::IncludeExcludeKeyFilter;
+        use std::path::PathBuf;
+
+        let path = std::env::temp_dir().join(format!("hl-ui-test-{}.log", std::process::id()));
+        let data = (0..3000)
+            .map(|i| {
+                let level = if i % 1000 == 999 { "error" } else { "info" };
+                format!("{{\"level\":\"{}\",\"msg\":\"m{}\"}}\n", level, i)
+            })
+            .collect::<String>();
+        fs::write(&path, data).unwrap();
+
+        let settings = Settings::default();
+        let indexer = Indexer::new(1, 4096, 4096, PathBuf::new(), &settings.fields.predefined).with_cache(false);
+        let document = Document::new(vec![Arc::new(IndexedInput::open(&path, &indexer).unwrap())]);
+        assert_eq!(document.len(), 3000);
+        let filter = Filter::default();
+        let mut viewer = Viewer::new(
+            document,
+            Parser::new(ParserSettings::new(&settings.fields.predefined, &settings.fields.ignore, false)),
+            RecordFormatter::new(
+                Arc::new(Theme::none()),
+                DateTimeFormatter::new(LinuxDateFormat::new("%T").compile(), Tz::IANA(chrono_tz::UTC)),
+                false,
+                Arc::new(IncludeExcludeKeyFilter::default()),
+                settings.formatting.clone(),
+            ),
+            &filter,
+            InputFormat::default(),
+            TextFormat::new(&settings.text_input, &settings.level_aliases).unwrap(),
+        );
+
+        assert_eq!(viewer.find("M2000", 0, true).unwrap(), Some(2000));
+        assert_eq!(viewer.find("m2000", 1999, false).unwrap(), None);
+        assert_eq!(viewer.back(3000, 10).unwrap(), 2990);
+
+        // only errors are shown
+        for c in ['3', '4', '5', '6'] {
+            viewer.handle(Key::Char(c)).unwrap();
+        }
+        assert_eq!(viewer.visible(0, true).unwrap(), Some(999));
+        assert_eq!(viewer.visible(2998, false).unwrap(), Some(1999));
+        assert_eq!(viewer.back(3000, 2).unwrap(), 1999);
+        assert_eq!(viewer.find("m", 1000, true).unwrap(), Some(1999));
+        viewer.handle(Key::Char('G')).unwrap();
+        assert_eq!(viewer.top, 999);
+        viewer.handle(Key::Char('g')).unwrap();
+        assert_eq!(viewer.top, 999);
+
+        fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_contains() {
+        assert!(contains(b"Connection Refused", b"refused"));
+        assert!(!contains(b"ok", b"refused"));
+        assert!(contains(b"ok", b""));
+    }
+}